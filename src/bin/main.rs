@@ -1,62 +1,82 @@
-use std::io::prelude::*;
-use std::net::TcpListener;
-use std::sync::Arc;
-use std::time::Instant;
-
-use servum::cli::{self, tui};
-use servum::{http, http::HTTPRequest, multiprocessing::ThreadPool};
+use servum::cli::{self, tui, ConfigOutcome};
+use servum::server::Server;
 
 fn main() {
-    tui::print_logo();
-
-    let config = Arc::new(cli::Config::new());
-
-    tui::print_info();
-
-    let listener =
-        TcpListener::bind(format!("{}:{}", &config.address, &config.port))
-            .unwrap();
-    let pool = ThreadPool::new(config.threads);
-
-    tui::print_config(config.clone());
+    let config = match cli::Config::new() {
+        Ok(ConfigOutcome::Run(config)) => config,
+        Ok(ConfigOutcome::Help(text)) | Ok(ConfigOutcome::Version(text)) => {
+            println!("{}", text);
+            std::process::exit(0);
+        }
+        Ok(ConfigOutcome::Completions(script)) => {
+            print!("{}", script);
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!(
+                "Error while parsing arguments: {}\nUse --help for more information on available arguments",
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    match config.validate() {
+        Ok(lines) => {
+            for warning in config.startup_warnings() {
+                eprintln!("Warning: {}", warning);
+            }
 
-    if config.verbose {
-        tui::print_verbose_header();
+            if config.check {
+                for line in lines {
+                    println!("{}", line);
+                }
+                std::process::exit(0);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
 
-    for stream in listener.incoming() {
-        let mut stream = stream.unwrap();
-        let verbose = config.verbose;
-        let conf = config.clone();
-
-        #[allow(clippy::unused_io_amount)]
-        pool.execute(move || {
-            let mut buffer = [0; 1024];
-
-            stream.read(&mut buffer).unwrap();
-
-            let timer = Instant::now();
-            let req = HTTPRequest::new(&buffer);
-
-            if let Ok(req) = req {
-                let res = http::handle_connection(&req, conf);
-
-                if verbose {
-                    tui::print_verbose_stats(&req, &res, timer);
-                }
+    tui::print_logo(&config);
+    tui::print_info(&config);
+
+    let server = match Server::bind(config) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let config = server.config().clone();
+
+    if config.open {
+        let url = cli::open::browser_url(
+            config.scheme(),
+            &config.address,
+            config.port,
+        );
+
+        if let Err(e) = cli::open::open(&url) {
+            eprintln!("Warning: could not open {} in the browser: {}", url, e);
+        }
+    }
 
-                match req.method {
-                    "HEAD" => stream.write(res.header().as_slice()),
-                    _ => stream.write(res.into_bytes().as_slice()),
-                }
-                .unwrap();
-            } else if verbose {
-                eprintln!("ERR: Invalid HTTP request: {}", req.unwrap_err());
-            }
+    tui::print_config(config);
 
-            stream.flush().unwrap();
-        });
+    // Stop gracefully on Ctrl+C, printing the summary and removing the files
+    // created by the server
+    #[cfg(unix)]
+    {
+        let shutdown = server.shutdown_handle();
+        if let Err(e) = cli::signal::on_shutdown(move || shutdown.shutdown()) {
+            eprintln!("Warning: could not handle Ctrl+C: {}", e);
+        }
     }
 
-    println!("Shutting down");
+    if let Err(e) = server.run() {
+        eprintln!("ERR: {}", e);
+    }
 }
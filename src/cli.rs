@@ -1,6 +1,21 @@
 //! CLI arguments parser and help
+pub mod completions;
 mod config;
 mod err;
+pub mod logger;
+pub mod metrics;
+pub mod net;
+pub mod open;
+pub mod options;
+#[cfg(unix)]
+pub mod signal;
+#[cfg(feature = "tls")]
+pub mod tls;
+mod toml;
 pub mod tui;
 
-pub use config::Config;
+pub use config::{
+    default_threads, Config, ConfigOutcome, CONFIG_FILE,
+    DEFAULT_LOG_ROTATE_KEEP, DEFAULT_MAX_UPLOAD_SIZE, VERSION,
+};
+pub use err::CliError;
@@ -0,0 +1,409 @@
+//! Shell completion scripts generated from the option registry
+use super::options::{CliOption, OPTIONS};
+use std::str::FromStr;
+
+/// Name of the command completions are generated for
+const COMMAND: &str = "servum";
+
+/// Shells completion scripts can be generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl FromStr for Shell {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "powershell" => Ok(Shell::PowerShell),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Names of the supported shells, as accepted by `servum completions`
+const SHELLS: &str = "bash zsh fish powershell";
+
+/// Return the completion script of servum for `shell`.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::cli::completions::{script, Shell};
+/// assert!(script(Shell::Bash).contains("--port"));
+/// ```
+pub fn script(shell: Shell) -> String {
+    generate(shell, OPTIONS)
+}
+
+/// Return the completion script for `shell` completing `options`.
+fn generate(shell: Shell, options: &[CliOption]) -> String {
+    match shell {
+        Shell::Bash => bash(options),
+        Shell::Zsh => zsh(options),
+        Shell::Fish => fish(options),
+        Shell::PowerShell => powershell(options),
+    }
+}
+
+/// Whether the value of an option is a path, to complete file names.
+fn takes_path(opt: &CliOption) -> bool {
+    matches!(opt.value, Some("DIR") | Some("FILE") | Some("PEM"))
+}
+
+/// Return all forms of an option, e.g. `-p` and `--port`.
+fn forms(opt: &CliOption) -> Vec<String> {
+    opt.short
+        .map(|short| format!("-{}", short))
+        .into_iter()
+        .chain(std::iter::once(format!("--{}", opt.long)))
+        .collect()
+}
+
+/// Return the forms of all options matching `filter`, separated by `sep`.
+fn all_forms<F>(options: &[CliOption], sep: &str, filter: F) -> String
+where
+    F: Fn(&CliOption) -> bool,
+{
+    options
+        .iter()
+        .filter(|opt| filter(opt))
+        .flat_map(forms)
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+fn bash(options: &[CliOption]) -> String {
+    format!(
+        r#"_{cmd}() {{
+    local cur prev
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+
+    if [[ $COMP_CWORD -eq 2 && "$prev" == completions ]]; then
+        COMPREPLY=($(compgen -W "{shells}" -- "$cur"))
+        return 0
+    fi
+
+    case "$prev" in
+        {paths})
+            COMPREPLY=($(compgen -f -- "$cur"))
+            return 0
+            ;;
+        {values})
+            return 0
+            ;;
+    esac
+
+    if [[ "$cur" == -* ]]; then
+        COMPREPLY=($(compgen -W "{all}" -- "$cur"))
+    else
+        COMPREPLY=($(compgen -d -- "$cur"))
+    fi
+}}
+
+complete -F _{cmd} {cmd}
+"#,
+        cmd = COMMAND,
+        shells = SHELLS,
+        paths = all_forms(options, "|", takes_path),
+        values = all_forms(options, "|", |opt| {
+            opt.value.is_some() && !takes_path(opt)
+        }),
+        all = all_forms(options, " ", |_| true),
+    )
+}
+
+fn zsh(options: &[CliOption]) -> String {
+    let escape = |s: &str| {
+        s.replace('\'', r"'\''")
+            .replace('[', r"\[")
+            .replace(']', r"\]")
+    };
+    let specs: String = options
+        .iter()
+        .map(|opt| {
+            let names = match opt.short {
+                Some(short) => format!(
+                    "'(-{short} --{long})'{{-{short},--{long}}}",
+                    short = short,
+                    long = opt.long
+                ),
+                None => format!("--{}", opt.long),
+            };
+            let value = match opt.value {
+                Some(value) if takes_path(opt) => {
+                    format!(":{}:_files", escape(value))
+                }
+                Some(value) => format!(":{}: ", escape(value)),
+                None => String::new(),
+            };
+            let repeat = if opt.repeatable { "'*'" } else { "" };
+
+            format!(
+                "    {}{}'[{}]{}' \\\n",
+                repeat,
+                names,
+                escape(opt.summary),
+                value
+            )
+        })
+        .collect();
+
+    format!(
+        r#"#compdef {cmd}
+
+_{cmd}() {{
+    if (( CURRENT == 3 )) && [[ $words[2] == completions ]]; then
+        _values 'shell' {shells}
+        return
+    fi
+
+    _arguments -s \
+{specs}    '1:base directory:_files -/'
+}}
+
+_{cmd} "$@"
+"#,
+        cmd = COMMAND,
+        shells = SHELLS,
+        specs = specs,
+    )
+}
+
+fn fish(options: &[CliOption]) -> String {
+    let escape = |s: &str| s.replace('\\', r"\\").replace('\'', r"\'");
+    let lines: String = options
+        .iter()
+        .map(|opt| {
+            let short = match opt.short {
+                Some(short) => format!(" -s {}", short),
+                None => String::new(),
+            };
+            let value = match opt.value {
+                Some(_) if takes_path(opt) => " -r -F",
+                Some(_) => " -x",
+                None => "",
+            };
+
+            format!(
+                "complete -c {} -n '{}'{} -l {}{} -d '{}'\n",
+                COMMAND,
+                "not __fish_seen_subcommand_from completions",
+                short,
+                opt.long,
+                value,
+                escape(opt.summary)
+            )
+        })
+        .collect();
+
+    format!(
+        "complete -c {cmd} -n '__fish_use_subcommand' -a completions \
+         -d 'Print a completion script'\n\
+         complete -c {cmd} -n '__fish_seen_subcommand_from completions' -f \
+         -a '{shells}'\n\
+         {lines}",
+        cmd = COMMAND,
+        shells = SHELLS,
+        lines = lines,
+    )
+}
+
+fn powershell(options: &[CliOption]) -> String {
+    let escape = |s: &str| s.replace('\'', "''");
+    let results: String = options
+        .iter()
+        .flat_map(|opt| {
+            forms(opt).into_iter().map(move |form| {
+                format!(
+                    "        [CompletionResult]::new('{}', '{}', \
+                     [CompletionResultType]::ParameterName, '{}')\n",
+                    form,
+                    opt.long,
+                    escape(opt.summary)
+                )
+            })
+        })
+        .collect();
+
+    format!(
+        r#"using namespace System.Management.Automation
+
+Register-ArgumentCompleter -Native -CommandName '{cmd}' -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+
+    $completions = @(
+{results}    )
+
+    $completions | Where-Object {{ $_.CompletionText -like "$wordToComplete*" }}
+}}
+"#,
+        cmd = COMMAND,
+        results = results,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIXTURE: &[CliOption] = &[
+        CliOption {
+            short: Some("p"),
+            long: "port",
+            value: Some("NUM"),
+            repeatable: false,
+            summary: "Port to listen on.",
+            help: "",
+        },
+        CliOption {
+            short: None,
+            long: "log-file",
+            value: Some("FILE"),
+            repeatable: false,
+            summary: "Append the request log to a file.",
+            help: "",
+        },
+        CliOption {
+            short: None,
+            long: "ignore",
+            value: Some("GLOB"),
+            repeatable: true,
+            summary: "Don't serve [matching] paths.",
+            help: "",
+        },
+        CliOption {
+            short: Some("q"),
+            long: "quiet",
+            value: None,
+            repeatable: false,
+            summary: "Don't log requests.",
+            help: "",
+        },
+    ];
+
+    #[test]
+    fn shells() {
+        assert_eq!("bash".parse(), Ok(Shell::Bash));
+        assert_eq!("powershell".parse(), Ok(Shell::PowerShell));
+        assert_eq!("tcsh".parse::<Shell>(), Err(()));
+    }
+
+    #[test]
+    fn all_options() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+            let script = script(shell);
+
+            for opt in OPTIONS {
+                assert!(script.contains(opt.long), "{:?} {}", shell, opt.long);
+            }
+        }
+    }
+
+    #[test]
+    fn bash_snapshot() {
+        assert_eq!(
+            generate(Shell::Bash, FIXTURE),
+            r#"_servum() {
+    local cur prev
+    cur="${COMP_WORDS[COMP_CWORD]}"
+    prev="${COMP_WORDS[COMP_CWORD-1]}"
+
+    if [[ $COMP_CWORD -eq 2 && "$prev" == completions ]]; then
+        COMPREPLY=($(compgen -W "bash zsh fish powershell" -- "$cur"))
+        return 0
+    fi
+
+    case "$prev" in
+        --log-file)
+            COMPREPLY=($(compgen -f -- "$cur"))
+            return 0
+            ;;
+        -p|--port|--ignore)
+            return 0
+            ;;
+    esac
+
+    if [[ "$cur" == -* ]]; then
+        COMPREPLY=($(compgen -W "-p --port --log-file --ignore -q --quiet" -- "$cur"))
+    else
+        COMPREPLY=($(compgen -d -- "$cur"))
+    fi
+}
+
+complete -F _servum servum
+"#
+        );
+    }
+
+    #[test]
+    fn zsh_snapshot() {
+        assert_eq!(
+            generate(Shell::Zsh, FIXTURE),
+            r#"#compdef servum
+
+_servum() {
+    if (( CURRENT == 3 )) && [[ $words[2] == completions ]]; then
+        _values 'shell' bash zsh fish powershell
+        return
+    fi
+
+    _arguments -s \
+    '(-p --port)'{-p,--port}'[Port to listen on.]:NUM: ' \
+    --log-file'[Append the request log to a file.]:FILE:_files' \
+    '*'--ignore'[Don'\''t serve \[matching\] paths.]:GLOB: ' \
+    '(-q --quiet)'{-q,--quiet}'[Don'\''t log requests.]' \
+    '1:base directory:_files -/'
+}
+
+_servum "$@"
+"#
+        );
+    }
+
+    #[test]
+    fn fish_snapshot() {
+        assert_eq!(
+            generate(Shell::Fish, FIXTURE),
+            r#"complete -c servum -n '__fish_use_subcommand' -a completions -d 'Print a completion script'
+complete -c servum -n '__fish_seen_subcommand_from completions' -f -a 'bash zsh fish powershell'
+complete -c servum -n 'not __fish_seen_subcommand_from completions' -s p -l port -x -d 'Port to listen on.'
+complete -c servum -n 'not __fish_seen_subcommand_from completions' -l log-file -r -F -d 'Append the request log to a file.'
+complete -c servum -n 'not __fish_seen_subcommand_from completions' -l ignore -x -d 'Don\'t serve [matching] paths.'
+complete -c servum -n 'not __fish_seen_subcommand_from completions' -s q -l quiet -d 'Don\'t log requests.'
+"#
+        );
+    }
+
+    #[test]
+    fn powershell_snapshot() {
+        assert_eq!(
+            generate(Shell::PowerShell, FIXTURE),
+            r#"using namespace System.Management.Automation
+
+Register-ArgumentCompleter -Native -CommandName 'servum' -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+
+    $completions = @(
+        [CompletionResult]::new('-p', 'port', [CompletionResultType]::ParameterName, 'Port to listen on.')
+        [CompletionResult]::new('--port', 'port', [CompletionResultType]::ParameterName, 'Port to listen on.')
+        [CompletionResult]::new('--log-file', 'log-file', [CompletionResultType]::ParameterName, 'Append the request log to a file.')
+        [CompletionResult]::new('--ignore', 'ignore', [CompletionResultType]::ParameterName, 'Don''t serve [matching] paths.')
+        [CompletionResult]::new('-q', 'quiet', [CompletionResultType]::ParameterName, 'Don''t log requests.')
+        [CompletionResult]::new('--quiet', 'quiet', [CompletionResultType]::ParameterName, 'Don''t log requests.')
+    )
+
+    $completions | Where-Object { $_.CompletionText -like "$wordToComplete*" }
+}
+"#
+        );
+    }
+}
@@ -1,5 +1,212 @@
-use super::err::CliError;
-use std::{env, path::PathBuf};
+#[cfg(feature = "tls")]
+use super::tls;
+use super::{
+    completions::{self, Shell},
+    err::CliError,
+    logger::{LogFormat, QuietErrors},
+    net, options, toml,
+    tui::{self, Verbosity},
+};
+use crate::{
+    clock::{Clock, SystemClock},
+    files::{
+        base_dir::{self, BaseLink},
+        ignore,
+        ignore::IgnoreRules,
+        mime,
+    },
+    http::{
+        self, CacheRules, ListingCache, ManifestCache, NotFoundCache,
+        RedirectRules,
+    },
+    multiprocessing::DEFAULT_QUEUE_LIMIT,
+    server::{self, Overflow},
+    Error,
+};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+/// Version of servum, as given in the crate manifest
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Maximum number of threads used by default
+const MAX_DEFAULT_THREADS: usize = 16;
+
+/// Return the default number of threads, i.e. the available parallelism
+/// capped at [`MAX_DEFAULT_THREADS`], or 4 if it is unknown.
+pub fn default_threads() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get().min(MAX_DEFAULT_THREADS))
+        .unwrap_or(4)
+}
+
+/// Name of the config file automatically read from the base directory
+pub const CONFIG_FILE: &str = "servum.toml";
+
+/// Default number of rotated log files kept with `--log-rotate-size`
+pub const DEFAULT_LOG_ROTATE_KEEP: usize = 5;
+
+/// Default size limit of uploads with `--upload`, in megabytes
+pub const DEFAULT_MAX_UPLOAD_SIZE: u64 = 100;
+
+/// Default maximum number of components of the paths listed with
+/// `--manifest`
+pub const DEFAULT_MANIFEST_MAX_DEPTH: usize = 16;
+
+/// Default maximum number of files listed with `--manifest`
+pub const DEFAULT_MANIFEST_MAX_ENTRIES: usize = 10_000;
+
+/// Default time after which a response still being written is abandoned,
+/// with `--request-deadline`
+pub const DEFAULT_REQUEST_DEADLINE: Duration = Duration::from_secs(300);
+
+/// Default maximum number of components of a request path
+pub const DEFAULT_MAX_PATH_DEPTH: usize = 64;
+
+/// Default maximum length of a component of a request path in bytes, the
+/// longest file name most file systems allow
+pub const DEFAULT_MAX_SEGMENT_LEN: usize = 255;
+
+/// Request methods answered with `405 Method Not Allowed` by default, as
+/// they are only sent by vulnerability scanners to a static server
+pub const DEFAULT_BLOCKED_METHODS: [&str; 3] = ["TRACE", "TRACK", "CONNECT"];
+
+/// Options that can be set with `SERVUM_*` environment variables
+const ENV_OPTIONS: &[&str] = &[
+    "--acme-dir",
+    "--address",
+    "--base-dir",
+    "--cache-control",
+    "--config",
+    "--connection-overflow",
+    "--default-mime",
+    "--delay",
+    "--log-file",
+    "--log-format",
+    "--log-path-width",
+    "--log-rotate-keep",
+    "--log-rotate-size",
+    "--listing-cache-ttl",
+    "--manifest-max-depth",
+    "--manifest-max-entries",
+    "--max-connections",
+    "--max-path-depth",
+    "--max-segment-length",
+    "--max-upload-size",
+    "--pid-file",
+    "--port",
+    "--port-retry",
+    "--queue",
+    "--recv-buffer",
+    "--redirect-http",
+    "--request-deadline",
+    "--send-buffer",
+    "--stats-interval",
+    "--threads",
+    "--throttle",
+    "--tls-cert",
+    "--tls-key",
+    "--unix-socket",
+];
+
+/// Flags that can be set with `SERVUM_*` environment variables
+const ENV_FLAGS: &[&str] = &[
+    "--allow-encoded-slash",
+    "--cache",
+    "--follow-base-symlink",
+    "--health",
+    "--live-reload",
+    "--manifest",
+    "--no-color",
+    "--no-config",
+    "--no-ignore-file",
+    "--no-list-dir",
+    "--no-logo",
+    "--no-nodelay",
+    "--no-timestamps",
+    "--no-warnings",
+    "--open",
+    "--public",
+    "--quiet",
+    "--reuse-port",
+    "--silent",
+    "--trust-proxy",
+    "--upload",
+    "--verbose",
+];
+
+/// Return the name of the environment variable of an option, e.g.
+/// `SERVUM_NO_LIST_DIR` for `--no-list-dir`.
+fn env_name(option: &str) -> String {
+    format!(
+        "SERVUM_{}",
+        option
+            .trim_start_matches('-')
+            .replace('-', "_")
+            .to_uppercase()
+    )
+}
+
+/// Parse the value of the environment variable of a flag, e.g. `1`, `yes` or
+/// `false`, ignoring case. An empty value disables the flag.
+fn env_flag(val: &str) -> Option<bool> {
+    match val.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" => Some(true),
+        "0" | "false" | "no" | "" => Some(false),
+        _ => None,
+    }
+}
+
+/// Check that a Unix socket can be bound at `path`, removing the socket file
+/// right away.
+#[cfg(unix)]
+fn check_unix_socket(path: &Path) -> Result<(), CliError> {
+    net::bind_unix(path)?;
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Fail as Unix sockets are only supported on Unix.
+#[cfg(not(unix))]
+fn check_unix_socket(_path: &Path) -> Result<(), CliError> {
+    Err(CliError::IOError(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--unix-socket is only supported on Unix",
+    )))
+}
+
+/// Return the value of `option` in a config file located in `dir`, resolving
+/// relative paths from `dir`.
+fn config_value<'v>(option: &str, val: &'v str, dir: &Path) -> Cow<'v, str> {
+    match option {
+        "--acme-dir" | "--base-dir" | "--log-file" | "--mime-file"
+        | "--overlay" | "--pid-file" | "--tls-cert" | "--tls-key" => {
+            Cow::Owned(dir.join(val).to_string_lossy().into_owned())
+        }
+        _ => Cow::Borrowed(val),
+    }
+}
+
+/// Result of successfully parsing user arguments.
+///
+/// Returned by [`Config::from_args`]. Either the server should run with the
+/// given [`Config`], or the help or version text or a shell completion script
+/// should be printed before exiting normally.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)] // Only created once on startup
+pub enum ConfigOutcome {
+    Run(Config),
+    Help(String),
+    Version(String),
+    Completions(String),
+}
 
 /// Rudimentary argument parsing and user configuration.
 ///
@@ -10,22 +217,214 @@ use std::{env, path::PathBuf};
 /// through the [`Default`] trait by calling `Config::default()`.
 ///
 /// Possible configurable options include:
+/// - `acme_dir`: [`Option<PathBuf>`] (default: [`None`])  
+///   Directory to answer ACME HTTP challenges from, under
+///   [`ACME_PATH`](http::ACME_PATH), regardless of the ignore rules. See
+///   [`acme_challenge`](http::acme_challenge).
 /// - `address`: [`String`] (default: `"127.0.0.1"`)  
-///   Server address to bind to. Default is the loopback address 127.0.0.1, i.e
+///   Server address to bind to. Either an IPv4 or IPv6 address (without
+///   brackets) or a hostname. Default is the loopback address 127.0.0.1, i.e
 ///   localhost.
+/// - `allow_encoded_slash`: [`bool`] (default: `false`)  
+///   Whether to serve request paths with a percent-encoded slash or
+///   backslash, `%2F` or `%5C`, taking it as part of a file name rather than
+///   as a separator. If `false`, such requests are answered with
+///   `400 Bad Request`.
 /// - `base_dir`: [`PathBuf`] (default: current directory)  
 ///   Base directory to serve files from. Defaults to the current directory
 ///   ([`env::current_dir`])
+/// - `base_link`: [`BaseLink`] (default: current directory)  
+///   Base directory as given, before resolving symlinks, followed with
+///   `follow_base_symlink`.
+/// - `blocked_methods`: [`Vec<String>`] (default:
+///   [`DEFAULT_BLOCKED_METHODS`])  
+///   Request methods answered with `405 Method Not Allowed` before looking at
+///   their path. Methods set with `--block-method` are added to the default
+///   ones.
+/// - `cache`: [`bool`] (default: `false`)  
+///   Whether or not to remember request paths found missing for a short
+///   while, see [`NotFoundCache`]. Missing paths aren't cached with `upload`,
+///   so that writes show right away.
+/// - `cache_control`: [`Option<String>`] (default: [`None`])  
+///   `Cache-Control` header of successful responses not matching any of the
+///   `cache_rules`. If [`None`], no header is sent.
+/// - `cache_rules`: [`CacheRules`] (default: empty)  
+///   `Cache-Control` headers by URL path glob, set with `--cache-rule`. The
+///   first matching rule wins.
+/// - `check`: [`bool`] (default: `false`)  
+///   Whether to only validate the configuration and exit instead of serving.
+///   See [`Config::validate`].
+/// - `clock`: [`Arc<dyn Clock>`] (default: [`SystemClock`])  
+///   Source of the current time, e.g. for log timestamps. Replaced by a
+///   [`TestClock`](crate::clock::TestClock) in tests.
+/// - `color`: [`bool`] (default: `true`)  
+///   Whether or not to color the request log on the console. Disabled by the
+///   `NO_COLOR` environment variable.
+/// - `config`: [`Option<PathBuf>`] (default: [`None`])  
+///   Config file to read options from. If [`None`], the `servum.toml` file in
+///   the base directory is read if it exists.
+/// - `config_file`: [`bool`] (default: `true`)  
+///   Whether or not to read a config file at all.
+/// - `connection_overflow`: [`Overflow`] (default: [`Overflow::Reject`])  
+///   What to do with new connections while `max_connections` connections are
+///   open.
+/// - `delay`: [`u64`] (default: `0`)  
+///   Milliseconds to wait before writing each response, to simulate latency.
+/// - `default_mime`: [`Option<String>`] (default: [`None`])  
+///   MIME type to use for files with an unknown or missing extension. If
+///   [`None`], no `Content-Type` header is sent and browsers sniff the content.
+/// - `follow_base_symlink`: [`bool`] (default: `false`)  
+///   Whether or not to resolve `base_link` again while serving, at most once
+///   every [`BASE_LINK_TTL`](base_dir::BASE_LINK_TTL), so that swapping a
+///   symlinked base directory takes effect without a restart. See
+///   [`Config::current_base_dir`].
+/// - `health`: [`bool`] (default: `false`)  
+///   Whether or not to answer requests to `/_servum/health` with a health
+///   check. See [`health_check`](crate::http::health_check).
+/// - `ignore`: [`IgnoreRules`] (default: empty)  
+///   Glob patterns of paths to exclude from listings and requests, relative to
+///   the base directory. Set with `--ignore` and read from the `.servumignore`
+///   file in the base directory.
+/// - `ignore_file`: [`bool`] (default: `true`)  
+///   Whether or not to read the `.servumignore` file in the base directory.
+/// - `live_reload`: [`bool`] (default: `false`)  
+///   Whether or not to reload HTML pages in the browser when a file below the
+///   base directory changes. See [`LiveReload`](crate::http::LiveReload).
+/// - `logo`: [`bool`] (default: `true`)  
+///   Whether or not to print the logo on startup.
 /// - `list_dir`: [`bool`] (default: `true`)  
 ///   Whether or not to list directories. Defaults to yes.
-/// - `port`: [`usize`] (default: `8080`)  
+/// - `listing_cache`: [`ListingCache`] (default: empty)  
+///   Rendered directory listings, kept with `listing_cache_ttl`.
+/// - `listing_cache_ttl`: [`Option<Duration>`] (default: [`None`])  
+///   Time for which rendered directory listings are reused, as long as the
+///   listed directories are unchanged. Listings aren't cached with `upload`,
+///   so that writes show right away. If [`None`], they aren't cached either.
+/// - `log_file`: [`Option<PathBuf>`] (default: [`None`])  
+///   File to append the request log to, independently of `verbose`.
+/// - `log_format`: [`LogFormat`] (default: [`LogFormat::Pretty`])  
+///   Format of the request log, for both the console and the log file.
+/// - `log_path_width`: [`usize`] (default: `32`)  
+///   Maximum width of the path column of the pretty request log, or `0` to
+///   never truncate paths.
+/// - `log_rotate_keep`: [`usize`] (default: [`DEFAULT_LOG_ROTATE_KEEP`])  
+///   Number of rotated log files kept, see `log_rotate_size`.
+/// - `log_rotate_size`: [`Option<u64>`] (default: [`None`])  
+///   Size in megabytes from which the log file is renamed to `<name>.1`,
+///   shifting older rotated files, and a new one is started. Requires
+///   `log_file`. If [`None`], the log file grows unbounded.
+/// - `manifest`: [`bool`] (default: `false`)  
+///   Whether or not to answer requests to `/_servum/manifest.json` with a
+///   JSON list of the files served. See [`manifest`](crate::http::manifest).
+/// - `manifest_cache`: [`ManifestCache`] (default: empty)  
+///   Manifest last rendered, reused for a while unless files are added,
+///   removed or renamed, and cleared by the live reload watcher on every
+///   change.
+/// - `manifest_max_depth`: [`usize`] (default:
+///   [`DEFAULT_MANIFEST_MAX_DEPTH`])  
+///   Maximum number of components of the paths listed in the manifest.
+/// - `manifest_max_entries`: [`usize`] (default:
+///   [`DEFAULT_MANIFEST_MAX_ENTRIES`])  
+///   Maximum number of files listed in the manifest.
+/// - `max_connections`: [`Option<usize>`] (default: [`None`])  
+///   Maximum number of connections open at once, whether queued or being
+///   handled. If [`None`], the number of connections is unlimited.
+/// - `max_path_depth`: [`usize`] (default: [`DEFAULT_MAX_PATH_DEPTH`])  
+///   Maximum number of components of a request path, above which it is
+///   answered with `400 Bad Request`.
+/// - `max_segment_len`: [`usize`] (default: [`DEFAULT_MAX_SEGMENT_LEN`])  
+///   Maximum length of a component of a request path in bytes, once
+///   percent-decoded, above which it is answered with `400 Bad Request`.
+/// - `max_upload_size`: [`u64`] (default: [`DEFAULT_MAX_UPLOAD_SIZE`])  
+///   Size limit of a request body with `upload` in megabytes, above which
+///   it is answered with `413 Payload Too Large`.
+/// - `mime_overrides`: [`HashMap`] (default: empty)  
+///   User-defined MIME types by lowercase file extension (without the leading
+///   `.`). Consulted before the built-in MIME types.
+/// - `nodelay`: [`bool`] (default: `true`)  
+///   Whether or not to disable Nagle's algorithm on connections, so that small
+///   responses are sent right away instead of being delayed.
+/// - `not_found_cache`: [`NotFoundCache`] (default: empty)  
+///   Request paths found missing, kept with `cache` and cleared by the live
+///   reload watcher on every change.
+/// - `open`: [`bool`] (default: `false`)  
+///   Whether or not to open the server URL in the browser on startup.
+/// - `overlays`: [`Vec<PathBuf>`] (default: empty)  
+///   Directories to serve files from when the base directory has none at the
+///   requested path, tried in order. Directory listings show the entries of
+///   all of them, those of the base directory first. See [`Config::roots`].
+/// - `pid_file`: [`Option<PathBuf>`] (default: [`None`])  
+///   File to write the process id to once the server is listening, removed
+///   on shutdown. See [`PidFile`](crate::files::pid::PidFile).
+/// - `port`: [`u16`] (default: `8080`)  
 ///   What port to listen on. Defaults to 8080. Ports, such as port `80` (HTTP)
-///   need elevated privileges to bind to.
-/// - `threads`: [`usize`] (default: `4`)  
+///   need elevated privileges to bind to. Port `0` lets the operating system
+///   choose a free port.
+/// - `port_retry`: [`usize`] (default: `0`)  
+///   Number of following ports to try if the port is already in use.
+/// - `queue`: [`usize`] (default: [`DEFAULT_QUEUE_LIMIT`])  
+///   Number of connections waiting for a free thread before further
+///   connections are answered with `503 Service Unavailable`.
+/// - `quiet_errors`: [`QuietErrors`] (default: empty)  
+///   Status codes of responses left out of the request log, e.g. `404`.
+/// - `recv_buffer`: [`Option<usize>`] (default: [`None`])  
+///   Size of the receive buffer of connections in bytes. If [`None`], the
+///   operating system default is used. Only supported on Linux.
+/// - `redirects`: [`RedirectRules`] (default: empty)  
+///   Redirects by URL path, set with `--redirect`, answered before looking
+///   for files. The first matching rule wins.
+/// - `redirect_http`: [`Option<u16>`] (default: [`None`])  
+///   Port of a second, plain HTTP listener on `address` answering every
+///   request with `301 Moved Permanently` to the same URL over HTTPS.
+///   Requires `tls_cert` and `tls_key`.
+/// - `request_deadline`: [`Option<Duration>`] (default:
+///   [`DEFAULT_REQUEST_DEADLINE`])  
+///   Time from reading a request after which writing its response is given
+///   up and the connection closed, so that clients reading very slowly don't
+///   hold a thread. If [`None`], responses take as long as they take. The
+///   live reload event stream isn't bound by it.
+/// - `reuse_port`: [`bool`] (default: `false`)  
+///   Whether or not to give each thread a listener of its own, bound to the
+///   same port with `SO_REUSEPORT`, instead of handing connections over to
+///   the threads from a single listener. Only supported on Linux.
+/// - `send_buffer`: [`Option<usize>`] (default: [`None`])  
+///   Size of the send buffer of connections in bytes. If [`None`], the
+///   operating system default is used. Only supported on Linux.
+/// - `stats_interval`: [`Option<Duration>`] (default: [`None`])  
+///   Interval at which statistics of the thread pool are printed to the
+///   console, or [`None`] to not print them.
+/// - `threads`: [`usize`] (default: [`default_threads`])  
 ///   Number of threads to use to respond to incoming request in parallel.
-///   Value must be greater than 0. Default is 4.
-/// - `verbose`: [`bool`] (default: `true`)  
-///   Whether or not to be verbose and log stats about incoming requests.
+///   Value must be greater than 0, which is checked when parsing arguments.
+///   Defaults to the number of available CPUs, capped at 16, or 4 if unknown.
+/// - `tls_cert`: [`Option<PathBuf>`] (default: [`None`])  
+///   PEM file with the certificate chain to serve HTTPS with. Requires
+///   `tls_key` and the `tls` feature.
+/// - `tls_key`: [`Option<PathBuf>`] (default: [`None`])  
+///   PEM file with the private key to serve HTTPS with.
+/// - `throttle`: [`u64`] (default: `0`)  
+///   Bandwidth limit of each response body in kilobytes per second, to
+///   simulate slow connections, or `0` for no limit.
+/// - `timestamps`: [`bool`] (default: `true`)  
+///   Whether or not to show timestamps in the pretty request log.
+/// - `trust_proxy`: [`bool`] (default: `false`)  
+///   Whether or not to log the client address a proxy sends in the
+///   `X-Forwarded-For` header. Only set it behind a proxy, as clients can
+///   send any address.
+/// - `unix_socket`: [`Option<PathBuf>`] (default: [`None`])  
+///   Unix domain socket to listen on instead of `address` and `port`. Only
+///   supported on Unix.
+/// - `upload`: [`bool`] (default: `false`)  
+///   Whether or not to let anyone reaching the server write files below the
+///   base directory, with `PUT` requests or the form shown above directory
+///   listings, and delete them with `DELETE` requests.
+/// - `verbosity`: [`Verbosity`] (default: [`Verbosity::Normal`])  
+///   What to print to the console: nothing, startup information only,
+///   startup information and stats about incoming requests, or debugging
+///   information as well, such as how request paths were resolved.
+/// - `warnings`: [`bool`] (default: `true`)  
+///   Whether or not to print warnings about the configuration on startup,
+///   see [`Config::startup_warnings`].
 ///   Default is true.
 ///
 /// # Example
@@ -38,24 +437,148 @@ use std::{env, path::PathBuf};
 /// assert_eq!(config.port, 8080);
 /// // Do some more fancy stuff...
 /// ```
+#[derive(Debug, Clone)]
 pub struct Config {
+    pub acme_dir: Option<PathBuf>,
     pub address: String,
+    pub allow_encoded_slash: bool,
     pub base_dir: PathBuf,
+    pub base_link: BaseLink,
+    pub blocked_methods: Vec<String>,
+    pub cache: bool,
+    pub cache_control: Option<String>,
+    pub cache_rules: CacheRules,
+    pub check: bool,
+    pub clock: Arc<dyn Clock>,
+    pub color: bool,
+    pub config: Option<PathBuf>,
+    pub config_file: bool,
+    pub connection_overflow: Overflow,
+    pub default_mime: Option<String>,
+    pub delay: u64,
+    pub follow_base_symlink: bool,
+    pub health: bool,
+    pub ignore: IgnoreRules,
+    pub ignore_file: bool,
     pub list_dir: bool,
-    pub port: usize,
+    pub listing_cache: ListingCache,
+    pub listing_cache_ttl: Option<Duration>,
+    pub live_reload: bool,
+    pub logo: bool,
+    pub log_file: Option<PathBuf>,
+    pub log_format: LogFormat,
+    pub log_path_width: usize,
+    pub log_rotate_keep: usize,
+    pub log_rotate_size: Option<u64>,
+    pub manifest: bool,
+    pub manifest_cache: ManifestCache,
+    pub manifest_max_depth: usize,
+    pub manifest_max_entries: usize,
+    pub max_connections: Option<usize>,
+    pub max_path_depth: usize,
+    pub max_segment_len: usize,
+    pub max_upload_size: u64,
+    pub mime_overrides: HashMap<String, String>,
+    pub nodelay: bool,
+    pub not_found_cache: NotFoundCache,
+    pub open: bool,
+    pub overlays: Vec<PathBuf>,
+    pub pid_file: Option<PathBuf>,
+    pub port: u16,
+    pub port_retry: usize,
+    pub queue: usize,
+    pub quiet_errors: QuietErrors,
+    pub recv_buffer: Option<usize>,
+    pub redirects: RedirectRules,
+    pub redirect_http: Option<u16>,
+    pub request_deadline: Option<Duration>,
+    pub reuse_port: bool,
+    pub send_buffer: Option<usize>,
+    pub stats_interval: Option<Duration>,
     pub threads: usize,
-    pub verbose: bool,
+    pub throttle: u64,
+    pub timestamps: bool,
+    pub trust_proxy: bool,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub unix_socket: Option<PathBuf>,
+    pub upload: bool,
+    pub verbosity: Verbosity,
+    pub warnings: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            acme_dir: None,
             address: String::from("127.0.0.1"),
+            clock: Arc::new(SystemClock),
             port: 8080,
+            port_retry: 0,
+            queue: DEFAULT_QUEUE_LIMIT,
+            max_connections: None,
+            max_path_depth: DEFAULT_MAX_PATH_DEPTH,
+            max_segment_len: DEFAULT_MAX_SEGMENT_LEN,
+            allow_encoded_slash: false,
+            max_upload_size: DEFAULT_MAX_UPLOAD_SIZE,
+            connection_overflow: Overflow::default(),
+            quiet_errors: QuietErrors::default(),
+            reuse_port: false,
+            nodelay: true,
+            send_buffer: None,
+            stats_interval: None,
+            recv_buffer: None,
+            redirects: RedirectRules::default(),
+            redirect_http: None,
+            request_deadline: Some(DEFAULT_REQUEST_DEADLINE),
+            open: false,
+            overlays: Vec::new(),
+            blocked_methods: DEFAULT_BLOCKED_METHODS
+                .iter()
+                .map(|method| method.to_string())
+                .collect(),
+            cache: false,
+            cache_control: None,
+            cache_rules: CacheRules::default(),
+            check: false,
+            color: true,
+            timestamps: true,
+            trust_proxy: false,
+            log_file: None,
+            tls_cert: None,
+            tls_key: None,
+            unix_socket: None,
+            pid_file: None,
+            upload: false,
+            log_format: LogFormat::default(),
+            log_path_width: tui::DEFAULT_PATH_WIDTH,
+            log_rotate_keep: DEFAULT_LOG_ROTATE_KEEP,
+            log_rotate_size: None,
             base_dir: env::current_dir().unwrap(),
-            threads: 4,
-            verbose: true,
+            base_link: BaseLink::new(env::current_dir().unwrap()),
+            follow_base_symlink: false,
+            threads: default_threads(),
+            delay: 0,
+            throttle: 0,
+            verbosity: Verbosity::Normal,
+            logo: true,
+            warnings: true,
             list_dir: true,
+            listing_cache: ListingCache::default(),
+            listing_cache_ttl: None,
+            health: false,
+            manifest: false,
+            manifest_cache: ManifestCache::default(),
+            manifest_max_depth: DEFAULT_MANIFEST_MAX_DEPTH,
+            manifest_max_entries: DEFAULT_MANIFEST_MAX_ENTRIES,
+            not_found_cache: NotFoundCache::default(),
+            live_reload: false,
+            mime_overrides: HashMap::new(),
+            default_mime: None,
+            ignore: IgnoreRules::default(),
+            ignore_file: true,
+            config: None,
+            config_file: true,
         }
     }
 }
@@ -63,14 +586,15 @@ impl Default for Config {
 impl Config {
     /// Create a new user configuration from environment arguments.
     ///
-    /// Read and parse environment arguments from the user and collect them
-    /// into a [`Config`] struct.
+    /// Thin wrapper around [`Config::from_args`] for the servum binary. Read and
+    /// parse environment arguments from the user and collect them into a
+    /// [`Config`] struct.
     ///
-    /// If errors are encountered or the help menu is requested, the current
-    /// process will be exit with code `1` (error) or `0` (help) accordingly.
-    pub fn new() -> Config {
+    /// Returns what the user asked for, e.g. to run the server or print the
+    /// help menu, or an [`Error::Cli`] if the arguments can't be parsed.
+    #[allow(clippy::new_ret_no_self)] // Mirrors `Config::from_args`
+    pub fn new() -> Result<ConfigOutcome, Error> {
         let mut args: Vec<String> = env::args().skip(1).collect();
-        let mut conf = Config::default();
 
         // Skip first element (executable name, i.e. "servum") if being
         // invoked from cargo
@@ -79,169 +603,2706 @@ impl Config {
             args.remove(0);
         }
 
-        // Insert base-dir if first argument is <BASE_DIR>
-        if !args.is_empty() && !args[0].starts_with('-') {
-            args.insert(0, String::from("--base-dir"));
+        Ok(Config::from_args(args)?)
+    }
+
+    /// Create a new user configuration from a list of arguments.
+    ///
+    /// The arguments must not include the executable name. An argument that is
+    /// not an option, i.e. doesn't start with `-`, is used as the base
+    /// directory, as is any argument following `--`. If the first argument is
+    /// `completions`, the completion script for the shell given next is
+    /// returned instead. Once the
+    /// arguments are parsed, the `.servumignore` file of the base directory is
+    /// read, unless disabled.
+    ///
+    /// Options are applied in order of precedence: defaults, then the config
+    /// file, then `SERVUM_*` environment variables, then arguments. The config
+    /// file is the one given with `--config`, or otherwise the `servum.toml`
+    /// file in the base directory, if any. See [`Config::load_config`].
+    ///
+    /// Returns the [`ConfigOutcome`] of the arguments, i.e. either a
+    /// configuration to run the server with or the help or version text to
+    /// print, or a [`CliError`] on unknown arguments or invalid values.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::cli::{tui::Verbosity, Config, ConfigOutcome};
+    /// let args = vec!["--port", "3000", "--quiet"];
+    /// let outcome = Config::from_args(args.into_iter().map(String::from));
+    ///
+    /// match outcome {
+    ///     Ok(ConfigOutcome::Run(config)) => {
+    ///         assert_eq!(config.port, 3000);
+    ///         assert_eq!(config.verbosity, Verbosity::Quiet);
+    ///     }
+    ///     _ => panic!("Expected a config"),
+    /// }
+    /// ```
+    pub fn from_args<I>(args: I) -> Result<ConfigOutcome, CliError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let args: Vec<String> = args.into_iter().collect();
+
+        if args.first().is_some_and(|arg| arg == "completions") {
+            return Config::completions(&args[1..]);
         }
 
-        let showed_help = Config::parse_args(&args, &mut conf).unwrap_or_else(|e| {
-            eprintln!(
-                "Error while parsing arguments: {}\nUse --help for more information on available arguments",
-                e
-            );
-            std::process::exit(1);
-        });
+        // Find the config file from the environment and arguments first, as
+        // they may change its location or the base directory
+        let mut probe = Config::default();
+        probe.probe_env(|name| env::var(name).ok());
+
+        if let Some(outcome) = probe.probe_args(&args) {
+            return Ok(outcome);
+        }
+
+        let mut conf = Config::default();
+
+        if let Some(path) = probe.config_path() {
+            conf.load_config(&path)?;
+        }
+
+        conf.apply_env(|name| env::var(name).ok())?;
+        Config::parse_args(&args, &mut conf)?;
+
+        conf.load_ignore_file().map_err(|e| {
+            io::Error::new(e.kind(), format!("{}: {}", ignore::IGNORE_FILE, e))
+        })?;
+
+        Ok(ConfigOutcome::Run(conf))
+    }
+
+    /// Take the options locating the config file, i.e. `config`, `no_config`
+    /// and `base_dir`, from the environment variables returned by `var`.
+    ///
+    /// Values are stored as given and invalid ones skipped, as they are only
+    /// checked when applying the environment after reading the config file.
+    fn probe_env<F>(&mut self, var: F)
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(path) = var(&env_name("--config")) {
+            self.config = Some(PathBuf::from(path));
+        }
+        if let Some(dir) = var(&env_name("--base-dir")) {
+            self.base_dir = PathBuf::from(dir);
+        }
+        if let Some(enable) =
+            var(&env_name("--no-config")).as_deref().and_then(env_flag)
+        {
+            self.config_file = !enable;
+        }
+    }
+
+    /// Take the options locating the config file, i.e. `--config`,
+    /// `--no-config` and the base directory, from the arguments `args`.
+    ///
+    /// Like [`Config::probe_env`], no other option is applied and no argument
+    /// checked, which is left to [`Config::parse_args`] once the config file
+    /// is read. Returns the outcome of `--help` or `--version` if given.
+    fn probe_args(&mut self, args: &[String]) -> Option<ConfigOutcome> {
+        let mut it = args.iter().map(String::as_str);
+        let mut positional = false;
+
+        while let Some(el) = it.next() {
+            if el == "--" && !positional {
+                positional = true;
+                continue;
+            }
+
+            if positional || !el.starts_with('-') {
+                self.base_dir = PathBuf::from(el);
+                continue;
+            }
+
+            match el {
+                "-h" => return Some(ConfigOutcome::Help(Config::help_short())),
+                "--help" => {
+                    return Some(ConfigOutcome::Help(Config::help_long()))
+                }
+                "-V" | "--version" => {
+                    return Some(ConfigOutcome::Version(Config::version()))
+                }
+                _ => (),
+            }
+
+            let (arg, val) = match el.split_once('=') {
+                Some((arg, val)) => (arg, Some(val)),
+                None => (el, None),
+            };
+            let opt = match options::find(arg) {
+                Some(opt) => opt,
+                None => continue,
+            };
+            // Skip the values of other options, which may start with a dash
+            let val = match (opt.value, val) {
+                (Some(_), None) => it.next(),
+                (_, val) => val,
+            };
+
+            match (opt.long, val) {
+                ("config", Some(val)) => self.config = Some(PathBuf::from(val)),
+                ("base-dir", Some(val)) => self.base_dir = PathBuf::from(val),
+                ("no-config", None) => self.config_file = false,
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    /// Parse the arguments of the `completions` command, i.e. the shell to
+    /// print the completion script for.
+    fn completions(args: &[String]) -> Result<ConfigOutcome, CliError> {
+        let shell = match args {
+            [] => {
+                return Err(CliError::MissingVal(String::from("completions")))
+            }
+            [shell] => shell,
+            [_, arg, ..] => return Err(CliError::InvalidArg(arg.clone())),
+        };
+        let shell: Shell = shell
+            .parse()
+            .map_err(|_| CliError::invalid_val("completions", shell))?;
+
+        Ok(ConfigOutcome::Completions(completions::script(shell)))
+    }
+
+    /// Return the path of the config file to read, if any.
+    ///
+    /// This is the `config` option if set, or otherwise the `servum.toml` file
+    /// in the base directory if it exists. Returns [`None`] if the
+    /// `config_file` option is disabled.
+    pub fn config_path(&self) -> Option<PathBuf> {
+        if !self.config_file {
+            return None;
+        }
+
+        match &self.config {
+            Some(path) => Some(path.clone()),
+            None => {
+                let path = self.base_dir.join(CONFIG_FILE);
+                path.is_file().then_some(path)
+            }
+        }
+    }
+
+    /// Read options from a config file.
+    ///
+    /// Config files use a subset of TOML. Top-level keys are named after the
+    /// long form of an option, e.g. `port = 3000` for `--port 3000`. Flags take
+    /// a boolean, e.g. `quiet = true`, and repeatable options, such as
+    /// `--ignore`, take an array of strings. Options taking `KEY=VALUE` pairs,
+    /// such as `--mime`, can also be given as a table:
+    ///
+    /// ```toml
+    /// port = 3000
+    /// ignore = ["*.log", "drafts/"]
+    ///
+    /// [mime]
+    /// glsl = "text/plain"
+    /// ```
+    ///
+    /// Relative paths for `acme-dir`, `base-dir`, `log-file`, `mime-file`,
+    /// `overlay`, `pid-file`, `tls-cert` and `tls-key` are resolved from the
+    /// directory containing the config file. Syntax errors and invalid
+    /// options are reported as [`CliError::ConfigFile`] errors mentioning the
+    /// line number.
+    pub fn load_config(&mut self, path: &Path) -> Result<(), CliError> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+        })?;
+        let items = toml::parse(&contents).map_err(|e| {
+            CliError::ConfigFile(path.to_path_buf(), e.line, e.msg)
+        })?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        for item in items {
+            let line = item.line;
+
+            self.apply_config_item(item, dir).map_err(|e| {
+                CliError::ConfigFile(path.to_path_buf(), line, e.to_string())
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply a single item of a config file located in `dir`.
+    fn apply_config_item(
+        &mut self,
+        item: toml::Item,
+        dir: &Path,
+    ) -> Result<(), CliError> {
+        let key = item.key.replace('_', "-");
+        let option = format!("--{}", item.table.as_ref().unwrap_or(&key));
+
+        if option == "--config" || option == "--no-config" {
+            return Err(CliError::InvalidArg(option));
+        }
+
+        match (item.table, item.value) {
+            (Some(_), toml::Value::String(val)) => {
+                self.set_option(&option, &format!("{}={}", key, val))
+            }
+            (None, toml::Value::String(val)) => {
+                self.set_option(&option, &config_value(&option, &val, dir))
+            }
+            (None, toml::Value::Integer(val)) => {
+                self.set_option(&option, &val.to_string())
+            }
+            (None, toml::Value::Bool(val)) => match self.set_flag(&option, val)
+            {
+                true => Ok(()),
+                false => Err(CliError::invalid_val(&key, &val.to_string())),
+            },
+            (None, toml::Value::Array(vals)) => {
+                vals.iter().try_for_each(|val| {
+                    self.set_option(&option, &config_value(&option, val, dir))
+                })
+            }
+            (Some(table), val) => Err(CliError::invalid_val(
+                &format!("{}.{}", table, key),
+                &val.to_string(),
+            )),
+        }
+    }
+
+    /// Read the `.servumignore` file in the base directory into the ignore
+    /// rules.
+    ///
+    /// Does nothing if the `ignore_file` option is disabled or if the file does
+    /// not exist. Invalid patterns are reported as
+    /// [`io::ErrorKind::InvalidData`] errors mentioning the line number.
+    /// A base directory that isn't a directory is left for
+    /// [`Config::validate`] to report.
+    pub fn load_ignore_file(&mut self) -> io::Result<()> {
+        if !self.ignore_file || !self.base_dir.is_dir() {
+            return Ok(());
+        }
+
+        let contents =
+            match fs::read_to_string(self.base_dir.join(ignore::IGNORE_FILE)) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+                Err(e) => return Err(e),
+            };
+
+        let rules = IgnoreRules::parse(&contents).map_err(|line| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid pattern on line {}", line),
+            )
+        })?;
+
+        self.ignore.extend(rules);
+        Ok(())
+    }
+
+    /// Check that the server can start with this configuration.
+    ///
+    /// Verifies that the base directory exists, is a directory and is
+    /// readable, see [`base_dir::scan`], that the address is valid, that the
+    /// TLS certificate and key can be loaded, that the port or the Unix socket
+    /// can be bound (the listener is dropped right away) and that the
+    /// directory of the log file exists. Ignore patterns and MIME overrides
+    /// are already checked while parsing.
+    ///
+    /// Returns the resolved configuration as `key = value` lines, using the
+    /// same keys and syntax as the config file, or the first failure.
+    pub fn validate(&self) -> Result<Vec<String>, CliError> {
+        base_dir::scan(&self.base_dir, &self.ignore)?;
+
+        if net::parse_address(&self.address).is_none() {
+            return Err(CliError::invalid_val("--address", &self.address));
+        }
+
+        self.tls_config()?;
 
-        if showed_help {
-            std::process::exit(0);
+        if self.redirect_http.is_some() && self.scheme() != "https" {
+            return Err(CliError::RequiredArg(
+                String::from("--redirect-http"),
+                String::from("--tls-cert"),
+            ));
         }
 
-        conf
+        match &self.unix_socket {
+            Some(path) => check_unix_socket(path)?,
+            None => {
+                let listener = match self.reuse_port {
+                    true => net::bind_reuse_port(
+                        &self.address,
+                        self.port,
+                        self.port_retry,
+                        self.threads,
+                    )?
+                    .remove(0),
+                    false => {
+                        net::bind(&self.address, self.port, self.port_retry)?
+                    }
+                };
+                net::set_buffer_sizes(
+                    &listener,
+                    self.send_buffer,
+                    self.recv_buffer,
+                )?;
+
+                // Bound while the main listener is held, so that the same
+                // port can't be used for both
+                if let Some(port) = self.redirect_http {
+                    server::bind_redirect(&self.address, port)?;
+                }
+            }
+        }
+
+        if self.log_rotate_size.is_some() && self.log_file.is_none() {
+            return Err(CliError::RequiredArg(
+                String::from("--log-rotate-size"),
+                String::from("--log-file"),
+            ));
+        }
+
+        if let Some(path) = &self.log_file {
+            let dir = match path.parent() {
+                Some(dir) if dir != Path::new("") => dir,
+                _ => Path::new("."),
+            };
+
+            if !dir.is_dir() {
+                return Err(CliError::IOError(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{}: No such directory", dir.display()),
+                )));
+            }
+        }
+
+        Ok(self.summary())
+    }
+
+    /// Return warnings about a configuration that is valid but likely not
+    /// what the user meant, e.g. serving an empty directory or a directory
+    /// with [sensitive entries] such as `.git`.
+    ///
+    /// The base directory is scanned with [`base_dir::scan`]. If it can't
+    /// be, no warnings are returned, as [`Config::validate`] fails anyway.
+    /// None are returned either if the `warnings` option is disabled.
+    ///
+    /// [sensitive entries]: base_dir::SENSITIVE_ENTRIES
+    pub fn startup_warnings(&self) -> Vec<String> {
+        let scan = match base_dir::scan(&self.base_dir, &self.ignore) {
+            Ok(scan) if self.warnings => scan,
+            _ => return Vec::new(),
+        };
+        let mut warnings = Vec::new();
+
+        if !scan.sensitive.is_empty() {
+            warnings.push(format!(
+                "{} contains {}, which can be downloaded by anyone reaching \
+                 the server. Leave them out with --ignore or a {} file.",
+                self.base_dir.display(),
+                scan.sensitive.join(", "),
+                ignore::IGNORE_FILE
+            ));
+        }
+
+        if self.upload && self.listing_cache_ttl.is_some() {
+            warnings.push(String::from(
+                "Directory listings aren't cached with --upload, so that \
+                 uploads show right away. --listing-cache-ttl has no effect.",
+            ));
+        }
+
+        if self.upload && self.cache {
+            warnings.push(String::from(
+                "Missing paths aren't cached with --upload, so that uploads \
+                 show right away. --cache has no effect.",
+            ));
+        }
+
+        if scan.entries == 0 {
+            warnings.push(format!(
+                "Serving an empty directory, {}. Did you mean to run a \
+                 build first?",
+                self.base_dir.display()
+            ));
+        } else if !scan.has_index && !self.list_dir {
+            warnings.push(format!(
+                "{} has no {} and directory listings are disabled, so / \
+                 will answer 404 Not Found",
+                self.base_dir.display(),
+                base_dir::INDEX_FILE
+            ));
+        }
+
+        warnings
+    }
+
+    /// Return the base directory to serve the current request from.
+    ///
+    /// This is `base_dir`, unless `follow_base_symlink` is set, in which case
+    /// `base_link` is resolved again if it wasn't within the last
+    /// [`BASE_LINK_TTL`](base_dir::BASE_LINK_TTL) of the `clock`. If it
+    /// can't be resolved at all, `base_dir` is used.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::cli::Config;
+    /// let config = Config::default();
+    /// assert_eq!(config.current_base_dir(), config.base_dir);
+    /// ```
+    pub fn current_base_dir(&self) -> Cow<'_, Path> {
+        if !self.follow_base_symlink {
+            return Cow::Borrowed(&self.base_dir);
+        }
+
+        match self.base_link.resolve(self.clock.instant()) {
+            Ok(dir) => Cow::Owned(dir),
+            Err(_) => Cow::Borrowed(&self.base_dir),
+        }
+    }
+
+    /// Return the directories files are served from: the
+    /// [current base directory](Config::current_base_dir), followed by the
+    /// `overlays` in order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::cli::Config;
+    /// use std::path::PathBuf;
+    ///
+    /// let config = Config {
+    ///     base_dir: PathBuf::from("dist"),
+    ///     overlays: vec![PathBuf::from("public")],
+    ///     ..Config::default()
+    /// };
+    /// assert_eq!(config.roots(), [PathBuf::from("dist"), PathBuf::from("public")]);
+    /// ```
+    pub fn roots(&self) -> Vec<Cow<'_, Path>> {
+        let mut roots = vec![self.current_base_dir()];
+        roots.extend(self.overlays.iter().map(|dir| Cow::Borrowed(&**dir)));
+        roots
+    }
+
+    /// Return the URL scheme the server is reached with, i.e. `https` if TLS
+    /// is configured and `http` otherwise.
+    pub fn scheme(&self) -> &'static str {
+        match self.tls_cert.is_some() && self.tls_key.is_some() {
+            true => "https",
+            false => "http",
+        }
+    }
+
+    /// Load the TLS server configuration from `tls_cert` and `tls_key`.
+    ///
+    /// Returns [`None`] if TLS is not configured, or an error if only one of
+    /// the files is given or they cannot be loaded.
+    #[cfg(feature = "tls")]
+    pub fn tls_config(
+        &self,
+    ) -> Result<Option<Arc<rustls::ServerConfig>>, CliError> {
+        match (&self.tls_cert, &self.tls_key) {
+            (Some(cert), Some(key)) => Ok(Some(tls::server_config(cert, key)?)),
+            (Some(_), None) => Err(CliError::RequiredArg(
+                String::from("--tls-cert"),
+                String::from("--tls-key"),
+            )),
+            (None, Some(_)) => Err(CliError::RequiredArg(
+                String::from("--tls-key"),
+                String::from("--tls-cert"),
+            )),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Check that TLS is not configured, as servum was built without the
+    /// `tls` feature.
+    #[cfg(not(feature = "tls"))]
+    pub fn tls_config(&self) -> Result<Option<()>, CliError> {
+        match self.tls_cert.is_some() || self.tls_key.is_some() {
+            true => Err(CliError::IOError(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "servum was built without TLS support, rebuild it with \
+                 `--features tls` to use --tls-cert and --tls-key",
+            ))),
+            false => Ok(None),
+        }
+    }
+
+    /// Return the options differing from [`Config::default`], using the
+    /// keys of the config file, as compact `key=value` entries, e.g.
+    /// `["no-list-dir", "cache-control=no-cache"]`.
+    ///
+    /// Boolean options turned on are given by their key alone, and lists
+    /// are joined with commas.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::cli::Config;
+    /// let config = Config {
+    ///     list_dir: false,
+    ///     delay: 200,
+    ///     ..Config::default()
+    /// };
+    ///
+    /// assert_eq!(config.diff_from_default(), ["delay=200", "no-list-dir"]);
+    /// assert!(Config::default().diff_from_default().is_empty());
+    /// ```
+    pub fn diff_from_default(&self) -> Vec<String> {
+        let mut defaults = Config::default().values();
+        // Options only listed along with the option they depend on
+        defaults.extend([
+            (
+                "max-upload-size",
+                toml::Value::Integer(DEFAULT_MAX_UPLOAD_SIZE as i64),
+            ),
+            (
+                "log-rotate-keep",
+                toml::Value::Integer(DEFAULT_LOG_ROTATE_KEEP as i64),
+            ),
+            (
+                "manifest-max-depth",
+                toml::Value::Integer(DEFAULT_MANIFEST_MAX_DEPTH as i64),
+            ),
+            (
+                "manifest-max-entries",
+                toml::Value::Integer(DEFAULT_MANIFEST_MAX_ENTRIES as i64),
+            ),
+        ]);
+
+        self.values()
+            .into_iter()
+            .filter(|(key, value)| {
+                !defaults.iter().any(|(k, v)| k == key && v == value)
+            })
+            .map(|(key, value)| match value {
+                toml::Value::Bool(true) => key.to_string(),
+                toml::Value::String(s) => format!("{}={}", key, s),
+                toml::Value::Array(values) => {
+                    format!("{}={}", key, values.join(","))
+                }
+                value => format!("{}={}", key, value),
+            })
+            .collect()
+    }
+
+    /// Return the configuration as `key = value` lines, as printed by
+    /// `--check`.
+    fn summary(&self) -> Vec<String> {
+        self.values()
+            .into_iter()
+            .map(|(key, value)| format!("{} = {}", key, value))
+            .collect()
+    }
+
+    /// Return the configuration as values of the keys of the config file.
+    fn values(&self) -> Vec<(&'static str, toml::Value)> {
+        let path =
+            |path: &Path| toml::Value::String(path.display().to_string());
+        let mut mime: Vec<String> = self
+            .mime_overrides
+            .iter()
+            .map(|(ext, mime)| format!("{}={}", ext, mime))
+            .collect();
+        mime.sort();
+
+        let mut values = vec![
+            ("address", toml::Value::String(self.address.clone())),
+            ("port", toml::Value::Integer(self.port.into())),
+            ("port-retry", toml::Value::Integer(self.port_retry as i64)),
+            ("base-dir", path(&self.base_dir)),
+            ("threads", toml::Value::Integer(self.threads as i64)),
+            ("queue", toml::Value::Integer(self.queue as i64)),
+            (
+                "connection-overflow",
+                toml::Value::String(self.connection_overflow.to_string()),
+            ),
+            ("delay", toml::Value::Integer(self.delay as i64)),
+            ("throttle", toml::Value::Integer(self.throttle as i64)),
+            (
+                "request-deadline",
+                toml::Value::Integer(
+                    self.request_deadline.map_or(0, |d| d.as_secs() as i64),
+                ),
+            ),
+            (
+                "max-path-depth",
+                toml::Value::Integer(self.max_path_depth as i64),
+            ),
+            (
+                "max-segment-length",
+                toml::Value::Integer(self.max_segment_len as i64),
+            ),
+        ];
+
+        if !self.overlays.is_empty() {
+            let overlays = self
+                .overlays
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect();
+            values.push(("overlay", toml::Value::Array(overlays)));
+        }
+        if let Some(acme_dir) = &self.acme_dir {
+            values.push(("acme-dir", path(acme_dir)));
+        }
+        if let Some(max) = self.max_connections {
+            values.push(("max-connections", toml::Value::Integer(max as i64)));
+        }
+        if self.upload {
+            values.push((
+                "max-upload-size",
+                toml::Value::Integer(self.max_upload_size as i64),
+            ));
+        }
+        if self.manifest {
+            values.extend([
+                (
+                    "manifest-max-depth",
+                    toml::Value::Integer(self.manifest_max_depth as i64),
+                ),
+                (
+                    "manifest-max-entries",
+                    toml::Value::Integer(self.manifest_max_entries as i64),
+                ),
+            ]);
+        }
+        if let Some(size) = self.send_buffer {
+            values.push(("send-buffer", toml::Value::Integer(size as i64)));
+        }
+        if let Some(size) = self.recv_buffer {
+            values.push(("recv-buffer", toml::Value::Integer(size as i64)));
+        }
+        if let Some(default_mime) = &self.default_mime {
+            values.push((
+                "default-mime",
+                toml::Value::String(default_mime.clone()),
+            ));
+        }
+        if !mime.is_empty() {
+            values.push(("mime", toml::Value::Array(mime)));
+        }
+        if let Some(cache_control) = &self.cache_control {
+            values.push((
+                "cache-control",
+                toml::Value::String(cache_control.clone()),
+            ));
+        }
+        if !self.cache_rules.is_empty() {
+            let rules = self.cache_rules.rules().collect();
+            values.push(("cache-rule", toml::Value::Array(rules)));
+        }
+        if !self.redirects.is_empty() {
+            let rules = self.redirects.rules().collect();
+            values.push(("redirect", toml::Value::Array(rules)));
+        }
+        if !self.ignore.is_empty() {
+            let ignore = self.ignore.patterns().map(String::from).collect();
+            values.push(("ignore", toml::Value::Array(ignore)));
+        }
+        if let Some(interval) = self.stats_interval {
+            values.push((
+                "stats-interval",
+                toml::Value::Integer(interval.as_secs() as i64),
+            ));
+        }
+        if let Some(ttl) = self.listing_cache_ttl {
+            values.push((
+                "listing-cache-ttl",
+                toml::Value::Integer(ttl.as_secs() as i64),
+            ));
+        }
+        if let Some(log_file) = &self.log_file {
+            values.push(("log-file", path(log_file)));
+        }
+        if let Some(size) = self.log_rotate_size {
+            values.extend([
+                ("log-rotate-size", toml::Value::Integer(size as i64)),
+                (
+                    "log-rotate-keep",
+                    toml::Value::Integer(self.log_rotate_keep as i64),
+                ),
+            ]);
+        }
+        if let Some(tls_cert) = &self.tls_cert {
+            values.push(("tls-cert", path(tls_cert)));
+        }
+        if let Some(tls_key) = &self.tls_key {
+            values.push(("tls-key", path(tls_key)));
+        }
+        if let Some(port) = self.redirect_http {
+            values.push(("redirect-http", toml::Value::Integer(port.into())));
+        }
+        if let Some(unix_socket) = &self.unix_socket {
+            values.push(("unix-socket", path(unix_socket)));
+        }
+        if let Some(pid_file) = &self.pid_file {
+            values.push(("pid-file", path(pid_file)));
+        }
+
+        values.extend([
+            (
+                "log-format",
+                toml::Value::String(self.log_format.to_string()),
+            ),
+            (
+                "quiet-errors",
+                toml::Value::Array(self.quiet_errors.values()),
+            ),
+            (
+                "block-method",
+                toml::Value::Array(self.blocked_methods.clone()),
+            ),
+            (
+                "log-path-width",
+                toml::Value::Integer(self.log_path_width as i64),
+            ),
+            ("no-timestamps", toml::Value::Bool(!self.timestamps)),
+            ("no-color", toml::Value::Bool(!self.color)),
+            ("open", toml::Value::Bool(self.open)),
+            (
+                "quiet",
+                toml::Value::Bool(self.verbosity == Verbosity::Quiet),
+            ),
+            (
+                "silent",
+                toml::Value::Bool(self.verbosity == Verbosity::Silent),
+            ),
+            (
+                "verbose",
+                toml::Value::Bool(self.verbosity == Verbosity::Debug),
+            ),
+            ("no-logo", toml::Value::Bool(!self.logo)),
+            ("no-warnings", toml::Value::Bool(!self.warnings)),
+            ("no-list-dir", toml::Value::Bool(!self.list_dir)),
+            (
+                "follow-base-symlink",
+                toml::Value::Bool(self.follow_base_symlink),
+            ),
+            ("health", toml::Value::Bool(self.health)),
+            ("cache", toml::Value::Bool(self.cache)),
+            ("manifest", toml::Value::Bool(self.manifest)),
+            ("live-reload", toml::Value::Bool(self.live_reload)),
+            ("reuse-port", toml::Value::Bool(self.reuse_port)),
+            ("trust-proxy", toml::Value::Bool(self.trust_proxy)),
+            (
+                "allow-encoded-slash",
+                toml::Value::Bool(self.allow_encoded_slash),
+            ),
+            ("no-nodelay", toml::Value::Bool(!self.nodelay)),
+            ("no-ignore-file", toml::Value::Bool(!self.ignore_file)),
+            ("upload", toml::Value::Bool(self.upload)),
+        ]);
+
+        values
     }
 
     /// Parse environment arguments and update a user [`Config`] instance
     ///
     /// This function may return an error when it encounters an unknown
-    /// argument or an invalid value.
-    ///
-    /// If parsing succeeds, the function will return a boolean indicating
-    /// whether the help menu has been shown (`true`), indicating that the
-    /// program may exit normally (exit code `0`), or not, meaning the program
-    /// can continue.
-    fn parse_args<'a>(
-        args: &'a [String],
+    /// argument or an invalid value, or when an option is repeated or
+    /// conflicts with another one (see [`options::CONFLICTS`]). The base
+    /// directory may be given once, either with `--base-dir` or as a
+    /// positional argument anywhere, e.g. after `--`.
+    ///
+    /// If parsing succeeds, the function will return the help or version
+    /// [`ConfigOutcome`] if requested, indicating that the program may exit
+    /// normally (exit code `0`), or [`None`], meaning the program can continue.
+    fn parse_args(
+        args: &[String],
         conf: &mut Config,
-    ) -> Result<bool, CliError<'a>> {
+    ) -> Result<Option<ConfigOutcome>, CliError> {
         let mut it = args.iter();
+        let mut seen = Vec::new();
+        let mut positional = false;
 
         while let Some(el) = it.next() {
-            let mut el = el.as_str();
+            let el = el.as_str();
 
-            // Parse flags first
-            match el {
-                "-q" | "--quiet" => {
-                    conf.verbose = false;
-                    continue;
-                }
-                "--no-list-dir" => {
-                    conf.list_dir = false;
-                    continue;
+            if el == "--" && !positional {
+                positional = true;
+                continue;
+            }
+
+            if positional || !el.starts_with('-') {
+                if seen.contains(&"base-dir") {
+                    return Err(CliError::DuplicateArg(String::from(
+                        "<BASE_DIR>",
+                    )));
                 }
+
+                seen.push("base-dir");
+                conf.set_option("--base-dir", el)?;
+                continue;
+            }
+
+            // Parse help and version first
+            match el {
                 "-h" => {
-                    println!("{}", Config::help_short());
-                    return Ok(true);
+                    return Ok(Some(ConfigOutcome::Help(Config::help_short())))
                 }
                 "--help" => {
-                    println!("{}", Config::help_long());
-                    return Ok(true);
+                    return Ok(Some(ConfigOutcome::Help(Config::help_long())))
+                }
+                "-V" | "--version" => {
+                    return Ok(Some(ConfigOutcome::Version(Config::version())))
                 }
                 _ => (),
             }
 
-            let val = match el.contains('=') {
-                false => it.next().map(|v| v.as_str()),
-                true => {
-                    let mut temp = el.split('=');
-                    el = temp.next().unwrap();
-                    temp.next()
-                }
+            let (arg, val) = match el.split_once('=') {
+                Some((arg, val)) => (arg, Some(val)),
+                None => (el, None),
             };
-            let val = val.ok_or(CliError::MissingVal(el))?;
+            let opt = options::find(arg)
+                .ok_or_else(|| CliError::InvalidArg(arg.to_string()))?;
 
-            match el {
-                "--base-dir" => {
-                    conf.base_dir = PathBuf::from(val).canonicalize()?
+            Config::see(&mut seen, arg)?;
+
+            match (opt.value, val) {
+                (None, None) => {
+                    conf.set_flag(arg, true);
                 }
-                "-a" | "--address" => conf.address = val.to_string(),
-                "-p" | "--port" => {
-                    conf.port = val
-                        .parse::<usize>()
-                        .map_err(|_| CliError::InvalidVal("--port", val))?
+                (None, Some(_)) => {
+                    return Err(CliError::InvalidArg(arg.to_string()))
                 }
-                "-t" | "--threads" => {
-                    conf.threads = val
-                        .parse::<usize>()
-                        .map_err(|_| CliError::InvalidVal("--threads", val))?
+                (Some(_), val) => {
+                    let val = val
+                        .or_else(|| it.next().map(String::as_str))
+                        .ok_or_else(|| CliError::MissingVal(arg.to_string()))?;
+
+                    conf.set_option(arg, val)?;
                 }
-                arg => return Err(CliError::InvalidArg(arg)),
             }
         }
 
-        Ok(false)
+        Ok(None)
     }
 
-    /// Return the help menu header common to all help menus.
-    fn help_header() -> &'static str {
-        "servum 1.0.0
-Ulysse McConnell <ulysse.mcconnell+dev@protonmail.com>
+    /// Record that the argument `arg` was given, using the list of options
+    /// `seen` so far.
+    ///
+    /// Returns an error if a non-repeatable option is given again or if
+    /// the option conflicts with a previously seen one. Unknown arguments are
+    /// ignored.
+    fn see(seen: &mut Vec<&'static str>, arg: &str) -> Result<(), CliError> {
+        let opt = match options::find(arg) {
+            Some(opt) => opt,
+            None => return Ok(()),
+        };
 
-servum is a simple, bare-bones and fast static web server for local
-(web-)development. HTTP only.
+        if !opt.repeatable && seen.contains(&opt.long) {
+            return Err(CliError::DuplicateArg(format!("--{}", opt.long)));
+        }
 
-Project home page: https://github.com/umcconnell/servum
+        for (a, b) in options::CONFLICTS {
+            let other = match opt.long {
+                long if long == *a => b,
+                long if long == *b => a,
+                _ => continue,
+            };
 
+            if seen.contains(other) {
+                return Err(CliError::ConflictingArgs(
+                    format!("--{}", other),
+                    format!("--{}", opt.long),
+                ));
+            }
+        }
 
-USAGE:
-    servum 
-    servum [BASE_DIR]
-    servum [BASE_DIR] [OPTIONS]"
+        seen.push(opt.long);
+        Ok(())
     }
 
-    /// Return the help menu in its verbose form.
-    pub fn help_long() -> String {
-        [
-            Config::help_header(),
-            "
-
-ARGS:
-    <BASE_DIR>
-            Base directory to serve content from. All sub-directories and files
-            will be served. Default is the current directory.
+    /// Enable or disable a boolean flag, such as `--quiet`.
+    ///
+    /// Returns `false` if `flag` is not a known flag.
+    fn set_flag(&mut self, flag: &str, enable: bool) -> bool {
+        match flag {
+            "-q" | "--quiet" if enable => self.verbosity = Verbosity::Quiet,
+            "--silent" if enable => self.verbosity = Verbosity::Silent,
+            "-v" | "--verbose" if enable => self.verbosity = Verbosity::Debug,
+            "-q" | "--quiet" | "--silent" | "-v" | "--verbose" => {
+                self.verbosity = Verbosity::Normal
+            }
+            "--no-logo" => self.logo = !enable,
+            "--no-warnings" => self.warnings = !enable,
+            "--no-list-dir" => self.list_dir = !enable,
+            "--no-ignore-file" => self.ignore_file = !enable,
+            "--upload" => self.upload = enable,
+            "--no-config" => self.config_file = !enable,
+            "--open" => self.open = enable,
+            "--follow-base-symlink" => self.follow_base_symlink = enable,
+            "--health" => self.health = enable,
+            "--cache" => self.cache = enable,
+            "--manifest" => self.manifest = enable,
+            "--live-reload" => self.live_reload = enable,
+            "--reuse-port" => self.reuse_port = enable,
+            "--trust-proxy" => self.trust_proxy = enable,
+            "--allow-encoded-slash" => self.allow_encoded_slash = enable,
+            "--no-nodelay" => self.nodelay = !enable,
+            "--check" => self.check = enable,
+            "--no-color" => self.color = !enable,
+            "--no-timestamps" => self.timestamps = !enable,
+            "--public" if enable => self.address = String::from("0.0.0.0"),
+            "--public" => (),
+            _ => return false,
+        }
 
-OPTIONS:
-    -a, --address <STRING>:
-            Address to listen on. Default is the loopback address 127.0.0.1,
-            i.e localhost.
-    -p, --port <NUM>:
-            Port to listen on. Note that some ports, such as port 80 (HTTP)
-            require elevated privileges to bind to and may already be in use.
-            Default is 8080.
-    -t, --threads <NUM>:
-            Number of parallel threads to handle request with. Must be at least
-            1. Default is 4.
-    -q, --quiet:
-            Don't be verbose and stop printing information about incoming
-            requests.
-        --no-list-dir:
-            Don't list directories and prevent directory traversals by returning
-            \"403 Permission Denied\" responses when attempting to access a
-            directory.
-    -h, --help:
-            Show this help. Use -h for a quick summary of available commands and
-            --help for a more detailed view.
-",
-        ]
-        .concat()
+        true
+    }
+
+    /// Set an option taking a value, such as `--port`.
+    ///
+    /// Returns an error if `arg` is not a known option or if the value is
+    /// invalid.
+    fn set_option(&mut self, arg: &str, val: &str) -> Result<(), CliError> {
+        match arg {
+            "--base-dir" => {
+                self.base_dir = PathBuf::from(val).canonicalize()?;
+                self.base_link = BaseLink::new(env::current_dir()?.join(val));
+            }
+            "-a" | "--address" => {
+                self.address = net::parse_address(val)
+                    .ok_or_else(|| CliError::invalid_val("--address", val))?
+            }
+            "-p" | "--port" => {
+                self.port = val
+                    .parse::<u16>()
+                    .map_err(|_| CliError::invalid_val("--port", val))?
+            }
+            "--queue" => {
+                self.queue = val
+                    .parse::<usize>()
+                    .map_err(|_| CliError::invalid_val("--queue", val))?
+            }
+            "--max-connections" => {
+                self.max_connections = Some(
+                    val.parse::<usize>()
+                        .ok()
+                        .filter(|&max| max >= 1)
+                        .ok_or_else(|| {
+                            CliError::invalid_val("--max-connections", val)
+                        })?,
+                )
+            }
+            "--send-buffer" | "--recv-buffer" => {
+                let size = val
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&size| size >= 1 && size <= i32::MAX as usize)
+                    .ok_or_else(|| CliError::invalid_val(arg, val))?;
+
+                match arg {
+                    "--send-buffer" => self.send_buffer = Some(size),
+                    _ => self.recv_buffer = Some(size),
+                }
+            }
+            "--connection-overflow" => {
+                self.connection_overflow = val.parse().map_err(|_| {
+                    CliError::invalid_val("--connection-overflow", val)
+                })?
+            }
+            "--port-retry" => {
+                self.port_retry = val
+                    .parse::<usize>()
+                    .map_err(|_| CliError::invalid_val("--port-retry", val))?
+            }
+            "-t" | "--threads" => {
+                self.threads = match val {
+                    "auto" => default_threads(),
+                    val => val
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&threads| threads >= 1)
+                        .ok_or_else(|| {
+                            CliError::invalid_val("--threads", val)
+                        })?,
+                }
+            }
+            "--mime" => {
+                let (ext, mime) = mime::parse_override(val)
+                    .ok_or_else(|| CliError::invalid_val("--mime", val))?;
+                self.mime_overrides.insert(ext, mime);
+            }
+            "--ignore" => self
+                .ignore
+                .add(val)
+                .ok_or_else(|| CliError::invalid_val("--ignore", val))?,
+            "--default-mime" => self.default_mime = Some(val.to_string()),
+            "--cache-control" => {
+                let value = http::validate_header("Cache-Control", val)
+                    .map_err(|_| {
+                        CliError::invalid_val("--cache-control", val)
+                    })?;
+                self.cache_control = Some(value.to_string())
+            }
+            "--quiet-errors" => self
+                .quiet_errors
+                .add(val)
+                .ok_or_else(|| CliError::invalid_val("--quiet-errors", val))?,
+            "--cache-rule" => self
+                .cache_rules
+                .add(val)
+                .ok_or_else(|| CliError::invalid_val("--cache-rule", val))?,
+            "--config" => self.config = Some(PathBuf::from(val)),
+            "--unix-socket" => self.unix_socket = Some(PathBuf::from(val)),
+            "--pid-file" => self.pid_file = Some(PathBuf::from(val)),
+            "--log-file" => self.log_file = Some(PathBuf::from(val)),
+            "--acme-dir" => {
+                self.acme_dir = Some(PathBuf::from(val).canonicalize()?)
+            }
+            "--overlay" => {
+                let dir = PathBuf::from(val).canonicalize()?;
+                if !dir.is_dir() {
+                    return Err(CliError::invalid_val("--overlay", val));
+                }
+                self.overlays.push(dir);
+            }
+            "--delay" => {
+                self.delay = val
+                    .parse::<u64>()
+                    .map_err(|_| CliError::invalid_val("--delay", val))?
+            }
+            "--throttle" => {
+                self.throttle = val
+                    .parse::<u64>()
+                    .map_err(|_| CliError::invalid_val("--throttle", val))?
+            }
+            "--tls-cert" => self.tls_cert = Some(PathBuf::from(val)),
+            "--tls-key" => self.tls_key = Some(PathBuf::from(val)),
+            "--block-method" => {
+                if !http::is_token(val) {
+                    return Err(CliError::invalid_val("--block-method", val));
+                }
+                if !self.blocked_methods.iter().any(|method| method == val) {
+                    self.blocked_methods.push(val.to_string());
+                }
+            }
+            "--redirect" => self
+                .redirects
+                .add(val)
+                .ok_or_else(|| CliError::invalid_val("--redirect", val))?,
+            "--redirect-http" => {
+                self.redirect_http = Some(val.parse::<u16>().map_err(|_| {
+                    CliError::invalid_val("--redirect-http", val)
+                })?)
+            }
+            "--log-path-width" => {
+                self.log_path_width = val.parse::<usize>().map_err(|_| {
+                    CliError::invalid_val("--log-path-width", val)
+                })?
+            }
+            "--log-rotate-size" => {
+                self.log_rotate_size = Some(
+                    val.parse::<u64>()
+                        .ok()
+                        .filter(|&size| size >= 1)
+                        .ok_or_else(|| {
+                            CliError::invalid_val("--log-rotate-size", val)
+                        })?,
+                )
+            }
+            "--max-path-depth" | "--max-segment-length" => {
+                let max = val
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&max| max >= 1)
+                    .ok_or_else(|| CliError::invalid_val(arg, val))?;
+
+                match arg {
+                    "--max-path-depth" => self.max_path_depth = max,
+                    _ => self.max_segment_len = max,
+                }
+            }
+            "--request-deadline" => {
+                let secs = val.parse::<u64>().map_err(|_| {
+                    CliError::invalid_val("--request-deadline", val)
+                })?;
+                self.request_deadline =
+                    Some(Duration::from_secs(secs)).filter(|d| !d.is_zero())
+            }
+            "--listing-cache-ttl" => {
+                let secs = val
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|&secs| secs >= 1)
+                    .ok_or_else(|| {
+                        CliError::invalid_val("--listing-cache-ttl", val)
+                    })?;
+                self.listing_cache_ttl = Some(Duration::from_secs(secs))
+            }
+            "--stats-interval" => {
+                let secs = val
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|&secs| secs >= 1)
+                    .ok_or_else(|| {
+                        CliError::invalid_val("--stats-interval", val)
+                    })?;
+                self.stats_interval = Some(Duration::from_secs(secs))
+            }
+            "--manifest-max-depth" | "--manifest-max-entries" => {
+                let max = val
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&max| max >= 1)
+                    .ok_or_else(|| CliError::invalid_val(arg, val))?;
+
+                match arg {
+                    "--manifest-max-depth" => self.manifest_max_depth = max,
+                    _ => self.manifest_max_entries = max,
+                }
+            }
+            "--max-upload-size" => {
+                self.max_upload_size = val
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|&size| size >= 1)
+                    .ok_or_else(|| {
+                        CliError::invalid_val("--max-upload-size", val)
+                    })?
+            }
+            "--log-rotate-keep" => {
+                self.log_rotate_keep = val
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&keep| keep >= 1)
+                    .ok_or_else(|| {
+                        CliError::invalid_val("--log-rotate-keep", val)
+                    })?
+            }
+            "--log-format" => {
+                self.log_format = val
+                    .parse()
+                    .map_err(|_| CliError::invalid_val("--log-format", val))?
+            }
+            "--mime-file" => {
+                let contents = fs::read_to_string(val)?;
+                let overrides =
+                    mime::parse_override_file(&contents).map_err(|e| {
+                        CliError::ConfigFile(
+                            PathBuf::from(val),
+                            e.line,
+                            e.msg.into(),
+                        )
+                    })?;
+                self.mime_overrides.extend(overrides);
+            }
+            arg => return Err(CliError::InvalidArg(arg.to_string())),
+        }
+
+        Ok(())
+    }
+
+    /// Update the config from `SERVUM_*` environment variables.
+    ///
+    /// Each option in [`ENV_OPTIONS`] and flag in [`ENV_FLAGS`] can be set
+    /// with an environment variable named after it, e.g. `SERVUM_PORT` for
+    /// `--port` or `SERVUM_QUIET` for `--quiet`. Flags accept `1`, `true` or
+    /// `yes` to enable and `0`, `false`, `no` or an empty value to disable them,
+    /// overriding the config file. Following the [`NO_COLOR`] convention, a
+    /// non-empty `NO_COLOR` variable disables colors.
+    ///
+    /// [`NO_COLOR`]: https://no-color.org
+    ///
+    /// Variables are looked up using `var`, which allows injecting variables
+    /// in tests. Invalid values are reported as [`CliError::InvalidVal`]
+    /// mentioning the variable name.
+    fn apply_env<F>(&mut self, var: F) -> Result<(), CliError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        for option in ENV_OPTIONS {
+            let name = env_name(option);
+
+            if let Some(val) = var(&name) {
+                self.set_option(option, &val)
+                    .map_err(|_| CliError::invalid_val(&name, &val))?;
+            }
+        }
+
+        if var("NO_COLOR").is_some_and(|val| !val.is_empty()) {
+            self.color = false;
+        }
+
+        for flag in ENV_FLAGS {
+            let name = env_name(flag);
+
+            if let Some(val) = var(&name) {
+                let enable = env_flag(&val)
+                    .ok_or_else(|| CliError::invalid_val(&name, &val))?;
+                self.set_flag(flag, enable);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return the version line, e.g. `servum 1.0.3 (1a2b3c4)`.
+    ///
+    /// The version is taken from the crate manifest. The short git commit hash
+    /// is appended if it was available at build time.
+    pub fn version() -> String {
+        match option_env!("SERVUM_GIT_HASH") {
+            Some(hash) => format!("servum {} ({})", VERSION, hash),
+            None => format!("servum {}", VERSION),
+        }
+    }
+
+    /// Return the help menu header common to all help menus.
+    fn help_header() -> String {
+        Config::version()
+            + "
+Ulysse McConnell <ulysse.mcconnell+dev@protonmail.com>
+
+servum is a simple, bare-bones and fast static web server for local
+(web-)development. HTTP only.
+
+Project home page: https://github.com/umcconnell/servum
+
+
+USAGE:
+    servum [OPTIONS] [BASE_DIR]
+    servum [OPTIONS] -- <BASE_DIR>
+    servum completions <SHELL>"
+    }
+
+    /// Return the help menu in its verbose form.
+    pub fn help_long() -> String {
+        [
+            &Config::help_header(),
+            "
+
+ARGS:
+    <BASE_DIR>
+            Base directory to serve content from. All sub-directories and files
+            will be served. Default is the current directory. May be given
+            before, between or after options. Arguments following -- are
+            always taken as BASE_DIR, e.g. for directories starting with -.
+
+COMMANDS:
+    completions <SHELL>
+            Print the completion script for SHELL, one of bash, zsh, fish or
+            powershell, and exit.
+
+OPTIONS:
+",
+            &options::help_long()
+                .replace("{threads}", &default_threads().to_string()),
+            "
+ENVIRONMENT:
+    SERVUM_ADDRESS, SERVUM_BASE_DIR, SERVUM_CACHE_CONTROL, SERVUM_CONFIG,
+    SERVUM_DEFAULT_MIME, SERVUM_DELAY, SERVUM_LOG_FILE, SERVUM_LOG_FORMAT,
+    SERVUM_LOG_PATH_WIDTH, SERVUM_PORT, SERVUM_PORT_RETRY, SERVUM_QUEUE,
+    SERVUM_THREADS, SERVUM_THROTTLE, SERVUM_TLS_CERT, SERVUM_TLS_KEY:
+            Set the value of the corresponding option. Arguments take
+            precedence over environment variables.
+    SERVUM_NO_COLOR, SERVUM_NO_CONFIG, SERVUM_NO_IGNORE_FILE,
+    SERVUM_NO_LIST_DIR, SERVUM_NO_LOGO, SERVUM_NO_TIMESTAMPS, SERVUM_OPEN,
+    SERVUM_PUBLIC, SERVUM_QUIET, SERVUM_SILENT:
+            Enable the corresponding flag when set to 1, true or yes, or
+            disable it when set to 0, false or no.
+    NO_COLOR:
+            Don't color the request log when set to a non-empty value.
+",
+        ]
+        .concat()
     }
 
     /// Return the help menu in its short form.
     pub fn help_short() -> String {
         [
-            Config::help_header(),
+            &Config::help_header(),
             "
 
 ARGS:
     <BASE_DIR>    Optional directory to serve content from.
 
 OPTIONS:
-    -a, --address <STRING>:     Address to listen on. Default is 127.0.0.1
-    -p, --port <NUM>:           Port to listen on. Default is 8080
-    -t, --threads <NUM>:        Number of threads. Default is 4.
-    -q, --quiet:                Don't be verbose.
-        --no-list-dir:          Don't list directories.
-    -h, --help:                 Show this help. Use --help for more details.
 ",
+            &options::help_short()
+                .replace("{threads}", &default_threads().to_string()),
         ]
         .concat()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDir;
+    use std::path::Path;
+
+    // Helpers
+    fn outcome(args: &[&str]) -> Result<ConfigOutcome, CliError> {
+        Config::from_args(args.iter().map(|a| a.to_string()))
+    }
+
+    fn parse(args: &[&str]) -> Config {
+        match outcome(args) {
+            Ok(ConfigOutcome::Run(conf)) => conf,
+            res => panic!("Expected a config, got {:?}", res),
+        }
+    }
+
+    fn parse_err(args: &[&str]) -> CliError {
+        outcome(args).unwrap_err()
+    }
+
+    fn is_invalid_val(err: &CliError, arg: &str, val: &str) -> bool {
+        matches!(err, CliError::InvalidVal(a, v) if a == arg && v == val)
+    }
+
+    #[test]
+    fn defaults() {
+        let conf = parse(&[]);
+
+        assert_eq!(conf.address, "127.0.0.1");
+        assert_eq!(conf.port, 8080);
+        assert_eq!(conf.threads, default_threads());
+        assert_eq!(conf.base_dir, env::current_dir().unwrap());
+        assert_eq!(conf.verbosity, Verbosity::Normal);
+        assert!(conf.list_dir);
+    }
+
+    #[test]
+    fn options() {
+        let conf = parse(&[
+            "-a",
+            "0.0.0.0",
+            "--port",
+            "3000",
+            "-t",
+            "2",
+            "-q",
+            "--no-list-dir",
+        ]);
+
+        assert_eq!(conf.address, "0.0.0.0");
+        assert_eq!(conf.port, 3000);
+        assert_eq!(conf.threads, 2);
+        assert_eq!(conf.verbosity, Verbosity::Quiet);
+        assert!(!conf.list_dir);
+    }
+
+    #[test]
+    fn port_retry() {
+        assert_eq!(parse(&[]).port_retry, 0);
+        assert_eq!(parse(&["-p", "0", "--port-retry", "5"]).port_retry, 5);
+        assert!(is_invalid_val(
+            &parse_err(&["--port-retry", "-1"]),
+            "--port-retry",
+            "-1"
+        ));
+    }
+
+    #[test]
+    fn public() {
+        assert_eq!(parse(&["--public"]).address, "0.0.0.0");
+    }
+
+    #[test]
+    fn log_format() {
+        assert_eq!(parse(&[]).log_format, LogFormat::Pretty);
+        assert_eq!(
+            parse(&["--log-format", "json"]).log_format,
+            LogFormat::Json
+        );
+        assert!(is_invalid_val(
+            &parse_err(&["--log-format", "xml"]),
+            "--log-format",
+            "xml"
+        ));
+    }
+
+    #[test]
+    fn health() {
+        assert!(!parse(&[]).health);
+        assert!(parse(&["--health"]).health);
+    }
+
+    #[test]
+    fn cache() {
+        assert!(!parse(&[]).cache);
+        let conf = parse(&["--cache"]);
+        assert!(conf.cache);
+        assert_eq!(conf.diff_from_default(), ["cache"]);
+    }
+
+    #[test]
+    fn live_reload() {
+        assert!(!parse(&[]).live_reload);
+        assert!(parse(&["--live-reload"]).live_reload);
+    }
+
+    #[test]
+    fn reuse_port() {
+        assert!(!parse(&[]).reuse_port);
+        assert!(parse(&["--reuse-port", "-t", "4"]).reuse_port);
+        assert!(matches!(
+            parse_err(&["--reuse-port", "--max-connections", "8"]),
+            CliError::ConflictingArgs(_, _)
+        ));
+    }
+
+    #[test]
+    fn trust_proxy() {
+        assert!(!parse(&[]).trust_proxy);
+        assert!(parse(&["--trust-proxy"]).trust_proxy);
+    }
+
+    #[test]
+    fn pid_file() {
+        assert_eq!(parse(&[]).pid_file, None);
+        assert_eq!(
+            parse(&["--pid-file", "/run/servum.pid"]).pid_file,
+            Some(PathBuf::from("/run/servum.pid"))
+        );
+    }
+
+    #[test]
+    fn unix_socket() {
+        assert_eq!(parse(&[]).unix_socket, None);
+        assert_eq!(
+            parse(&["--unix-socket", "/run/servum.sock"]).unix_socket,
+            Some(PathBuf::from("/run/servum.sock"))
+        );
+
+        for other in [&["-p", "80"][..], &["--public"], &["--reuse-port"]] {
+            let mut args = vec!["--unix-socket", "servum.sock"];
+            args.extend(other);
+            assert!(matches!(
+                parse_err(&args),
+                CliError::ConflictingArgs(_, _)
+            ));
+        }
+    }
+
+    #[test]
+    fn socket_options() {
+        let conf = parse(&[]);
+        assert!(conf.nodelay);
+        assert_eq!((conf.send_buffer, conf.recv_buffer), (None, None));
+
+        let conf = parse(&[
+            "--no-nodelay",
+            "--send-buffer",
+            "262144",
+            "--recv-buffer=65536",
+        ]);
+        assert!(!conf.nodelay);
+        assert_eq!(conf.send_buffer, Some(262144));
+        assert_eq!(conf.recv_buffer, Some(65536));
+
+        assert!(is_invalid_val(
+            &parse_err(&["--send-buffer", "0"]),
+            "--send-buffer",
+            "0"
+        ));
+        assert!(is_invalid_val(
+            &parse_err(&["--recv-buffer", "4294967296"]),
+            "--recv-buffer",
+            "4294967296"
+        ));
+    }
+
+    #[test]
+    fn open() {
+        assert!(!parse(&[]).open);
+        assert!(parse(&["--open"]).open);
+    }
+
+    #[test]
+    fn options_with_equals() {
+        let conf = parse(&["--address=::1", "-p=3000", "--threads=8"]);
+
+        assert_eq!(conf.address, "::1");
+        assert_eq!(conf.port, 3000);
+        assert_eq!(conf.threads, 8);
+    }
+
+    #[test]
+    fn base_dir() {
+        let expected = Path::new("example").canonicalize().unwrap();
+
+        assert_eq!(parse(&["example"]).base_dir, expected);
+        assert_eq!(parse(&["example", "-p", "3000"]).base_dir, expected);
+        assert_eq!(parse(&["--base-dir", "example"]).base_dir, expected);
+    }
+
+    #[test]
+    fn acme_dir() {
+        let expected = Path::new("example/pages").canonicalize().unwrap();
+
+        assert_eq!(
+            parse(&["--acme-dir", "example/pages"]).acme_dir,
+            Some(expected)
+        );
+        assert_eq!(parse(&[]).acme_dir, None);
+        assert!(matches!(
+            parse_err(&["--acme-dir", "i_dont_exist"]),
+            CliError::IOError(_)
+        ));
+    }
+
+    #[test]
+    fn overlays() {
+        let conf = parse(&["--overlay", "example/pages", "--overlay=example"]);
+
+        assert_eq!(
+            conf.overlays,
+            [
+                Path::new("example/pages").canonicalize().unwrap(),
+                Path::new("example").canonicalize().unwrap()
+            ]
+        );
+        assert_eq!(conf.roots().len(), 3);
+        assert_eq!(conf.roots()[0], conf.base_dir);
+        assert!(matches!(
+            parse_err(&["--overlay", "i_dont_exist"]),
+            CliError::IOError(_)
+        ));
+        assert!(is_invalid_val(
+            &parse_err(&["--overlay", "example/index.html"]),
+            "--overlay",
+            "example/index.html"
+        ));
+    }
+
+    #[test]
+    fn follow_base_symlink() {
+        let conf = parse(&["--follow-base-symlink", "example"]);
+        let cwd = env::current_dir().unwrap();
+
+        assert!(conf.follow_base_symlink);
+        assert_eq!(conf.base_link.path(), cwd.join("example"));
+        assert_eq!(conf.current_base_dir(), conf.base_dir);
+        assert!(!parse(&[]).follow_base_symlink);
+        assert_eq!(parse(&[]).base_link.path(), cwd);
+    }
+
+    #[test]
+    fn base_dir_positional() {
+        let expected = Path::new("example").canonicalize().unwrap();
+
+        assert_eq!(parse(&["-p", "3000", "example"]).base_dir, expected);
+        assert_eq!(parse(&["-q", "example", "-p", "3000"]).base_dir, expected);
+        assert_eq!(parse(&["--port=3000", "example"]).base_dir, expected);
+    }
+
+    #[test]
+    fn base_dir_duplicate() {
+        assert!(matches!(
+            parse_err(&["example", "src"]),
+            CliError::DuplicateArg(ref a) if a == "<BASE_DIR>"
+        ));
+        assert!(matches!(
+            parse_err(&["--base-dir", "example", "src"]),
+            CliError::DuplicateArg(ref a) if a == "<BASE_DIR>"
+        ));
+        assert!(matches!(
+            parse_err(&["example", "--base-dir", "src"]),
+            CliError::DuplicateArg(ref a) if a == "--base-dir"
+        ));
+    }
+
+    #[test]
+    fn base_dir_separator() {
+        let dir = TempDir::new();
+        let dash = dir.path().join("-dist");
+        fs::create_dir(&dash).unwrap();
+        let dash = dash.to_str().unwrap();
+
+        let conf = parse(&["-p", "3000", "--", dash]);
+        assert_eq!(conf.base_dir, Path::new(dash).canonicalize().unwrap());
+        assert_eq!(conf.port, 3000);
+
+        // Only the first separator is special
+        assert!(matches!(
+            parse_err(&["--", dash, "--"]),
+            CliError::DuplicateArg(_)
+        ));
+        assert!(matches!(parse_err(&["--", "-p"]), CliError::IOError(_)));
+    }
+
+    #[test]
+    fn base_dir_missing() {
+        let err = parse_err(&["i_dont_exist"]);
+
+        assert!(matches!(err, CliError::IOError(_)));
+    }
+
+    #[test]
+    fn invalid_arg() {
+        let err = parse_err(&["--i-dont-exist", "value"]);
+
+        assert!(
+            matches!(err, CliError::InvalidArg(ref a) if a == "--i-dont-exist")
+        );
+        assert_eq!(err.to_string(), "Invalid argument --i-dont-exist found.");
+    }
+
+    #[test]
+    fn invalid_arg_suggestion() {
+        let err = parse_err(&["--adress", "0.0.0.0"]);
+
+        assert_eq!(
+            err.to_string(),
+            "Invalid argument --adress found. Did you mean `--address`?"
+        );
+    }
+
+    #[test]
+    fn duplicate_args() {
+        let cases: [&[&str]; 4] = [
+            &["--port", "8000", "--port", "9000"],
+            &["-p", "8000", "--port=9000"],
+            &["-q", "--quiet"],
+            &["example", "--base-dir", "example"],
+        ];
+
+        for args in cases.iter() {
+            let err = parse_err(args);
+
+            assert!(matches!(err, CliError::DuplicateArg(_)), "{:?}", args);
+        }
+        assert_eq!(
+            parse_err(&["-t", "1", "-t", "2"]).to_string(),
+            "Argument --threads cannot be given more than once"
+        );
+    }
+
+    #[test]
+    fn repeatable_args() {
+        let conf = parse(&["--ignore", "a", "--ignore", "b", "--mime", "a=b"]);
+
+        assert!(conf.ignore.is_ignored(Path::new("b"), false));
+        assert_eq!(
+            parse(&["--mime", "a=b", "--mime", "c=d"])
+                .mime_overrides
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn conflicting_args() {
+        let err = parse_err(&["--public", "-a", "::1"]);
+
+        assert!(matches!(
+            err,
+            CliError::ConflictingArgs(ref a, ref b)
+                if a == "--public" && b == "--address"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Arguments --public and --address cannot be used together"
+        );
+        assert!(matches!(
+            parse_err(&["--no-config", "--config", "servum.toml"]),
+            CliError::ConflictingArgs(_, _)
+        ));
+    }
+
+    #[test]
+    fn options_registered() {
+        let registered = |arg: &str| {
+            options::OPTIONS
+                .iter()
+                .any(|opt| arg.trim_start_matches('-') == opt.long)
+        };
+
+        for arg in ENV_OPTIONS.iter().chain(ENV_FLAGS) {
+            assert!(registered(arg), "{}", arg);
+        }
+        for opt in options::OPTIONS {
+            let arg = format!("--{}", opt.long);
+
+            match opt.value {
+                None => assert!(
+                    Config::default().set_flag(&arg, true)
+                        || ["--help", "--version"].contains(&arg.as_str()),
+                    "{}",
+                    arg
+                ),
+                Some(_) => assert!(
+                    !matches!(
+                        Config::default().set_option(&arg, ""),
+                        Err(CliError::InvalidArg(_))
+                    ),
+                    "{}",
+                    arg
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn missing_val() {
+        let err = parse_err(&["--port"]);
+
+        assert!(matches!(err, CliError::MissingVal(ref a) if a == "--port"));
+    }
+
+    #[test]
+    fn address() {
+        assert_eq!(parse(&["-a", "::1"]).address, "::1");
+        assert_eq!(parse(&["-a", "[::1]"]).address, "::1");
+        assert_eq!(parse(&["-a", "0.0.0.0"]).address, "0.0.0.0");
+        assert_eq!(parse(&["-a", "localhost"]).address, "localhost");
+
+        for address in ["::1:8080:", "[localhost]", "local host", ""].iter() {
+            let err = parse_err(&["--address", address]);
+
+            assert!(is_invalid_val(&err, "--address", address));
+        }
+    }
+
+    #[test]
+    fn invalid_val() {
+        assert!(is_invalid_val(
+            &parse_err(&["-p", "http"]),
+            "--port",
+            "http"
+        ));
+        assert!(is_invalid_val(&parse_err(&["-t", "-1"]), "--threads", "-1"));
+    }
+
+    #[test]
+    fn port_range() {
+        assert_eq!(parse(&["-p", "0"]).port, 0);
+        assert_eq!(parse(&["-p", "1"]).port, 1);
+        assert_eq!(parse(&["-p", "65535"]).port, 65535);
+        assert!(is_invalid_val(
+            &parse_err(&["-p", "65536"]),
+            "--port",
+            "65536"
+        ));
+    }
+
+    #[test]
+    fn threads_range() {
+        assert_eq!(parse(&["-t", "1"]).threads, 1);
+        assert!(is_invalid_val(&parse_err(&["-t", "0"]), "--threads", "0"));
+    }
+
+    #[test]
+    fn threads_auto() {
+        let threads = default_threads();
+
+        assert!((1..=MAX_DEFAULT_THREADS).contains(&threads));
+        assert_eq!(parse(&["-t", "16"]).threads, 16);
+        assert_eq!(parse(&["--threads", "auto"]).threads, threads);
+        assert!(
+            Config::help_short().contains(&format!("Default is {}.", threads))
+        );
+    }
+
+    // Helper
+    fn apply_env(vars: &[(&str, &str)]) -> Result<Config, CliError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let mut conf = Config::default();
+
+        conf.apply_env(|name| vars.get(name).cloned())?;
+        Ok(conf)
+    }
+
+    #[test]
+    fn env_names() {
+        assert_eq!(env_name("--port"), "SERVUM_PORT");
+        assert_eq!(env_name("--no-list-dir"), "SERVUM_NO_LIST_DIR");
+    }
+
+    #[test]
+    fn env_options() {
+        let conf = apply_env(&[
+            ("SERVUM_ADDRESS", "0.0.0.0"),
+            ("SERVUM_PORT", "3000"),
+            ("SERVUM_THREADS", "2"),
+            ("SERVUM_BASE_DIR", "example"),
+            ("SERVUM_NO_LIST_DIR", "1"),
+            ("SERVUM_QUIET", "TRUE"),
+        ])
+        .unwrap();
+
+        assert_eq!(conf.address, "0.0.0.0");
+        assert_eq!(conf.port, 3000);
+        assert_eq!(conf.threads, 2);
+        assert_eq!(conf.base_dir, Path::new("example").canonicalize().unwrap());
+        assert!(!conf.list_dir);
+        assert_eq!(conf.verbosity, Verbosity::Quiet);
+    }
+
+    #[test]
+    fn verbosity() {
+        assert_eq!(parse(&["--silent"]).verbosity, Verbosity::Silent);
+        assert!(parse(&[]).logo);
+        assert!(!parse(&["--no-logo"]).logo);
+        assert!(matches!(
+            parse_err(&["-q", "--silent"]),
+            CliError::ConflictingArgs(_, _)
+        ));
+
+        let conf = apply_env(&[("SERVUM_SILENT", "1")]).unwrap();
+        assert_eq!(conf.verbosity, Verbosity::Silent);
+
+        assert_eq!(parse(&["-v"]).verbosity, Verbosity::Debug);
+        assert_eq!(parse(&["--verbose"]).verbosity, Verbosity::Debug);
+        assert!(matches!(
+            parse_err(&["--verbose", "-q"]),
+            CliError::ConflictingArgs(_, _)
+        ));
+    }
+
+    #[test]
+    fn cache_rules() {
+        let conf = parse(&[
+            "--cache-rule",
+            "assets/**=max-age=31536000, immutable",
+            "--cache-rule=**/*.html=no-cache",
+            "--cache-control",
+            "max-age=60",
+        ]);
+
+        assert_eq!(conf.cache_control.as_deref(), Some("max-age=60"));
+        assert_eq!(conf.cache_rules.lookup("/index.html"), Some("no-cache"));
+        assert!(is_invalid_val(
+            &parse_err(&["--cache-rule", "no-cache"]),
+            "--cache-rule",
+            "no-cache"
+        ));
+        assert!(is_invalid_val(
+            &parse_err(&["--cache-control", "a\nb"]),
+            "--cache-control",
+            "a\nb"
+        ));
+    }
+
+    #[test]
+    fn redirects() {
+        let conf = parse(&[
+            "--redirect",
+            "/old-blog=/blog",
+            "--redirect=/docs/*=https://docs.example.com:301",
+        ]);
+
+        assert_eq!(
+            conf.redirects.lookup("/old-blog"),
+            Some((302, "/blog".into()))
+        );
+        assert_eq!(
+            conf.redirects.lookup("/docs/a.html"),
+            Some((301, String::from("https://docs.example.com")))
+        );
+        assert!(is_invalid_val(
+            &parse_err(&["--redirect", "/a=/b:200"]),
+            "--redirect",
+            "/a=/b:200"
+        ));
+    }
+
+    #[test]
+    fn block_method() {
+        assert_eq!(parse(&[]).blocked_methods, DEFAULT_BLOCKED_METHODS);
+
+        let conf =
+            parse(&["--block-method", "PROPFIND", "--block-method=TRACE"]);
+        assert_eq!(
+            conf.blocked_methods,
+            ["TRACE", "TRACK", "CONNECT", "PROPFIND"]
+        );
+        assert!(conf.diff_from_default().contains(&String::from(
+            "block-method=TRACE,TRACK,CONNECT,PROPFIND"
+        )));
+
+        for method in ["", "GET /", "TR\u{c4}CE"] {
+            assert!(is_invalid_val(
+                &parse_err(&["--block-method", method]),
+                "--block-method",
+                method
+            ));
+        }
+    }
+
+    #[test]
+    fn quiet_errors() {
+        let conf = parse(&["--quiet-errors", "404", "--quiet-errors=304"]);
+
+        assert!(conf.quiet_errors.contains(404));
+        assert!(conf.quiet_errors.contains(304));
+        assert!(!conf.quiet_errors.contains(500));
+        assert!(is_invalid_val(
+            &parse_err(&["--quiet-errors", "some"]),
+            "--quiet-errors",
+            "some"
+        ));
+    }
+
+    #[test]
+    fn queue() {
+        assert_eq!(parse(&[]).queue, DEFAULT_QUEUE_LIMIT);
+        assert_eq!(parse(&["--queue", "16"]).queue, 16);
+        assert!(is_invalid_val(&parse_err(&["--queue=-1"]), "--queue", "-1"));
+    }
+
+    #[test]
+    fn max_connections() {
+        let conf = parse(&[]);
+        assert_eq!(conf.max_connections, None);
+        assert_eq!(conf.connection_overflow, Overflow::Reject);
+
+        let conf =
+            parse(&["--max-connections", "64", "--connection-overflow=wait"]);
+        assert_eq!(conf.max_connections, Some(64));
+        assert_eq!(conf.connection_overflow, Overflow::Wait);
+
+        assert!(is_invalid_val(
+            &parse_err(&["--max-connections", "0"]),
+            "--max-connections",
+            "0"
+        ));
+        assert!(is_invalid_val(
+            &parse_err(&["--connection-overflow", "drop"]),
+            "--connection-overflow",
+            "drop"
+        ));
+    }
+
+    #[test]
+    fn upload() {
+        let conf = parse(&[]);
+        assert!(!conf.upload);
+        assert_eq!(conf.max_upload_size, DEFAULT_MAX_UPLOAD_SIZE);
+
+        let conf = parse(&["--upload", "--max-upload-size", "5"]);
+        assert!(conf.upload);
+        assert_eq!(conf.max_upload_size, 5);
+
+        assert!(is_invalid_val(
+            &parse_err(&["--max-upload-size", "0"]),
+            "--max-upload-size",
+            "0"
+        ));
+    }
+
+    #[test]
+    fn stats_interval() {
+        assert_eq!(parse(&[]).stats_interval, None);
+        assert_eq!(
+            parse(&["--stats-interval", "10"]).stats_interval,
+            Some(Duration::from_secs(10))
+        );
+        assert!(is_invalid_val(
+            &parse_err(&["--stats-interval", "0"]),
+            "--stats-interval",
+            "0"
+        ));
+    }
+
+    #[test]
+    fn listing_cache_ttl() {
+        assert_eq!(parse(&[]).listing_cache_ttl, None);
+        assert_eq!(
+            parse(&["--listing-cache-ttl", "30"]).listing_cache_ttl,
+            Some(Duration::from_secs(30))
+        );
+        assert!(is_invalid_val(
+            &parse_err(&["--listing-cache-ttl", "0"]),
+            "--listing-cache-ttl",
+            "0"
+        ));
+    }
+
+    #[test]
+    fn manifest() {
+        let conf = parse(&[]);
+        assert!(!conf.manifest);
+        assert_eq!(conf.manifest_max_depth, DEFAULT_MANIFEST_MAX_DEPTH);
+        assert_eq!(conf.manifest_max_entries, DEFAULT_MANIFEST_MAX_ENTRIES);
+
+        let conf = parse(&[
+            "--manifest",
+            "--manifest-max-depth",
+            "4",
+            "--manifest-max-entries=500",
+        ]);
+        assert!(conf.manifest);
+        assert_eq!(conf.manifest_max_depth, 4);
+        assert_eq!(conf.manifest_max_entries, 500);
+        assert_eq!(
+            conf.diff_from_default(),
+            [
+                "manifest-max-depth=4",
+                "manifest-max-entries=500",
+                "manifest"
+            ]
+        );
+
+        for arg in ["--manifest-max-depth", "--manifest-max-entries"] {
+            assert!(is_invalid_val(&parse_err(&[arg, "0"]), arg, "0"));
+        }
+    }
+
+    #[test]
+    fn request_deadline() {
+        assert_eq!(parse(&[]).request_deadline, Some(DEFAULT_REQUEST_DEADLINE));
+        assert_eq!(
+            parse(&["--request-deadline", "30"]).request_deadline,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(parse(&["--request-deadline=0"]).request_deadline, None);
+        assert!(is_invalid_val(
+            &parse_err(&["--request-deadline", "-1"]),
+            "--request-deadline",
+            "-1"
+        ));
+    }
+
+    #[test]
+    fn path_limits() {
+        let conf = parse(&[]);
+        assert_eq!(conf.max_path_depth, DEFAULT_MAX_PATH_DEPTH);
+        assert_eq!(conf.max_segment_len, DEFAULT_MAX_SEGMENT_LEN);
+
+        let conf =
+            parse(&["--max-path-depth", "8", "--max-segment-length=1024"]);
+        assert_eq!(conf.max_path_depth, 8);
+        assert_eq!(conf.max_segment_len, 1024);
+
+        for arg in ["--max-path-depth", "--max-segment-length"] {
+            assert!(is_invalid_val(&parse_err(&[arg, "0"]), arg, "0"));
+        }
+    }
+
+    #[test]
+    fn allow_encoded_slash() {
+        assert!(!parse(&[]).allow_encoded_slash);
+        assert!(parse(&["--allow-encoded-slash"]).allow_encoded_slash);
+    }
+
+    #[test]
+    fn simulated_latency() {
+        let conf = parse(&["--delay", "200", "--throttle=50"]);
+
+        assert_eq!(conf.delay, 200);
+        assert_eq!(conf.throttle, 50);
+        assert_eq!(parse(&[]).throttle, 0);
+        assert!(is_invalid_val(
+            &parse_err(&["--delay", "-1"]),
+            "--delay",
+            "-1"
+        ));
+    }
+
+    #[test]
+    fn log_rotation() {
+        let conf = parse(&[]);
+        assert_eq!(conf.log_rotate_size, None);
+        assert_eq!(conf.log_rotate_keep, DEFAULT_LOG_ROTATE_KEEP);
+
+        let conf = parse(&["--log-rotate-size", "10", "--log-rotate-keep=3"]);
+        assert_eq!(conf.log_rotate_size, Some(10));
+        assert_eq!(conf.log_rotate_keep, 3);
+
+        assert!(is_invalid_val(
+            &parse_err(&["--log-rotate-size", "0"]),
+            "--log-rotate-size",
+            "0"
+        ));
+        assert!(is_invalid_val(
+            &parse_err(&["--log-rotate-keep", "0"]),
+            "--log-rotate-keep",
+            "0"
+        ));
+    }
+
+    #[test]
+    fn validate_log_rotation_requires_file() {
+        let dir = TempDir::new();
+        let conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            port: 0,
+            log_rotate_size: Some(1),
+            ..Config::default()
+        };
+
+        assert!(matches!(
+            conf.validate(),
+            Err(CliError::RequiredArg(ref a, ref b))
+                if a == "--log-rotate-size" && b == "--log-file"
+        ));
+    }
+
+    #[test]
+    fn redirect_http() {
+        assert_eq!(parse(&[]).redirect_http, None);
+        assert_eq!(parse(&["--redirect-http", "80"]).redirect_http, Some(80));
+        assert!(is_invalid_val(
+            &parse_err(&["--redirect-http", "http"]),
+            "--redirect-http",
+            "http"
+        ));
+        assert!(matches!(
+            parse_err(&["--unix-socket", "a.sock", "--redirect-http", "80"]),
+            CliError::ConflictingArgs(..)
+        ));
+
+        let dir = TempDir::new();
+        let conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            port: 0,
+            redirect_http: Some(0),
+            ..Config::default()
+        };
+        assert!(matches!(
+            conf.validate(),
+            Err(CliError::RequiredArg(ref a, ref b))
+                if a == "--redirect-http" && b == "--tls-cert"
+        ));
+    }
+
+    #[test]
+    fn log_path_width() {
+        assert_eq!(parse(&[]).log_path_width, 32);
+        assert_eq!(parse(&["--log-path-width", "0"]).log_path_width, 0);
+    }
+
+    #[test]
+    fn no_timestamps() {
+        assert!(parse(&[]).timestamps);
+        assert!(!parse(&["--no-timestamps"]).timestamps);
+    }
+
+    #[test]
+    fn no_color() {
+        assert!(parse(&[]).color);
+        assert!(!parse(&["--no-color"]).color);
+        assert!(!apply_env(&[("NO_COLOR", "1")]).unwrap().color);
+        assert!(apply_env(&[("NO_COLOR", "")]).unwrap().color);
+    }
+
+    #[test]
+    fn env_disabled_flags() {
+        let conf =
+            apply_env(&[("SERVUM_NO_LIST_DIR", "0"), ("SERVUM_QUIET", "")])
+                .unwrap();
+
+        assert!(conf.list_dir);
+        assert_eq!(conf.verbosity, Verbosity::Normal);
+    }
+
+    #[test]
+    fn env_invalid() {
+        let err = apply_env(&[("SERVUM_PORT", "http")]).unwrap_err();
+
+        assert!(is_invalid_val(&err, "SERVUM_PORT", "http"));
+        assert_eq!(
+            err.to_string(),
+            "Invalid value http for argument SERVUM_PORT found"
+        );
+
+        let err = apply_env(&[("SERVUM_QUIET", "maybe")]).unwrap_err();
+        assert!(is_invalid_val(&err, "SERVUM_QUIET", "maybe"));
+
+        let err =
+            apply_env(&[("SERVUM_BASE_DIR", "i_dont_exist")]).unwrap_err();
+        assert!(is_invalid_val(&err, "SERVUM_BASE_DIR", "i_dont_exist"));
+    }
+
+    #[test]
+    fn env_precedence() {
+        let mut conf = apply_env(&[("SERVUM_PORT", "3000")]).unwrap();
+        let args = vec![String::from("--port"), String::from("4000")];
+
+        Config::parse_args(&args, &mut conf).unwrap();
+
+        assert_eq!(conf.port, 4000);
+    }
+
+    fn config_dir() -> TempDir {
+        let dir = TempDir::new();
+        dir.file(
+            CONFIG_FILE,
+            "port = 3000\nquiet = true\nignore = [\"*.log\", \"drafts/\"]\n\n\
+             [mime]\nglsl = \"text/plain\"\n",
+        );
+        dir
+    }
+
+    #[test]
+    fn config_file() {
+        let dir = config_dir();
+        let conf = parse(&[dir.path().to_str().unwrap()]);
+
+        assert_eq!(conf.port, 3000);
+        assert_eq!(conf.verbosity, Verbosity::Quiet);
+        assert!(conf.ignore.is_ignored(Path::new("debug.log"), false));
+        assert!(conf.ignore.is_ignored(Path::new("drafts"), true));
+        assert_eq!(conf.mime_overrides["glsl"], "text/plain");
+    }
+
+    #[test]
+    fn config_file_explicit() {
+        let dir = TempDir::new();
+        let path = dir.file("other.toml", "base_dir = \".\"\nthreads = 2\n");
+        let conf = parse(&["--config", path.to_str().unwrap()]);
+
+        assert_eq!(conf.base_dir, dir.path());
+        assert_eq!(conf.threads, 2);
+    }
+
+    #[test]
+    fn config_file_precedence() {
+        let dir = config_dir();
+        let conf = parse(&[dir.path().to_str().unwrap(), "-p", "4000"]);
+
+        assert_eq!(conf.port, 4000);
+        assert_eq!(conf.verbosity, Verbosity::Quiet);
+    }
+
+    #[test]
+    fn no_config() {
+        let dir = config_dir();
+        let conf = parse(&[dir.path().to_str().unwrap(), "--no-config"]);
+
+        assert_eq!(conf.port, 8080);
+        assert_eq!(conf.verbosity, Verbosity::Normal);
+    }
+
+    #[test]
+    fn probe_args() {
+        let args: Vec<String> = [
+            "--mime-file",
+            "i_dont_exist.types",
+            "--ignore",
+            "--config",
+            "--config=other.toml",
+            "i_dont_exist",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let mut probe = Config::default();
+
+        // Other options are skipped along with their values, unchecked
+        assert!(probe.probe_args(&args).is_none());
+        assert_eq!(probe.config, Some(PathBuf::from("other.toml")));
+        assert_eq!(probe.base_dir, Path::new("i_dont_exist"));
+        assert!(probe.ignore.is_empty() && probe.mime_overrides.is_empty());
+
+        let args = [String::from("--no-config"), String::from("--help")];
+        assert!(matches!(
+            Config::default().probe_args(&args),
+            Some(ConfigOutcome::Help(_))
+        ));
+    }
+
+    #[test]
+    fn config_file_missing() {
+        let err = parse_err(&["--config", "i_dont_exist.toml"]);
+
+        assert!(matches!(err, CliError::IOError(_)));
+    }
+
+    #[test]
+    fn config_file_errors() {
+        let cases = [
+            ("port = 1\nport = \"http\"\n", 2),
+            ("\nthreads = 1 2\n", 2),
+            ("quiet = true\nchatty = true\n", 2),
+            ("port = true\n", 1),
+            ("config = \"other.toml\"\n", 1),
+            ("[mime]\nglsl = 1\n", 2),
+        ];
+
+        for (contents, line) in cases.iter() {
+            let dir = TempDir::new();
+            let path = dir.file(CONFIG_FILE, contents);
+            let err = Config::default().load_config(&path).unwrap_err();
+
+            assert!(
+                matches!(err, CliError::ConfigFile(_, l, _) if l == *line),
+                "{:?}: {:?}",
+                contents,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn help() {
+        assert!(matches!(
+            outcome(&["-h"]),
+            Ok(ConfigOutcome::Help(ref h)) if *h == Config::help_short()
+        ));
+        assert!(matches!(
+            outcome(&["-p", "3000", "--help"]),
+            Ok(ConfigOutcome::Help(ref h)) if *h == Config::help_long()
+        ));
+    }
+
+    #[test]
+    fn version() {
+        assert!(matches!(
+            outcome(&["--version"]),
+            Ok(ConfigOutcome::Version(ref v)) if *v == Config::version()
+        ));
+        assert!(Config::version().starts_with("servum "));
+        assert!(Config::version().contains(env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn completions() {
+        assert!(matches!(
+            outcome(&["completions", "bash"]),
+            Ok(ConfigOutcome::Completions(ref s))
+                if *s == completions::script(Shell::Bash)
+        ));
+        assert!(matches!(
+            parse_err(&["completions"]),
+            CliError::MissingVal(ref a) if a == "completions"
+        ));
+        assert!(is_invalid_val(
+            &parse_err(&["completions", "tcsh"]),
+            "completions",
+            "tcsh"
+        ));
+        assert!(matches!(
+            parse_err(&["completions", "zsh", "-p"]),
+            CliError::InvalidArg(ref a) if a == "-p"
+        ));
+    }
+
+    #[test]
+    fn version_short() {
+        assert!(matches!(outcome(&["-V"]), Ok(ConfigOutcome::Version(_))));
+    }
+
+    #[test]
+    fn version_with_value() {
+        let err = parse_err(&["--version=foo"]);
+
+        assert!(matches!(err, CliError::InvalidArg(ref a) if a == "--version"));
+    }
+
+    #[test]
+    fn help_shows_version() {
+        assert!(Config::help_short().starts_with(&Config::version()));
+        assert!(Config::help_long().starts_with(&Config::version()));
+    }
+
+    #[test]
+    fn mime_overrides() {
+        let conf = parse(&[
+            "--mime",
+            "glsl=text/plain",
+            "--mime=.BIN=application/wasm",
+        ]);
+
+        assert_eq!(conf.mime_overrides.len(), 2);
+        assert_eq!(conf.mime_overrides["glsl"], "text/plain");
+        assert_eq!(conf.mime_overrides["bin"], "application/wasm");
+    }
+
+    #[test]
+    fn default_mime() {
+        assert_eq!(Config::default().default_mime, None);
+
+        let conf = parse(&["--default-mime", "application/octet-stream"]);
+
+        assert_eq!(conf.default_mime.unwrap(), "application/octet-stream");
+    }
+
+    #[test]
+    fn mime_override_invalid() {
+        let err = parse_err(&["--mime", "glsl"]);
+
+        assert!(is_invalid_val(&err, "--mime", "glsl"));
+    }
+
+    #[test]
+    fn ignore() {
+        let conf = parse(&["--ignore", "*.log", "--ignore=drafts/"]);
+
+        assert!(conf.ignore_file);
+        assert!(conf.ignore.is_ignored(Path::new("debug.log"), false));
+        assert!(conf.ignore.is_ignored(Path::new("drafts"), true));
+    }
+
+    #[test]
+    fn no_ignore_file() {
+        let conf = parse(&["--no-ignore-file"]);
+
+        assert!(!conf.ignore_file);
+    }
+
+    #[test]
+    fn check() {
+        assert!(parse(&["--check"]).check);
+        assert!(!parse(&[]).check);
+    }
+
+    #[test]
+    fn validate() {
+        let conf = parse(&["example", "-p", "0", "--mime", "glsl=text/plain"]);
+        let lines = conf.validate().unwrap();
+
+        assert_eq!(lines[0], "address = \"127.0.0.1\"");
+        assert_eq!(lines[1], "port = 0");
+        assert!(lines.contains(&String::from("mime = [\"glsl=text/plain\"]")));
+        assert!(lines.contains(&String::from("log-format = \"pretty\"")));
+        assert!(lines.contains(&String::from("no-list-dir = false")));
+    }
+
+    #[test]
+    fn diff_from_default() {
+        assert!(parse(&[]).diff_from_default().is_empty());
+
+        let conf = parse(&[
+            "--no-list-dir",
+            "--upload",
+            "-p",
+            "8000",
+            "--mime",
+            "glsl=text/plain",
+            "--mime",
+            "vert=text/plain",
+        ]);
+        assert_eq!(
+            conf.diff_from_default(),
+            [
+                "port=8000",
+                "mime=glsl=text/plain,vert=text/plain",
+                "no-list-dir",
+                "upload",
+            ]
+        );
+    }
+
+    #[test]
+    fn warnings() {
+        let dir = TempDir::new();
+        let conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        let warnings = conf.startup_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("Serving an empty directory"));
+
+        dir.file("app.js", "");
+        assert!(conf.startup_warnings().is_empty());
+
+        let no_list = Config {
+            list_dir: false,
+            ..conf.clone()
+        };
+        assert!(no_list.startup_warnings()[0].contains("has no index.html"));
+
+        dir.file("index.html", "");
+        assert!(no_list.startup_warnings().is_empty());
+
+        let missing = Config {
+            base_dir: dir.path().join("dist"),
+            ..conf
+        };
+        assert!(missing.startup_warnings().is_empty());
+    }
+
+    #[test]
+    fn sensitive_entries_warning() {
+        let dir = TempDir::new();
+        dir.file("index.html", "");
+        dir.file(".git/config", "");
+        dir.file("id_rsa", "");
+        let conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+
+        let warnings = conf.startup_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("contains .git, id_rsa,"));
+        assert!(warnings[0].contains("--ignore"));
+
+        let conf = Config {
+            warnings: false,
+            ..conf
+        };
+        assert!(conf.startup_warnings().is_empty());
+        assert!(!parse(&["--no-warnings"]).warnings);
+        assert!(parse(&[]).warnings);
+    }
+
+    #[test]
+    fn validate_errors() {
+        let dir = TempDir::new();
+        let valid = Config {
+            base_dir: dir.path().to_path_buf(),
+            port: 0,
+            ..Config::default()
+        };
+        assert!(valid.validate().is_ok());
+
+        let conf = Config {
+            base_dir: dir.path().join("i_dont_exist"),
+            ..valid.clone()
+        };
+        let err = conf.validate().unwrap_err();
+        assert!(err.to_string().contains("i_dont_exist"));
+
+        let conf = Config {
+            base_dir: dir.file("index.html", ""),
+            ..valid.clone()
+        };
+        assert!(conf.validate().is_err());
+
+        let conf = Config {
+            address: String::from("not an address"),
+            ..valid.clone()
+        };
+        assert!(is_invalid_val(
+            &conf.validate().unwrap_err(),
+            "--address",
+            "not an address"
+        ));
+
+        let conf = Config {
+            log_file: Some(dir.path().join("logs/access.log")),
+            ..valid.clone()
+        };
+        assert!(conf.validate().is_err());
+    }
+
+    #[test]
+    fn tls() {
+        let conf = parse(&["--tls-cert", "cert.pem", "--tls-key", "key.pem"]);
+
+        assert_eq!(conf.tls_cert.as_deref(), Some(Path::new("cert.pem")));
+        assert_eq!(conf.scheme(), "https");
+        assert_eq!(parse(&[]).scheme(), "http");
+        assert!(parse(&[]).tls_config().unwrap().is_none());
+        // Missing files, or TLS is unsupported
+        assert!(conf.tls_config().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "tls")]
+    fn tls_required_arg() {
+        let err = parse(&["--tls-key", "key.pem"]).tls_config().unwrap_err();
+
+        assert!(matches!(
+            err,
+            CliError::RequiredArg(ref a, ref b)
+                if a == "--tls-key" && b == "--tls-cert"
+        ));
+    }
+
+    #[test]
+    fn validate_port_in_use() {
+        let listener = net::bind("127.0.0.1", 0, 0).unwrap();
+        let conf = Config {
+            port: listener.local_addr().unwrap().port(),
+            ..Config::default()
+        };
+
+        let err = conf.validate().unwrap_err().to_string();
+        assert!(err.contains("is already in use — try --port 0"), "{}", err);
+    }
+
+    #[test]
+    fn load_ignore_file() {
+        let dir = TempDir::new();
+        dir.file(ignore::IGNORE_FILE, "# Secrets\n.env\n\nbuild/\n");
+
+        let mut conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        conf.load_ignore_file().unwrap();
+
+        assert!(conf.ignore.is_ignored(Path::new(".env"), false));
+        assert!(conf.ignore.is_ignored(Path::new("build"), true));
+    }
+
+    #[test]
+    fn load_ignore_file_disabled() {
+        let dir = TempDir::new();
+        dir.file(ignore::IGNORE_FILE, ".env\n");
+
+        let mut conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            ignore_file: false,
+            ..Config::default()
+        };
+        conf.load_ignore_file().unwrap();
+
+        assert!(conf.ignore.is_empty());
+    }
+
+    #[test]
+    fn load_ignore_file_missing() {
+        let dir = TempDir::new();
+        let mut conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+
+        assert!(conf.load_ignore_file().is_ok());
+        assert!(conf.ignore.is_empty());
+    }
+
+    #[test]
+    fn load_ignore_file_invalid() {
+        let dir = TempDir::new();
+        dir.file(ignore::IGNORE_FILE, ".env\n/\n");
+
+        let mut conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        let err = conf.load_ignore_file().unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn mime_file_missing() {
+        let err = parse_err(&["--mime-file", "i_dont_exist.types"]);
+
+        assert!(matches!(err, CliError::IOError(_)));
+    }
+
+    #[test]
+    fn mime_file_errors() {
+        let dir = TempDir::new();
+        let path = dir.file("mime.types", "glsl text/plain\nbin\n");
+        let err = parse_err(&["--mime-file", path.to_str().unwrap()]);
+
+        assert!(matches!(err, CliError::ConfigFile(ref p, 2, _) if *p == path));
+        assert!(err.to_string().contains("expected an extension"));
+    }
+
+    #[test]
+    fn from_args_loads_ignore_file() {
+        let dir = TempDir::new();
+        dir.file(ignore::IGNORE_FILE, ".env\n");
+
+        let base_dir = dir.path().to_str().unwrap();
+
+        assert!(parse(&[base_dir])
+            .ignore
+            .is_ignored(Path::new(".env"), false));
+        assert!(parse(&[base_dir, "--no-ignore-file"]).ignore.is_empty());
+    }
+}
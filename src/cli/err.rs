@@ -1,30 +1,51 @@
-use std::io;
+use super::options;
+use std::{io, path::PathBuf};
 
 /// Possible errors encountered when parsing user arguments.
 #[derive(Debug)]
-pub enum CliError<'a> {
-    InvalidArg(&'a str),
-    InvalidVal(&'a str, &'a str),
-    MissingVal(&'a str),
+pub enum CliError {
+    InvalidArg(String),
+    InvalidVal(String, String),
+    MissingVal(String),
+    DuplicateArg(String),
+    ConflictingArgs(String, String),
+    RequiredArg(String, String),
     IOError(io::Error),
+    ConfigFile(PathBuf, usize, String),
 }
 
-impl std::error::Error for CliError<'_> {
+impl CliError {
+    /// Create a new [`CliError::InvalidVal`] error for an argument and value.
+    pub fn invalid_val(arg: &str, val: &str) -> Self {
+        CliError::InvalidVal(arg.to_string(), val.to_string())
+    }
+}
+
+impl std::error::Error for CliError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match *self {
             CliError::InvalidArg(_) => None,
             CliError::InvalidVal(_, _) => None,
             CliError::MissingVal(_) => None,
+            CliError::DuplicateArg(_) => None,
+            CliError::ConflictingArgs(_, _) => None,
+            CliError::RequiredArg(_, _) => None,
             CliError::IOError(_) => None,
+            CliError::ConfigFile(_, _, _) => None,
         }
     }
 }
 
-impl std::fmt::Display for CliError<'_> {
+impl std::fmt::Display for CliError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match *self {
+        match self {
             CliError::InvalidArg(a) => {
-                write!(f, "Invalid argument {} found.", a)
+                write!(f, "Invalid argument {} found.", a)?;
+
+                match options::suggest(a) {
+                    Some(option) => write!(f, " Did you mean `{}`?", option),
+                    None => Ok(()),
+                }
             }
             CliError::MissingVal(a) => {
                 write!(f, "Missing value for argument {}", a)
@@ -32,13 +53,25 @@ impl std::fmt::Display for CliError<'_> {
             CliError::InvalidVal(a, v) => {
                 write!(f, "Invalid value {} for argument {} found", v, a)
             }
-            CliError::IOError(ref err) => err.fmt(f),
+            CliError::DuplicateArg(a) => {
+                write!(f, "Argument {} cannot be given more than once", a)
+            }
+            CliError::ConflictingArgs(a, b) => {
+                write!(f, "Arguments {} and {} cannot be used together", a, b)
+            }
+            CliError::RequiredArg(a, b) => {
+                write!(f, "Argument {} requires argument {}", a, b)
+            }
+            CliError::IOError(err) => err.fmt(f),
+            CliError::ConfigFile(path, line, msg) => {
+                write!(f, "{}, line {}: {}", path.display(), line, msg)
+            }
         }
     }
 }
 
-impl<'a> From<io::Error> for CliError<'a> {
-    fn from(err: io::Error) -> CliError<'a> {
+impl From<io::Error> for CliError {
+    fn from(err: io::Error) -> CliError {
         CliError::IOError(err)
     }
 }
@@ -0,0 +1,1029 @@
+//! Request logging to the console and to a log file
+use crate::{
+    cli::{metrics::Metrics, tui, Config},
+    clock::Clock,
+    http::{
+        forwarded_for, json_string, DateTime, HTTPRequest, HTTPResponse,
+        ServedBy, Written, FORWARDED_FOR_HEADER, REQUEST_ID_HEADER,
+    },
+};
+use std::{
+    ffi::OsString,
+    fmt, fs,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, IsTerminal, Write},
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Format of the request log lines.
+///
+/// Chosen once at startup with `--log-format` and used for both the console
+/// and the log file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human-readable columns, the default
+    #[default]
+    Pretty,
+    /// Apache Common Log Format, e.g.
+    /// `127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET / HTTP/1.1" 200 2326`
+    Common,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(LogFormat::Pretty),
+            "common" => Ok(LogFormat::Common),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            LogFormat::Pretty => "pretty",
+            LogFormat::Common => "common",
+            LogFormat::Json => "json",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Status codes of responses left out of the request log.
+///
+/// Set with the repeatable `--quiet-errors` flag, taking either a status code
+/// or `all` for all client and server errors, i.e. codes from 400. Filtered
+/// responses are still served normally.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::cli::logger::QuietErrors;
+/// let mut quiet = QuietErrors::default();
+/// quiet.add("404").unwrap();
+///
+/// assert!(quiet.contains(404));
+/// assert!(!quiet.contains(500));
+///
+/// quiet.add("all").unwrap();
+/// assert!(quiet.contains(500));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuietErrors {
+    all: bool,
+    codes: Vec<usize>,
+}
+
+impl QuietErrors {
+    /// Add a status code, or `all` for all errors.
+    ///
+    /// Returns [`None`] if `val` is neither `all` nor a status code from 100
+    /// to 599.
+    pub fn add(&mut self, val: &str) -> Option<()> {
+        match val {
+            "all" => self.all = true,
+            val => {
+                let code = val
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|code| (100..600).contains(code))?;
+                self.codes.push(code);
+            }
+        }
+        Some(())
+    }
+
+    /// Whether responses with status `code` are left out of the log.
+    pub fn contains(&self, code: usize) -> bool {
+        (self.all && code >= 400) || self.codes.contains(&code)
+    }
+
+    /// Whether no responses are left out of the log.
+    pub fn is_empty(&self) -> bool {
+        !self.all && self.codes.is_empty()
+    }
+
+    /// Return the filtered codes as given to `--quiet-errors`.
+    pub fn values(&self) -> Vec<String> {
+        let all = self.all.then(|| String::from("all"));
+
+        all.into_iter()
+            .chain(self.codes.iter().map(usize::to_string))
+            .collect()
+    }
+}
+
+/// Options of the pretty log format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    /// Color the status code and timing columns with ANSI escape codes
+    pub color: bool,
+    /// Prepend a UTC timestamp with millisecond precision to each line
+    pub timestamps: bool,
+    /// Width of the path column, with longer paths being truncated, or `0`
+    /// to never truncate paths
+    pub path_width: usize,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            color: false,
+            timestamps: true,
+            path_width: tui::DEFAULT_PATH_WIDTH,
+        }
+    }
+}
+
+impl LogFormat {
+    /// Format a request record as a single log line, without the trailing
+    /// newline.
+    ///
+    /// The `style` only applies to the pretty format, as the other formats
+    /// are meant to be machine-readable. The request id is left out of the
+    /// common format, which has no field for it.
+    ///
+    /// The body of `HEAD` responses isn't sent, so its length is shown in
+    /// parentheses in the pretty format, marked with `body_omitted` in JSON
+    /// and logged as `-` in the common format.
+    ///
+    /// With a `forwarded_for` address, the common format logs it as the host
+    /// and ends with the address of the proxy, e.g. `via 127.0.0.1`, while
+    /// JSON has both the `remote` and the `forwarded_for` address.
+    pub fn format(&self, record: &Record, style: Style) -> String {
+        match self {
+            LogFormat::Pretty => {
+                let code = format!("{: <6}", record.status);
+                // Padded to the width of the column title
+                let time = format!(
+                    "{: <10}",
+                    format!("{: <4}μs", record.duration.as_micros())
+                );
+                let size = match record.body_omitted {
+                    true => format!("({})", record.bytes),
+                    false => record.bytes.to_string(),
+                };
+                let timestamp = match style.timestamps {
+                    true => record.time.to_iso8601() + " ",
+                    false => String::new(),
+                };
+
+                let line = format!(
+                    "{timestamp}[{req_method: <6} {req_path: <width$}] -> \t{res_code} {res_msg: <24} {time} {size: <10} {served_by: <15} {id}",
+                    timestamp = timestamp,
+                    req_method = record.method,
+                    req_path = tui::truncate_path(&record.path, style.path_width),
+                    width = tui::path_column_width(style.path_width),
+                    res_code = match style.color {
+                        true => tui::paint(&code, tui::status_color(record.status)),
+                        false => code,
+                    },
+                    res_msg = record.msg,
+                    time = match style.color {
+                        true => tui::paint(&time, tui::DIM),
+                        false => time,
+                    },
+                    size = size,
+                    served_by = record.served_by.to_string(),
+                    id = record.id.as_deref().unwrap_or_default(),
+                );
+                line.trim_end().to_string()
+            }
+            LogFormat::Common => format!(
+                "{} - - [{}] \"{} {} HTTP/1.1\" {} {}{}",
+                record
+                    .forwarded_for
+                    .or_else(|| record.remote.map(|addr| addr.ip()))
+                    .map(|ip| ip.to_string())
+                    .unwrap_or_else(|| String::from("-")),
+                record.time.to_common_log(),
+                record.method,
+                record.path,
+                record.status,
+                match record.body_omitted {
+                    true => String::from("-"),
+                    false => record.bytes.to_string(),
+                },
+                match (record.forwarded_for, record.remote) {
+                    (Some(_), Some(proxy)) => format!(" via {}", proxy.ip()),
+                    _ => String::new(),
+                }
+            ),
+            LogFormat::Json => format!(
+                "{{\"timestamp\":{},\"remote\":{},\"forwarded_for\":{},\
+                 \"method\":{},\"path\":{},\"status\":{},\"bytes\":{},\"header_bytes\":{},\
+                 \"body_omitted\":{},\"duration_us\":{},\"served_by\":{},\
+                 \"request_id\":{}}}",
+                json_string(&record.time.to_iso8601()),
+                record
+                    .remote
+                    .map(|addr| json_string(&addr.to_string()))
+                    .unwrap_or_else(|| String::from("null")),
+                record
+                    .forwarded_for
+                    .map(|ip| json_string(&ip.to_string()))
+                    .unwrap_or_else(|| String::from("null")),
+                json_string(record.method),
+                json_string(&record.path),
+                record.status,
+                record.bytes,
+                record.header_bytes,
+                record.body_omitted,
+                record.duration.as_micros(),
+                json_string(&record.served_by.to_string()),
+                record
+                    .id
+                    .as_deref()
+                    .map(json_string)
+                    .unwrap_or_else(|| String::from("null")),
+            ),
+        }
+    }
+}
+
+/// Information about a request and its response to log.
+#[derive(Debug, Clone)]
+pub struct Record<'a> {
+    pub method: &'a str,
+    pub path: String,
+    pub status: usize,
+    pub msg: &'a str,
+    /// Number of body bytes sent, or the length of the body that wasn't
+    /// sent if `body_omitted`
+    pub bytes: usize,
+    /// Number of header bytes sent, status line included
+    pub header_bytes: usize,
+    /// Whether the body was left out of the response, i.e. for `HEAD`
+    /// requests
+    pub body_omitted: bool,
+    pub duration: Duration,
+    pub remote: Option<SocketAddr>,
+    /// Address of the client passed on by a trusted proxy in the
+    /// `X-Forwarded-For` header, with `--trust-proxy`
+    pub forwarded_for: Option<IpAddr>,
+    pub time: DateTime,
+    /// Id of the request sent in the `X-Request-Id` header, if any
+    pub id: Option<String>,
+    pub served_by: ServedBy,
+}
+
+impl<'a> Record<'a> {
+    /// Create a new record for a request and its response, of which the
+    /// bytes `written` were sent. `timer` was started when the request was
+    /// received and `remote` is the address of the client, if known. The
+    /// request id is taken from the response, and the time from `clock`.
+    pub fn new(
+        req: &HTTPRequest<'a>,
+        res: &HTTPResponse<'a>,
+        written: Written,
+        timer: Instant,
+        remote: Option<SocketAddr>,
+        clock: &dyn Clock,
+    ) -> Self {
+        let body_omitted = res.omits_body();
+
+        Record {
+            method: req.method,
+            path: req.target(),
+            status: res.status.code,
+            msg: res.status.msg,
+            bytes: match body_omitted {
+                true => res.body.len(),
+                false => written.body,
+            },
+            header_bytes: written.header,
+            body_omitted,
+            duration: clock.instant().saturating_duration_since(timer),
+            remote,
+            forwarded_for: None,
+            time: DateTime::from(clock.now()),
+            id: res
+                .headers
+                .iter()
+                .find(|(name, _)| name == REQUEST_ID_HEADER)
+                .map(|(_, id)| id.clone()),
+            served_by: res.served_by,
+        }
+    }
+}
+
+/// Logger for incoming requests, shared by all workers.
+///
+/// Each request is logged as one line to the console, unless disabled, and to
+/// the log file, if any. The log file is flushed after every line and rotated
+/// once it reaches the `log_rotate_size`, if set.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::cli::{logger::Logger, tui::Verbosity, Config};
+/// let config = Config {
+///     verbosity: Verbosity::Quiet,
+///     ..Config::default()
+/// };
+/// let logger = Logger::new(&config).unwrap();
+///
+/// assert!(!logger.is_enabled());
+/// ```
+#[derive(Debug)]
+pub struct Logger {
+    console: bool,
+    style: Style,
+    file: Option<Mutex<LogFile>>,
+    format: LogFormat,
+    quiet: QuietErrors,
+    trust_proxy: bool,
+    metrics: Metrics,
+    clock: Arc<dyn Clock>,
+}
+
+impl Logger {
+    /// Create a new logger from a user [`Config`].
+    ///
+    /// Logs to the console if the `verbosity` shows requests and to the
+    /// `log_file`, if any, using the `log_format`. Console output is colored
+    /// if the `color` option is set and stdout is a terminal. Timestamps are
+    /// shown unless the `timestamps` option is disabled and paths are
+    /// truncated to `log_path_width` characters. Responses with a status in
+    /// `quiet_errors` are not logged. With `trust_proxy`, the client address
+    /// sent by a proxy in `X-Forwarded-For` is logged as well.
+    ///
+    /// The log file is opened in append mode and created if missing. Returns
+    /// an error if the log file cannot be opened, e.g. if its directory
+    /// doesn't exist.
+    pub fn new(config: &Config) -> io::Result<Logger> {
+        let rotation = config.log_rotate_size.map(|mb| Rotation {
+            max_size: mb.saturating_mul(1_000_000),
+            keep: config.log_rotate_keep,
+        });
+        let file = match &config.log_file {
+            Some(path) => Some(Mutex::new(LogFile::open(path, rotation)?)),
+            None => None,
+        };
+
+        Ok(Logger {
+            console: config.verbosity.shows_requests(),
+            style: Style {
+                color: config.color && io::stdout().is_terminal(),
+                timestamps: config.timestamps,
+                path_width: config.log_path_width,
+            },
+            file,
+            format: config.log_format,
+            quiet: config.quiet_errors.clone(),
+            trust_proxy: config.trust_proxy,
+            metrics: Metrics::new(),
+            clock: config.clock.clone(),
+        })
+    }
+
+    /// Return whether requests are logged anywhere.
+    pub fn is_enabled(&self) -> bool {
+        self.console || self.file.is_some()
+    }
+
+    /// Print the table header of the request log to the console, if using the
+    /// pretty format.
+    pub fn header(&self) {
+        if self.console && self.format == LogFormat::Pretty {
+            tui::print_verbose_header(self.style);
+        }
+    }
+
+    /// Print a status message to the console, if requests are logged there.
+    ///
+    /// Status messages are not written to the log file.
+    pub fn info(&self, msg: &str) {
+        if self.console {
+            println!("{}", msg);
+        }
+    }
+
+    /// Return the counters of all requests logged so far.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Log a request and its response, of which the bytes `written` were
+    /// sent. `timer` was started when the request was received and `remote`
+    /// is the address of the client, if known.
+    ///
+    /// Every request is counted in the [`Logger::metrics`], even if it is
+    /// left out of the log.
+    pub fn log(
+        &self,
+        req: &HTTPRequest,
+        res: &HTTPResponse,
+        written: Written,
+        timer: Instant,
+        remote: Option<SocketAddr>,
+    ) {
+        let mut record =
+            Record::new(req, res, written, timer, remote, &*self.clock);
+        if self.trust_proxy {
+            record.forwarded_for =
+                req.header(FORWARDED_FOR_HEADER).and_then(forwarded_for);
+        }
+        self.metrics.record(
+            &record.path,
+            record.status,
+            written,
+            record.duration,
+        );
+
+        if !self.is_enabled() || self.quiet.contains(res.status.code) {
+            return;
+        }
+
+        let plain = Style {
+            color: false,
+            ..self.style
+        };
+        let line = self.format.format(&record, plain);
+
+        if self.console {
+            match self.style.color {
+                true => println!("{}", self.format.format(&record, self.style)),
+                false => println!("{}", line),
+            }
+        }
+
+        if let Some(file) = &self.file {
+            // A poisoned lock still holds a usable writer
+            let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+
+            if let Err(e) = file.write_line(&line) {
+                eprintln!("ERR: Could not write to log file: {}", e);
+            }
+        }
+    }
+}
+
+/// When to rotate a [`LogFile`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rotation {
+    /// Size in bytes from which the file is rotated
+    max_size: u64,
+    /// Number of rotated files kept, `<name>.1` being the most recent
+    keep: usize,
+}
+
+/// Log file, rotated by size if configured.
+///
+/// Lines are written whole and the file is rotated between lines, all under
+/// the lock of the [`Logger`], so no line is split across files.
+#[derive(Debug)]
+struct LogFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// Size of the current file in bytes
+    size: u64,
+    rotation: Option<Rotation>,
+}
+
+impl LogFile {
+    /// Open the log file at `path` in append mode, see [`open`].
+    fn open(path: &Path, rotation: Option<Rotation>) -> io::Result<LogFile> {
+        let file = open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(LogFile {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            size,
+            rotation,
+        })
+    }
+
+    /// Write a line and flush it, then rotate the file if it reached the
+    /// maximum size.
+    ///
+    /// A failed rotation is reported on stderr and the current file is kept,
+    /// so that requests are still logged. Rotation is tried again after the
+    /// next line.
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()?;
+        self.size += line.len() as u64 + 1;
+
+        if let Some(rotation) = self.rotation {
+            if self.size >= rotation.max_size {
+                if let Err(e) = self.rotate(rotation.keep) {
+                    eprintln!("ERR: Could not rotate log file: {}", e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Shift the rotated files, `<name>.1` becoming `<name>.2` and so on up to
+    /// `keep` files, move the current file to `<name>.1` and open a new one.
+    fn rotate(&mut self, keep: usize) -> io::Result<()> {
+        let oldest = rotated(&self.path, keep);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+
+        for n in (1..keep).rev() {
+            let from = rotated(&self.path, n);
+            if from.exists() {
+                fs::rename(&from, rotated(&self.path, n + 1))?;
+            }
+        }
+
+        // Until the new file is open, lines keep going to the renamed file
+        fs::rename(&self.path, rotated(&self.path, 1))?;
+        self.writer = BufWriter::new(open(&self.path)?);
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+/// Return the path of the `n`th rotated log file, e.g. `access.log.2`.
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = OsString::from(path);
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Open a log file in append mode, creating it if missing.
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| {
+            io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        cli::tui::Verbosity, clock::TestClock, http::HTTPStatus,
+        test_utils::TempDir,
+    };
+    use std::{fs, time::UNIX_EPOCH};
+
+    // Helper
+    fn record() -> Record<'static> {
+        Record {
+            method: "GET",
+            path: String::from("/say \"hi\".html"),
+            status: 404,
+            msg: "Not Found",
+            bytes: 2326,
+            header_bytes: 152,
+            body_omitted: false,
+            duration: Duration::from_micros(42),
+            remote: Some("127.0.0.1:51234".parse().unwrap()),
+            forwarded_for: None,
+            time: DateTime::from(
+                UNIX_EPOCH + Duration::from_millis(971_186_136_250),
+            ),
+            id: Some(String::from("1a2b00000007")),
+            served_by: ServedBy::BaseDir,
+        }
+    }
+
+    #[test]
+    fn format_pretty() {
+        assert_eq!(
+            LogFormat::Pretty.format(&record(), Style::default()),
+            "2000-10-10T13:55:36.250Z [GET    /say \"hi\".html                  ] -> \t404    Not Found                42  μs     2326       base-dir        1a2b00000007"
+        );
+
+        let health = Record {
+            id: None,
+            served_by: ServedBy::Internal("health"),
+            ..record()
+        };
+        assert!(LogFormat::Pretty
+            .format(&health, Style::default())
+            .ends_with("42  μs     2326       internal:health"));
+
+        let head = Record {
+            method: "HEAD",
+            body_omitted: true,
+            ..record()
+        };
+        assert!(LogFormat::Pretty
+            .format(&head, Style::default())
+            .contains("42  μs     (2326)     base-dir "));
+    }
+
+    #[test]
+    fn format_pretty_colored() {
+        let style = Style {
+            color: true,
+            timestamps: false,
+            ..Style::default()
+        };
+        let line = LogFormat::Pretty.format(&record(), style);
+
+        assert!(line.contains("\x1b[33m404   \x1b[0m"));
+        assert!(line.contains("\x1b[2m42  μs    \x1b[0m 2326       base-dir "));
+        assert_eq!(
+            LogFormat::Common.format(&record(), style),
+            LogFormat::Common.format(&record(), Style::default())
+        );
+    }
+
+    #[test]
+    fn format_pretty_width() {
+        let styles = [
+            Style::default(),
+            Style {
+                timestamps: false,
+                ..Style::default()
+            },
+            Style {
+                path_width: 8,
+                ..Style::default()
+            },
+            Style {
+                path_width: 0,
+                ..Style::default()
+            },
+        ];
+
+        for style in styles.iter().copied() {
+            let header = tui::format_verbose_header(style);
+            let arrow = header.chars().position(|c| c == ']');
+
+            for len in [0, 7, 8, 9, 31, 32, 33, 64].iter() {
+                if style.path_width == 0 && *len >= 32 {
+                    continue;
+                }
+
+                let record = Record {
+                    path: format!("/{}", "é".repeat(*len)),
+                    ..record()
+                };
+                let line = LogFormat::Pretty.format(&record, style);
+
+                assert_eq!(line.chars().position(|c| c == ']'), arrow);
+            }
+        }
+    }
+
+    #[test]
+    fn format_pretty_no_timestamps() {
+        let style = Style {
+            timestamps: false,
+            ..Style::default()
+        };
+
+        assert!(LogFormat::Pretty
+            .format(&record(), style)
+            .starts_with("[GET"));
+    }
+
+    #[test]
+    fn format_pretty_long_path() {
+        let record = Record {
+            path: String::from("/assets/images/icons/social/mastodon.svg"),
+            ..record()
+        };
+        let style = |path_width| Style {
+            timestamps: false,
+            path_width,
+            ..Style::default()
+        };
+
+        assert!(LogFormat::Pretty
+            .format(&record, style(16))
+            .starts_with("[GET    …al/mastodon.svg] ->"));
+        assert!(LogFormat::Pretty.format(&record, style(0)).starts_with(
+            "[GET    /assets/images/icons/social/mastodon.svg] ->"
+        ));
+    }
+
+    #[test]
+    fn format_common() {
+        assert_eq!(
+            LogFormat::Common.format(&record(), Style::default()),
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET /say \"hi\".html HTTP/1.1\" 404 2326"
+        );
+
+        let record = Record {
+            method: "HEAD",
+            body_omitted: true,
+            ..record()
+        };
+        assert!(LogFormat::Common
+            .format(&record, Style::default())
+            .ends_with("\"HEAD /say \"hi\".html HTTP/1.1\" 404 -"));
+    }
+
+    #[test]
+    fn format_json() {
+        assert_eq!(
+            LogFormat::Json.format(&record(), Style::default()),
+            r#"{"timestamp":"2000-10-10T13:55:36.250Z","remote":"127.0.0.1:51234","forwarded_for":null,"method":"GET","path":"/say \"hi\".html","status":404,"bytes":2326,"header_bytes":152,"body_omitted":false,"duration_us":42,"served_by":"base-dir","request_id":"1a2b00000007"}"#
+        );
+    }
+
+    #[test]
+    fn format_no_remote() {
+        let record = Record {
+            remote: None,
+            ..record()
+        };
+
+        assert!(LogFormat::Common
+            .format(&record, Style::default())
+            .starts_with("- - - ["));
+        assert!(LogFormat::Json
+            .format(&record, Style::default())
+            .contains(r#""remote":null"#));
+    }
+
+    #[test]
+    fn parse_format() {
+        assert_eq!("pretty".parse(), Ok(LogFormat::Pretty));
+        assert_eq!("common".parse(), Ok(LogFormat::Common));
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert_eq!("JSON".parse::<LogFormat>(), Err(()));
+    }
+
+    // Helper
+    fn logger(dir: &TempDir) -> Logger {
+        let config = Config {
+            verbosity: Verbosity::Quiet,
+            log_file: Some(dir.path().join("access.log")),
+            ..Config::default()
+        };
+
+        Logger::new(&config).unwrap()
+    }
+
+    #[test]
+    fn log_file() {
+        let dir = TempDir::new();
+        let req = HTTPRequest::new(b"GET /index.html HTTP/1.1").unwrap();
+        let res = HTTPResponse::from(HTTPStatus::from(404));
+
+        logger(&dir).log(&req, &res, Written::default(), Instant::now(), None);
+
+        let log = fs::read_to_string(dir.path().join("access.log")).unwrap();
+        assert_eq!(log.lines().count(), 1);
+        assert!(log.contains("/index.html"));
+        assert!(log.contains("404"));
+    }
+
+    #[test]
+    fn log_clock() {
+        let dir = TempDir::new();
+        let start = UNIX_EPOCH + Duration::from_millis(971_186_136_250);
+        let clock = Arc::new(TestClock::new(start));
+        let config = Config {
+            verbosity: Verbosity::Quiet,
+            log_file: Some(dir.path().join("access.log")),
+            log_format: LogFormat::Json,
+            clock: clock.clone(),
+            ..Config::default()
+        };
+        let req = HTTPRequest::new(b"GET / HTTP/1.1").unwrap();
+        let res = HTTPResponse::from(HTTPStatus::from(200));
+
+        let timer = clock.instant();
+        clock.advance(Duration::from_micros(42));
+        Logger::new(&config).unwrap().log(
+            &req,
+            &res,
+            Written::default(),
+            timer,
+            None,
+        );
+
+        let log = fs::read_to_string(dir.path().join("access.log")).unwrap();
+        assert!(log.contains(r#""timestamp":"2000-10-10T13:55:36.250Z""#));
+        assert!(log.contains(r#""duration_us":42,"#));
+    }
+
+    #[test]
+    fn format_forwarded_for() {
+        let record = Record {
+            forwarded_for: Some("203.0.113.7".parse().unwrap()),
+            ..record()
+        };
+
+        let line = LogFormat::Common.format(&record, Style::default());
+        assert!(line.starts_with("203.0.113.7 - - ["));
+        assert!(line.ends_with(" 404 2326 via 127.0.0.1"));
+        assert!(LogFormat::Json.format(&record, Style::default()).contains(
+            r#""remote":"127.0.0.1:51234","forwarded_for":"203.0.113.7","#
+        ));
+    }
+
+    #[test]
+    fn log_trust_proxy() {
+        let dir = TempDir::new();
+        let remote = Some("127.0.0.1:51234".parse().unwrap());
+        let buffer = "GET / HTTP/1.1\r\nX-Forwarded-For: 203.0.113.7, \
+                      10.0.0.2\r\n\r\n";
+        let req = HTTPRequest::new(buffer.as_bytes()).unwrap();
+        let res = HTTPResponse::from(HTTPStatus::from(200));
+
+        for trust_proxy in [false, true] {
+            let config = Config {
+                verbosity: Verbosity::Quiet,
+                log_file: Some(dir.path().join("access.log")),
+                log_format: LogFormat::Common,
+                trust_proxy,
+                ..Config::default()
+            };
+            let logger = Logger::new(&config).unwrap();
+            logger.log(&req, &res, Written::default(), Instant::now(), remote);
+        }
+
+        let log = fs::read_to_string(dir.path().join("access.log")).unwrap();
+        let lines = log.lines().collect::<Vec<_>>();
+        assert!(lines[0].starts_with("127.0.0.1 - - ["));
+        assert!(!lines[0].contains("via"));
+        assert!(lines[1].starts_with("203.0.113.7 - - ["));
+        assert!(lines[1].ends_with(" via 127.0.0.1"));
+    }
+
+    #[test]
+    fn log_quiet_errors() {
+        let dir = TempDir::new();
+        let mut config = Config {
+            verbosity: Verbosity::Quiet,
+            log_file: Some(dir.path().join("access.log")),
+            ..Config::default()
+        };
+        config.quiet_errors.add("404").unwrap();
+        let logger = Logger::new(&config).unwrap();
+
+        for (path, code) in [("/missing", 404), ("/", 200), ("/secret", 403)] {
+            let req = format!("GET {} HTTP/1.1", path);
+            let req = HTTPRequest::new(req.as_bytes()).unwrap();
+            let res = HTTPResponse::from(HTTPStatus::from(code));
+
+            logger.log(&req, &res, Written::default(), Instant::now(), None);
+
+            // The response itself is left untouched
+            assert_eq!(res.status.code, code);
+        }
+
+        let log = fs::read_to_string(dir.path().join("access.log")).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        assert!(!log.contains("/missing"));
+        assert!(log.contains("/secret"));
+    }
+
+    #[test]
+    fn quiet_errors() {
+        let mut quiet = QuietErrors::default();
+
+        assert!(quiet.is_empty());
+        assert!(quiet.add("99").is_none());
+        assert!(quiet.add("600").is_none());
+        assert!(quiet.add("none").is_none());
+
+        quiet.add("304").unwrap();
+        quiet.add("all").unwrap();
+
+        assert!(quiet.contains(304));
+        assert!(quiet.contains(404));
+        assert!(quiet.contains(503));
+        assert!(!quiet.contains(200));
+        assert_eq!(quiet.values(), vec!["all", "304"]);
+    }
+
+    #[test]
+    fn log_file_appends() {
+        let dir = TempDir::new();
+        dir.file("access.log", "previous line\n");
+        let req = HTTPRequest::new(b"GET / HTTP/1.1").unwrap();
+        let res = HTTPResponse::from(HTTPStatus::from(200));
+
+        logger(&dir).log(&req, &res, Written::default(), Instant::now(), None);
+        logger(&dir).log(&req, &res, Written::default(), Instant::now(), None);
+
+        let log = fs::read_to_string(dir.path().join("access.log")).unwrap();
+        assert_eq!(log.lines().count(), 3);
+        assert!(log.starts_with("previous line\n"));
+    }
+
+    // Helper: log file rotated after each line of 5 bytes or more
+    fn rotating(dir: &TempDir, keep: usize) -> LogFile {
+        let rotation = Rotation { max_size: 5, keep };
+        LogFile::open(&dir.path().join("access.log"), Some(rotation)).unwrap()
+    }
+
+    // Helper: names and contents of the files in `dir`, sorted
+    fn files(dir: &TempDir) -> Vec<(String, String)> {
+        let mut files: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                (
+                    path.file_name().unwrap().to_string_lossy().into_owned(),
+                    fs::read_to_string(&path).unwrap(),
+                )
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn log_file_rotation() {
+        let dir = TempDir::new();
+        let mut file = rotating(&dir, 2);
+
+        file.write_line("ab").unwrap();
+        assert_eq!(files(&dir), [("access.log".into(), "ab\n".into())]);
+
+        file.write_line("cd").unwrap();
+        assert_eq!(
+            files(&dir),
+            [
+                ("access.log".into(), "".into()),
+                ("access.log.1".into(), "ab\ncd\n".into()),
+            ]
+        );
+
+        for line in ["first", "second", "third"] {
+            file.write_line(line).unwrap();
+        }
+        assert_eq!(
+            files(&dir),
+            [
+                ("access.log".into(), "".into()),
+                ("access.log.1".into(), "third\n".into()),
+                ("access.log.2".into(), "second\n".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn log_file_rotation_resumes() {
+        let dir = TempDir::new();
+        dir.file("access.log", "123\n");
+
+        // The size of the existing file counts towards the threshold
+        rotating(&dir, 1).write_line("5").unwrap();
+        assert_eq!(
+            files(&dir),
+            [
+                ("access.log".into(), "".into()),
+                ("access.log.1".into(), "123\n5\n".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn log_file_rotation_error() {
+        let dir = TempDir::new();
+        let mut file = rotating(&dir, 1);
+
+        // The rotated file can't be replaced by a directory
+        fs::create_dir(dir.path().join("access.log.1")).unwrap();
+        fs::write(dir.path().join("access.log.1").join("keep"), "").unwrap();
+
+        file.write_line("first").unwrap();
+        file.write_line("second").unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("access.log")).unwrap(),
+            "first\nsecond\n"
+        );
+    }
+
+    #[test]
+    fn rotated_paths() {
+        assert_eq!(
+            rotated(Path::new("logs/access.log"), 3),
+            Path::new("logs/access.log.3")
+        );
+    }
+
+    #[test]
+    fn log_file_missing_dir() {
+        let dir = TempDir::new();
+        let config = Config {
+            log_file: Some(dir.path().join("missing").join("access.log")),
+            ..Config::default()
+        };
+        let err = Logger::new(&config).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().contains("access.log"));
+    }
+}
@@ -0,0 +1,377 @@
+//! Request counters, summarized when the server shuts down
+use crate::http::Written;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// Upper bounds of the response time histogram buckets, in microseconds.
+/// Slower responses fall into a last, unbounded bucket.
+pub const BUCKETS: [u64; 13] = [
+    1_000, 2_000, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000,
+    1_000_000, 2_500_000, 5_000_000, 10_000_000,
+];
+
+/// Number of busiest paths listed in the [`Summary`]
+pub const TOP_PATHS: usize = 5;
+
+/// Number of distinct paths counted, so that requests to random paths, e.g.
+/// by a scanner, can't use up the memory of the server
+const MAX_PATHS: usize = 10_000;
+
+/// Return the index of the histogram bucket of a response time.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::cli::metrics::{bucket, BUCKETS};
+/// use std::time::Duration;
+///
+/// assert_eq!(bucket(Duration::from_micros(1_000)), 0);
+/// assert_eq!(bucket(Duration::from_micros(1_001)), 1);
+/// assert_eq!(bucket(Duration::from_secs(60)), BUCKETS.len());
+/// ```
+pub fn bucket(duration: Duration) -> usize {
+    let micros = duration.as_micros();
+
+    BUCKETS
+        .iter()
+        .position(|&bound| micros <= u128::from(bound))
+        .unwrap_or(BUCKETS.len())
+}
+
+/// Counters of the requests served, shared by all workers.
+///
+/// Counters are updated for every request, including requests left out of
+/// the request log. Take a [`Summary`] to read them.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    requests: AtomicU64,
+    /// Requests by status class, from `1xx` to `5xx`
+    classes: [AtomicU64; 5],
+    bytes: AtomicU64,
+    header_bytes: AtomicU64,
+    total_micros: AtomicU64,
+    histogram: [AtomicU64; BUCKETS.len() + 1],
+    paths: Mutex<HashMap<String, u64>>,
+}
+
+impl Metrics {
+    /// Create empty counters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a request to `path`, answered with `status` after `duration`,
+    /// and the bytes `written` to the client.
+    pub fn record(
+        &self,
+        path: &str,
+        status: usize,
+        written: Written,
+        duration: Duration,
+    ) {
+        let micros = duration.as_micros().min(u64::MAX as u128) as u64;
+
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if let Some(class) = self.classes.get((status / 100).wrapping_sub(1)) {
+            class.fetch_add(1, Ordering::Relaxed);
+        }
+        self.bytes.fetch_add(written.body as u64, Ordering::Relaxed);
+        self.header_bytes
+            .fetch_add(written.header as u64, Ordering::Relaxed);
+        self.total_micros.fetch_add(micros, Ordering::Relaxed);
+        self.histogram[bucket(duration)].fetch_add(1, Ordering::Relaxed);
+
+        let mut paths = self.paths.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = paths.get_mut(path) {
+            *count += 1;
+        } else if paths.len() < MAX_PATHS {
+            paths.insert(path.to_string(), 1);
+        }
+    }
+
+    /// Return a snapshot of the counters.
+    pub fn summary(&self) -> Summary {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let paths = self.paths.lock().unwrap_or_else(|e| e.into_inner());
+
+        Summary {
+            requests: load(&self.requests),
+            classes: self.classes.each_ref().map(load),
+            bytes: load(&self.bytes),
+            header_bytes: load(&self.header_bytes),
+            total_time: Duration::from_micros(load(&self.total_micros)),
+            histogram: self.histogram.each_ref().map(load),
+            top_paths: top_paths(&paths, TOP_PATHS),
+        }
+    }
+}
+
+/// Return the `n` most requested paths with their counts, most requested
+/// first. Ties are ordered by path.
+pub fn top_paths(paths: &HashMap<String, u64>, n: usize) -> Vec<(String, u64)> {
+    let mut top: Vec<_> = paths
+        .iter()
+        .map(|(path, &count)| (path.clone(), count))
+        .collect();
+
+    top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top.truncate(n);
+    top
+}
+
+/// Snapshot of the [`Metrics`] of a server, printed on shutdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// Number of requests served
+    pub requests: u64,
+    /// Requests by status class, from `1xx` to `5xx`
+    pub classes: [u64; 5],
+    /// Number of body bytes sent
+    pub bytes: u64,
+    /// Number of header bytes sent, status lines included
+    pub header_bytes: u64,
+    /// Sum of all response times
+    pub total_time: Duration,
+    /// Number of responses by [`bucket`] of their response time
+    pub histogram: [u64; BUCKETS.len() + 1],
+    /// Most requested paths with their counts, see [`top_paths`]
+    pub top_paths: Vec<(String, u64)>,
+}
+
+impl Summary {
+    /// Average response time, if any request was served.
+    pub fn average(&self) -> Option<Duration> {
+        match self.requests {
+            0 => None,
+            n => Some(self.total_time / n as u32),
+        }
+    }
+
+    /// Upper bound of the histogram bucket of the `p`th percentile of the
+    /// response times, for `p` from 0 to 100.
+    ///
+    /// Returns [`None`] if no request was served, and [`Duration::MAX`] if
+    /// the percentile is in the last, unbounded bucket.
+    pub fn percentile(&self, p: u64) -> Option<Duration> {
+        let total: u64 = self.histogram.iter().sum();
+        if total == 0 {
+            return None;
+        }
+
+        // Rank of the percentile, rounded up
+        let rank = (total * p.min(100)).div_ceil(100).max(1);
+        let mut seen = 0;
+
+        for (i, count) in self.histogram.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(
+                    BUCKETS
+                        .get(i)
+                        .map_or(Duration::MAX, |&b| Duration::from_micros(b)),
+                );
+            }
+        }
+
+        Some(Duration::MAX)
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let classes: Vec<String> = self
+            .classes
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(i, count)| format!("{}xx: {}", i + 1, count))
+            .collect();
+
+        write!(f, "Requests served: {}", self.requests)?;
+        if !classes.is_empty() {
+            write!(f, " ({})", classes.join(", "))?;
+        }
+        writeln!(f)?;
+        write!(f, "Bytes sent: {}", format_bytes(self.bytes))?;
+        if self.header_bytes > 0 {
+            write!(f, " (headers: {})", format_bytes(self.header_bytes))?;
+        }
+        writeln!(f)?;
+
+        if let (Some(average), Some(p95)) =
+            (self.average(), self.percentile(95))
+        {
+            let p95 = match p95 {
+                Duration::MAX => format!(
+                    "> {}",
+                    format_duration(Duration::from_micros(
+                        BUCKETS[BUCKETS.len() - 1]
+                    ))
+                ),
+                p95 => format!("≤ {}", format_duration(p95)),
+            };
+            writeln!(
+                f,
+                "Response time: {} average, p95 {}",
+                format_duration(average),
+                p95
+            )?;
+        }
+
+        if !self.top_paths.is_empty() {
+            writeln!(f, "Busiest paths:")?;
+            for (path, count) in &self.top_paths {
+                writeln!(f, "  {: >6}  {}", count, path)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Format a number of bytes with SI units, e.g. `1.5 MB`.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["kB", "MB", "GB", "TB"];
+
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Format a response time, e.g. `850 μs`, `12.5 ms` or `2.50 s`.
+fn format_duration(duration: Duration) -> String {
+    let micros = duration.as_micros();
+
+    match micros {
+        0..=999 => format!("{} μs", micros),
+        1_000..=999_999 => format!("{:.1} ms", micros as f64 / 1000.0),
+        _ => format!("{:.2} s", duration.as_secs_f64()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Helper: bytes written for a response with a body of `body` bytes
+    fn written(body: usize) -> Written {
+        Written { header: 100, body }
+    }
+
+    #[test]
+    fn buckets() {
+        assert_eq!(bucket(Duration::ZERO), 0);
+        assert_eq!(bucket(Duration::from_millis(3)), 2);
+        assert_eq!(bucket(Duration::from_millis(10)), 3);
+        assert_eq!(bucket(Duration::from_secs(10)), BUCKETS.len() - 1);
+        assert_eq!(bucket(Duration::from_micros(10_000_001)), BUCKETS.len());
+    }
+
+    #[test]
+    fn percentiles() {
+        let metrics = Metrics::new();
+        assert_eq!(metrics.summary().percentile(95), None);
+
+        for _ in 0..95 {
+            metrics.record("/", 200, written(0), Duration::from_micros(500));
+        }
+        assert_eq!(
+            metrics.summary().percentile(95),
+            Some(Duration::from_millis(1))
+        );
+
+        for _ in 0..5 {
+            metrics.record("/", 200, written(0), Duration::from_millis(40));
+        }
+        let summary = metrics.summary();
+        assert_eq!(summary.percentile(95), Some(Duration::from_millis(1)));
+        assert_eq!(summary.percentile(96), Some(Duration::from_millis(50)));
+
+        metrics.record("/", 200, written(0), Duration::from_secs(60));
+        assert_eq!(metrics.summary().percentile(100), Some(Duration::MAX));
+    }
+
+    #[test]
+    fn busiest_paths() {
+        let paths = HashMap::from([
+            (String::from("/a"), 3),
+            (String::from("/b"), 7),
+            (String::from("/c"), 3),
+            (String::from("/d"), 1),
+        ]);
+
+        assert_eq!(
+            top_paths(&paths, 3),
+            vec![
+                (String::from("/b"), 7),
+                (String::from("/a"), 3),
+                (String::from("/c"), 3),
+            ]
+        );
+        assert_eq!(top_paths(&paths, 10).len(), 4);
+    }
+
+    #[test]
+    fn summary() {
+        let metrics = Metrics::new();
+        metrics.record(
+            "/index.html",
+            200,
+            written(1500),
+            Duration::from_millis(2),
+        );
+        metrics.record(
+            "/index.html",
+            304,
+            written(0),
+            Duration::from_millis(1),
+        );
+        metrics.record("/missing", 404, written(500), Duration::from_millis(3));
+
+        let summary = metrics.summary();
+        assert_eq!(summary.requests, 3);
+        assert_eq!(summary.classes, [0, 1, 1, 1, 0]);
+        assert_eq!(summary.bytes, 2000);
+        assert_eq!(summary.header_bytes, 300);
+        assert_eq!(summary.average(), Some(Duration::from_millis(2)));
+        assert_eq!(
+            summary.to_string(),
+            "Requests served: 3 (2xx: 1, 3xx: 1, 4xx: 1)\n\
+             Bytes sent: 2.0 kB (headers: 300 B)\n\
+             Response time: 2.0 ms average, p95 ≤ 5.0 ms\n\
+             Busiest paths:\n\
+             \x20      2  /index.html\n\
+             \x20      1  /missing\n"
+        );
+    }
+
+    #[test]
+    fn empty_summary() {
+        assert_eq!(
+            Metrics::new().summary().to_string(),
+            "Requests served: 0\nBytes sent: 0 B\n"
+        );
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(format_bytes(999), "999 B");
+        assert_eq!(format_bytes(1_500_000), "1.5 MB");
+        assert_eq!(format_duration(Duration::from_micros(850)), "850 μs");
+        assert_eq!(format_duration(Duration::from_millis(2500)), "2.50 s");
+    }
+}
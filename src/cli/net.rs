@@ -0,0 +1,645 @@
+//! Network helpers to find out where the server can be reached
+use std::{
+    convert::TryFrom,
+    io,
+    net::{
+        IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, ToSocketAddrs,
+        UdpSocket,
+    },
+};
+#[cfg(unix)]
+use std::{
+    fs,
+    os::unix::fs::FileTypeExt,
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+};
+
+#[cfg(target_os = "linux")]
+mod sys;
+
+/// Addresses to probe for local addresses.
+///
+/// One public address and one address for each private IPv4 range, so that
+/// the local address of each interface routing to them is found. No packets
+/// are sent when probing.
+const PROBES: &[&str] = &[
+    "8.8.8.8:80",
+    "10.254.254.254:80",
+    "172.31.255.254:80",
+    "192.168.255.254:80",
+];
+
+/// Parse a user-supplied address to listen on.
+///
+/// Accepts IPv4 and IPv6 literals, with or without brackets, and hostnames,
+/// such as `localhost`. Returns the address without brackets, or [`None`] if
+/// the address is invalid.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::cli::net::parse_address;
+/// assert_eq!(parse_address("[::1]").unwrap(), "::1");
+/// assert_eq!(parse_address("localhost").unwrap(), "localhost");
+/// assert_eq!(parse_address("::1:8080:"), None);
+/// ```
+pub fn parse_address(address: &str) -> Option<String> {
+    if let Some(inner) =
+        address.strip_prefix('[').and_then(|a| a.strip_suffix(']'))
+    {
+        return inner.parse::<Ipv6Addr>().ok().map(|_| inner.to_string());
+    }
+
+    if address.parse::<IpAddr>().is_ok() || is_hostname(address) {
+        Some(address.to_string())
+    } else {
+        None
+    }
+}
+
+/// Return whether `host` is a syntactically valid hostname, i.e. non-empty
+/// labels of ASCII letters, digits and hyphens separated by dots.
+fn is_hostname(host: &str) -> bool {
+    host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        // Reject invalid IPv4 addresses, such as 256.0.0.1
+        && !host.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// Resolve the socket addresses to listen on for an address and a port.
+///
+/// IP literals are used as-is, while hostnames are resolved and may yield
+/// several addresses.
+pub fn socket_addrs(address: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    match address.parse::<IpAddr>() {
+        Ok(ip) => Ok(vec![SocketAddr::new(ip, port)]),
+        Err(_) => Ok((address, port).to_socket_addrs()?.collect()),
+    }
+}
+
+/// Bind a [`TcpListener`] to an address and a port.
+///
+/// If the port is already in use, up to `retries` following ports are tried
+/// before giving up. Port `0` lets the operating system choose a free port,
+/// which can be read back with [`TcpListener::local_addr`].
+///
+/// Errors are explained with [`bind_error`].
+pub fn bind(
+    address: &str,
+    port: u16,
+    retries: usize,
+) -> io::Result<TcpListener> {
+    let last = last_port(port, retries);
+    let mut current = port;
+
+    loop {
+        let addrs = socket_addrs(address, current)
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput));
+
+        match addrs.and_then(|addrs| TcpListener::bind(addrs.as_slice())) {
+            Err(e)
+                if e.kind() == io::ErrorKind::AddrInUse
+                    && port != 0
+                    && current < last =>
+            {
+                current += 1;
+            }
+            Err(e) => return Err(bind_error(address, port, retries, e)),
+            res => return res,
+        }
+    }
+}
+
+/// Bind `count` listeners to the same address and port with `SO_REUSEPORT`,
+/// so that the operating system spreads incoming connections between them.
+///
+/// The port is chosen like with [`bind`], making sure it is free at first:
+/// otherwise, the listeners could share the port with another server of the
+/// same user. Only supported on Linux.
+#[cfg(target_os = "linux")]
+pub fn bind_reuse_port(
+    address: &str,
+    port: u16,
+    retries: usize,
+    count: usize,
+) -> io::Result<Vec<TcpListener>> {
+    let addr = bind(address, port, retries)?.local_addr()?;
+
+    (0..count.max(1))
+        .map(|_| {
+            sys::bind_reuse_port(&addr)
+                .map_err(|e| bind_error(address, addr.port(), 0, e))
+        })
+        .collect()
+}
+
+/// Bind listeners with `SO_REUSEPORT`, which is only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn bind_reuse_port(
+    _address: &str,
+    _port: u16,
+    _retries: usize,
+    _count: usize,
+) -> io::Result<Vec<TcpListener>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--reuse-port is only supported on Linux",
+    ))
+}
+
+/// Shut down a listener bound with [`bind_reuse_port`], so that accepting
+/// connections on it fails right away, including in other threads.
+#[cfg(target_os = "linux")]
+pub fn shutdown_listener(listener: &TcpListener) -> io::Result<()> {
+    sys::shutdown_listener(listener)
+}
+
+/// Set the send and receive buffer sizes of a listener, in bytes, which the
+/// connections it accepts inherit. Only supported on Linux.
+#[cfg(target_os = "linux")]
+pub fn set_buffer_sizes(
+    listener: &TcpListener,
+    send: Option<usize>,
+    recv: Option<usize>,
+) -> io::Result<()> {
+    sys::set_buffer_sizes(listener, send, recv)
+}
+
+/// Set the buffer sizes of a listener, which is only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn set_buffer_sizes(
+    _listener: &TcpListener,
+    send: Option<usize>,
+    recv: Option<usize>,
+) -> io::Result<()> {
+    match (send, recv) {
+        (None, None) => Ok(()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "--send-buffer and --recv-buffer are only supported on Linux",
+        )),
+    }
+}
+
+/// Shut down a listener bound with [`bind_reuse_port`], which is only
+/// supported on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn shutdown_listener(_listener: &TcpListener) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Return the last port tried by [`bind`] for `port` and `retries`.
+fn last_port(port: u16, retries: usize) -> u16 {
+    port.saturating_add(u16::try_from(retries).unwrap_or(u16::MAX))
+}
+
+/// Explain why binding to `address` and `port` failed, suggesting a fix
+/// where possible. The returned error keeps the kind of `err`.
+///
+/// `retries` is the number of following ports tried, as passed to [`bind`].
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::cli::net::bind_error;
+/// use std::io::{Error, ErrorKind};
+///
+/// let err = bind_error("0.0.0.0", 80, 0, ErrorKind::PermissionDenied.into());
+/// assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+/// assert_eq!(
+///     err.to_string(),
+///     "binding to port 80 requires elevated privileges"
+/// );
+/// ```
+pub fn bind_error(
+    address: &str,
+    port: u16,
+    retries: usize,
+    err: io::Error,
+) -> io::Error {
+    let last = last_port(port, retries);
+    let msg = match err.kind() {
+        io::ErrorKind::AddrInUse if last > port => format!(
+            "ports {}–{} are already in use — try --port 0 or pick another \
+             port",
+            port, last
+        ),
+        io::ErrorKind::AddrInUse => format!(
+            "port {} is already in use — try --port 0 or pick another port",
+            port
+        ),
+        io::ErrorKind::PermissionDenied if (1..1024).contains(&port) => {
+            format!("binding to port {} requires elevated privileges", port)
+        }
+        io::ErrorKind::AddrNotAvailable => {
+            format!("address '{}' is not available on this machine", address)
+        }
+        io::ErrorKind::InvalidInput => format!("invalid address '{}'", address),
+        _ => {
+            format!("could not listen at {}: {}", host_port(address, port), err)
+        }
+    };
+
+    io::Error::new(err.kind(), msg)
+}
+
+/// Bind a [`UnixListener`] to the socket file at `path`.
+///
+/// A socket file left behind by a server that didn't shut down cleanly is
+/// removed first, as long as nothing listens on it anymore. Other existing
+/// files are never removed.
+#[cfg(unix)]
+pub fn bind_unix(path: &Path) -> io::Result<UnixListener> {
+    let res = match UnixListener::bind(path) {
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse && is_stale(path) => {
+            fs::remove_file(path).and_then(|_| UnixListener::bind(path))
+        }
+        res => res,
+    };
+
+    res.map_err(|e| unix_bind_error(path, e))
+}
+
+/// Whether `path` is a socket file nothing listens on.
+#[cfg(unix)]
+fn is_stale(path: &Path) -> bool {
+    let is_socket = fs::symlink_metadata(path)
+        .map(|meta| meta.file_type().is_socket())
+        .unwrap_or(false);
+
+    is_socket
+        && matches!(
+            UnixStream::connect(path),
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused
+        )
+}
+
+/// Explain why binding to the Unix socket at `path` failed. The returned
+/// error keeps the kind of `err`.
+#[cfg(unix)]
+fn unix_bind_error(path: &Path, err: io::Error) -> io::Error {
+    let is_socket = fs::symlink_metadata(path)
+        .map(|meta| meta.file_type().is_socket())
+        .unwrap_or(false);
+
+    let msg = match err.kind() {
+        io::ErrorKind::AddrInUse if is_socket => {
+            format!("socket {} is already in use", path.display())
+        }
+        io::ErrorKind::AddrInUse => {
+            format!("{} already exists and is not a socket", path.display())
+        }
+        _ => format!("could not listen at {}: {}", path.display(), err),
+    };
+
+    io::Error::new(err.kind(), msg)
+}
+
+/// Return the URL of a server listening at `address` and `port`, using
+/// `scheme`, i.e. `http` or `https`.
+///
+/// IPv6 addresses are enclosed in brackets, e.g. `http://[::1]:8080`.
+pub fn url(scheme: &str, address: &str, port: u16) -> String {
+    format!("{}://{}", scheme, host_port(address, port))
+}
+
+/// Join an address and a port, enclosing IPv6 addresses in brackets.
+fn host_port(address: &str, port: u16) -> String {
+    match address.parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) => format!("[{}]:{}", ip, port),
+        _ => format!("{}:{}", address, port),
+    }
+}
+
+/// Return whether `address` is a wildcard address, such as `0.0.0.0`,
+/// meaning the server listens on all interfaces.
+pub fn is_wildcard(address: &str) -> bool {
+    address
+        .parse::<IpAddr>()
+        .map(|ip| ip.is_unspecified())
+        .unwrap_or(false)
+}
+
+/// Return the non-loopback IPv4 addresses of this machine.
+///
+/// Addresses are detected by connecting a UDP socket to the addresses in
+/// [`PROBES`] and reading its local address, which doesn't require any
+/// platform-specific APIs. Interfaces without a route to any of the probed
+/// addresses are not found. Returns an empty list if detection fails.
+pub fn local_ipv4_addrs() -> Vec<Ipv4Addr> {
+    let mut addrs = Vec::new();
+
+    for probe in PROBES {
+        let addr = UdpSocket::bind("0.0.0.0:0")
+            .and_then(|socket| {
+                socket.connect(probe)?;
+                socket.local_addr()
+            })
+            .map(|addr| addr.ip());
+
+        if let Ok(IpAddr::V4(ip)) = addr {
+            if !ip.is_loopback() && !ip.is_unspecified() && !addrs.contains(&ip)
+            {
+                addrs.push(ip);
+            }
+        }
+    }
+
+    addrs
+}
+
+/// Return the URLs the server listening at `address` and `port` can be
+/// reached at.
+///
+/// For the IPv4 wildcard address, this is the loopback address followed by
+/// each of the `local` addresses of this machine, or just the wildcard address
+/// if no local addresses are known.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::cli::net::server_urls;
+/// # use std::net::Ipv4Addr;
+/// let local = [Ipv4Addr::new(192, 168, 1, 2)];
+///
+/// assert_eq!(
+///     server_urls("http", "0.0.0.0", 8080, &local),
+///     vec!["http://127.0.0.1:8080", "http://192.168.1.2:8080"]
+/// );
+/// ```
+pub fn server_urls(
+    scheme: &str,
+    address: &str,
+    port: u16,
+    local: &[Ipv4Addr],
+) -> Vec<String> {
+    match address.parse::<Ipv4Addr>() {
+        Ok(ip) if ip.is_unspecified() && !local.is_empty() => {
+            std::iter::once(Ipv4Addr::LOCALHOST)
+                .chain(local.iter().copied())
+                .map(|ip| url(scheme, &ip.to_string(), port))
+                .collect()
+        }
+        _ => vec![url(scheme, address, port)],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wildcard() {
+        assert!(is_wildcard("0.0.0.0"));
+        assert!(is_wildcard("::"));
+        assert!(!is_wildcard("127.0.0.1"));
+        assert!(!is_wildcard("localhost"));
+    }
+
+    #[test]
+    fn addresses() {
+        let cases = [
+            ("::1", Some("::1")),
+            ("[::1]", Some("::1")),
+            ("::", Some("::")),
+            ("[fe80::1]", Some("fe80::1")),
+            ("0.0.0.0", Some("0.0.0.0")),
+            ("127.0.0.1", Some("127.0.0.1")),
+            ("localhost", Some("localhost")),
+            ("my-host.local", Some("my-host.local")),
+            ("", None),
+            ("[127.0.0.1]", None),
+            ("[::1", None),
+            ("::1:8080:", None),
+            ("256.0.0.1", None),
+            ("-host", None),
+            ("host..local", None),
+            ("local host", None),
+            ("127.0.0.1:8080", None),
+        ];
+
+        for (address, expected) in cases.iter() {
+            assert_eq!(
+                parse_address(address).as_deref(),
+                *expected,
+                "{}",
+                address
+            );
+        }
+    }
+
+    #[test]
+    fn socket_addresses() {
+        assert_eq!(
+            socket_addrs("::1", 8080).unwrap(),
+            vec!["[::1]:8080".parse().unwrap()]
+        );
+        assert_eq!(
+            socket_addrs("0.0.0.0", 80).unwrap(),
+            vec!["0.0.0.0:80".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn bind_any_port() {
+        let listener = bind("127.0.0.1", 0, 0).unwrap();
+
+        assert_ne!(listener.local_addr().unwrap().port(), 0);
+    }
+
+    #[test]
+    fn bind_busy_port() {
+        let busy = bind("127.0.0.1", 0, 0).unwrap();
+        let port = busy.local_addr().unwrap().port();
+
+        let err = bind("127.0.0.1", port, 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "port {} is already in use — try --port 0 or pick another \
+                 port",
+                port
+            )
+        );
+
+        let listener = bind("127.0.0.1", port, 10).unwrap();
+        let retried = listener.local_addr().unwrap().port();
+        assert!(retried > port && retried <= port + 10);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn bind_reuse_port_listeners() {
+        let listeners = bind_reuse_port("127.0.0.1", 0, 0, 4).unwrap();
+        let addr = listeners[0].local_addr().unwrap();
+        assert_eq!(listeners.len(), 4);
+        assert!(listeners.iter().all(|l| l.local_addr().unwrap() == addr));
+
+        // Connections are spread by a hash of the client address, so many
+        // connections reach each listener with a high probability
+        let clients: Vec<_> = (0..64)
+            .map(|_| std::net::TcpStream::connect(addr).unwrap())
+            .collect();
+        for listener in &listeners {
+            listener.set_nonblocking(true).unwrap();
+            assert!(listener.accept().is_ok());
+        }
+        drop(clients);
+
+        // Other servers can't share the port
+        let err = bind_reuse_port("127.0.0.1", addr.port(), 0, 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        assert!(bind("127.0.0.1", addr.port(), 0).is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn buffer_sizes() {
+        let listener = bind("127.0.0.1", 0, 0).unwrap();
+        set_buffer_sizes(&listener, Some(64 * 1024), Some(32 * 1024)).unwrap();
+
+        let _client =
+            std::net::TcpStream::connect(listener.local_addr().unwrap())
+                .unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        // Linux doubles the sizes set for its bookkeeping
+        assert_eq!(
+            sys::buffer_sizes(&stream).unwrap(),
+            (128 * 1024, 64 * 1024)
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn bind_unix_socket() {
+        let dir = crate::test_utils::TempDir::new();
+        let path = dir.path().join("servum.sock");
+
+        let listener = bind_unix(&path).unwrap();
+        let err = bind_unix(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        assert_eq!(
+            err.to_string(),
+            format!("socket {} is already in use", path.display())
+        );
+
+        // The socket file outlives the listener
+        drop(listener);
+        assert!(path.exists());
+        assert!(bind_unix(&path).is_ok());
+
+        let file = dir.file("index.html", "");
+        let err = bind_unix(&file).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("{} already exists and is not a socket", file.display())
+        );
+        assert!(file.exists());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn shutdown_wakes_accept() {
+        let listener = bind_reuse_port("127.0.0.1", 0, 0, 1).unwrap().remove(0);
+        let listener = std::sync::Arc::new(listener);
+        let accepting = {
+            let listener = listener.clone();
+            std::thread::spawn(move || listener.accept().map(drop))
+        };
+
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        shutdown_listener(&listener).unwrap();
+        assert!(accepting.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn bind_errors() {
+        let busy = bind("127.0.0.1", 0, 0).unwrap();
+        let port = busy.local_addr().unwrap().port();
+        let err = TcpListener::bind(("127.0.0.1", port)).unwrap_err();
+
+        assert_eq!(
+            bind_error("127.0.0.1", port, 2, err).to_string(),
+            format!(
+                "ports {}–{} are already in use — try --port 0 or pick \
+                 another port",
+                port,
+                port + 2
+            )
+        );
+
+        let denied = || io::Error::from(io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            bind_error("::", 443, 0, denied()).to_string(),
+            "binding to port 443 requires elevated privileges"
+        );
+        assert_eq!(
+            bind_error("::", 8080, 0, denied()).to_string(),
+            "could not listen at [::]:8080: permission denied"
+        );
+
+        let unavailable = io::ErrorKind::AddrNotAvailable.into();
+        assert_eq!(
+            bind_error("10.1.2.3", 8080, 0, unavailable).to_string(),
+            "address '10.1.2.3' is not available on this machine"
+        );
+    }
+
+    #[test]
+    fn bind_invalid_address() {
+        let err = bind("256.0.0.1", 8080, 0).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "invalid address '256.0.0.1'");
+    }
+
+    #[test]
+    fn ipv6_urls() {
+        assert_eq!(url("http", "::1", 8080), "http://[::1]:8080");
+        assert_eq!(url("http", "localhost", 8080), "http://localhost:8080");
+        assert_eq!(url("https", "localhost", 443), "https://localhost:443");
+        assert_eq!(server_urls("http", "::", 80, &[]), vec!["http://[::]:80"]);
+    }
+
+    #[test]
+    fn urls() {
+        let local = [Ipv4Addr::new(192, 168, 1, 2), Ipv4Addr::new(10, 0, 0, 3)];
+
+        assert_eq!(
+            server_urls("http", "127.0.0.1", 3000, &local),
+            vec!["http://127.0.0.1:3000"]
+        );
+        assert_eq!(
+            server_urls("http", "0.0.0.0", 3000, &local),
+            vec![
+                "http://127.0.0.1:3000",
+                "http://192.168.1.2:3000",
+                "http://10.0.0.3:3000"
+            ]
+        );
+    }
+
+    #[test]
+    fn urls_fallback() {
+        assert_eq!(
+            server_urls("http", "0.0.0.0", 3000, &[]),
+            vec!["http://0.0.0.0:3000"]
+        );
+    }
+
+    #[test]
+    fn local_addrs() {
+        for ip in local_ipv4_addrs() {
+            assert!(!ip.is_loopback());
+        }
+    }
+}
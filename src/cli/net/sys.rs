@@ -0,0 +1,239 @@
+//! Socket options the standard library doesn't expose, such as
+//! `SO_REUSEPORT`, which must be set before binding, or the buffer sizes.
+//!
+//! The few socket calls needed are declared here rather than taken from the
+//! `libc` crate. The C library is linked by the standard library anyway.
+use std::{
+    convert::TryFrom,
+    io,
+    net::{SocketAddr, TcpListener},
+    os::raw::{c_int, c_void},
+    os::unix::io::{AsRawFd, FromRawFd, OwnedFd},
+};
+
+const AF_INET: u16 = 2;
+const AF_INET6: u16 = 10;
+const SHUT_RDWR: c_int = 2;
+/// Same backlog as [`TcpListener::bind`]
+const BACKLOG: c_int = 128;
+
+#[cfg(not(any(
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "sparc64"
+)))]
+mod consts {
+    use std::os::raw::c_int;
+
+    pub const SOCK_STREAM: c_int = 1;
+    pub const SOCK_CLOEXEC: c_int = 0o2000000;
+    pub const SOL_SOCKET: c_int = 1;
+    pub const SO_REUSEADDR: c_int = 2;
+    pub const SO_REUSEPORT: c_int = 15;
+    pub const SO_SNDBUF: c_int = 7;
+    pub const SO_RCVBUF: c_int = 8;
+}
+
+#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+mod consts {
+    use std::os::raw::c_int;
+
+    pub const SOCK_STREAM: c_int = 2;
+    pub const SOCK_CLOEXEC: c_int = 0o2000000;
+    pub const SOL_SOCKET: c_int = 0xffff;
+    pub const SO_REUSEADDR: c_int = 0x0004;
+    pub const SO_REUSEPORT: c_int = 0x0200;
+    pub const SO_SNDBUF: c_int = 0x1001;
+    pub const SO_RCVBUF: c_int = 0x1002;
+}
+
+#[cfg(target_arch = "sparc64")]
+mod consts {
+    use std::os::raw::c_int;
+
+    pub const SOCK_STREAM: c_int = 1;
+    pub const SOCK_CLOEXEC: c_int = 0x400000;
+    pub const SOL_SOCKET: c_int = 0xffff;
+    pub const SO_REUSEADDR: c_int = 0x0004;
+    pub const SO_REUSEPORT: c_int = 0x0200;
+    pub const SO_SNDBUF: c_int = 0x1001;
+    pub const SO_RCVBUF: c_int = 0x1002;
+}
+
+use consts::*;
+
+extern "C" {
+    fn socket(domain: c_int, ty: c_int, protocol: c_int) -> c_int;
+    fn setsockopt(
+        fd: c_int,
+        level: c_int,
+        name: c_int,
+        value: *const c_void,
+        len: u32,
+    ) -> c_int;
+    #[cfg(test)]
+    fn getsockopt(
+        fd: c_int,
+        level: c_int,
+        name: c_int,
+        value: *mut c_void,
+        len: *mut u32,
+    ) -> c_int;
+    fn bind(fd: c_int, addr: *const c_void, len: u32) -> c_int;
+    fn listen(fd: c_int, backlog: c_int) -> c_int;
+    fn shutdown(fd: c_int, how: c_int) -> c_int;
+}
+
+/// `struct sockaddr_in`
+#[repr(C)]
+struct SockaddrIn {
+    family: u16,
+    port: u16,
+    addr: [u8; 4],
+    zero: [u8; 8],
+}
+
+/// `struct sockaddr_in6`
+#[repr(C)]
+struct SockaddrIn6 {
+    family: u16,
+    port: u16,
+    flowinfo: u32,
+    addr: [u8; 16],
+    scope_id: u32,
+}
+
+/// Turn the return value of a socket call into a result.
+fn check(ret: c_int) -> io::Result<c_int> {
+    match ret {
+        -1 => Err(io::Error::last_os_error()),
+        ret => Ok(ret),
+    }
+}
+
+/// Bind a listener to `addr` with both `SO_REUSEADDR`, as the standard
+/// library sets, and `SO_REUSEPORT`, so that further listeners can be bound
+/// to the same address by this user.
+pub fn bind_reuse_port(addr: &SocketAddr) -> io::Result<TcpListener> {
+    let domain = match addr {
+        SocketAddr::V4(_) => AF_INET,
+        SocketAddr::V6(_) => AF_INET6,
+    };
+
+    // SAFETY: a successful call returns a new descriptor owned by nobody else
+    let fd = unsafe {
+        let fd = check(socket(domain.into(), SOCK_STREAM | SOCK_CLOEXEC, 0))?;
+        OwnedFd::from_raw_fd(fd)
+    };
+
+    set_option(fd.as_raw_fd(), SO_REUSEADDR, 1)?;
+    set_option(fd.as_raw_fd(), SO_REUSEPORT, 1)?;
+
+    // SAFETY: the address points to a sockaddr of the given length
+    check(unsafe {
+        match addr {
+            SocketAddr::V4(addr) => {
+                let sockaddr = SockaddrIn {
+                    family: AF_INET,
+                    port: addr.port().to_be(),
+                    addr: addr.ip().octets(),
+                    zero: [0; 8],
+                };
+                bind(
+                    fd.as_raw_fd(),
+                    &sockaddr as *const SockaddrIn as *const c_void,
+                    std::mem::size_of::<SockaddrIn>() as u32,
+                )
+            }
+            SocketAddr::V6(addr) => {
+                let sockaddr = SockaddrIn6 {
+                    family: AF_INET6,
+                    port: addr.port().to_be(),
+                    flowinfo: addr.flowinfo(),
+                    addr: addr.ip().octets(),
+                    scope_id: addr.scope_id(),
+                };
+                bind(
+                    fd.as_raw_fd(),
+                    &sockaddr as *const SockaddrIn6 as *const c_void,
+                    std::mem::size_of::<SockaddrIn6>() as u32,
+                )
+            }
+        }
+    })?;
+
+    // SAFETY: the descriptor is a bound socket
+    check(unsafe { listen(fd.as_raw_fd(), BACKLOG) })?;
+
+    Ok(TcpListener::from(fd))
+}
+
+/// Set the send and receive buffer sizes of a socket, in bytes. The sizes of
+/// a listener are inherited by the connections it accepts.
+pub fn set_buffer_sizes<S: AsRawFd>(
+    socket: &S,
+    send: Option<usize>,
+    recv: Option<usize>,
+) -> io::Result<()> {
+    for (option, size) in [(SO_SNDBUF, send), (SO_RCVBUF, recv)] {
+        if let Some(size) = size {
+            let size = c_int::try_from(size)
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+            set_option(socket.as_raw_fd(), option, size)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Return the send and receive buffer sizes of a socket, in bytes. Linux
+/// reports twice the sizes set, to account for its bookkeeping overhead.
+#[cfg(test)]
+pub fn buffer_sizes<S: AsRawFd>(socket: &S) -> io::Result<(usize, usize)> {
+    let send = get_option(socket.as_raw_fd(), SO_SNDBUF)?;
+    let recv = get_option(socket.as_raw_fd(), SO_RCVBUF)?;
+
+    Ok((send as usize, recv as usize))
+}
+
+/// Set an integer socket option.
+fn set_option(fd: c_int, option: c_int, value: c_int) -> io::Result<()> {
+    // SAFETY: the value points to an int of the given length
+    check(unsafe {
+        setsockopt(
+            fd,
+            SOL_SOCKET,
+            option,
+            &value as *const c_int as *const c_void,
+            std::mem::size_of::<c_int>() as u32,
+        )
+    })
+    .map(drop)
+}
+
+/// Read an integer socket option.
+#[cfg(test)]
+fn get_option(fd: c_int, option: c_int) -> io::Result<c_int> {
+    let mut value: c_int = 0;
+    let mut len = std::mem::size_of::<c_int>() as u32;
+
+    // SAFETY: the value points to an int of the given length
+    check(unsafe {
+        getsockopt(
+            fd,
+            SOL_SOCKET,
+            option,
+            &mut value as *mut c_int as *mut c_void,
+            &mut len,
+        )
+    })?;
+
+    Ok(value)
+}
+
+/// Shut down a listener, waking up the threads blocked accepting connections
+/// on it with an error.
+pub fn shutdown_listener(listener: &TcpListener) -> io::Result<()> {
+    // SAFETY: the descriptor is owned by the listener, which outlives the call
+    check(unsafe { shutdown(listener.as_raw_fd(), SHUT_RDWR) }).map(drop)
+}
@@ -0,0 +1,105 @@
+//! Open the served URL in the user's browser
+use super::net;
+use std::{
+    io,
+    net::IpAddr,
+    process::{Command, Stdio},
+};
+
+/// Return the URL to open in the browser for a server listening at `address`
+/// and `port`, using `scheme`, i.e. `http` or `https`.
+///
+/// Wildcard addresses, such as `0.0.0.0`, are replaced by `localhost`.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::cli::open::browser_url;
+/// assert_eq!(browser_url("http", "0.0.0.0", 8080), "http://localhost:8080/");
+/// assert_eq!(browser_url("http", "::1", 8080), "http://[::1]:8080/");
+/// ```
+pub fn browser_url(scheme: &str, address: &str, port: u16) -> String {
+    let address = match address.parse::<IpAddr>() {
+        Ok(ip) if ip.is_unspecified() => "localhost",
+        _ => address,
+    };
+
+    net::url(scheme, address, port) + "/"
+}
+
+/// Return the command opening `url` with the platform's default handler.
+fn opener(url: &str) -> Command {
+    #[cfg(target_os = "windows")]
+    let command = {
+        let mut command = Command::new("cmd");
+        // The empty argument is the window title used by start
+        command.args(["/C", "start", "", url]);
+        command
+    };
+
+    #[cfg(target_os = "macos")]
+    let command = {
+        let mut command = Command::new("open");
+        command.arg(url);
+        command
+    };
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let command = {
+        let mut command = Command::new("xdg-open");
+        command.arg(url);
+        command
+    };
+
+    command
+}
+
+/// Open `url` in the user's default browser.
+///
+/// Spawns the platform opener (`xdg-open`, `open` or `cmd /C start`) without
+/// waiting for it to finish. Returns an error if the opener could not be
+/// started.
+pub fn open(url: &str) -> io::Result<()> {
+    opener(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn urls() {
+        let cases = [
+            ("127.0.0.1", 8080, "http://127.0.0.1:8080/"),
+            ("0.0.0.0", 3000, "http://localhost:3000/"),
+            ("::", 3000, "http://localhost:3000/"),
+            ("::1", 80, "http://[::1]:80/"),
+            ("example.local", 80, "http://example.local:80/"),
+        ];
+
+        for (address, port, expected) in cases.iter() {
+            assert_eq!(browser_url("http", address, *port), *expected);
+        }
+
+        assert_eq!(
+            browser_url("https", "0.0.0.0", 8443),
+            "https://localhost:8443/"
+        );
+    }
+
+    #[test]
+    fn opener_args() {
+        let command = opener("http://localhost:8080/");
+        let args: Vec<_> = command.get_args().collect();
+
+        assert_eq!(
+            args.last().unwrap().to_str(),
+            Some("http://localhost:8080/")
+        );
+    }
+}
@@ -0,0 +1,964 @@
+//! Registry of the command line options known to servum
+
+/// Description of a command line option.
+///
+/// The registry of all options, [`OPTIONS`], drives argument parsing and is
+/// used to generate the help menus and shell completions, and to suggest the
+/// closest option on unknown arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CliOption {
+    /// Short form without the leading `-`, e.g. `p` for `-p`
+    pub short: Option<&'static str>,
+    /// Long form without the leading `--`, e.g. `port` for `--port`
+    pub long: &'static str,
+    /// Name of the value taken by the option, e.g. `NUM`, or [`None`] for
+    /// flags
+    pub value: Option<&'static str>,
+    /// Whether the option may be given multiple times
+    pub repeatable: bool,
+    /// One-line summary for the short help menu
+    pub summary: &'static str,
+    /// Detailed description for the long help menu
+    pub help: &'static str,
+}
+
+/// All command line options, in the order they appear in the help menus.
+pub const OPTIONS: &[CliOption] = &[
+    CliOption {
+        short: Some("a"),
+        long: "address",
+        value: Some("STRING"),
+        repeatable: false,
+        summary: "Address to listen on. Default is 127.0.0.1",
+        help: "Address to listen on. May be an IPv4 or IPv6 address, with or \
+               without brackets, or a hostname. Use :: to listen on all IPv6 \
+               interfaces and, on most platforms, all IPv4 interfaces too. \
+               Default is the loopback address 127.0.0.1, i.e localhost.",
+    },
+    CliOption {
+        short: None,
+        long: "public",
+        value: None,
+        repeatable: false,
+        summary: "Listen on all interfaces (0.0.0.0).",
+        help:
+            "Listen on all interfaces, i.e. the address 0.0.0.0, to make the \
+               server reachable from other devices on the network. The \
+               network URLs of the server are printed on startup.",
+    },
+    CliOption {
+        short: Some("p"),
+        long: "port",
+        value: Some("NUM"),
+        repeatable: false,
+        summary: "Port to listen on. Default is 8080",
+        help:
+            "Port to listen on. Note that some ports, such as port 80 (HTTP) \
+               require elevated privileges to bind to and may already be in \
+               use. Must be between 0 and 65535. Use 0 to let the operating \
+               system choose a free port. Default is 8080.",
+    },
+    CliOption {
+        short: None,
+        long: "port-retry",
+        value: Some("NUM"),
+        repeatable: false,
+        summary: "Try NUM following ports if in use.",
+        help: "If the port is already in use, try up to NUM following ports \
+               before giving up. Default is 0.",
+    },
+    CliOption {
+        short: None,
+        long: "unix-socket",
+        value: Some("PATH"),
+        repeatable: false,
+        summary: "Listen on a Unix socket instead (Unix).",
+        help: "Listen on a Unix domain socket at PATH instead of a TCP port, \
+               e.g. behind a reverse proxy on the same host. A stale socket \
+               file left at PATH is replaced, and the socket file is removed \
+               on shutdown. Only supported on Unix.",
+    },
+    CliOption {
+        short: None,
+        long: "pid-file",
+        value: Some("PATH"),
+        repeatable: false,
+        summary: "Write the process id to a file while running.",
+        help: "Write the process id to PATH once the server is listening, \
+               and remove the file on shutdown, for start and stop scripts. \
+               The server refuses to start if the file belongs to a process \
+               which is still running. A stale file left by a crash is \
+               replaced with a warning.",
+    },
+    CliOption {
+        short: None,
+        long: "tls-cert",
+        value: Some("PEM"),
+        repeatable: false,
+        summary: "Serve HTTPS with this certificate chain.",
+        help: "Serve HTTPS using the PEM-encoded certificate chain PEM. \
+               Requires --tls-key and servum being built with the tls \
+               feature.",
+    },
+    CliOption {
+        short: None,
+        long: "tls-key",
+        value: Some("PEM"),
+        repeatable: false,
+        summary: "Serve HTTPS with this private key.",
+        help: "Serve HTTPS using the PEM-encoded private key PEM. Requires \
+               --tls-cert.",
+    },
+    CliOption {
+        short: None,
+        long: "redirect-http",
+        value: Some("PORT"),
+        repeatable: false,
+        summary: "Redirect plain HTTP on PORT to HTTPS.",
+        help: "Also listen for plain HTTP on PORT, answering every request \
+               with 301 Moved Permanently to the same URL over HTTPS, e.g. \
+               --redirect-http 80 next to --port 443. Requires --tls-cert \
+               and --tls-key.",
+    },
+    CliOption {
+        short: None,
+        long: "delay",
+        value: Some("MS"),
+        repeatable: false,
+        summary: "Wait MS milliseconds before responding.",
+        help: "Simulate latency by waiting MS milliseconds before writing \
+               each response, including error pages. Default is 0.",
+    },
+    CliOption {
+        short: None,
+        long: "throttle",
+        value: Some("KBPS"),
+        repeatable: false,
+        summary: "Limit bandwidth to KBPS kB/s per request.",
+        help: "Simulate a slow connection by limiting the response body of \
+               each request to KBPS kilobytes per second. Default is 0, \
+               i.e. unlimited.",
+    },
+    CliOption {
+        short: None,
+        long: "request-deadline",
+        value: Some("SECS"),
+        repeatable: false,
+        summary: "Give up on responses after SECS seconds.",
+        help: "Close the connection if writing a response takes longer than \
+               SECS seconds from reading the request, so that clients reading \
+               very slowly don't hold a thread. The live reload event stream \
+               is exempt. Default is 300, 0 disables the deadline.",
+    },
+    CliOption {
+        short: None,
+        long: "base-dir",
+        value: Some("DIR"),
+        repeatable: false,
+        summary: "Directory to serve content from.",
+        help: "Base directory to serve content from. Same as the BASE_DIR \
+               argument.",
+    },
+    CliOption {
+        short: None,
+        long: "overlay",
+        value: Some("DIR"),
+        repeatable: true,
+        summary: "Serve files missing from the base dir from DIR.",
+        help: "Serve files from DIR when the base directory has none at the \
+               requested path, e.g. --base-dir dist --overlay public. \
+               Overlays are tried in the order given, and directory listings \
+               show the entries of all directories, those of the base \
+               directory winning on name conflicts. Uploads are always \
+               written to the base directory. Can be given multiple times.",
+    },
+    CliOption {
+        short: None,
+        long: "acme-dir",
+        value: Some("DIR"),
+        repeatable: false,
+        summary: "Directory of ACME challenge files.",
+        help: "Answer ACME HTTP challenges, i.e. requests below \
+               /.well-known/acme-challenge/, with the files of DIR, whatever \
+               the ignore rules. With --redirect-http, challenges are \
+               answered over plain HTTP instead of being redirected.",
+    },
+    CliOption {
+        short: Some("t"),
+        long: "threads",
+        value: Some("NUM|auto"),
+        repeatable: false,
+        summary: "Number of threads. Default is {threads}.",
+        help: "Number of parallel threads to handle request with. Must be at \
+               least 1, or auto to use the number of available CPUs, capped \
+               at 16. Default is auto, i.e. {threads} on this machine.",
+    },
+    CliOption {
+        short: None,
+        long: "queue",
+        value: Some("NUM"),
+        repeatable: false,
+        summary: "Answer 503 beyond NUM waiting requests.",
+        help: "Queue up to NUM connections waiting for a free thread. Further \
+               connections are answered with 503 Service Unavailable right \
+               away. Default is 1024.",
+    },
+    CliOption {
+        short: None,
+        long: "stats-interval",
+        value: Some("SECS"),
+        repeatable: false,
+        summary: "Print thread pool statistics every SECS seconds.",
+        help: "Print statistics of the thread pool to the console every SECS \
+               seconds: busy and total threads, waiting and open \
+               connections, jobs done and time spent on them.",
+    },
+    CliOption {
+        short: None,
+        long: "max-connections",
+        value: Some("NUM"),
+        repeatable: false,
+        summary: "Keep at most NUM connections open.",
+        help: "Keep at most NUM connections open at once, whether waiting \
+               or being handled, so that clients can't exhaust the file \
+               descriptors of the server. What happens to further \
+               connections is set with --connection-overflow. Unlimited by \
+               default.",
+    },
+    CliOption {
+        short: None,
+        long: "connection-overflow",
+        value: Some("MODE"),
+        repeatable: false,
+        summary: "At --max-connections: reject or wait.",
+        help: "What to do with new connections while --max-connections \
+               connections are open, either reject to answer them with 503 \
+               Service Unavailable right away, or wait to stop accepting \
+               connections until one is closed. Default is reject.",
+    },
+    CliOption {
+        short: None,
+        long: "reuse-port",
+        value: None,
+        repeatable: false,
+        summary: "Accept connections on every thread (Linux).",
+        help: "Give each thread a listener of its own, bound to the same \
+               port with SO_REUSEPORT, and let the operating system spread \
+               connections between them. Each thread accepts and handles \
+               its connections itself, without a shared queue, so --queue \
+               has no effect. Can't be combined with --max-connections. \
+               Only supported on Linux.",
+    },
+    CliOption {
+        short: None,
+        long: "no-nodelay",
+        value: None,
+        repeatable: false,
+        summary: "Let the OS batch small writes (Nagle).",
+        help: "Keep Nagle's algorithm enabled on connections, letting the \
+               operating system hold back small writes to batch them. By \
+               default, TCP_NODELAY is set so that small responses, such as \
+               error pages, aren't delayed by up to 40 ms.",
+    },
+    CliOption {
+        short: None,
+        long: "send-buffer",
+        value: Some("BYTES"),
+        repeatable: false,
+        summary: "Send buffer size of connections (Linux).",
+        help: "Size of the send buffer of each connection in bytes. The \
+               operating system may round or cap it. Uses the system \
+               default unless set. Only supported on Linux.",
+    },
+    CliOption {
+        short: None,
+        long: "recv-buffer",
+        value: Some("BYTES"),
+        repeatable: false,
+        summary: "Receive buffer size of connections (Linux).",
+        help: "Size of the receive buffer of each connection in bytes. The \
+               operating system may round or cap it. Uses the system \
+               default unless set. Only supported on Linux.",
+    },
+    CliOption {
+        short: None,
+        long: "default-mime",
+        value: Some("TYPE"),
+        repeatable: false,
+        summary: "MIME type for unknown extensions.",
+        help: "MIME type to use for files with an unknown or missing \
+               extension, e.g. application/octet-stream. By default, no \
+               Content-Type header is sent for such files.",
+    },
+    CliOption {
+        short: None,
+        long: "cache-control",
+        value: Some("VALUE"),
+        repeatable: false,
+        summary: "Send a Cache-Control header.",
+        help: "Send the Cache-Control header VALUE with successful responses \
+               not matched by any --cache-rule, e.g. no-cache. By default, no \
+               Cache-Control header is sent.",
+    },
+    CliOption {
+        short: None,
+        long: "cache-rule",
+        value: Some("GLOB=VALUE"),
+        repeatable: true,
+        summary: "Set Cache-Control for matching paths.",
+        help: "Send the Cache-Control header VALUE with successful responses \
+               to URL paths matching GLOB, e.g. \
+               --cache-rule 'assets/**=max-age=31536000, immutable'. Rules \
+               are tried in order and the first match wins. Can be given \
+               multiple times.",
+    },
+    CliOption {
+        short: None,
+        long: "block-method",
+        value: Some("METHOD"),
+        repeatable: true,
+        summary: "Answer METHOD requests with 405.",
+        help: "Answer requests with the method METHOD with 405 Method Not \
+               Allowed before looking at their path, e.g. --block-method \
+               PROPFIND. TRACE, TRACK and CONNECT are always blocked. Can be \
+               given multiple times.",
+    },
+    CliOption {
+        short: None,
+        long: "redirect",
+        value: Some("FROM=TO[:CODE]"),
+        repeatable: true,
+        summary: "Redirect requests for FROM to TO.",
+        help: "Answer requests for the URL path FROM with a redirect to TO, \
+               a path or an absolute URL, before looking for files, e.g. \
+               --redirect /docs/*=https://docs.example.com/*:301. FROM \
+               ending with * matches every path below it, segment by \
+               segment, so /go* matches /go/x but not /gopher, and a \
+               trailing * in TO is replaced by the rest of the path. CODE \
+               is 301, 302, 303, 307 or 308 and defaults to 302. Rules are \
+               tried in order and the first match wins. Can be given \
+               multiple times.",
+    },
+    CliOption {
+        short: None,
+        long: "mime",
+        value: Some("EXT=TYPE"),
+        repeatable: true,
+        summary: "Override the MIME type of an extension.",
+        help: "Serve files with the extension EXT using the MIME type TYPE, \
+               e.g. --mime glsl=text/plain. Takes precedence over the \
+               built-in MIME types. May be given multiple times.",
+    },
+    CliOption {
+        short: None,
+        long: "mime-file",
+        value: Some("FILE"),
+        repeatable: true,
+        summary: "Read MIME type overrides from a file.",
+        help: "Read MIME type overrides from FILE. Each line contains an \
+               extension and a MIME type separated by whitespace, e.g. \
+               \"glsl text/plain\". Blank lines and lines starting with # are \
+               ignored.",
+    },
+    CliOption {
+        short: None,
+        long: "open",
+        value: None,
+        repeatable: false,
+        summary: "Open the server URL in the browser.",
+        help: "Open the server URL in the default browser once the server is \
+               listening. Wildcard addresses are replaced by localhost.",
+    },
+    CliOption {
+        short: None,
+        long: "log-file",
+        value: Some("FILE"),
+        repeatable: false,
+        summary: "Append the request log to a file.",
+        help: "Append the request log to FILE, creating it if missing. The \
+               log is written independently of the console output, even with \
+               --quiet.",
+    },
+    CliOption {
+        short: None,
+        long: "log-rotate-size",
+        value: Some("MB"),
+        repeatable: false,
+        summary: "Rotate the log file at MB megabytes.",
+        help: "Once the log file reaches MB megabytes, rename it to \
+               FILE.1, shifting older rotated files to FILE.2 and so on, and \
+               start a new log file. Requires --log-file. By default, the \
+               log file is never rotated.",
+    },
+    CliOption {
+        short: None,
+        long: "log-rotate-keep",
+        value: Some("NUM"),
+        repeatable: false,
+        summary: "Keep NUM rotated log files. Default is 5",
+        help: "Number of rotated log files to keep with --log-rotate-size. \
+               The oldest file is deleted when another one is rotated. \
+               Default is 5.",
+    },
+    CliOption {
+        short: None,
+        long: "log-format",
+        value: Some("FORMAT"),
+        repeatable: false,
+        summary: "Log format: pretty, common or json.",
+        help: "Format of the request log, either pretty for human-readable \
+               columns, common for the Apache Common Log Format or json for \
+               one JSON object per request. Default is pretty.",
+    },
+    CliOption {
+        short: None,
+        long: "trust-proxy",
+        value: None,
+        repeatable: false,
+        summary: "Log the client address sent by a proxy.",
+        help: "Log the first address of the X-Forwarded-For header as the \
+               client address, for servers behind a reverse proxy or tunnel \
+               which would otherwise log every request as coming from the \
+               proxy. The common and json log formats show the address of \
+               the proxy as well. Only use it behind a proxy, as clients can \
+               send any address.",
+    },
+    CliOption {
+        short: None,
+        long: "no-color",
+        value: None,
+        repeatable: false,
+        summary: "Don't color the request log.",
+        help: "Don't color the request log. Colors are also disabled when \
+               the output is not a terminal or the NO_COLOR environment \
+               variable is set.",
+    },
+    CliOption {
+        short: None,
+        long: "quiet-errors",
+        value: Some("CODE|all"),
+        repeatable: true,
+        summary: "Don't log responses with this status.",
+        help: "Don't log responses with the status code CODE, e.g. 404, or \
+               with any error status for all, on the console or in the log \
+               file. The responses are still served normally. Can be given \
+               multiple times.",
+    },
+    CliOption {
+        short: None,
+        long: "log-path-width",
+        value: Some("NUM"),
+        repeatable: false,
+        summary: "Width of the logged paths. 0 is unlimited.",
+        help: "Maximum width of the path column of the pretty request log. \
+               Longer paths are shortened from the start, keeping the file \
+               name. Use 0 to never shorten paths. Default is 32.",
+    },
+    CliOption {
+        short: None,
+        long: "no-timestamps",
+        value: None,
+        repeatable: false,
+        summary: "Don't show timestamps in the request log.",
+        help: "Don't prepend a UTC timestamp to each line of the pretty \
+               request log.",
+    },
+    CliOption {
+        short: Some("q"),
+        long: "quiet",
+        value: None,
+        repeatable: false,
+        summary: "Don't print incoming requests.",
+        help: "Don't be verbose and stop printing information about incoming \
+               requests. Startup information is still printed.",
+    },
+    CliOption {
+        short: None,
+        long: "silent",
+        value: None,
+        repeatable: false,
+        summary: "Don't print anything but errors.",
+        help: "Don't print anything to stdout, including the logo, the \
+               configuration and incoming requests. Errors are still printed \
+               to stderr.",
+    },
+    CliOption {
+        short: Some("v"),
+        long: "verbose",
+        value: None,
+        repeatable: false,
+        summary: "Print debugging information.",
+        help: "Print debugging information to stderr in addition to incoming \
+               requests: how each request path was resolved to a file, from \
+               the percent-decoded path to the listing or index fallback \
+               answering it, and when connections are closed.",
+    },
+    CliOption {
+        short: None,
+        long: "no-logo",
+        value: None,
+        repeatable: false,
+        summary: "Don't print the logo on startup.",
+        help: "Don't print the servum logo on startup.",
+    },
+    CliOption {
+        short: None,
+        long: "no-warnings",
+        value: None,
+        repeatable: false,
+        summary: "Don't warn about the configuration on startup.",
+        help: "Don't print warnings on startup, e.g. about serving an empty \
+               directory or entries that usually hold secrets, such as \
+               .git, .env, id_rsa or .aws, directly in the base directory.",
+    },
+    CliOption {
+        short: None,
+        long: "follow-base-symlink",
+        value: None,
+        repeatable: false,
+        summary: "Follow swaps of a symlinked base directory.",
+        help: "Resolve the base directory again while serving, at most once \
+               a second, instead of only on startup. Useful when the base \
+               directory is a symlink swapped between release directories \
+               on deploys, which then take effect without a restart.",
+    },
+    CliOption {
+        short: None,
+        long: "health",
+        value: None,
+        repeatable: false,
+        summary: "Serve a health check at /_servum/health.",
+        help: "Answer GET /_servum/health with a small JSON status, e.g. for \
+               container health checks or readiness probes. The status is \
+               503 Service Unavailable if the base directory can't be read, \
+               and 200 OK otherwise.",
+    },
+    CliOption {
+        short: None,
+        long: "manifest",
+        value: None,
+        repeatable: false,
+        summary: "Serve a JSON list of all files being served.",
+        help: "Answer GET /_servum/manifest.json with a JSON list of every \
+               file served from the base directory, with its path, size, \
+               modification time and MIME type, e.g. for a frontend building \
+               its navigation. Ignored files are left out.",
+    },
+    CliOption {
+        short: None,
+        long: "manifest-max-depth",
+        value: Some("NUM"),
+        repeatable: false,
+        summary: "List files up to NUM directories deep.",
+        help: "Leave files with paths of more than NUM components out of the \
+               manifest, which is then marked as truncated. Default is 16.",
+    },
+    CliOption {
+        short: None,
+        long: "manifest-max-entries",
+        value: Some("NUM"),
+        repeatable: false,
+        summary: "List at most NUM files in the manifest.",
+        help: "Stop walking the base directory for the manifest after NUM \
+               files, shallower files first, and mark it as truncated. \
+               Default is 10000.",
+    },
+    CliOption {
+        short: None,
+        long: "live-reload",
+        value: None,
+        repeatable: false,
+        summary: "Reload pages when files change.",
+        help: "Watch the base directory for changes and reload open HTML \
+               pages when a file is added, modified or removed. A small \
+               script listening to /_servum/events is injected into HTML \
+               responses. Each open page holds a connection, served by its \
+               own thread rather than a worker thread.",
+    },
+    CliOption {
+        short: None,
+        long: "no-list-dir",
+        value: None,
+        repeatable: false,
+        summary: "Don't list directories.",
+        help: "Don't list directories and prevent directory traversals by \
+               returning \"403 Permission Denied\" responses when attempting \
+               to access a directory.",
+    },
+    CliOption {
+        short: None,
+        long: "ignore",
+        value: Some("GLOB"),
+        repeatable: true,
+        summary: "Exclude matching paths.",
+        help:
+            "Exclude paths matching GLOB from directory listings and respond \
+               with \"404 Not Found\" when requesting them. Patterns are \
+               relative to the base directory and may use *, ? and **. \
+               Patterns ending in / only match directories. May be given \
+               multiple times.",
+    },
+    CliOption {
+        short: None,
+        long: "no-ignore-file",
+        value: None,
+        repeatable: false,
+        summary: "Don't read .servumignore.",
+        help: "Don't read ignore patterns from the .servumignore file in the \
+               base directory. The file contains one pattern per line.",
+    },
+    CliOption {
+        short: None,
+        long: "upload",
+        value: None,
+        repeatable: false,
+        summary: "Let anyone upload and delete files.",
+        help: "Let anyone reaching the server write files below the base \
+               directory: PUT /path/file writes the request body to that \
+               file, directory listings get a form uploading files to the \
+               directory, and DELETE /path/file removes that file or empty \
+               directory. Ignored paths, symbolic links and paths \
+               outside of the base directory can't be written to. Only use \
+               it on a trusted network.",
+    },
+    CliOption {
+        short: None,
+        long: "listing-cache-ttl",
+        value: Some("SECS"),
+        repeatable: false,
+        summary: "Reuse directory listings for SECS seconds.",
+        help: "Reuse rendered directory listings for up to SECS seconds, as \
+               long as no entry is added, removed or renamed, so that large \
+               directories aren't read on every request. Sizes and times of \
+               the entries may be outdated by that long. Has no effect with \
+               --upload.",
+    },
+    CliOption {
+        short: None,
+        long: "cache",
+        value: None,
+        repeatable: false,
+        summary: "Remember missing paths for a second.",
+        help: "Answer requests for a path found missing less than a second \
+               before with \"404 Not Found\" without looking it up again, \
+               e.g. when a client keeps polling a missing file. With \
+               --live-reload, paths are forgotten as soon as a file changes. \
+               Has no effect with --upload.",
+    },
+    CliOption {
+        short: None,
+        long: "max-path-depth",
+        value: Some("NUM"),
+        repeatable: false,
+        summary: "Limit request paths to NUM components.",
+        help: "Answer requests for paths with more than NUM components with \
+               400 Bad Request, without looking for files. Default is 64.",
+    },
+    CliOption {
+        short: None,
+        long: "max-segment-length",
+        value: Some("BYTES"),
+        repeatable: false,
+        summary: "Limit path components to BYTES bytes.",
+        help: "Answer requests for paths with a component longer than BYTES \
+               bytes, once percent-decoded, with 400 Bad Request. Default is \
+               255, the longest file name most file systems allow.",
+    },
+    CliOption {
+        short: None,
+        long: "allow-encoded-slash",
+        value: None,
+        repeatable: false,
+        summary: "Serve paths with %2F or %5C as part of a name.",
+        help: "Serve request paths containing a percent-encoded slash or \
+               backslash, %2F or %5C, taking it literally as part of a file \
+               name, e.g. a%2Fb for a file named a%2Fb, rather than as a \
+               separator. By default such requests are answered with 400 Bad \
+               Request, as they are mostly traversal attempts.",
+    },
+    CliOption {
+        short: None,
+        long: "max-upload-size",
+        value: Some("MB"),
+        repeatable: false,
+        summary: "Limit uploads to MB megabytes.",
+        help: "Answer uploads larger than MB megabytes with 413 Payload Too \
+               Large. Default is 100.",
+    },
+    CliOption {
+        short: None,
+        long: "config",
+        value: Some("FILE"),
+        repeatable: false,
+        summary: "Read options from a TOML file.",
+        help:
+            "Read options from the TOML file FILE. Keys are named after long \
+               options, e.g. port = 3000 or ignore = [\"*.log\"]. By default, \
+               the servum.toml file in the base directory is read if it \
+               exists. Arguments and environment variables take precedence \
+               over the config file.",
+    },
+    CliOption {
+        short: None,
+        long: "no-config",
+        value: None,
+        repeatable: false,
+        summary: "Don't read servum.toml.",
+        help: "Don't read any config file.",
+    },
+    CliOption {
+        short: None,
+        long: "check",
+        value: None,
+        repeatable: false,
+        summary: "Validate the configuration and exit.",
+        help: "Check that the base directory is readable, the address is \
+               valid, the port can be bound and the log file can be \
+               created, then print the resolved configuration and exit \
+               without serving. Exits with code 1 on the first failure. \
+               Warnings, e.g. about an empty base directory, are printed \
+               to stderr but don't fail the check.",
+    },
+    CliOption {
+        short: Some("h"),
+        long: "help",
+        value: None,
+        repeatable: false,
+        summary: "Show this help. Use --help for more details.",
+        help: "Show this help. Use -h for a quick summary of available \
+               commands and --help for a more detailed view.",
+    },
+    CliOption {
+        short: Some("V"),
+        long: "version",
+        value: None,
+        repeatable: false,
+        summary: "Show version information.",
+        help: "Show version information and exit.",
+    },
+];
+
+/// Pairs of options that cannot be used together
+pub const CONFLICTS: &[(&str, &str)] = &[
+    ("address", "public"),
+    ("config", "no-config"),
+    ("quiet", "silent"),
+    ("quiet", "verbose"),
+    ("silent", "verbose"),
+    ("max-connections", "reuse-port"),
+    ("address", "unix-socket"),
+    ("public", "unix-socket"),
+    ("port", "unix-socket"),
+    ("port-retry", "unix-socket"),
+    ("reuse-port", "unix-socket"),
+    ("open", "unix-socket"),
+    ("redirect-http", "unix-socket"),
+];
+
+/// Maximum width of the help menus
+const HELP_WIDTH: usize = 80;
+
+/// Width of the label column of the short help menu
+const LABEL_WIDTH: usize = 28;
+
+/// Maximum edit distance of a suggested option to an unknown argument
+const MAX_DISTANCE: usize = 2;
+
+impl CliOption {
+    /// Return the label of the option in the help menus, e.g.
+    /// `-p, --port <NUM>:` or `    --public:`.
+    fn label(&self) -> String {
+        let short = match self.short {
+            Some(short) => format!("-{}, ", short),
+            None => String::from("    "),
+        };
+        let value = match self.value {
+            Some(value) => format!(" <{}>", value),
+            None => String::new(),
+        };
+
+        format!("{}--{}{}:", short, self.long, value)
+    }
+}
+
+/// Find a known option by its short (e.g. `-p`) or long (e.g. `--port`) form.
+pub fn find(arg: &str) -> Option<&'static CliOption> {
+    OPTIONS.iter().find(|opt| match arg.strip_prefix("--") {
+        Some(long) => long == opt.long,
+        None => opt.short.is_some() && arg.strip_prefix('-') == opt.short,
+    })
+}
+
+/// Return the options section of the short help menu, one line per option.
+///
+/// Summaries of options with labels too long for the label column are moved
+/// to the next line.
+pub fn help_short() -> String {
+    OPTIONS
+        .iter()
+        .map(|opt| match opt.label() {
+            label if label.chars().count() < LABEL_WIDTH => {
+                format!(
+                    "    {:<width$}{}\n",
+                    label,
+                    opt.summary,
+                    width = LABEL_WIDTH
+                )
+            }
+            label => format!(
+                "    {}\n    {:<width$}{}\n",
+                label,
+                "",
+                opt.summary,
+                width = LABEL_WIDTH
+            ),
+        })
+        .collect()
+}
+
+/// Return the options section of the long help menu, with the description
+/// of each option indented and wrapped below its label.
+pub fn help_long() -> String {
+    OPTIONS
+        .iter()
+        .map(|opt| format!("    {}\n{}", opt.label(), wrap(opt.help, 12)))
+        .collect()
+}
+
+/// Wrap `text` into lines of at most [`HELP_WIDTH`] characters, each indented
+/// by `indent` spaces.
+fn wrap(text: &str, indent: usize) -> String {
+    let mut wrapped = String::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        if !line.is_empty()
+            && indent + line.chars().count() + 1 + word.chars().count()
+                > HELP_WIDTH
+        {
+            wrapped += &format!("{}{}\n", " ".repeat(indent), line);
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line += word;
+    }
+
+    if !line.is_empty() {
+        wrapped += &format!("{}{}\n", " ".repeat(indent), line);
+    }
+    wrapped
+}
+
+/// Return the long form of the known option closest to an unknown argument,
+/// e.g. `--address` for `--adress`, or [`None`] if no option is close.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::cli::options::suggest;
+/// assert_eq!(suggest("--no-listdir"), Some("--no-list-dir".to_string()));
+/// assert_eq!(suggest("--foo"), None);
+/// ```
+pub fn suggest(arg: &str) -> Option<String> {
+    let name = arg.strip_prefix("--")?;
+
+    OPTIONS
+        .iter()
+        .map(|opt| (edit_distance(name, opt.long), opt.long))
+        .filter(|&(dist, _)| dist <= MAX_DISTANCE && dist * 2 <= name.len())
+        .min_by_key(|&(dist, _)| dist)
+        .map(|(_, long)| format!("--{}", long))
+}
+
+/// Return the Levenshtein distance between two strings, i.e. the number of
+/// single character insertions, deletions and substitutions to turn `a`
+/// into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1];
+
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            curr.push((prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1));
+        }
+        prev = curr;
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn find_options() {
+        assert_eq!(find("-p").unwrap().long, "port");
+        assert_eq!(find("--port").unwrap().long, "port");
+        assert_eq!(find("--p"), None);
+        assert_eq!(find("-port"), None);
+        assert_eq!(find("p"), None);
+    }
+
+    #[test]
+    fn conflicts_registered() {
+        for (a, b) in CONFLICTS {
+            assert!(OPTIONS.iter().any(|opt| opt.long == *a), "{}", a);
+            assert!(OPTIONS.iter().any(|opt| opt.long == *b), "{}", b);
+        }
+    }
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("port", ""), 4);
+        assert_eq!(edit_distance("adress", "address"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn suggestions() {
+        let cases = [
+            ("--adress", "--address"),
+            ("--no-listdir", "--no-list-dir"),
+            ("--prot", "--port"),
+            ("--qiuet", "--quiet"),
+            ("--thread", "--threads"),
+            ("--no-confg", "--no-config"),
+        ];
+
+        for (arg, expected) in cases.iter() {
+            assert_eq!(suggest(arg).as_deref(), Some(*expected), "{}", arg);
+        }
+    }
+
+    #[test]
+    fn no_suggestions() {
+        for arg in ["--foo", "--chatty", "--x", "-x", "--", "bar"].iter() {
+            assert_eq!(suggest(arg), None, "{}", arg);
+        }
+    }
+
+    #[test]
+    fn wrapping() {
+        let text = "aaaa bbbb cccc";
+
+        assert_eq!(wrap(text, 2), "  aaaa bbbb cccc\n");
+        assert_eq!(
+            wrap(text, 70),
+            format!("{0}aaaa bbbb\n{0}cccc\n", " ".repeat(70))
+        );
+    }
+
+    #[test]
+    fn help_width() {
+        for line in help_short().lines().chain(help_long().lines()) {
+            assert!(line.chars().count() <= HELP_WIDTH, "{}", line);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Graceful shutdown on `SIGINT` and `SIGTERM`, e.g. when pressing Ctrl+C.
+//!
+//! A signal handler may hardly do anything safely, so it only writes a byte to
+//! a pipe, and a thread reading the other end shuts the server down. As in
+//! [`net`](crate::cli::net), the few calls needed are declared here rather
+//! than taken from the `libc` crate.
+use std::{
+    fs::File,
+    io::{self, Read},
+    os::raw::{c_int, c_void},
+    os::unix::io::FromRawFd,
+    sync::atomic::{AtomicI32, Ordering},
+    thread,
+};
+
+pub(crate) const SIGINT: c_int = 2;
+pub(crate) const SIGTERM: c_int = 15;
+const SIG_DFL: usize = 0;
+const SIG_ERR: usize = !0;
+const F_SETFD: c_int = 2;
+const FD_CLOEXEC: c_int = 1;
+
+extern "C" {
+    fn pipe(fds: *mut c_int) -> c_int;
+    fn fcntl(fd: c_int, cmd: c_int, arg: c_int) -> c_int;
+    fn write(fd: c_int, buf: *const c_void, count: usize) -> isize;
+    fn signal(signum: c_int, handler: usize) -> usize;
+    #[cfg(test)]
+    fn raise(signum: c_int) -> c_int;
+}
+
+/// Write end of the pipe the signal handler reports to
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Turn the return value of a call into a result.
+fn check(ret: c_int) -> io::Result<c_int> {
+    match ret {
+        -1 => Err(io::Error::last_os_error()),
+        ret => Ok(ret),
+    }
+}
+
+/// Signal handler, reporting the signal to the pipe.
+extern "C" fn report(_signum: c_int) {
+    // SAFETY: write is async-signal-safe and the buffer is one byte long. A
+    // full pipe already holds a signal to report.
+    unsafe {
+        write(
+            PIPE.load(Ordering::SeqCst),
+            b"\0".as_ptr() as *const c_void,
+            1,
+        );
+    }
+}
+
+/// Set the handler of `SIGINT` and `SIGTERM`, [`report`] or the default
+/// one, which terminates the process.
+fn set_handlers(handler: usize) -> io::Result<()> {
+    for signum in [SIGINT, SIGTERM] {
+        // SAFETY: the handler is either SIG_DFL or a function only making
+        // async-signal-safe calls
+        if unsafe { signal(signum, handler) } == SIG_ERR {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+/// Call `shutdown` on a thread of its own once the process receives `SIGINT`
+/// or `SIGTERM`, instead of the process being terminated right away.
+///
+/// The default handlers are restored once `shutdown` was called, so that a
+/// second signal, e.g. pressing Ctrl+C again while requests are finishing,
+/// terminates the process. Only the `shutdown` passed last is called.
+pub fn on_shutdown<F>(shutdown: F) -> io::Result<()>
+where
+    F: FnOnce() + Send + 'static,
+{
+    let mut fds = [-1; 2];
+    // SAFETY: fds has room for the two descriptors returned
+    check(unsafe { pipe(fds.as_mut_ptr()) })?;
+    // SAFETY: the descriptors are new and owned by nobody else
+    let (mut reader, writer) =
+        unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+    for fd in fds {
+        // Children, such as the browser, must not keep the pipe open
+        // SAFETY: the descriptor is open, owned by the files above
+        check(unsafe { fcntl(fd, F_SETFD, FD_CLOEXEC) })?;
+    }
+
+    thread::Builder::new()
+        .name(String::from("servum-signal"))
+        .spawn(move || {
+            // The handler may write to the pipe for as long as it is set
+            let _writer = writer;
+            let mut signal = [0];
+
+            if reader.read_exact(&mut signal).is_ok() {
+                let _ = set_handlers(SIG_DFL);
+                shutdown();
+            }
+        })?;
+
+    PIPE.store(fds[1], Ordering::SeqCst);
+    set_handlers(report as extern "C" fn(c_int) as usize)
+}
+
+/// Send `signum` to the current thread, calling its handler.
+#[cfg(test)]
+pub(crate) fn send(signum: c_int) -> io::Result<()> {
+    // SAFETY: raise has no preconditions
+    check(unsafe { raise(signum) }).map(drop)
+}
@@ -0,0 +1,90 @@
+//! HTTPS support, enabled by the `tls` feature
+use rustls::{
+    crypto::ring,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    ServerConfig, ServerConnection, StreamOwned,
+};
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    path::Path,
+    sync::Arc,
+};
+
+/// A TLS session over an accepted connection, TCP by default.
+///
+/// The handshake is performed transparently on the first read or write.
+pub type TlsStream<S = TcpStream> = StreamOwned<ServerConnection, S>;
+
+/// Return an [`io::ErrorKind::InvalidData`] error mentioning `path`.
+fn invalid(path: &Path, msg: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), msg),
+    )
+}
+
+/// Load the TLS server configuration from a PEM-encoded certificate chain
+/// and private key.
+///
+/// Only HTTP/1.1 is advertised to clients.
+pub fn server_config(cert: &Path, key: &Path) -> io::Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| invalid(cert, e))?;
+
+    if certs.is_empty() {
+        return Err(invalid(cert, "No certificate found"));
+    }
+
+    let key_der =
+        PrivateKeyDer::from_pem_file(key).map_err(|e| invalid(key, e))?;
+
+    let mut config =
+        ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_no_client_auth()
+            .with_single_cert(certs, key_der)
+            .map_err(|e| invalid(key, e))?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(Arc::new(config))
+}
+
+/// Start a TLS session on an accepted connection.
+pub fn accept<S: Read + Write>(
+    config: &Arc<ServerConfig>,
+    stream: S,
+) -> io::Result<TlsStream<S>> {
+    let conn =
+        ServerConnection::new(config.clone()).map_err(io::Error::other)?;
+
+    Ok(StreamOwned::new(conn, stream))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn missing_files() {
+        let dir = TempDir::new();
+        let cert = dir.path().join("cert.pem");
+        let key = dir.path().join("key.pem");
+
+        assert!(server_config(&cert, &key).is_err());
+    }
+
+    #[test]
+    fn no_certificate() {
+        let dir = TempDir::new();
+        let cert = dir.file("cert.pem", "");
+        let key = dir.file("key.pem", "");
+        let err = server_config(&cert, &key).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("cert.pem"));
+    }
+}
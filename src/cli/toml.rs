@@ -0,0 +1,409 @@
+//! Minimal parser for a subset of TOML used by `servum.toml` config files
+use std::{collections::HashSet, fmt, iter::Peekable, str::Chars};
+
+/// A TOML value.
+///
+/// Only strings, integers, booleans and arrays of strings are supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Array(Vec<String>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::Integer(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Array(values) => write!(f, "{:?}", values),
+        }
+    }
+}
+
+/// A key-value pair, along with its table and the line it was defined on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub line: usize,
+    pub table: Option<String>,
+    pub key: String,
+    pub value: Value,
+}
+
+/// A parse error with the (1-based) line it occurred on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub msg: String,
+}
+
+/// Parse a TOML document into a list of items, in order of definition.
+///
+/// Supported are comments, bare keys, basic and literal strings, integers,
+/// booleans, (multi-line) arrays of strings and one level of `[table]`s.
+/// Duplicate keys within a table are rejected.
+pub fn parse(contents: &str) -> Result<Vec<Item>, ParseError> {
+    Parser {
+        chars: contents.chars().peekable(),
+        line: 1,
+    }
+    .parse()
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn parse(mut self) -> Result<Vec<Item>, ParseError> {
+        let mut items = Vec::new();
+        let mut table: Option<String> = None;
+        let mut seen = HashSet::new();
+
+        loop {
+            self.skip_whitespace(true);
+
+            match self.chars.peek() {
+                None => return Ok(items),
+                Some('[') => {
+                    self.chars.next();
+                    self.skip_whitespace(false);
+                    let name = self.key()?;
+                    self.skip_whitespace(false);
+                    self.expect(']')?;
+                    self.end_of_line()?;
+
+                    if !seen.insert((None, name.clone())) {
+                        return Err(self.error("Duplicate table"));
+                    }
+                    table = Some(name);
+                }
+                Some(_) => {
+                    let line = self.line;
+                    let key = self.key()?;
+                    self.skip_whitespace(false);
+                    self.expect('=')?;
+                    self.skip_whitespace(false);
+                    let value = self.value()?;
+                    self.end_of_line()?;
+
+                    if !seen.insert((table.clone(), key.clone())) {
+                        return Err(ParseError {
+                            line,
+                            msg: format!("Duplicate key {}", key),
+                        });
+                    }
+
+                    items.push(Item {
+                        line,
+                        table: table.clone(),
+                        key,
+                        value,
+                    });
+                }
+            }
+        }
+    }
+
+    fn error(&self, msg: &str) -> ParseError {
+        ParseError {
+            line: self.line,
+            msg: msg.to_string(),
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    /// Skip spaces and tabs, and optionally newlines and comments.
+    fn skip_whitespace(&mut self, newlines: bool) {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                ' ' | '\t' => (),
+                '\r' | '\n' if newlines => (),
+                '#' if newlines => self.skip_comment(),
+                _ => return,
+            }
+            self.next();
+        }
+    }
+
+    /// Skip to the end of the current line, without consuming the newline.
+    fn skip_comment(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c == '\n' {
+                return;
+            }
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        match self.chars.peek() {
+            Some(&c) if c == expected => {
+                self.next();
+                Ok(())
+            }
+            _ => Err(self.error(&format!("Expected '{}'", expected))),
+        }
+    }
+
+    /// Expect only whitespace or a comment until the end of the line.
+    fn end_of_line(&mut self) -> Result<(), ParseError> {
+        self.skip_whitespace(false);
+
+        match self.chars.peek() {
+            None | Some('\n') => Ok(()),
+            Some('\r') => {
+                self.chars.next();
+                self.expect('\n')
+            }
+            Some('#') => {
+                self.skip_comment();
+                Ok(())
+            }
+            Some(_) => Err(self.error("Expected end of line")),
+        }
+    }
+
+    fn key(&mut self) -> Result<String, ParseError> {
+        let mut key = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                break;
+            }
+            key.push(c);
+            self.chars.next();
+        }
+
+        match key.is_empty() {
+            true => Err(self.error("Expected a key")),
+            false => Ok(key),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        match self.chars.peek() {
+            Some('"') | Some('\'') => Ok(Value::String(self.string()?)),
+            Some('[') => self.array(),
+            Some('t') | Some('f') => self.bool(),
+            Some(c) if c.is_ascii_digit() || *c == '+' || *c == '-' => {
+                self.integer()
+            }
+            _ => Err(self.error("Expected a value")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        let quote = self.next().unwrap();
+        let mut s = String::new();
+
+        loop {
+            // Don't consume newlines to report the line of the string
+            let c = match self.chars.peek() {
+                None | Some('\n') => {
+                    return Err(self.error("Unterminated string"))
+                }
+                Some(_) => self.next().unwrap(),
+            };
+
+            match c {
+                c if c == quote => return Ok(s),
+                '\\' if quote == '"' => s.push(self.escape()?),
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, ParseError> {
+        match self.next() {
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('u') => {
+                let hex: String = (0..4).filter_map(|_| self.next()).collect();
+
+                u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error("Invalid unicode escape"))
+            }
+            _ => Err(self.error("Invalid escape sequence")),
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, ParseError> {
+        self.next();
+        let mut values = Vec::new();
+
+        loop {
+            self.skip_whitespace(true);
+
+            match self.chars.peek() {
+                Some(']') => {
+                    self.next();
+                    return Ok(Value::Array(values));
+                }
+                Some('"') | Some('\'') => values.push(self.string()?),
+                _ => return Err(self.error("Expected a string or ']'")),
+            }
+
+            self.skip_whitespace(true);
+
+            match self.chars.peek() {
+                Some(',') => {
+                    self.next();
+                }
+                Some(']') => (),
+                _ => return Err(self.error("Expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-')
+            {
+                break;
+            }
+            word.push(c);
+            self.chars.next();
+        }
+        word
+    }
+
+    fn bool(&mut self) -> Result<Value, ParseError> {
+        match self.word().as_str() {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => Err(self.error("Expected a value")),
+        }
+    }
+
+    fn integer(&mut self) -> Result<Value, ParseError> {
+        self.word()
+            .replace('_', "")
+            .parse::<i64>()
+            .map(Value::Integer)
+            .map_err(|_| self.error("Invalid integer"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Helper
+    fn item(line: usize, table: Option<&str>, key: &str, value: Value) -> Item {
+        Item {
+            line,
+            table: table.map(String::from),
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn values() {
+        let doc = r#"
+# Server
+port = 3000
+quiet = true
+list-dir = false
+address = "0.0.0.0" # comment
+base_dir = 'C:\www'
+escaped = "tab\t\"quoted\" \u00e9"
+"#;
+
+        assert_eq!(
+            parse(doc).unwrap(),
+            vec![
+                item(3, None, "port", Value::Integer(3000)),
+                item(4, None, "quiet", Value::Bool(true)),
+                item(5, None, "list-dir", Value::Bool(false)),
+                item(6, None, "address", Value::String("0.0.0.0".into())),
+                item(7, None, "base_dir", Value::String("C:\\www".into())),
+                item(
+                    8,
+                    None,
+                    "escaped",
+                    Value::String("tab\t\"quoted\" é".into())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        let doc = "a = []\nb = [\"x\", 'y',]\nc = [\n  \"z\", # comment\n]\n";
+
+        assert_eq!(
+            parse(doc).unwrap(),
+            vec![
+                item(1, None, "a", Value::Array(vec![])),
+                item(2, None, "b", Value::Array(vec!["x".into(), "y".into()])),
+                item(3, None, "c", Value::Array(vec!["z".into()])),
+            ]
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let doc = "port = 1\n\n[mime]\nglsl = \"text/plain\"\n";
+
+        assert_eq!(
+            parse(doc).unwrap(),
+            vec![
+                item(1, None, "port", Value::Integer(1)),
+                item(
+                    4,
+                    Some("mime"),
+                    "glsl",
+                    Value::String("text/plain".into())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(
+            parse("a = -1_000").unwrap()[0].value,
+            Value::Integer(-1000)
+        );
+        assert_eq!(parse("a = +5").unwrap()[0].value, Value::Integer(5));
+    }
+
+    #[test]
+    fn errors() {
+        let cases = [
+            ("a = \"unterminated\n", 1),
+            ("\n\na = 1 b = 2", 3),
+            ("a = nope", 1),
+            ("a = 12abc", 1),
+            ("a = [1, 2]", 1),
+            ("[table\n", 1),
+            ("a = 1\na = 2\n", 2),
+            ("= 1", 1),
+            ("a = \"\\q\"", 1),
+        ];
+
+        for (doc, line) in cases.iter() {
+            assert_eq!(parse(doc).unwrap_err().line, *line, "{}", doc);
+        }
+    }
+}
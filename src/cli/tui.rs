@@ -1,11 +1,85 @@
 use crate::{
-    cli::Config,
-    http::{HTTPRequest, HTTPResponse},
+    cli::{
+        logger::{LogFormat, Record, Style},
+        net, Config,
+    },
+    clock::SystemClock,
+    http::{HTTPRequest, HTTPResponse, Written},
 };
 use std::{sync::Arc, time::Instant};
 
-/// Print ASCII Art of `servum` to the console.
-pub fn print_logo() {
+/// Output level of servum on the console.
+///
+/// Levels are ordered, with each level printing everything the previous one
+/// does. Errors are always printed to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Verbosity {
+    /// Print nothing to stdout
+    Silent,
+    /// Print startup information, but not incoming requests
+    Quiet,
+    /// Print startup information and incoming requests
+    #[default]
+    Normal,
+    /// Print everything, including debugging information such as
+    /// connections closed by the client
+    Debug,
+}
+
+impl Verbosity {
+    /// Return whether startup information, such as the logo and the served
+    /// directory, is printed.
+    pub fn shows_startup(&self) -> bool {
+        *self >= Verbosity::Quiet
+    }
+
+    /// Return whether incoming requests are printed.
+    pub fn shows_requests(&self) -> bool {
+        *self >= Verbosity::Normal
+    }
+
+    /// Return whether debugging information is printed.
+    pub fn shows_debug(&self) -> bool {
+        *self >= Verbosity::Debug
+    }
+}
+
+/// Default width of the path column of the request log
+pub const DEFAULT_PATH_WIDTH: usize = 32;
+
+/// ANSI escape code for green text
+pub const GREEN: &str = "\x1b[32m";
+/// ANSI escape code for yellow text
+pub const YELLOW: &str = "\x1b[33m";
+/// ANSI escape code for red text
+pub const RED: &str = "\x1b[31m";
+/// ANSI escape code for dimmed text
+pub const DIM: &str = "\x1b[2m";
+/// ANSI escape code resetting all styles
+pub const RESET: &str = "\x1b[0m";
+
+/// Wrap `text` in the ANSI escape code `style`, resetting the style after.
+pub fn paint(text: &str, style: &str) -> String {
+    format!("{}{}{}", style, text, RESET)
+}
+
+/// Return the color of an HTTP status code: green for 2xx, yellow for 3xx
+/// and 4xx and red for everything else.
+pub fn status_color(code: usize) -> &'static str {
+    match code {
+        200..=299 => GREEN,
+        300..=499 => YELLOW,
+        _ => RED,
+    }
+}
+
+/// Print ASCII Art of `servum` to the console, unless disabled with
+/// `--no-logo` or silenced.
+pub fn print_logo(config: &Config) {
+    if !config.logo || !config.verbosity.shows_startup() {
+        return;
+    }
+
     println!(
         r"______________________   _____  ________ ___ 
 __  ___/  _ \_  ___/_ | / /  / / /_  __ `__ \
@@ -15,33 +89,186 @@ _(__  )/  __/  /   __ |/ // /_/ /_  / / / / /
     );
 }
 
-/// Print general information about help and quitting to the console.
-pub fn print_info() {
+/// Print general information about help and quitting to the console, unless
+/// silenced.
+pub fn print_info(config: &Config) {
+    if !config.verbosity.shows_startup() {
+        return;
+    }
+
     println!("To exit, just press Ctrl/Cmd+C at any time");
     println!("To get help, pass --help");
     println!();
 }
 
-/// Print information about the current user [`Config`] to the console.
+/// Print information about the current user [`Config`] to the console, unless
+/// silenced.
 pub fn print_config(config: Arc<Config>) {
+    if !config.verbosity.shows_startup() {
+        return;
+    }
+
     println!("Serving {}", config.base_dir.display());
-    println!(
-        "Server listening at http://{}:{}",
-        config.address, config.port
-    );
+    println!("Using {} threads", config.threads);
+    if let Some(options) = format_options(&config) {
+        println!("{}", options);
+    }
+
+    if config.delay > 0 || config.throttle > 0 {
+        println!("{}", format_simulation(&config));
+    }
+
+    if let Some(path) = &config.unix_socket {
+        println!("Server listening on the Unix socket {}", path.display());
+    } else if net::is_wildcard(&config.address) {
+        let local = net::local_ipv4_addrs();
+
+        println!("Server listening on all interfaces at");
+        for url in net::server_urls(
+            config.scheme(),
+            &config.address,
+            config.port,
+            &local,
+        ) {
+            println!("    {}", url);
+        }
+    } else {
+        println!(
+            "Server listening at {}",
+            net::url(config.scheme(), &config.address, config.port)
+        );
+    }
+    if let Some(port) = config.redirect_http {
+        println!(
+            "Redirecting {} to HTTPS",
+            net::url("http", &config.address, port)
+        );
+    }
+    if config.upload {
+        let warning = format!(
+            "WARNING: Uploads are enabled, anyone reaching the server can \
+             write and delete files in {} (up to {} MB each)",
+            config.base_dir.display(),
+            config.max_upload_size
+        );
+        match config.color {
+            true => println!("{}", paint(&warning, YELLOW)),
+            false => println!("{}", warning),
+        }
+    }
     println!();
 }
 
+/// Truncate a path to at most `width` characters, keeping its end, i.e. the
+/// file name, and marking the elided start with `…`.
+///
+/// A `width` of `0` never truncates.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::cli::tui::truncate_path;
+/// assert_eq!(truncate_path("/static/js/app.js", 10), "…js/app.js");
+/// assert_eq!(truncate_path("/index.html", 32), "/index.html");
+/// ```
+pub fn truncate_path(path: &str, width: usize) -> String {
+    let len = path.chars().count();
+
+    if width == 0 || len <= width {
+        return path.to_string();
+    }
+
+    std::iter::once('…')
+        .chain(path.chars().skip(len - width + 1))
+        .collect()
+}
+
+/// Return the width of the path column for a maximum path width, as set by
+/// `--log-path-width`.
+///
+/// Unlimited paths (`0`) are padded to the default width.
+pub fn path_column_width(path_width: usize) -> usize {
+    match path_width {
+        0 => DEFAULT_PATH_WIDTH,
+        width => width,
+    }
+}
+
+/// Return the startup notice of the simulated latency and bandwidth, e.g.
+/// `Simulating a slow connection: 200 ms delay, 50 kB/s per request`.
+pub fn format_simulation(config: &Config) -> String {
+    let mut limits = Vec::new();
+
+    if config.delay > 0 {
+        limits.push(format!("{} ms delay", config.delay));
+    }
+    if config.throttle > 0 {
+        limits.push(format!("{} kB/s per request", config.throttle));
+    }
+
+    format!("Simulating a slow connection: {}", limits.join(", "))
+}
+
+/// Options of the config file printed in their own startup lines, and left
+/// out of [`format_options`]
+const SHOWN_OPTIONS: [&str; 8] = [
+    "address",
+    "port",
+    "base-dir",
+    "threads",
+    "delay",
+    "throttle",
+    "unix-socket",
+    "redirect-http",
+];
+
+/// Return the startup line of the options differing from their defaults, e.g.
+/// `Options: no-list-dir, cache-control=no-cache`, or [`None`] if there are
+/// none. See [`Config::diff_from_default`].
+pub fn format_options(config: &Config) -> Option<String> {
+    let options = config
+        .diff_from_default()
+        .into_iter()
+        .filter(|option| {
+            let key = option.split('=').next().unwrap_or_default();
+            !SHOWN_OPTIONS.contains(&key)
+        })
+        .collect::<Vec<String>>();
+
+    match options.is_empty() {
+        true => None,
+        false => Some(format!("Options: {}", options.join(", "))),
+    }
+}
+
 /// Print table header of verbose output to the console.
-pub fn print_verbose_header() {
-    println!(
-        "[{req_method: <6} {req_path: <32}] -> \t{res_code: <6} {res_msg: <24} Time in μs",
+pub fn print_verbose_header(style: Style) {
+    let header = format_verbose_header(style);
+    // The tab after the arrow spans three columns
+    let width = header.chars().count() + 2;
+
+    println!("{}", header);
+    println!("{}", "-".repeat(width));
+}
+
+/// Format the column titles of the verbose output, aligned with the rows of
+/// [`format_verbose_stats`] with the same `style`.
+pub fn format_verbose_header(style: Style) -> String {
+    format!(
+        "{timestamp}[{req_method: <6} {req_path: <width$}] -> \t{res_code: <6} {res_msg: <24} Time in μs Size       Served by       Request ID",
+        timestamp = match style.timestamps {
+            true => format!("{: <25}", "Timestamp (UTC)"),
+            false => String::new(),
+        },
         req_method = "Method",
-        req_path = "/path/to/file/...",
+        req_path = truncate_path(
+            "/path/to/file/...",
+            path_column_width(style.path_width)
+        ),
+        width = path_column_width(style.path_width),
         res_code = "Code",
         res_msg = "Message",
-    );
-    println!("{}", "-".repeat(90));
+    )
 }
 
 /// Print verbose stats about a request to the console.
@@ -50,16 +277,85 @@ pub fn print_verbose_stats(
     res: &HTTPResponse,
     timer: Instant,
 ) {
-    println!(
-        "[{req_method: <6} {req_path: <33}] -> \t{res_code: <6} {res_msg: <24} {time: <4}μs",
-        req_method = req.method,
-        req_path = {
-            let mut path = req.filepath.display().to_string();
-            path.truncate(32);
-            path
-        },
-        res_code = res.status.code,
-        res_msg = res.status.msg,
-        time = timer.elapsed().as_micros(),
-    );
+    println!("{}", format_verbose_stats(req, res, timer));
+}
+
+/// Format verbose stats about a request as printed by
+/// [`print_verbose_stats`], as if the whole response was written.
+pub fn format_verbose_stats(
+    req: &HTTPRequest,
+    res: &HTTPResponse,
+    timer: Instant,
+) -> String {
+    let written = Written {
+        header: res.header().len(),
+        body: res.body.len(),
+    };
+
+    LogFormat::Pretty.format(
+        &Record::new(req, res, written, timer, None, &SystemClock),
+        Style::default(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn truncate() {
+        let cases = [
+            ("", 4, ""),
+            ("/a/b", 4, "/a/b"),
+            ("/a/b", 5, "/a/b"),
+            ("/a/bc", 4, "…/bc"),
+            ("/very/long/path/file.txt", 12, "…th/file.txt"),
+            ("/photos/été/café.jpg", 10, "…/café.jpg"),
+            ("/日本語/ファイル.html", 8, "…イル.html"),
+            ("/very/long/path/file.txt", 0, "/very/long/path/file.txt"),
+            ("/a/b", 1, "…"),
+        ];
+
+        for (path, width, expected) in cases.iter() {
+            let truncated = truncate_path(path, *width);
+
+            assert_eq!(truncated, *expected, "{} {}", path, width);
+            assert!(*width == 0 || truncated.chars().count() <= *width);
+        }
+    }
+
+    #[test]
+    fn simulation() {
+        let config = Config {
+            delay: 200,
+            throttle: 50,
+            ..Config::default()
+        };
+
+        assert_eq!(
+            format_simulation(&config),
+            "Simulating a slow connection: 200 ms delay, 50 kB/s per request"
+        );
+        assert_eq!(
+            format_simulation(&Config { delay: 0, ..config }),
+            "Simulating a slow connection: 50 kB/s per request"
+        );
+    }
+
+    #[test]
+    fn options() {
+        assert_eq!(format_options(&Config::default()), None);
+
+        let config = Config {
+            port: 8000,
+            delay: 200,
+            list_dir: false,
+            health: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            format_options(&config).unwrap(),
+            "Options: no-list-dir, health"
+        );
+    }
 }
@@ -0,0 +1,98 @@
+//! Source of the current time, replaceable in tests
+use std::{
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Source of the current time for time-dependent features, such as log
+/// timestamps, request durations and rate-limited warnings.
+///
+/// The server uses the clock of its [`Config`](crate::cli::Config), which is
+/// the [`SystemClock`] by default. Tests can use a [`TestClock`] instead to
+/// control time without sleeping.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Return the current wall-clock time.
+    fn now(&self) -> SystemTime;
+
+    /// Return the current monotonic time, to measure durations.
+    fn instant(&self) -> Instant;
+}
+
+/// Clock reading the time of the operating system.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock standing still until moved by hand, for tests.
+///
+/// Both the wall-clock and the monotonic time move forward with
+/// [`TestClock::advance`], while [`TestClock::set`] only changes the
+/// wall-clock time, like adjusting the system time does.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::clock::{Clock, TestClock};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let clock = TestClock::new(UNIX_EPOCH);
+/// let start = clock.instant();
+///
+/// clock.advance(Duration::from_secs(90));
+/// assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_secs(90));
+/// assert_eq!(clock.instant() - start, Duration::from_secs(90));
+///
+/// clock.set(UNIX_EPOCH);
+/// assert_eq!(clock.now(), UNIX_EPOCH);
+/// assert_eq!(clock.instant() - start, Duration::from_secs(90));
+/// ```
+#[derive(Debug)]
+pub struct TestClock {
+    /// Monotonic time the clock was created at
+    start: Instant,
+    /// Current wall-clock time and time elapsed since `start`
+    state: Mutex<(SystemTime, Duration)>,
+}
+
+impl TestClock {
+    /// Create a clock showing the wall-clock time `now`.
+    pub fn new(now: SystemTime) -> Self {
+        TestClock {
+            start: Instant::now(),
+            state: Mutex::new((now, Duration::ZERO)),
+        }
+    }
+
+    /// Move the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.0 += duration;
+        state.1 += duration;
+    }
+
+    /// Set the wall-clock time to `now`, leaving the monotonic time as is.
+    pub fn set(&self, now: SystemTime) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.0 = now;
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> SystemTime {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).0
+    }
+
+    fn instant(&self) -> Instant {
+        self.start + self.state.lock().unwrap_or_else(|e| e.into_inner()).1
+    }
+}
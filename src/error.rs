@@ -0,0 +1,123 @@
+//! Error type of the servum library
+use crate::{
+    cli::CliError, http::HTTPRequestError, multiprocessing::PoolError,
+};
+use std::{fmt, io};
+
+/// Any error returned by the public API of servum, wrapping the error of the
+/// module it comes from.
+///
+/// Every wrapped error converts into it, so that errors of different modules
+/// can be propagated with `?` alike.
+///
+/// # Example
+///
+/// ```rust
+/// use servum::{cli::Config, http::HTTPRequest, server::Server, Error};
+///
+/// fn bind_and_parse() -> Result<(), Error> {
+///     let server = Server::bind(Config {
+///         port: 0,
+///         base_dir: "example".into(),
+///         ..Config::default()
+///     })?;
+///     assert_ne!(server.local_addr().port(), 0);
+///
+///     let req = HTTPRequest::new(b"\xff\xfe")?;
+///     unreachable!("{} is not valid UTF-8", req);
+/// }
+///
+/// let err = bind_and_parse().unwrap_err();
+/// assert!(matches!(err, Error::Request(_)));
+/// assert_eq!(err.to_string(), "Request contains invalid Utf8 characters");
+/// ```
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file or socket failed, e.g. when binding the
+    /// address of the server.
+    Io(io::Error),
+    /// A request could not be parsed.
+    Request(HTTPRequestError),
+    /// The arguments or config file could not be parsed.
+    Cli(CliError),
+    /// The thread pool could not be started.
+    Pool(PoolError),
+    /// The configuration was parsed but can't be served, e.g. because its
+    /// TLS certificate can't be loaded.
+    Config(String),
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Request(e) => Some(e),
+            Error::Cli(e) => Some(e),
+            Error::Pool(e) => Some(e),
+            Error::Config(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => e.fmt(f),
+            Error::Request(e) => e.fmt(f),
+            Error::Cli(e) => e.fmt(f),
+            Error::Pool(e) => e.fmt(f),
+            Error::Config(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<HTTPRequestError> for Error {
+    fn from(err: HTTPRequestError) -> Error {
+        Error::Request(err)
+    }
+}
+
+impl From<CliError> for Error {
+    fn from(err: CliError) -> Error {
+        Error::Cli(err)
+    }
+}
+
+impl From<PoolError> for Error {
+    fn from(err: PoolError) -> Error {
+        Error::Pool(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn conversions() {
+        let err = Error::from(io::Error::from(io::ErrorKind::AddrInUse));
+        assert!(
+            matches!(&err, Error::Io(e) if e.kind() == io::ErrorKind::AddrInUse)
+        );
+        assert!(err.source().is_some());
+
+        let err = Error::from(CliError::MissingVal(String::from("--port")));
+        assert_eq!(err.to_string(), "Missing value for argument --port");
+        assert!(matches!(Error::from(PoolError::ZeroSize), Error::Pool(_)));
+        assert!(matches!(
+            Error::from(HTTPRequestError::NoMethod),
+            Error::Request(_)
+        ));
+
+        let err = Error::Config(String::from("No certificate found"));
+        assert_eq!(err.to_string(), "No certificate found");
+        assert!(err.source().is_none());
+    }
+}
@@ -1,4 +1,11 @@
 //! Filesystem and path utilities
+pub mod base_dir;
 pub mod file;
+pub mod glob;
+pub mod hash;
+pub mod ignore;
+pub mod manifest;
 pub mod mime;
 pub mod path;
+pub mod pid;
+pub mod watch;
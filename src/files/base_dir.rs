@@ -0,0 +1,252 @@
+use crate::files::ignore::IgnoreRules;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Name of the file served for the root URL
+pub const INDEX_FILE: &str = "index.html";
+
+/// Time for which [`BaseLink::resolve`] reuses the directory it resolved
+pub const BASE_LINK_TTL: Duration = Duration::from_secs(1);
+
+/// Entries that commonly hold secrets, e.g. credentials in `.git/config`,
+/// and shouldn't be served by accident
+pub const SENSITIVE_ENTRIES: [&str; 4] = [".git", ".env", "id_rsa", ".aws"];
+
+/// Entries found directly below the base directory by [`scan`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scan {
+    /// Number of entries, leaving out ignored ones
+    pub entries: usize,
+    /// Whether the directory has an [`INDEX_FILE`]
+    pub has_index: bool,
+    /// Names of the [`SENSITIVE_ENTRIES`] found, in the order of that list
+    pub sensitive: Vec<String>,
+}
+
+/// Check that `dir` can be served, by reading its entries once.
+///
+/// Entries matching `ignore` are left out, as they aren't served. Returns a
+/// one-line error naming `dir` if it doesn't exist, isn't a directory or
+/// can't be read.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::{base_dir, ignore::IgnoreRules};
+/// use std::path::Path;
+///
+/// let scan = base_dir::scan(Path::new("example"), &IgnoreRules::default());
+/// assert!(scan.unwrap().has_index);
+///
+/// let err = base_dir::scan(Path::new("Cargo.toml"), &IgnoreRules::default());
+/// assert_eq!(err.unwrap_err().to_string(), "Cargo.toml: Not a directory");
+/// ```
+pub fn scan(dir: &Path, ignore: &IgnoreRules) -> io::Result<Scan> {
+    let error = |kind, msg: &dyn std::fmt::Display| {
+        io::Error::new(kind, format!("{}: {}", dir.display(), msg))
+    };
+
+    match fs::metadata(dir) {
+        Ok(meta) if !meta.is_dir() => {
+            return Err(error(io::ErrorKind::InvalidInput, &"Not a directory"))
+        }
+        Ok(_) => (),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(error(e.kind(), &"No such directory"))
+        }
+        Err(e) => return Err(error(e.kind(), &e)),
+    }
+
+    let entries = fs::read_dir(dir).map_err(|e| {
+        error(e.kind(), &format!("Cannot read directory: {}", e))
+    })?;
+    let mut scan = Scan::default();
+
+    for entry in entries.flatten() {
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        let name = entry.file_name();
+
+        if ignore.is_ignored(Path::new(&name), is_dir) {
+            continue;
+        }
+
+        scan.entries += 1;
+        scan.has_index |= !is_dir && name == INDEX_FILE;
+        if let Some(name) = SENSITIVE_ENTRIES.iter().find(|&&s| name == s) {
+            scan.sensitive.push(name.to_string());
+        }
+    }
+
+    scan.sensitive
+        .sort_by_key(|name| SENSITIVE_ENTRIES.iter().position(|s| s == name));
+    Ok(scan)
+}
+
+/// Base directory as given by the user, before resolving symlinks, so that
+/// a symlink swapped between release directories can be followed with
+/// `--follow-base-symlink`.
+///
+/// Clones share the last resolved directory.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::base_dir::BaseLink;
+/// use std::{path::Path, time::Instant};
+///
+/// let link = BaseLink::new("example");
+/// let dir = link.resolve(Instant::now()).unwrap();
+///
+/// assert_eq!(dir, Path::new("example").canonicalize().unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct BaseLink {
+    path: PathBuf,
+    /// Directory last resolved, and when
+    resolved: Arc<Mutex<Option<(Instant, PathBuf)>>>,
+}
+
+impl BaseLink {
+    /// Create a link to the base directory at `path`, resolved lazily.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        BaseLink {
+            path: path.into(),
+            resolved: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Return the path as given.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return the canonical directory the path leads to, resolving it again
+    /// if it was last resolved [`BASE_LINK_TTL`] or more before `now`.
+    ///
+    /// If the path can't be resolved, e.g. while the symlink is replaced,
+    /// the directory resolved last is returned, or the error if there is
+    /// none.
+    pub fn resolve(&self, now: Instant) -> io::Result<PathBuf> {
+        let mut resolved =
+            self.resolved.lock().unwrap_or_else(|e| e.into_inner());
+
+        match &*resolved {
+            Some((at, dir))
+                if now.saturating_duration_since(*at) < BASE_LINK_TTL =>
+            {
+                return Ok(dir.clone())
+            }
+            _ => (),
+        }
+
+        match (self.path.canonicalize(), resolved.take()) {
+            (Ok(dir), _) => {
+                *resolved = Some((now, dir.clone()));
+                Ok(dir)
+            }
+            (Err(_), Some((_, dir))) => {
+                *resolved = Some((now, dir.clone()));
+                Ok(dir)
+            }
+            (Err(e), None) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn scan_entries() {
+        let dir = TempDir::new();
+        let ignore = IgnoreRules::default();
+        assert_eq!(scan(dir.path(), &ignore).unwrap(), Scan::default());
+
+        dir.file("css/style.css", "");
+        dir.file("notes.md", "");
+        let mut rules = IgnoreRules::default();
+        rules.add("*.md").unwrap();
+        assert_eq!(
+            scan(dir.path(), &rules).unwrap(),
+            Scan {
+                entries: 1,
+                has_index: false,
+                sensitive: Vec::new(),
+            }
+        );
+
+        dir.file("index.html", "");
+        assert!(scan(dir.path(), &ignore).unwrap().has_index);
+    }
+
+    #[test]
+    fn scan_sensitive() {
+        let dir = TempDir::new();
+        dir.file(".aws/credentials", "");
+        dir.file(".git/config", "");
+        dir.file(".env", "");
+        dir.file("docs/.env", "");
+        dir.file(".envrc", "");
+        let ignore = IgnoreRules::default();
+
+        assert_eq!(
+            scan(dir.path(), &ignore).unwrap().sensitive,
+            vec![".git", ".env", ".aws"]
+        );
+
+        let mut rules = IgnoreRules::default();
+        rules.add(".git/").unwrap();
+        rules.add(".env").unwrap();
+        assert_eq!(scan(dir.path(), &rules).unwrap().sensitive, vec![".aws"]);
+    }
+
+    #[test]
+    fn scan_errors() {
+        let dir = TempDir::new();
+        let ignore = IgnoreRules::default();
+
+        let missing = dir.path().join("dist");
+        let err = scan(&missing, &ignore).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(
+            err.to_string(),
+            format!("{}: No such directory", missing.display())
+        );
+
+        let file = dir.file("index.html", "");
+        let err = scan(&file, &ignore).unwrap_err();
+        assert!(err.to_string().ends_with("index.html: Not a directory"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn base_link() {
+        use std::os::unix::fs::symlink;
+        let dir = TempDir::new();
+        let (one, two) = (dir.path().join("one"), dir.path().join("two"));
+        fs::create_dir(&one).unwrap();
+        fs::create_dir(&two).unwrap();
+        let current = dir.path().join("current");
+        symlink(&one, &current).unwrap();
+
+        let link = BaseLink::new(&current);
+        let start = Instant::now();
+        assert_eq!(link.resolve(start).unwrap(), one);
+
+        fs::remove_file(&current).unwrap();
+        symlink(&two, &current).unwrap();
+        assert_eq!(link.clone().resolve(start).unwrap(), one);
+        assert_eq!(link.resolve(start + BASE_LINK_TTL).unwrap(), two);
+
+        // A dangling link keeps the last directory
+        fs::remove_file(&current).unwrap();
+        assert_eq!(link.resolve(start + BASE_LINK_TTL * 2).unwrap(), two);
+        assert!(BaseLink::new(&current).resolve(start).is_err());
+    }
+}
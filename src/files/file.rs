@@ -1,13 +1,268 @@
+use crate::http::{encode_path_segment, json_string, DateTime};
 use std::fmt;
-use std::fs::DirEntry;
+use std::fs::{self, DirEntry};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default chunk size of [`ChunkedReader`]
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Metadata of a file or directory in a directory listing
+///
+/// An owned snapshot of a [`std::fs::DirEntry`] and its metadata, used to
+/// render, sort and filter directory listings. Symbolic links are followed to
+/// determine whether the entry is a directory, its length and modification
+/// time.
+///
+/// Entries whose metadata cannot be read, e.g. dangling symbolic links, or
+/// files which cannot be opened for reading, are kept when created with
+/// [`Entry::from_dir_entry_lossy`] and carry the kind of error encountered in
+/// `error`, so that they are marked as unreadable instead of silently
+/// vanishing from listings. Directory entries which couldn't be read at all
+/// are kept as nameless placeholders, see [`Entry::from_error`].
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::file::Entry;
+/// let mut entries = std::fs::read_dir("example/")
+///     .unwrap()
+///     .map(|e| Entry::from_dir_entry(e.unwrap()).unwrap())
+///     .collect::<Vec<Entry>>();
+/// entries.sort_by(|a, b| a.name.cmp(&b.name));
+///
+/// let index = entries.iter().find(|e| e.name == "index.html").unwrap();
+///
+/// assert!(!index.is_dir);
+/// assert_eq!(index.to_html_row(), "<a href=\"./index.html\">index.html</a>");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    pub is_symlink: bool,
+    pub error: Option<io::ErrorKind>,
+}
+
+impl Entry {
+    /// Create a new Entry by reading the metadata of a [`DirEntry`]
+    ///
+    /// Non-UTF-8 file names are converted lossily. Errors while reading the
+    /// metadata, e.g. for dangling symbolic links, are returned as
+    /// [`std::io::Error`].
+    pub fn from_dir_entry(entry: DirEntry) -> io::Result<Entry> {
+        let file_type = entry.file_type()?;
+        let metadata = match file_type.is_symlink() {
+            true => fs::metadata(entry.path())?,
+            false => entry.metadata()?,
+        };
+
+        Ok(Entry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            is_symlink: file_type.is_symlink(),
+            error: None,
+        })
+    }
+
+    /// Create a new Entry from a [`DirEntry`], keeping unreadable entries
+    ///
+    /// In contrast to [`Entry::from_dir_entry`], errors while reading the
+    /// metadata are stored in the entry's `error` field. As much information
+    /// as possible, such as the file name and whether the entry is a symbolic
+    /// link, is still collected. Regular files are opened as well, so that
+    /// those which can't be read, e.g. for lack of permission, carry the
+    /// error too.
+    pub fn from_dir_entry_lossy(entry: DirEntry) -> Entry {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let file_type = entry.file_type().ok();
+        let path = entry.path();
+
+        match Self::from_dir_entry(entry) {
+            Ok(mut found) => {
+                // Only regular files, as opening a FIFO would block
+                if !found.is_dir && path.is_file() {
+                    found.error = fs::File::open(&path).err().map(|e| e.kind());
+                }
+                found
+            }
+            Err(e) => Entry {
+                name,
+                is_dir: file_type.map(|t| t.is_dir()).unwrap_or(false),
+                len: 0,
+                modified: None,
+                is_symlink: file_type.map(|t| t.is_symlink()).unwrap_or(false),
+                error: Some(e.kind()),
+            },
+        }
+    }
+
+    /// Create a nameless placeholder for a directory entry which couldn't be
+    /// read at all, carrying the kind of `error`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::files::file::Entry;
+    /// use std::io;
+    ///
+    /// let error = io::Error::from(io::ErrorKind::PermissionDenied);
+    /// let entry = Entry::from_error(&error);
+    ///
+    /// assert_eq!(entry.name, "");
+    /// assert_eq!(entry.to_html_row(), "(unreadable)");
+    /// ```
+    pub fn from_error(error: &io::Error) -> Entry {
+        Entry {
+            name: String::new(),
+            is_dir: false,
+            len: 0,
+            modified: None,
+            is_symlink: false,
+            error: Some(error.kind()),
+        }
+    }
+
+    /// Suffix appended to the name of directories
+    fn suffix(&self) -> &'static str {
+        match self.is_dir {
+            true => "/",
+            false => "",
+        }
+    }
+
+    /// Represent the entry as an HTML link relative to its directory
+    ///
+    /// The name is percent-encoded in the link, see [`encode_path_segment`].
+    /// Unreadable entries are marked with `(unreadable)`, and placeholders
+    /// without a name are only the marker.
+    pub fn to_html_row(&self) -> String {
+        if self.name.is_empty() {
+            return String::from("(unreadable)");
+        }
+
+        format!(
+            "<a href=\"./{href}{suffix}\">{name}{suffix}</a>{marker}",
+            href = encode_path_segment(&self.name),
+            name = self.name,
+            suffix = self.suffix(),
+            marker = match self.error {
+                Some(_) => " (unreadable)",
+                None => "",
+            }
+        )
+    }
+
+    /// Represent the entry as a line of a plain text listing, with its name
+    /// padded to `name_width` characters, followed by its size and UTC
+    /// modification time.
+    ///
+    /// The size of directories is left out, and unreadable entries are marked
+    /// with `(unreadable)` instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::files::file::Entry;
+    /// # use std::time::{Duration, UNIX_EPOCH};
+    /// let entry = Entry {
+    ///     name: String::from("about.html"),
+    ///     is_dir: false,
+    ///     len: 1234,
+    ///     modified: Some(UNIX_EPOCH + Duration::from_secs(784_111_777)),
+    ///     is_symlink: false,
+    ///     error: None,
+    /// };
+    ///
+    /// assert_eq!(entry.to_text_row(12), "about.html           1234  1994-11-06 08:49");
+    /// ```
+    pub fn to_text_row(&self, name_width: usize) -> String {
+        let name = format!("{}{}", self.name, self.suffix());
+        let details = match (self.error, self.is_dir) {
+            (Some(_), _) => String::from("(unreadable)"),
+            (None, is_dir) => {
+                let size = match is_dir {
+                    true => String::from("-"),
+                    false => self.len.to_string(),
+                };
+                let modified = self.modified.map_or_else(String::new, |m| {
+                    let date = DateTime::from(m);
+                    format!(
+                        "{:04}-{:02}-{:02} {:02}:{:02}",
+                        date.year, date.month, date.day, date.hour, date.minute
+                    )
+                });
+                format!("{:>12}  {}", size, modified)
+            }
+        };
+
+        format!("{:<width$} {}", name, details, width = name_width)
+            .trim_end()
+            .to_string()
+    }
+
+    /// Represent the entry as a JSON object
+    ///
+    /// The modification time is given in seconds since the Unix epoch, or
+    /// `null` if unavailable. The error of unreadable entries is given as a
+    /// string, or `null` for readable entries.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::files::file::Entry;
+    /// let entry = Entry {
+    ///     name: String::from("pages"),
+    ///     is_dir: true,
+    ///     len: 4096,
+    ///     modified: None,
+    ///     is_symlink: false,
+    ///     error: None,
+    /// };
+    ///
+    /// assert_eq!(
+    ///     entry.to_json(),
+    ///     r#"{"name":"pages","is_dir":true,"len":4096,"modified":null,"is_symlink":false,"error":null}"#
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let modified = self
+            .modified
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs().to_string())
+            .unwrap_or_else(|| String::from("null"));
+
+        let error = self
+            .error
+            .map(|e| json_string(&io::Error::from(e).to_string()))
+            .unwrap_or_else(|| String::from("null"));
+
+        format!(
+            "{{\"name\":{name},\"is_dir\":{is_dir},\"len\":{len},\"modified\":{modified},\"is_symlink\":{is_symlink},\"error\":{error}}}",
+            name = json_string(&self.name),
+            is_dir = self.is_dir,
+            len = self.len,
+            modified = modified,
+            is_symlink = self.is_symlink,
+            error = error,
+        )
+    }
+}
 
 /// Wrapper struct around [`std::fs::DirEntry`]
 ///
 /// This wrapper is used to display files when listing directories. The display
 /// trait [`fmt::Display`] will represent the file as a HTML link to the file
 /// with the corresponding file or folder name.
+#[deprecated(note = "use `Entry` and `Entry::to_html_row` instead")]
 pub struct File(pub DirEntry);
 
+#[allow(deprecated)]
 impl fmt::Display for File {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let is_dir = &self
@@ -27,3 +282,391 @@ impl fmt::Display for File {
         )
     }
 }
+
+/// Iterator reading a file in chunks
+///
+/// Wraps a [`std::fs::File`] and yields its content in chunks of at most
+/// `chunk_size` bytes (64 KB by default), so that large files can be streamed,
+/// hashed or sent in ranges without reading them into memory at once. The
+/// iterator ends at the end of the file or after the first error.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::file::ChunkedReader;
+/// let chunks = ChunkedReader::open("example/index.html")
+///     .unwrap()
+///     .with_chunk_size(16)
+///     .collect::<std::io::Result<Vec<Vec<u8>>>>()
+///     .unwrap();
+///
+/// assert!(chunks.iter().all(|chunk| chunk.len() <= 16));
+/// assert!(chunks[0].starts_with(b"<!DOCTYPE html>"));
+/// ```
+#[derive(Debug)]
+pub struct ChunkedReader {
+    file: fs::File,
+    chunk_size: usize,
+    done: bool,
+}
+
+impl ChunkedReader {
+    /// Create a new ChunkedReader from an open file
+    pub fn new(file: fs::File) -> Self {
+        ChunkedReader {
+            file,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            done: false,
+        }
+    }
+
+    /// Open the file at `path` for reading in chunks
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(fs::File::open(path)?))
+    }
+
+    /// Set the maximum size of the yielded chunks
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chunk size is zero.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0);
+
+        self.chunk_size = chunk_size;
+        self
+    }
+}
+
+impl Iterator for ChunkedReader {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut chunk = Vec::with_capacity(self.chunk_size);
+        let result = (&mut self.file)
+            .take(self.chunk_size as u64)
+            .read_to_end(&mut chunk);
+
+        match result {
+            Ok(0) => {
+                self.done = true;
+                None
+            }
+            Ok(_) => Some(Ok(chunk)),
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Read a regular file, following symbolic links
+///
+/// Special files, such as FIFOs, sockets and device nodes, are never opened,
+/// as reading them may block forever or never end. An error of kind
+/// [`io::ErrorKind::PermissionDenied`] is returned for them instead, as well
+/// as for directories.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::file::read_regular;
+/// use std::io;
+///
+/// assert!(read_regular("example/index.html").is_ok());
+///
+/// let err = read_regular("example/").unwrap_err();
+/// assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+/// ```
+pub fn read_regular<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    if !fs::metadata(&path)?.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Special files are not served",
+        ));
+    }
+
+    fs::read(path)
+}
+
+/// Read the bytes `start..end` of a file
+///
+/// The range is half-open, i.e. the byte at `end` is not included. Ranges
+/// reaching past the end of the file are cut off at the end of the file. An
+/// error of kind [`io::ErrorKind::InvalidInput`] is returned if `start` is
+/// greater than `end`.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::file::read_range;
+/// let bytes = read_range("example/index.html", 2, 9).unwrap();
+///
+/// assert_eq!(bytes, b"DOCTYPE");
+/// ```
+pub fn read_range<P: AsRef<Path>>(
+    path: P,
+    start: u64,
+    end: u64,
+) -> io::Result<Vec<u8>> {
+    if start > end {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Range start is greater than range end",
+        ));
+    }
+
+    let mut file = fs::File::open(path)?;
+    let mut bytes = Vec::new();
+
+    file.seek(SeekFrom::Start(start))?;
+    file.take(end - start).read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    // Helper
+    fn entry(dir: &Path, name: &str) -> Entry {
+        let entry = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap())
+            .find(|e| e.file_name() == name)
+            .unwrap();
+
+        Entry::from_dir_entry(entry).unwrap()
+    }
+
+    #[test]
+    fn entry_file() {
+        let dir = TempDir::new();
+        dir.file("ten.txt", b"0123456789");
+        let entry = entry(dir.path(), "ten.txt");
+
+        assert_eq!(entry.name, "ten.txt");
+        assert!(!entry.is_dir);
+        assert!(!entry.is_symlink);
+        assert_eq!(entry.len, 10);
+        assert!(entry.modified.is_some());
+        assert_eq!(entry.to_html_row(), "<a href=\"./ten.txt\">ten.txt</a>");
+    }
+
+    #[test]
+    fn entry_dir() {
+        let dir = TempDir::new();
+        dir.file("sub/file.txt", b"");
+        let entry = entry(dir.path(), "sub");
+
+        assert!(entry.is_dir);
+        assert_eq!(entry.to_html_row(), "<a href=\"./sub/\">sub/</a>");
+    }
+
+    #[test]
+    fn entry_encoded_href() {
+        let dir = TempDir::new();
+        dir.file("My Photos #1/a.jpg", b"");
+        let entry = entry(dir.path(), "My Photos #1");
+
+        assert_eq!(
+            entry.to_html_row(),
+            "<a href=\"./My%20Photos%20%231/\">My Photos #1/</a>"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn entry_symlink() {
+        let dir = TempDir::new();
+        dir.file("sub/file.txt", b"");
+        std::os::unix::fs::symlink(
+            dir.path().join("sub"),
+            dir.path().join("link"),
+        )
+        .unwrap();
+        let entry = entry(dir.path(), "link");
+
+        assert!(entry.is_dir);
+        assert!(entry.is_symlink);
+    }
+
+    #[test]
+    fn entry_json() {
+        let entry = Entry {
+            name: String::from("say \"hi\".txt"),
+            is_dir: false,
+            len: 3,
+            modified: Some(UNIX_EPOCH + std::time::Duration::from_secs(42)),
+            is_symlink: false,
+            error: None,
+        };
+
+        assert_eq!(
+            entry.to_json(),
+            r#"{"name":"say \"hi\".txt","is_dir":false,"len":3,"modified":42,"is_symlink":false,"error":null}"#
+        );
+    }
+
+    #[test]
+    fn entry_text() {
+        let mut entry = Entry {
+            name: String::from("pages"),
+            is_dir: true,
+            len: 4096,
+            modified: Some(UNIX_EPOCH + std::time::Duration::from_secs(42)),
+            is_symlink: false,
+            error: None,
+        };
+        assert_eq!(
+            entry.to_text_row(8),
+            "pages/              -  1970-01-01 00:00"
+        );
+
+        entry.modified = None;
+        entry.error = Some(io::ErrorKind::NotFound);
+        assert_eq!(entry.to_text_row(0), "pages/ (unreadable)");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn entry_dangling_symlink() {
+        let dir = TempDir::new();
+        std::os::unix::fs::symlink(
+            dir.path().join("gone"),
+            dir.path().join("link"),
+        )
+        .unwrap();
+        let dir_entry = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap())
+            .find(|e| e.file_name() == "link")
+            .unwrap();
+        let entry = Entry::from_dir_entry_lossy(dir_entry);
+
+        assert_eq!(entry.name, "link");
+        assert!(entry.is_symlink);
+        assert!(!entry.is_dir);
+        assert_eq!(entry.error, Some(io::ErrorKind::NotFound));
+        assert_eq!(
+            entry.to_html_row(),
+            "<a href=\"./link\">link</a> (unreadable)"
+        );
+        assert!(entry.to_json().ends_with(r#""error":"entity not found"}"#));
+    }
+
+    // Helper
+    fn chunks(path: &Path, chunk_size: usize) -> Vec<Vec<u8>> {
+        ChunkedReader::open(path)
+            .unwrap()
+            .with_chunk_size(chunk_size)
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn chunk_boundaries() {
+        let dir = TempDir::new();
+        let path = dir.file("ten.txt", b"0123456789");
+
+        assert_eq!(
+            chunks(&path, 5),
+            vec![b"01234".to_vec(), b"56789".to_vec()]
+        );
+        assert_eq!(
+            chunks(&path, 4),
+            vec![b"0123".to_vec(), b"4567".to_vec(), b"89".to_vec()]
+        );
+        assert_eq!(chunks(&path, 64), vec![b"0123456789".to_vec()]);
+    }
+
+    #[test]
+    fn chunk_default_size() {
+        let dir = TempDir::new();
+        let path = dir.file("big.bin", vec![7; DEFAULT_CHUNK_SIZE + 1]);
+        let chunks = chunks(&path, DEFAULT_CHUNK_SIZE);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].len(), DEFAULT_CHUNK_SIZE);
+        assert_eq!(chunks[1], vec![7]);
+    }
+
+    #[test]
+    fn chunk_empty_file() {
+        let dir = TempDir::new();
+        let path = dir.file("empty.txt", b"");
+
+        assert!(chunks(&path, 16).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn regular_special_files() {
+        let dir = TempDir::new();
+        let fifo = dir.path().join("fifo");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        std::os::unix::fs::symlink(&fifo, dir.path().join("link")).unwrap();
+
+        // Opening the FIFO would block until a writer shows up
+        for name in ["fifo", "link"] {
+            let err = read_regular(dir.path().join(name)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        }
+        assert_eq!(
+            read_regular("/dev/null").unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            read_regular(dir.path().join("missing")).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(read_regular(dir.file("a.txt", "a")).unwrap(), b"a");
+    }
+
+    #[test]
+    fn range() {
+        let dir = TempDir::new();
+        let path = dir.file("ten.txt", b"0123456789");
+
+        assert_eq!(read_range(&path, 0, 3).unwrap(), b"012");
+        assert_eq!(read_range(&path, 4, 4).unwrap(), b"");
+    }
+
+    #[test]
+    fn range_until_eof() {
+        let dir = TempDir::new();
+        let path = dir.file("ten.txt", b"0123456789");
+
+        assert_eq!(read_range(&path, 7, 10).unwrap(), b"789");
+        assert_eq!(read_range(&path, 7, 20).unwrap(), b"789");
+        assert_eq!(read_range(&path, 10, 20).unwrap(), b"");
+    }
+
+    #[test]
+    fn range_invalid() {
+        let dir = TempDir::new();
+        let path = dir.file("ten.txt", b"0123456789");
+        let err = read_range(&path, 5, 2).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn range_missing_file() {
+        let dir = TempDir::new();
+        let err = read_range(dir.path().join("missing"), 0, 1).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt;
+
+/// A compiled glob pattern matching `/`-separated paths.
+///
+/// Patterns are matched segment by segment against relative paths. Supported
+/// syntax:
+///
+/// - `*` matches any number of characters within a single path segment
+/// - `?` matches exactly one character within a single path segment
+/// - `**` as a whole segment matches any number of segments, including none
+///
+/// Leading and trailing `/` are ignored, i.e. patterns are always anchored at
+/// the start of the path they are matched against.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::glob::Glob;
+/// let glob = Glob::new("assets/**/*.js").unwrap();
+///
+/// assert!(glob.matches("assets/app.js"));
+/// assert!(glob.matches("assets/vendor/lib/react.js"));
+/// assert!(!glob.matches("src/app.js"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    pattern: String,
+    segments: Vec<String>,
+}
+
+impl Glob {
+    /// Compile a glob pattern.
+    ///
+    /// [`None`] is returned if the pattern does not contain any path segments,
+    /// e.g. for empty patterns or `/`.
+    pub fn new(pattern: &str) -> Option<Self> {
+        let segments: Vec<String> = pattern
+            .split('/')
+            .filter(|s| !s.is_empty() && *s != ".")
+            .map(String::from)
+            .collect();
+
+        if segments.is_empty() {
+            return None;
+        }
+
+        Some(Glob {
+            pattern: pattern.to_string(),
+            segments,
+        })
+    }
+
+    /// Check whether a relative, `/`-separated path matches the pattern.
+    pub fn matches(&self, path: &str) -> bool {
+        let path: Vec<&str> = path
+            .split('/')
+            .filter(|s| !s.is_empty() && *s != ".")
+            .collect();
+        let segments: Vec<&str> =
+            self.segments.iter().map(String::as_str).collect();
+
+        match_segments(&segments, &path)
+    }
+
+    /// The original pattern.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+/// Match pattern segments against path segments, expanding `**`.
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=path.len()).any(|i| match_segments(rest, &path[i..]))
+        }
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path_rest)) => {
+                match_segment(segment.as_bytes(), name.as_bytes())
+                    && match_segments(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Match a single segment containing `*` and `?` wildcards.
+fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(b'?') => {
+                // Skip a whole UTF-8 character
+                p += 1;
+                n += 1;
+                while n < name.len() && name[n] & 0xC0 == 0x80 {
+                    n += 1;
+                }
+            }
+            Some(c) if *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::Glob;
+
+    // Helper
+    fn matches(pattern: &str, path: &str) -> bool {
+        Glob::new(pattern).unwrap().matches(path)
+    }
+
+    #[test]
+    fn literal() {
+        assert!(matches("index.html", "index.html"));
+        assert!(matches("/pages/about.html", "pages/about.html"));
+        assert!(!matches("index.html", "pages/index.html"));
+        assert!(!matches("pages", "pages/about.html"));
+    }
+
+    #[test]
+    fn star() {
+        assert!(matches("*.log", "debug.log"));
+        assert!(matches("*", "anything"));
+        assert!(matches("a*b*c", "aXXbYYc"));
+        assert!(!matches("*.log", "logs/debug.log"));
+        assert!(!matches("*.log", "debug.log.txt"));
+    }
+
+    #[test]
+    fn question_mark() {
+        assert!(matches("file?.txt", "file1.txt"));
+        assert!(matches("?.txt", "🦀.txt"));
+        assert!(!matches("file?.txt", "file.txt"));
+        assert!(!matches("file?.txt", "file12.txt"));
+    }
+
+    #[test]
+    fn double_star() {
+        assert!(matches("**/*.map", "bundle.js.map"));
+        assert!(matches("**/*.map", "assets/js/bundle.js.map"));
+        assert!(matches("assets/**", "assets/js/app.js"));
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(!matches("a/**/b", "a/x/y/c"));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Glob::new("").is_none());
+        assert!(Glob::new("/").is_none());
+        assert!(Glob::new("./").is_none());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Glob::new("/assets/*").unwrap().to_string(), "/assets/*");
+    }
+}
@@ -0,0 +1,176 @@
+use std::{fs, io, io::Read, path::Path};
+
+/// FNV-1a 64-bit offset basis
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+/// FNV-1a 64-bit prime
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+/// Size of the chunks read by [`hash_reader`]
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Streaming 64-bit FNV-1a hasher
+///
+/// FNV-1a is not a cryptographic hash, but it is cheap, dependency-free and
+/// good enough to fingerprint file contents, e.g. for ETags. The hash is
+/// guaranteed to stay stable across releases, as changed hashes would silently
+/// invalidate client caches.
+///
+/// See [`http://www.isthe.com/chongo/tech/comp/fnv/`] for more information on
+/// the algorithm.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::hash::{hash_bytes, Fnv1a};
+/// let mut hasher = Fnv1a::new();
+/// hasher.update(b"foo");
+/// hasher.update(b"bar");
+///
+/// assert_eq!(hasher.finish(), hash_bytes(b"foobar"));
+/// ```
+///
+/// [`http://www.isthe.com/chongo/tech/comp/fnv/`]: http://www.isthe.com/chongo/tech/comp/fnv/
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1a(u64);
+
+impl Fnv1a {
+    /// Create a new hasher
+    pub fn new() -> Self {
+        Fnv1a(FNV_OFFSET)
+    }
+
+    /// Feed bytes into the hasher
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Return the hash of all bytes fed into the hasher so far
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hash a slice of bytes using 64-bit FNV-1a
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::hash::hash_bytes;
+/// assert_eq!(hash_bytes(b"a"), 0xaf63dc4c8601ec8c);
+/// ```
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.update(bytes);
+    hasher.finish()
+}
+
+/// Hash the contents of a reader using 64-bit FNV-1a
+///
+/// The reader is consumed in chunks of 64 KB, so that large files can be hashed
+/// without reading them into memory at once. The result is the same as calling
+/// [`hash_bytes`] on the full content.
+pub fn hash_reader<R: Read>(mut reader: R) -> io::Result<u64> {
+    let mut hasher = Fnv1a::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return Ok(hasher.finish()),
+            Ok(n) => hasher.update(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Generate a strong ETag for a file
+///
+/// The ETag combines the file length and the hash of its content, both as
+/// hexadecimal numbers, and is quoted as required by the `ETag` header.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::hash::strong_etag;
+/// # use std::path::Path;
+/// let etag = strong_etag(Path::new("example/index.html")).unwrap();
+///
+/// assert!(etag.starts_with('"'));
+/// assert!(etag.ends_with('"'));
+/// ```
+pub fn strong_etag(path: &Path) -> io::Result<String> {
+    let file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let hash = hash_reader(file)?;
+
+    Ok(format_etag(len, hash))
+}
+
+/// Format a file length and content hash as a quoted ETag
+fn format_etag(len: u64, hash: u64) -> String {
+    format!("\"{:x}-{:016x}\"", len, hash)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn known_answers() {
+        // Reference values from the FNV test suite
+        assert_eq!(hash_bytes(b""), 0xcbf29ce484222325);
+        assert_eq!(hash_bytes(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash_bytes(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn reader_matches_bytes() {
+        // Spans several chunks, with a partial chunk at the end
+        let content: Vec<u8> =
+            (0..CHUNK_SIZE * 2 + 123).map(|i| (i % 251) as u8).collect();
+
+        assert_eq!(
+            hash_reader(content.as_slice()).unwrap(),
+            hash_bytes(&content)
+        );
+    }
+
+    #[test]
+    fn reader_empty() {
+        assert_eq!(hash_reader(io::empty()).unwrap(), hash_bytes(b""));
+    }
+
+    #[test]
+    fn etag_format() {
+        assert_eq!(
+            format_etag(6, hash_bytes(b"foobar")),
+            "\"6-85944171f73967e8\""
+        );
+    }
+
+    #[test]
+    fn etag_file() {
+        let path = Path::new("example/index.html");
+        let content = fs::read(path).unwrap();
+
+        assert_eq!(
+            strong_etag(path).unwrap(),
+            format_etag(content.len() as u64, hash_bytes(&content))
+        );
+    }
+
+    #[test]
+    fn etag_missing_file() {
+        let etag = strong_etag(Path::new("example/i_dont_exist"));
+
+        assert_eq!(etag.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::files::glob::Glob;
+use std::path::{Component, Path};
+
+/// Name of the ignore file read from the base directory
+pub const IGNORE_FILE: &str = ".servumignore";
+
+/// A single ignore rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    glob: Glob,
+    dir_only: bool,
+}
+
+/// Set of glob patterns excluding paths from being served.
+///
+/// Rules come from the `--ignore` flag and the `.servumignore` file in the base
+/// directory. Patterns are relative to, and anchored at, the base directory.
+/// Patterns ending in `/` only match directories. When a directory is ignored,
+/// everything inside it is ignored as well. See [`Glob`] for the supported
+/// pattern syntax.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::ignore::IgnoreRules;
+/// # use std::path::Path;
+/// let mut rules = IgnoreRules::default();
+/// rules.add("*.log").unwrap();
+/// rules.add("drafts/").unwrap();
+///
+/// assert!(rules.is_ignored(Path::new("debug.log"), false));
+/// assert!(rules.is_ignored(Path::new("drafts/post.html"), false));
+/// assert!(!rules.is_ignored(Path::new("index.html"), false));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    /// Add a single pattern.
+    ///
+    /// Returns [`None`] if the pattern is not a valid [`Glob`].
+    pub fn add(&mut self, pattern: &str) -> Option<()> {
+        let pattern = pattern.trim();
+        let glob = Glob::new(pattern)?;
+
+        self.rules.push(Rule {
+            glob,
+            dir_only: pattern.ends_with('/'),
+        });
+        Some(())
+    }
+
+    /// Parse the contents of an ignore file.
+    ///
+    /// Each line contains one pattern. Blank lines and lines starting with `#`
+    /// are ignored. On error, the (1-based) number of the offending line is
+    /// returned.
+    pub fn parse(contents: &str) -> Result<Self, usize> {
+        let mut rules = IgnoreRules::default();
+
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            rules.add(line).ok_or(i + 1)?;
+        }
+
+        Ok(rules)
+    }
+
+    /// Append all rules of another rule set.
+    pub fn extend(&mut self, other: IgnoreRules) {
+        self.rules.extend(other.rules);
+    }
+
+    /// Iterate over the original patterns of all rules, in order.
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|rule| rule.glob.as_str())
+    }
+
+    /// Whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Check whether a path relative to the base directory is ignored.
+    ///
+    /// `is_dir` indicates whether the path itself is a directory. All of its
+    /// parents are checked as directories.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+
+        let segments: Vec<String> = path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(s) => Some(s.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+
+        (1..=segments.len()).any(|i| {
+            let prefix = segments[..i].join("/");
+            let prefix_is_dir = i < segments.len() || is_dir;
+
+            self.rules.iter().any(|rule| {
+                (!rule.dir_only || prefix_is_dir) && rule.glob.matches(&prefix)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_file() {
+        let rules =
+            IgnoreRules::parse("# Comment\n\n*.log\n  drafts/  \n").unwrap();
+
+        assert_eq!(rules.rules.len(), 2);
+        assert!(rules.rules[1].dir_only);
+        assert_eq!(
+            rules.patterns().collect::<Vec<_>>(),
+            vec!["*.log", "drafts/"]
+        );
+    }
+
+    #[test]
+    fn parse_file_errors() {
+        assert_eq!(IgnoreRules::parse("*.log\n/\n"), Err(2));
+    }
+
+    #[test]
+    fn anchored() {
+        let rules = IgnoreRules::parse("secret.txt\n").unwrap();
+
+        assert!(rules.is_ignored(Path::new("secret.txt"), false));
+        assert!(!rules.is_ignored(Path::new("pages/secret.txt"), false));
+    }
+
+    #[test]
+    fn any_depth() {
+        let rules = IgnoreRules::parse("**/*.map\n").unwrap();
+
+        assert!(rules.is_ignored(Path::new("app.js.map"), false));
+        assert!(rules.is_ignored(Path::new("assets/js/app.js.map"), false));
+    }
+
+    #[test]
+    fn dir_only() {
+        let rules = IgnoreRules::parse("build/\n").unwrap();
+
+        assert!(rules.is_ignored(Path::new("build"), true));
+        assert!(rules.is_ignored(Path::new("build/app.js"), false));
+        assert!(!rules.is_ignored(Path::new("build"), false));
+    }
+
+    #[test]
+    fn ignored_parent() {
+        let rules = IgnoreRules::parse(".git\n").unwrap();
+
+        assert!(rules.is_ignored(Path::new(".git/config"), false));
+        assert!(rules.is_ignored(Path::new(".git/refs/heads"), true));
+        assert!(!rules.is_ignored(Path::new(".github/workflows"), true));
+    }
+
+    #[test]
+    fn empty() {
+        let rules = IgnoreRules::default();
+
+        assert!(rules.is_empty());
+        assert!(!rules.is_ignored(Path::new("anything"), false));
+    }
+}
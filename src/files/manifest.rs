@@ -0,0 +1,241 @@
+use crate::files::ignore::IgnoreRules;
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// File found by [`walk`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestFile {
+    /// Path relative to the walked directory, with `/` separators
+    pub path: String,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Files found below a directory by [`walk`], sorted by path
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub files: Vec<ManifestFile>,
+    /// Whether files were left out for reaching the depth or entry limit
+    pub truncated: bool,
+    /// Directories read, the walked one first, with their modification time
+    /// from before reading them. Unless one of them changes, no file was
+    /// added, removed or renamed since.
+    pub dirs: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+/// Walk `root` recursively and return the regular files below it.
+///
+/// Entries matching `ignore`, relative to `root`, are left out, as are
+/// special files and unreadable subdirectories. Symbolic links to files are
+/// listed, while those to directories aren't followed, so that loops can't
+/// be walked. Files are listed up to `max_depth` path components, and up to
+/// `max_entries` of them, shallower ones first. Returns an error if `root`
+/// itself can't be read.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::{ignore::IgnoreRules, manifest};
+/// use std::path::Path;
+///
+/// let mut ignore = IgnoreRules::default();
+/// ignore.add("*.css").unwrap();
+/// let manifest = manifest::walk(Path::new("example"), &ignore, 2, 100).unwrap();
+///
+/// let paths = manifest.files.iter().map(|f| f.path.as_str());
+/// assert!(paths.clone().any(|path| path == "index.html"));
+/// assert!(paths.clone().any(|path| path == "pages/about.html"));
+/// assert!(!paths.clone().any(|path| path.ends_with(".css")));
+/// ```
+pub fn walk(
+    root: &Path,
+    ignore: &IgnoreRules,
+    max_depth: usize,
+    max_entries: usize,
+) -> io::Result<Manifest> {
+    let mut manifest = Manifest::default();
+    // Directories left to read, relative to root, with their depth
+    let mut dirs = VecDeque::from([(PathBuf::new(), 0)]);
+
+    while let Some((dir, depth)) = dirs.pop_front() {
+        let path = root.join(&dir);
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        let read = match fs::read_dir(&path) {
+            Ok(read) => read,
+            Err(e) if depth == 0 => return Err(e),
+            Err(_) => continue,
+        };
+        manifest.dirs.push((path, modified));
+        let mut entries = read.flatten().collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let rel = dir.join(entry.file_name());
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(_) => continue,
+            };
+            let meta = match file_type.is_symlink() {
+                true => fs::metadata(entry.path()),
+                false => entry.metadata(),
+            };
+            let meta = match meta {
+                Ok(meta) => meta,
+                Err(_) => continue,
+            };
+
+            if ignore.is_ignored(&rel, meta.is_dir()) {
+                continue;
+            }
+            if meta.is_dir() && !file_type.is_symlink() {
+                match depth + 1 < max_depth {
+                    true => dirs.push_back((rel, depth + 1)),
+                    false => manifest.truncated = true,
+                }
+            } else if meta.is_file() {
+                if manifest.files.len() == max_entries {
+                    manifest.truncated = true;
+                    dirs.clear();
+                    break;
+                }
+                manifest.files.push(ManifestFile {
+                    path: url_path(&rel),
+                    len: meta.len(),
+                    modified: meta.modified().ok(),
+                });
+            }
+        }
+    }
+
+    manifest.files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(manifest)
+}
+
+/// Join the components of a relative path with `/`, whatever the platform.
+fn url_path(rel: &Path) -> String {
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    fn paths(manifest: &Manifest) -> Vec<&str> {
+        manifest.files.iter().map(|f| f.path.as_str()).collect()
+    }
+
+    #[test]
+    fn recursive() {
+        let dir = TempDir::new();
+        dir.file("b.txt", "bb");
+        dir.file("a/z.txt", "");
+        dir.file("a/b/c.jpg", "c");
+        dir.file("empty/.keep", "");
+        fs::create_dir(dir.path().join("really-empty")).unwrap();
+
+        let manifest =
+            walk(dir.path(), &IgnoreRules::default(), 16, 100).unwrap();
+        assert_eq!(
+            paths(&manifest),
+            ["a/b/c.jpg", "a/z.txt", "b.txt", "empty/.keep"]
+        );
+        assert!(!manifest.truncated);
+
+        let file = &manifest.files[2];
+        assert_eq!(file.len, 2);
+        assert!(file.modified.is_some());
+    }
+
+    #[test]
+    fn ignored() {
+        let dir = TempDir::new();
+        dir.file("index.html", "");
+        dir.file("drafts/post.html", "");
+        dir.file("img/a.jpg", "");
+        dir.file("img/a.psd", "");
+
+        let ignore = IgnoreRules::parse("drafts/\n**/*.psd\n").unwrap();
+        let manifest = walk(dir.path(), &ignore, 16, 100).unwrap();
+        assert_eq!(paths(&manifest), ["img/a.jpg", "index.html"]);
+    }
+
+    #[test]
+    fn limits() {
+        let dir = TempDir::new();
+        dir.file("1.txt", "");
+        dir.file("a/2.txt", "");
+        dir.file("a/b/3.txt", "");
+        let ignore = IgnoreRules::default();
+
+        let manifest = walk(dir.path(), &ignore, 2, 100).unwrap();
+        assert_eq!(paths(&manifest), ["1.txt", "a/2.txt"]);
+        assert!(manifest.truncated);
+
+        // Shallower files come first
+        let manifest = walk(dir.path(), &ignore, 16, 2).unwrap();
+        assert_eq!(paths(&manifest), ["1.txt", "a/2.txt"]);
+        assert!(manifest.truncated);
+
+        let manifest = walk(dir.path(), &ignore, 16, 3).unwrap();
+        assert!(!manifest.truncated);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = TempDir::new();
+        dir.file("site/a.txt", "a");
+        dir.file("outside.txt", "outside");
+        let site = dir.path().join("site");
+        symlink(dir.path().join("outside.txt"), site.join("link.txt")).unwrap();
+        symlink(&site, site.join("loop")).unwrap();
+        symlink(site.join("missing"), site.join("dangling")).unwrap();
+
+        let manifest = walk(&site, &IgnoreRules::default(), 16, 100).unwrap();
+        assert_eq!(paths(&manifest), ["a.txt", "link.txt"]);
+        assert_eq!(manifest.files[1].len, 7);
+    }
+
+    #[test]
+    fn walked_dirs() {
+        let dir = TempDir::new();
+        dir.file("a/b/c.txt", "");
+        dir.file("a/raw/d.txt", "");
+        let ignore = IgnoreRules::parse("a/raw/").unwrap();
+
+        let manifest = walk(dir.path(), &ignore, 16, 100).unwrap();
+        let dirs = manifest.dirs.iter().map(|(dir, _)| dir.clone());
+        assert_eq!(
+            dirs.collect::<Vec<_>>(),
+            [
+                dir.path().join(""),
+                dir.path().join("a"),
+                dir.path().join("a/b")
+            ]
+        );
+        assert!(manifest.dirs.iter().all(|(_, modified)| modified.is_some()));
+    }
+
+    #[test]
+    fn missing_root() {
+        let dir = TempDir::new();
+        let err = walk(
+            &dir.path().join("missing"),
+            &IgnoreRules::default(),
+            16,
+            100,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -1,10 +1,11 @@
-use std::path::Path;
+use std::{borrow::Cow, collections::HashMap, path::Path};
 
 /// Guess a file's MIME type based on it's extension
 ///
 /// This function will try to gues the appropriate MIME type for a given file
-/// by matching on it's file type. If no match is found, [`None`] is returned
-/// instead.
+/// by matching on it's file type. Extensions are matched case-insensitively
+/// and only the last extension is considered, i.e. `archive.tar.gz` is a gzip
+/// file. If no match is found, [`None`] is returned instead.
 ///
 /// The list of supported MIME types are adapted from
 /// [`https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/MIME_types/Common_types`]
@@ -22,87 +23,529 @@ use std::path::Path;
 /// [`https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/MIME_types/Common_types`]:
 /// https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/MIME_types/Common_types
 pub fn guess_mime_type(path: &Path) -> Option<&'static str> {
-    path.extension()
-        .and_then(|ext| match ext.to_str().unwrap() {
-            "aac" => Some("audio/aac"),
-            "abw" => Some("application/x-abiword"),
-            "arc" => Some("application/x-freearc"),
-            "avi" => Some("video/x-msvideo"),
-            "azw" => Some("application/vnd.amazon.ebook"),
-            "bin" => Some("application/octet-stream"),
-            "bmp" => Some("image/bmp"),
-            "bz" => Some("application/x-bzip"),
-            "bz2" => Some("application/x-bzip2"),
-            "csh" => Some("application/x-csh"),
-            "css" => Some("text/css"),
-            "csv" => Some("text/csv"),
-            "doc" => Some("application/msword"),
-            "docx" => {
-                Some("application/vnd.openxmlformats-officedocument.wordprocessingml.document")
-            }
-            "eot" => Some("application/vnd.ms-fontobject"),
-            "epub" => Some("application/epub+zip"),
-            "gz" => Some("application/gzip"),
-            "gif" => Some("image/gif"),
-            "htm" => Some("text/html"),
-            "html" => Some("text/html"),
-            "ico" => Some("image/vnd.microsoft.icon"),
-            "ics" => Some("text/calendar"),
-            "jar" => Some("application/java-archive"),
-            "jpeg" => Some("image/jpeg"),
-            "jpg" => Some("image/jpeg"),
-            "js" => Some("text/javascript"),
-            "json" => Some("application/json"),
-            "jsonld" => Some("application/ld+json"),
-            "mid" => Some("audio/midi"),
-            "midi" => Some("audio/midi"),
-            "mjs" => Some("text/javascript"),
-            "mp3" => Some("audio/mpeg"),
-            "mpeg" => Some("video/mpeg"),
-            "mpkg" => Some("application/vnd.apple.installer+xml"),
-            "odp" => Some("application/vnd.oasis.opendocument.presentation"),
-            "ods" => Some("application/vnd.oasis.opendocument.spreadsheet"),
-            "odt" => Some("application/vnd.oasis.opendocument.text"),
-            "oga" => Some("audio/ogg"),
-            "ogv" => Some("video/ogg"),
-            "ogx" => Some("application/ogg"),
-            "opus" => Some("audio/opus"),
-            "otf" => Some("font/otf"),
-            "png" => Some("image/png"),
-            "pdf" => Some("application/pdf"),
-            "php" => Some("application/x-httpd-php"),
-            "ppt" => Some("application/vnd.ms-powerpoint"),
-            "pptx" => {
-                Some("application/vnd.openxmlformats-officedocument.presentationml.presentation")
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+
+    match ext.as_str() {
+        "aac" => Some("audio/aac"),
+        "abw" => Some("application/x-abiword"),
+        "apng" => Some("image/apng"),
+        "arc" => Some("application/x-freearc"),
+        "avi" => Some("video/x-msvideo"),
+        "avif" => Some("image/avif"),
+        "azw" => Some("application/vnd.amazon.ebook"),
+        "bin" => Some("application/octet-stream"),
+        "bmp" => Some("image/bmp"),
+        "bz" => Some("application/x-bzip"),
+        "bz2" => Some("application/x-bzip2"),
+        "csh" => Some("application/x-csh"),
+        "css" => Some("text/css"),
+        "csv" => Some("text/csv"),
+        "doc" => Some("application/msword"),
+        "docx" => {
+            Some("application/vnd.openxmlformats-officedocument.wordprocessingml.document")
+        }
+        "eot" => Some("application/vnd.ms-fontobject"),
+        "epub" => Some("application/epub+zip"),
+        "flac" => Some("audio/flac"),
+        "gz" => Some("application/gzip"),
+        "gif" => Some("image/gif"),
+        "heic" => Some("image/heic"),
+        "htm" => Some("text/html"),
+        "html" => Some("text/html"),
+        "ico" => Some("image/vnd.microsoft.icon"),
+        "ics" => Some("text/calendar"),
+        "jar" => Some("application/java-archive"),
+        "jpeg" => Some("image/jpeg"),
+        "jpg" => Some("image/jpeg"),
+        "js" => Some("text/javascript"),
+        "json" => Some("application/json"),
+        "jsonld" => Some("application/ld+json"),
+        "jxl" => Some("image/jxl"),
+        "m2ts" => Some("video/mp2t"),
+        "m4a" => Some("audio/mp4"),
+        "m4v" => Some("video/mp4"),
+        "map" => Some("application/json"),
+        "md" => Some("text/markdown"),
+        "mid" => Some("audio/midi"),
+        "midi" => Some("audio/midi"),
+        "mjs" => Some("text/javascript"),
+        "mkv" => Some("video/x-matroska"),
+        "mp3" => Some("audio/mpeg"),
+        "mp4" => Some("video/mp4"),
+        "mpeg" => Some("video/mpeg"),
+        "mpkg" => Some("application/vnd.apple.installer+xml"),
+        "odp" => Some("application/vnd.oasis.opendocument.presentation"),
+        "ods" => Some("application/vnd.oasis.opendocument.spreadsheet"),
+        "odt" => Some("application/vnd.oasis.opendocument.text"),
+        "oga" => Some("audio/ogg"),
+        "ogv" => Some("video/ogg"),
+        "ogx" => Some("application/ogg"),
+        "opus" => Some("audio/opus"),
+        "otf" => Some("font/otf"),
+        "png" => Some("image/png"),
+        "pdf" => Some("application/pdf"),
+        "php" => Some("application/x-httpd-php"),
+        "ppt" => Some("application/vnd.ms-powerpoint"),
+        "pptx" => {
+            Some("application/vnd.openxmlformats-officedocument.presentationml.presentation")
+        }
+        "rar" => Some("application/vnd.rar"),
+        "rtf" => Some("application/rtf"),
+        "sh" => Some("application/x-sh"),
+        "svg" => Some("image/svg+xml"),
+        "swf" => Some("application/x-shockwave-flash"),
+        "tar" => Some("application/x-tar"),
+        "tif" => Some("image/tiff"),
+        "tiff" => Some("image/tiff"),
+        "toml" => Some("application/toml"),
+        // TypeScript sources are far more common on a development server than
+        // MPEG transport streams, which remain available through `.m2ts`.
+        "ts" => Some("text/plain"),
+        "ttf" => Some("font/ttf"),
+        "txt" => Some("text/plain"),
+        "vsd" => Some("application/vnd.visio"),
+        "wasm" => Some("application/wasm"),
+        "wav" => Some("audio/wav"),
+        "weba" => Some("audio/webm"),
+        "webm" => Some("video/webm"),
+        "webmanifest" => Some("application/manifest+json"),
+        "webp" => Some("image/webp"),
+        "woff" => Some("font/woff"),
+        "woff2" => Some("font/woff2"),
+        "xhtml" => Some("application/xhtml+xml"),
+        "xls" => Some("application/vnd.ms-excel"),
+        "xlsx" => Some("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+        "xml" => Some("application/xml"),
+        "xul" => Some("application/vnd.mozilla.xul+xml"),
+        "yaml" => Some("application/yaml"),
+        "yml" => Some("application/yaml"),
+        "zip" => Some("application/zip"),
+        "3gp" => Some("video/3gpp"),
+        "3g2" => Some("video/3gpp2"),
+        "7z" => Some("application/x-7z-compressed"),
+        "rs" => Some("text/x-rust"),
+        _ => None,
+    }
+}
+
+/// Guess a file's MIME type, consulting user overrides first
+///
+/// Overrides map lowercase extensions (without the leading `.`) to MIME types
+/// and take precedence over the built-in table of [`guess_mime_type`]. As the
+/// overrides are owned by the user config, matches are returned as owned
+/// [`Cow`]s, while built-in MIME types are borrowed.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::mime::guess_mime_type_with;
+/// # use std::{collections::HashMap, path::Path};
+/// let mut overrides = HashMap::new();
+/// overrides.insert(String::from("glsl"), String::from("text/plain"));
+///
+/// let shader = guess_mime_type_with(Path::new("shader.GLSL"), &overrides);
+/// let script = guess_mime_type_with(Path::new("main.js"), &overrides);
+///
+/// assert_eq!(shader.unwrap(), "text/plain");
+/// assert_eq!(script.unwrap(), "text/javascript");
+/// ```
+pub fn guess_mime_type_with(
+    path: &Path,
+    overrides: &HashMap<String, String>,
+) -> Option<Cow<'static, str>> {
+    if !overrides.is_empty() {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        if let Some(mime) = ext.and_then(|ext| overrides.get(&ext)) {
+            return Some(Cow::Owned(mime.clone()));
+        }
+    }
+
+    guess_mime_type(path).map(Cow::Borrowed)
+}
+
+/// Number of leading bytes inspected by [`sniff_mime`]
+const SNIFF_LEN: usize = 512;
+
+/// Guess a file's MIME type based on it's content
+///
+/// This function is meant for files without a (known) extension, such as
+/// `README`, `LICENSE` or hashed asset names. The first bytes are matched
+/// against the magic numbers of common image, document and archive formats.
+/// HTML and JSON are recognized by their leading characters. Otherwise, if the
+/// first 512 bytes are valid UTF-8 without NUL bytes, the content is assumed to
+/// be `text/plain`. If no match is found, [`None`] is returned instead.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::mime::sniff_mime;
+/// assert_eq!(sniff_mime(b"%PDF-1.7").unwrap(), "application/pdf");
+/// assert_eq!(sniff_mime(b"Hello World").unwrap(), "text/plain");
+/// assert_eq!(sniff_mime(&[0, 1, 2, 3]), None);
+/// ```
+pub fn sniff_mime(content: &[u8]) -> Option<&'static str> {
+    let magic: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"PK\x03\x04", "application/zip"),
+    ];
+
+    if let Some((_, mime)) = magic.iter().find(|(m, _)| content.starts_with(m))
+    {
+        return Some(mime);
+    }
+
+    if content.len() >= 12
+        && &content[..4] == b"RIFF"
+        && &content[8..12] == b"WEBP"
+    {
+        return Some("image/webp");
+    }
+
+    let sample = &content[..content.len().min(SNIFF_LEN)];
+
+    if !is_text(sample) {
+        return None;
+    }
+
+    let start = sample
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .map(|i| &sample[i..])?;
+    let starts_with_ignore_case = |prefix: &[u8]| {
+        start.len() >= prefix.len()
+            && start[..prefix.len()].eq_ignore_ascii_case(prefix)
+    };
+
+    if starts_with_ignore_case(b"<!DOCTYPE html")
+        || starts_with_ignore_case(b"<html")
+    {
+        Some("text/html")
+    } else if start[0] == b'{' || start[0] == b'[' {
+        Some("application/json")
+    } else {
+        Some("text/plain")
+    }
+}
+
+/// Check whether a sample of bytes looks like text
+///
+/// The sample must be valid UTF-8 without NUL bytes. A multi-byte character cut
+/// off at the end of the sample is tolerated.
+fn is_text(sample: &[u8]) -> bool {
+    if sample.contains(&0) {
+        return false;
+    }
+
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+/// Parse a single `ext=type` MIME override
+///
+/// The extension is lowercased and a leading `.` is stripped. [`None`] is
+/// returned if either side of the `=` is empty.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::mime::parse_override;
+/// let (ext, mime) = parse_override(".GLSL=text/plain").unwrap();
+///
+/// assert_eq!(ext, "glsl");
+/// assert_eq!(mime, "text/plain");
+/// assert!(parse_override("glsl").is_none());
+/// ```
+pub fn parse_override(val: &str) -> Option<(String, String)> {
+    let (ext, mime) = val.split_once('=')?;
+
+    normalize_override(ext, mime)
+}
+
+/// An invalid line of a MIME override file, with its (1-based) number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverrideError {
+    pub line: usize,
+    pub msg: &'static str,
+}
+
+/// Parse the contents of a MIME override file
+///
+/// Each line contains an extension and a MIME type separated by whitespace,
+/// e.g. `glsl text/plain`. Blank lines and lines starting with `#` are
+/// ignored. On error, the offending line and what is wrong with it are
+/// returned.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::mime::parse_override_file;
+/// let overrides = parse_override_file("# Shaders\nglsl text/plain\n").unwrap();
+///
+/// assert_eq!(overrides["glsl"], "text/plain");
+/// assert_eq!(parse_override_file("glsl\n").unwrap_err().line, 1);
+/// ```
+pub fn parse_override_file(
+    contents: &str,
+) -> Result<HashMap<String, String>, OverrideError> {
+    let mut overrides = HashMap::new();
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |msg| OverrideError { line: i + 1, msg };
+        let mut parts = line.split_whitespace();
+        let (ext, mime) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(ext), Some(mime), None) => normalize_override(ext, mime)
+                .ok_or_else(|| error("missing extension"))?,
+            _ => {
+                return Err(error(
+                    "expected an extension and a MIME type, e.g. \
+                     `glsl text/plain`",
+                ))
             }
-            "rar" => Some("application/vnd.rar"),
-            "rtf" => Some("application/rtf"),
-            "sh" => Some("application/x-sh"),
-            "svg" => Some("image/svg+xml"),
-            "swf" => Some("application/x-shockwave-flash"),
-            "tar" => Some("application/x-tar"),
-            "tif" => Some("image/tiff"),
-            "tiff" => Some("image/tiff"),
-            "ts" => Some("video/mp2t"),
-            "ttf" => Some("font/ttf"),
-            "txt" => Some("text/plain"),
-            "vsd" => Some("application/vnd.visio"),
-            "wav" => Some("audio/wav"),
-            "weba" => Some("audio/webm"),
-            "webm" => Some("video/webm"),
-            "webp" => Some("image/webp"),
-            "woff" => Some("font/woff"),
-            "woff2" => Some("font/woff2"),
-            "xhtml" => Some("application/xhtml+xml"),
-            "xls" => Some("application/vnd.ms-excel"),
-            "xlsx" => Some("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
-            "xml" => Some("application/xml"),
-            "xul" => Some("application/vnd.mozilla.xul+xml"),
-            "zip" => Some("application/zip"),
-            "3gp" => Some("video/3gpp"),
-            "3g2" => Some("video/3gpp2"),
-            "7z" => Some("application/x-7z-compressed"),
-            "rs" => Some("text/x-rust"),
-            _ => None,
-        })
+        };
+
+        overrides.insert(ext, mime);
+    }
+
+    Ok(overrides)
+}
+
+fn normalize_override(ext: &str, mime: &str) -> Option<(String, String)> {
+    let ext = ext.trim().trim_start_matches('.');
+    let mime = mime.trim();
+
+    if ext.is_empty() || mime.is_empty() {
+        return None;
+    }
+
+    Some((ext.to_ascii_lowercase(), mime.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lowercase_extension() {
+        let path = Path::new("/path/to/style.css");
+
+        assert_eq!(guess_mime_type(path).unwrap(), "text/css");
+    }
+
+    #[test]
+    fn mixed_case_extension() {
+        assert_eq!(
+            guess_mime_type(Path::new("PHOTO.JPG")).unwrap(),
+            "image/jpeg"
+        );
+        assert_eq!(
+            guess_mime_type(Path::new("Video.MP4")).unwrap(),
+            "video/mp4"
+        );
+        assert_eq!(
+            guess_mime_type(Path::new("index.HtMl")).unwrap(),
+            "text/html"
+        );
+    }
+
+    #[test]
+    fn missing_extension() {
+        assert_eq!(guess_mime_type(Path::new("Makefile")), None);
+        assert_eq!(guess_mime_type(Path::new("/path/to/dir/")), None);
+        assert_eq!(guess_mime_type(Path::new(".gitignore")), None);
+    }
+
+    #[test]
+    fn unknown_extension() {
+        assert_eq!(guess_mime_type(Path::new("file.unknownext")), None);
+    }
+
+    #[test]
+    fn modern_types() {
+        let cases = [
+            ("app.wasm", "application/wasm"),
+            ("photo.avif", "image/avif"),
+            ("anim.apng", "image/apng"),
+            ("song.flac", "audio/flac"),
+            ("song.m4a", "audio/mp4"),
+            ("clip.m4v", "video/mp4"),
+            ("movie.mkv", "video/x-matroska"),
+            ("README.md", "text/markdown"),
+            ("Cargo.toml", "application/toml"),
+            ("config.yaml", "application/yaml"),
+            ("config.yml", "application/yaml"),
+            ("bundle.js.map", "application/json"),
+            ("site.webmanifest", "application/manifest+json"),
+            ("photo.heic", "image/heic"),
+            ("photo.jxl", "image/jxl"),
+        ];
+
+        for (file, mime) in cases.iter() {
+            assert_eq!(
+                guess_mime_type(Path::new(file)),
+                Some(*mime),
+                "{}",
+                file
+            );
+        }
+    }
+
+    #[test]
+    fn typescript_is_text() {
+        assert_eq!(
+            guess_mime_type(Path::new("main.ts")).unwrap(),
+            "text/plain"
+        );
+        assert_eq!(
+            guess_mime_type(Path::new("stream.m2ts")).unwrap(),
+            "video/mp2t"
+        );
+    }
+
+    #[test]
+    fn multi_dot_extension() {
+        // Last extension wins
+        assert_eq!(
+            guess_mime_type(Path::new("archive.tar.gz")).unwrap(),
+            "application/gzip"
+        );
+        assert_eq!(
+            guess_mime_type(Path::new("bundle.min.js")).unwrap(),
+            "text/javascript"
+        );
+    }
+
+    #[test]
+    fn overrides_take_precedence() {
+        let mut overrides = HashMap::new();
+        overrides.insert(String::from("bin"), String::from("application/wasm"));
+
+        let mime = guess_mime_type_with(Path::new("module.bin"), &overrides);
+
+        assert!(matches!(mime, Some(Cow::Owned(_))));
+        assert_eq!(mime.unwrap(), "application/wasm");
+    }
+
+    #[test]
+    fn overrides_fall_back_to_builtin() {
+        let overrides = HashMap::new();
+        let mime = guess_mime_type_with(Path::new("index.html"), &overrides);
+
+        assert!(matches!(mime, Some(Cow::Borrowed("text/html"))));
+        assert!(
+            guess_mime_type_with(Path::new("Makefile"), &overrides).is_none()
+        );
+    }
+
+    #[test]
+    fn parse_single_override() {
+        assert_eq!(
+            parse_override("glsl=text/plain"),
+            Some((String::from("glsl"), String::from("text/plain")))
+        );
+        assert_eq!(parse_override("=text/plain"), None);
+        assert_eq!(parse_override("glsl="), None);
+        assert_eq!(parse_override("glsl"), None);
+    }
+
+    #[test]
+    fn parse_file() {
+        let contents =
+            "# comment\n\n  glsl   text/plain\n.BIN application/wasm\n";
+        let overrides = parse_override_file(contents).unwrap();
+
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides["glsl"], "text/plain");
+        assert_eq!(overrides["bin"], "application/wasm");
+    }
+
+    #[test]
+    fn parse_file_errors() {
+        let line = |contents| parse_override_file(contents).unwrap_err().line;
+
+        assert_eq!(line("glsl text/plain\nbin\n"), 2);
+        assert_eq!(line("a b c\n"), 1);
+        assert_eq!(
+            parse_override_file(". text/plain\n").unwrap_err().msg,
+            "missing extension"
+        );
+    }
+
+    #[test]
+    fn sniff_images() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let jpeg = b"\xff\xd8\xff\xe0\0\x10JFIF";
+        let webp = b"RIFF\x24\0\0\0WEBPVP8 ";
+
+        assert_eq!(sniff_mime(png).unwrap(), "image/png");
+        assert_eq!(sniff_mime(jpeg).unwrap(), "image/jpeg");
+        assert_eq!(sniff_mime(b"GIF87a\x01\0").unwrap(), "image/gif");
+        assert_eq!(sniff_mime(b"GIF89a\x01\0").unwrap(), "image/gif");
+        assert_eq!(sniff_mime(webp).unwrap(), "image/webp");
+    }
+
+    #[test]
+    fn sniff_documents_and_archives() {
+        let gzip = b"\x1f\x8b\x08\0\0\0\0\0";
+        let zip = b"PK\x03\x04\x14\0\0\0";
+
+        assert_eq!(sniff_mime(b"%PDF-1.4\n").unwrap(), "application/pdf");
+        assert_eq!(sniff_mime(gzip).unwrap(), "application/gzip");
+        assert_eq!(sniff_mime(zip).unwrap(), "application/zip");
+    }
+
+    #[test]
+    fn sniff_html() {
+        let doctype = b"\n  <!doctype html><html></html>";
+        let html = b"<HTML><body>Hi</body></HTML>";
+
+        assert_eq!(sniff_mime(doctype).unwrap(), "text/html");
+        assert_eq!(sniff_mime(html).unwrap(), "text/html");
+    }
+
+    #[test]
+    fn sniff_json() {
+        assert_eq!(sniff_mime(b"{\"a\": 1}").unwrap(), "application/json");
+        assert_eq!(sniff_mime(b"  [1, 2, 3]").unwrap(), "application/json");
+    }
+
+    #[test]
+    fn sniff_text() {
+        let license = "MIT License\n\nCopyright (c) 2020 Ulysse McConnell ✨";
+
+        assert_eq!(sniff_mime(license.as_bytes()).unwrap(), "text/plain");
+    }
+
+    #[test]
+    fn sniff_text_cut_off_char() {
+        // 511 ASCII bytes followed by a 4-byte 🦀, cut off by the sample size
+        let mut content = vec![b'a'; SNIFF_LEN - 1];
+        content.extend_from_slice("🦀".as_bytes());
+
+        assert_eq!(sniff_mime(&content).unwrap(), "text/plain");
+    }
+
+    #[test]
+    fn sniff_binary() {
+        assert_eq!(sniff_mime(b"text\0with nul"), None);
+        assert_eq!(sniff_mime(&[0x80, 0x81, 0x82]), None);
+        assert_eq!(sniff_mime(b""), None);
+        assert_eq!(sniff_mime(b"   \n"), None);
+    }
 }
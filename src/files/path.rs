@@ -24,7 +24,12 @@ fn from_hex(it: &mut std::slice::Iter<u8>) -> Option<u8> {
 /// Decode percent-encoded URIs
 ///
 /// Create a new [`PathBuf`] with all `%` decoded to UTF-8. Internally iterates
-/// the input and calls [`from_hex`] on all `%`.
+/// the input and calls [`from_hex`] on all `%`. Decoded bytes which aren't
+/// valid UTF-8 are replaced with `U+FFFD`, as they can't name a file served
+/// anyway.
+///
+/// Request paths should be decoded once, with [`process_path`], as decoding
+/// again would turn e.g. `%252e` into `.`.
 ///
 /// # Example
 ///
@@ -36,21 +41,45 @@ fn from_hex(it: &mut std::slice::Iter<u8>) -> Option<u8> {
 /// assert_eq!(decode_percents(path), PathBuf::from("/🦀.html"));
 /// ```
 pub fn decode_percents(path: &str) -> PathBuf {
+    decode(path, false).0
+}
+
+/// Decode percent-encoded URIs like [`decode_percents`], leaving encoded
+/// slashes and backslashes, i.e. `%2F` and `%5C`, as they are if
+/// `keep_separators`, and returning whether there were any.
+fn decode(path: &str, keep_separators: bool) -> (PathBuf, bool) {
     if !path.contains('%') {
-        return PathBuf::from(path);
+        return (PathBuf::from(path), false);
     }
 
     let mut acc: Vec<u8> = Vec::with_capacity(path.len() + 1);
     let mut it = path.as_bytes().iter();
+    let mut kept = false;
 
     while let Some(el) = it.next() {
         acc.push(match el {
-            b'%' => from_hex(&mut it).unwrap_or(b'%'),
+            b'%' => {
+                let mut hex = it.clone();
+                match from_hex(&mut hex) {
+                    Some(b'/' | b'\\') if keep_separators => {
+                        kept = true;
+                        b'%'
+                    }
+                    Some(byte) => {
+                        it = hex;
+                        byte
+                    }
+                    None => b'%',
+                }
+            }
             byte => *byte,
         })
     }
 
-    PathBuf::from(std::str::from_utf8(&acc).unwrap())
+    (
+        PathBuf::from(String::from_utf8_lossy(&acc).into_owned()),
+        kept,
+    )
 }
 
 /// Normalize a file path
@@ -106,10 +135,55 @@ pub fn normalize_path(path: &Path) -> PathBuf {
     ret
 }
 
+/// Request path which has been percent-decoded, returned by [`process_path`].
+///
+/// Routing code passes request paths around as `ProcessedPath`s rather than
+/// [`PathBuf`]s, so that decoding a path twice, which would turn `%252e%252e`
+/// into `..` and get past traversal checks, is a type error. Segments added
+/// with [`ProcessedPath::join`] aren't decoded at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessedPath {
+    path: PathBuf,
+    encoded_separator: bool,
+}
+
+impl ProcessedPath {
+    /// Return the decoded path, relative to the directory it is served from.
+    pub fn as_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return whether the request path had a percent-encoded slash or
+    /// backslash, which was left encoded, see [`process_path`].
+    pub fn has_encoded_separator(&self) -> bool {
+        self.encoded_separator
+    }
+
+    /// Append a segment which isn't percent-encoded, e.g. the name of an
+    /// uploaded file.
+    pub fn join<P: AsRef<Path>>(&self, segment: P) -> ProcessedPath {
+        ProcessedPath {
+            path: self.path.join(segment),
+            ..*self
+        }
+    }
+
+    /// Join the path onto `base_dir` and normalize it, see [`normalize_path`].
+    pub fn below(&self, base_dir: &Path) -> PathBuf {
+        normalize_path(&base_dir.join(&self.path))
+    }
+}
+
 /// Process a file path
 ///
-/// Combines [`decode_percents`] and [`normalize_path`] in one function. An
-/// additional base path is joined onto the input path to be processed.
+/// Decodes the path with [`decode_percents`], once and for all. The result
+/// can then be joined onto the base directory and normalized with
+/// [`ProcessedPath::below`].
+///
+/// Each segment is decoded on its own: percent-encoded slashes and
+/// backslashes, `%2F` and `%5C`, are data rather than separators, so they
+/// are left encoded instead of splitting a segment or adding `..` segments,
+/// and are reported by [`ProcessedPath::has_encoded_separator`].
 ///
 /// Example
 ///
@@ -119,16 +193,61 @@ pub fn normalize_path(path: &Path) -> PathBuf {
 /// let path = Path::new("./subdir/subsubdir/.././.././%F0%9F%A6%80.html");
 /// let base_dir = Path::new("/");
 ///
-/// assert_eq!(process_path(path, base_dir), PathBuf::from("/🦀.html"));
+/// assert_eq!(process_path(path).below(base_dir), PathBuf::from("/🦀.html"));
 /// ```
-pub fn process_path(path: &Path, base_dir: &Path) -> PathBuf {
-    let filename = decode_percents(path.to_str().unwrap());
-    normalize_path(&base_dir.join(filename))
+pub fn process_path(path: &Path) -> ProcessedPath {
+    let (path, encoded_separator) = decode(&path.to_string_lossy(), true);
+    ProcessedPath {
+        path,
+        encoded_separator,
+    }
+}
+
+/// Check that a decoded request path has at most `max_depth` components and
+/// that none of them is longer than `max_segment_len` bytes.
+///
+/// `.` components are left out, while `..` components count, as they are
+/// work for [`normalize_path`] all the same.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::path::within_limits;
+/// # use std::path::Path;
+/// assert!(within_limits(Path::new("/a/b/./c.html"), 3, 255));
+/// assert!(!within_limits(Path::new("/a/b/../c.html"), 3, 255));
+/// assert!(!within_limits(Path::new("/a/bbb"), 3, 2));
+/// ```
+pub fn within_limits(
+    path: &Path,
+    max_depth: usize,
+    max_segment_len: usize,
+) -> bool {
+    let mut depth = 0;
+
+    for component in path.components() {
+        let segment = match component {
+            Component::Normal(segment) => segment,
+            Component::ParentDir => component.as_os_str(),
+            _ => continue,
+        };
+
+        depth += 1;
+        if depth > max_depth || segment.len() > max_segment_len {
+            return false;
+        }
+    }
+
+    true
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)] // Tests predate the lint
 mod test {
-    use super::{decode_percents, normalize_path, Path, PathBuf};
+    use super::{
+        decode_percents, normalize_path, process_path, within_limits, Path,
+        PathBuf,
+    };
 
     #[test]
     fn decode_no_encoding() {
@@ -159,6 +278,59 @@ mod test {
         );
     }
 
+    #[test]
+    fn decode_invalid_utf8() {
+        assert_eq!(decode_percents("/%FF%C3.txt"), PathBuf::from("/��.txt"));
+    }
+
+    #[test]
+    fn decode_once() {
+        let path = process_path(Path::new("%252e%252e%252fsecret.txt"));
+        assert_eq!(path.as_path(), Path::new("%2e%2e%2fsecret.txt"));
+        assert_eq!(
+            path.below(Path::new("/srv")),
+            PathBuf::from("/srv/%2e%2e%2fsecret.txt")
+        );
+
+        // Joined segments are taken as is
+        let path = process_path(Path::new("docs/")).join("%2e%2e");
+        assert_eq!(
+            path.below(Path::new("/srv")),
+            Path::new("/srv/docs/%2e%2e")
+        );
+    }
+
+    #[test]
+    fn encoded_separators() {
+        for (raw, decoded) in [
+            ("a%2F..%2F..%2Fsecret", "a%2F..%2F..%2Fsecret"),
+            ("%2e%2e%2f%2e%2e%2fetc/passwd", "..%2f..%2fetc/passwd"),
+            ("..%5C..%5Csecret", "..%5C..%5Csecret"),
+            ("dir/%61%2Fb%20c.txt", "dir/a%2Fb c.txt"),
+        ] {
+            let path = process_path(Path::new(raw));
+            assert_eq!(path.as_path(), Path::new(decoded), "{}", raw);
+            assert!(path.has_encoded_separator(), "{}", raw);
+            assert!(path.join("x").has_encoded_separator());
+        }
+
+        // Each segment stays a single file name below the base directory
+        let path = process_path(Path::new("a%2F..%2F..%2Fsecret"));
+        assert_eq!(
+            path.below(Path::new("/srv")),
+            Path::new("/srv/a%2F..%2F..%2Fsecret")
+        );
+
+        // Double-encoded separators are decoded once, to plain text
+        let path = process_path(Path::new("a%252Fb"));
+        assert_eq!(path.as_path(), Path::new("a%2Fb"));
+        assert!(!path.has_encoded_separator());
+        assert!(!process_path(Path::new("a/b%20c")).has_encoded_separator());
+
+        // Only request paths keep them encoded
+        assert_eq!(decode_percents("a%2Fb%5Cc"), Path::new("a/b\\c"));
+    }
+
     #[test]
     fn normalize() {
         let path = "/a/b/c/./../../g";
@@ -172,4 +344,19 @@ mod test {
 
         assert_eq!(normalize_path(Path::new(path)), PathBuf::from("mid/6"));
     }
+
+    #[test]
+    fn limits() {
+        let deep = "/a".repeat(64);
+        assert!(within_limits(Path::new(&deep), 64, 255));
+        assert!(!within_limits(Path::new(&[&deep, "/a"].concat()), 64, 255));
+        // Repeated and trailing slashes don't count
+        assert!(within_limits(Path::new("//a///b/"), 2, 255));
+        assert!(!within_limits(Path::new("/../../.."), 2, 255));
+
+        let long = "é".repeat(128);
+        assert!(within_limits(Path::new(&long[2..]), 64, 255));
+        assert!(!within_limits(Path::new(&long), 64, 255));
+        assert!(within_limits(Path::new(""), 0, 0));
+    }
 }
@@ -0,0 +1,192 @@
+use std::{
+    fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+};
+
+/// File holding the process id of a running server, created with
+/// `--pid-file` for start and stop scripts and removed when dropped.
+///
+/// Creating a pid file fails with [`io::ErrorKind::AlreadyExists`] if the
+/// file names a process which is still alive. Files left over by a crash,
+/// whose process is gone or which don't hold a process id, are replaced.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::pid::PidFile;
+/// use std::fs;
+///
+/// let path = std::env::temp_dir().join("servum-pid-doc.pid");
+/// let pid_file = PidFile::create(&path).unwrap();
+/// let pid = fs::read_to_string(&path).unwrap();
+/// assert_eq!(pid.trim(), std::process::id().to_string());
+///
+/// drop(pid_file);
+/// assert!(!path.exists());
+/// ```
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+    stale: Option<String>,
+}
+
+impl PidFile {
+    /// Write the id of the current process to `path`, unless another
+    /// running process has written its id there.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<PidFile> {
+        let path = path.as_ref();
+        let stale = match fs::read_to_string(path) {
+            Ok(contents) => {
+                let contents = contents.trim();
+                match contents.parse::<u32>() {
+                    Ok(pid) if pid != process::id() && is_alive(pid) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::AlreadyExists,
+                            format!(
+                                "{} belongs to process {}, which is still \
+                                 running",
+                                path.display(),
+                                pid
+                            ),
+                        ))
+                    }
+                    _ => {
+                        fs::remove_file(path)?;
+                        Some(contents.to_string())
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        let pid_file = PidFile {
+            path: path.to_path_buf(),
+            stale,
+        };
+        writeln!(file, "{}", process::id())?;
+
+        Ok(pid_file)
+    }
+
+    /// Return the path of the pid file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return the contents of the stale pid file replaced on creation, if
+    /// any.
+    pub fn stale(&self) -> Option<&str> {
+        self.stale.as_deref()
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Return whether the process `pid` is running, probing it with `kill -0`.
+///
+/// `/proc` is looked up instead where it is mounted, so that processes of
+/// other users, which can't be signalled, count as running as well.
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    if Path::new("/proc/self").exists() {
+        return Path::new("/proc").join(pid.to_string()).exists();
+    }
+
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(true)
+}
+
+/// Return whether the process `pid` is running, looking it up in the task
+/// list.
+#[cfg(windows)]
+fn is_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .stderr(Stdio::null())
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .split_whitespace()
+                .any(|word| word == pid.to_string())
+        })
+        .unwrap_or(true)
+}
+
+/// Return whether the process `pid` may be running. Without a way to tell,
+/// every process is assumed to be, so that a pid file is never taken over.
+#[cfg(not(any(unix, windows)))]
+fn is_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn create_and_remove() {
+        let dir = TempDir::new();
+        let path = dir.path().join("servum.pid");
+
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(pid_file.path(), path);
+        assert_eq!(pid_file.stale(), None);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{}\n", process::id())
+        );
+
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn stale_takeover() {
+        let dir = TempDir::new();
+
+        // Larger than the highest pid Linux hands out
+        for stale in ["99999999", "not a pid\n", ""] {
+            let path = dir.file("servum.pid", stale);
+            let pid_file = PidFile::create(&path).unwrap();
+
+            assert_eq!(pid_file.stale(), Some(stale.trim()));
+            assert_eq!(
+                fs::read_to_string(&path).unwrap(),
+                format!("{}\n", process::id())
+            );
+        }
+
+        // A restarted process may get the pid of its crashed predecessor
+        let path = dir.file("servum.pid", process::id().to_string());
+        assert!(PidFile::create(&path).unwrap().stale().is_some());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn running_refused() {
+        let dir = TempDir::new();
+        let parent = std::os::unix::process::parent_id().to_string();
+        let path = dir.file("servum.pid", format!("{}\n", parent));
+
+        let err = PidFile::create(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(err.to_string().contains(&parent));
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}\n", parent));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::files::ignore::IgnoreRules;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Modification time and size of every file below a directory, by path
+/// relative to the directory
+pub type Snapshot = HashMap<PathBuf, (Option<SystemTime>, u64)>;
+
+/// Polling watcher of the files below a directory.
+///
+/// Each call to [`Watcher::poll`] takes a new [`Snapshot`] and compares it
+/// to the previous one, so no platform-specific file notification API is
+/// needed. Paths matching the ignore rules are skipped, and symbolic links to
+/// directories are not followed.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::files::{ignore::IgnoreRules, watch::Watcher};
+/// use std::{fs, path::PathBuf};
+///
+/// let dir = std::env::temp_dir().join("servum-watch-doc");
+/// fs::create_dir_all(&dir).unwrap();
+/// let mut watcher = Watcher::new(&dir, IgnoreRules::default());
+///
+/// fs::write(dir.join("index.html"), "<h1>Hi</h1>").unwrap();
+/// assert_eq!(watcher.poll(), vec![PathBuf::from("index.html")]);
+/// assert!(watcher.poll().is_empty());
+/// # fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Watcher {
+    dir: PathBuf,
+    ignore: IgnoreRules,
+    snapshot: Snapshot,
+}
+
+impl Watcher {
+    /// Create a watcher of `dir`, taking its first snapshot right away.
+    pub fn new(dir: &Path, ignore: IgnoreRules) -> Self {
+        let snapshot = snapshot(dir, &ignore);
+
+        Watcher {
+            dir: dir.to_path_buf(),
+            ignore,
+            snapshot,
+        }
+    }
+
+    /// Return the files added, modified or removed since the last poll, in
+    /// order.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let snapshot = snapshot(&self.dir, &self.ignore);
+        let changed = changes(&self.snapshot, &snapshot);
+
+        self.snapshot = snapshot;
+        changed
+    }
+}
+
+/// Take a snapshot of the files below `dir`, skipping paths matching
+/// `ignore`. Unreadable directories and files are skipped.
+pub fn snapshot(dir: &Path, ignore: &IgnoreRules) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        let entries = match fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let rel = match path.strip_prefix(dir) {
+                Ok(rel) => rel.to_path_buf(),
+                Err(_) => continue,
+            };
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+
+            if ignore.is_ignored(&rel, is_dir) {
+                continue;
+            }
+
+            if is_dir {
+                pending.push(path);
+            } else if let Ok(meta) = fs::metadata(&path) {
+                snapshot.insert(rel, (meta.modified().ok(), meta.len()));
+            }
+        }
+    }
+
+    snapshot
+}
+
+/// Return the paths added, modified or removed between two snapshots, in
+/// order.
+pub fn changes(old: &Snapshot, new: &Snapshot) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = new
+        .iter()
+        .filter(|(path, stamp)| old.get(*path) != Some(stamp))
+        .map(|(path, _)| path.clone())
+        .chain(old.keys().filter(|path| !new.contains_key(*path)).cloned())
+        .collect();
+
+    changed.sort();
+    changed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDir;
+    use std::time::Duration;
+
+    fn stamp(secs: u64, len: u64) -> (Option<SystemTime>, u64) {
+        (
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+            len,
+        )
+    }
+
+    #[test]
+    fn diff() {
+        let old = Snapshot::from([
+            (PathBuf::from("kept.html"), stamp(1, 10)),
+            (PathBuf::from("touched.css"), stamp(1, 10)),
+            (PathBuf::from("resized.js"), stamp(1, 10)),
+            (PathBuf::from("removed.png"), stamp(1, 10)),
+        ]);
+        let new = Snapshot::from([
+            (PathBuf::from("kept.html"), stamp(1, 10)),
+            (PathBuf::from("touched.css"), stamp(2, 10)),
+            (PathBuf::from("resized.js"), stamp(1, 12)),
+            (PathBuf::from("added.svg"), stamp(3, 10)),
+        ]);
+
+        assert_eq!(
+            changes(&old, &new),
+            vec![
+                PathBuf::from("added.svg"),
+                PathBuf::from("removed.png"),
+                PathBuf::from("resized.js"),
+                PathBuf::from("touched.css"),
+            ]
+        );
+        assert!(changes(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn poll() {
+        let dir = TempDir::new();
+        dir.file("index.html", "<h1>Hi</h1>");
+        dir.file("css/style.css", "h1 {}");
+        dir.file("build/app.js", "");
+        let mut ignore = IgnoreRules::default();
+        ignore.add("build/").unwrap();
+
+        let mut watcher = Watcher::new(dir.path(), ignore);
+        assert_eq!(watcher.snapshot.len(), 2);
+        assert!(watcher.poll().is_empty());
+
+        dir.file("css/style.css", "h1 { color: red }");
+        dir.file("build/app.js", "console.log(1)");
+        assert_eq!(watcher.poll(), vec![PathBuf::from("css/style.css")]);
+
+        fs::remove_file(dir.path().join("index.html")).unwrap();
+        assert_eq!(watcher.poll(), vec![PathBuf::from("index.html")]);
+    }
+}
@@ -1,14 +1,68 @@
 //! HTTP utilities
+mod accept;
+mod acme;
+mod cache;
+mod chunked;
+mod date;
+mod forwarded;
 mod handler;
+mod header_err;
+mod health;
 mod html;
+mod json;
+mod listing;
+mod live_reload;
+mod manifest;
+mod multipart;
+mod not_found;
+mod redirect;
 mod request;
 mod request_err;
+mod request_id;
 mod response;
+mod served_by;
 mod status;
+mod throttle;
+mod trace;
+mod upload;
+mod url;
 
+pub use accept::negotiate;
+pub use acme::{acme_challenge, ACME_PATH};
+pub use cache::CacheRules;
+pub(crate) use chunked::{discard_chunked, is_chunked};
+pub use chunked::{ChunkedBody, MAX_DISCARDED_BODY};
+pub use date::DateTime;
+pub use forwarded::{forwarded_for, FORWARDED_FOR_HEADER};
 pub use handler::handle_connection;
+pub use header_err::HeaderError;
+pub use health::{health_check, mark_started, uptime, HEALTH_PATH};
 pub use html::html_doc;
+pub use json::json_string;
+pub use listing::{ListingCache, ListingFormat, LISTING_CACHE_CAPACITY};
+pub use live_reload::{
+    inject_reload_script, sse_event, LiveReload, EVENTS_PATH,
+    EVENT_STREAM_HEADER, RELOAD_SCRIPT,
+};
+pub use manifest::{
+    manifest, ManifestCache, MANIFEST_CACHE_TTL, MANIFEST_PATH,
+};
+pub use multipart::{multipart_boundary, FormPart, Multipart};
+pub use not_found::{
+    NotFoundCache, NOT_FOUND_CACHE_CAPACITY, NOT_FOUND_CACHE_TTL,
+};
+pub use redirect::{https_redirect, RedirectRules};
 pub use request::HTTPRequest;
 pub use request_err::HTTPRequestError;
-pub use response::HTTPResponse;
-pub use status::HTTPStatus;
+pub use request_id::{generate_request_id, request_id, REQUEST_ID_HEADER};
+pub(crate) use response::is_token;
+pub use response::{
+    validate_header, write_header, write_response, HTTPResponse, Written,
+    PREVIEW_LIMIT,
+};
+pub use served_by::ServedBy;
+pub use status::{is_fd_exhausted, HTTPStatus};
+pub use throttle::Throttle;
+pub use trace::{ResolveStep, ResolveTrace};
+pub use upload::handle_upload;
+pub use url::{encode_path_segment, strip_path_prefix};
@@ -0,0 +1,249 @@
+/// Choose the media type to respond with from the `available` ones, based on
+/// the `Accept` header of a request, following [RFC 7231].
+///
+/// Each available type gets the quality of the most specific media range of
+/// the header matching it: a type with parameters beats a type, which beats
+/// `type/*`, which beats `*/*`. The type with the highest quality is chosen,
+/// and ties go to the type listed first in `available`, i.e. the server's
+/// preference. Types with a quality of `0` are never chosen, and [`None`] is
+/// returned if none of the available types is acceptable.
+///
+/// Malformed media ranges, e.g. with an invalid quality, are ignored.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::negotiate;
+/// let accept = "text/html,application/json;q=0.9,*/*;q=0.1";
+///
+/// assert_eq!(negotiate(accept, &["application/json", "text/html"]), Some("text/html"));
+/// assert_eq!(negotiate(accept, &["text/plain", "application/json"]), Some("application/json"));
+/// assert_eq!(negotiate("text/*;q=0", &["text/plain"]), None);
+/// ```
+///
+/// [RFC 7231]: https://tools.ietf.org/html/rfc7231#section-5.3.2
+pub fn negotiate<'a>(header: &str, available: &[&'a str]) -> Option<&'a str> {
+    let ranges = header
+        .split(',')
+        .filter_map(MediaRange::parse)
+        .collect::<Vec<MediaRange>>();
+
+    let mut best: Option<(u16, &str)> = None;
+    for &media in available {
+        let media_type = match MediaRange::parse(media) {
+            Some(media_type) => media_type,
+            None => continue,
+        };
+
+        let quality = ranges
+            .iter()
+            .filter(|range| range.matches(&media_type))
+            .fold(None, |most: Option<&MediaRange>, range| match most {
+                Some(most) if most.specificity() >= range.specificity() => {
+                    Some(most)
+                }
+                _ => Some(range),
+            })
+            .map_or(0, |range| range.quality);
+
+        if quality > best.map_or(0, |(quality, _)| quality) {
+            best = Some((quality, media));
+        }
+    }
+
+    best.map(|(_, media)| media)
+}
+
+/// A media range of an `Accept` header, or a media type
+#[derive(Debug, PartialEq, Eq)]
+struct MediaRange<'a> {
+    kind: &'a str,
+    subtype: &'a str,
+    /// Parameters other than the quality, in order
+    params: Vec<(&'a str, &'a str)>,
+    /// Quality in thousandths, from 0 to 1000
+    quality: u16,
+}
+
+impl<'a> MediaRange<'a> {
+    /// Parse a media range such as `text/html;level=1;q=0.5`, or return
+    /// [`None`] if it is malformed.
+    fn parse(range: &'a str) -> Option<Self> {
+        let mut parts = range.split(';');
+        let (kind, subtype) = parts.next()?.trim().split_once('/')?;
+        if kind.is_empty()
+            || subtype.is_empty()
+            || (kind == "*" && subtype != "*")
+        {
+            return None;
+        }
+
+        let mut params = Vec::new();
+        let mut quality = 1000;
+        for param in parts {
+            let (name, value) = param.split_once('=')?;
+            let (name, value) = (name.trim(), value.trim().trim_matches('"'));
+
+            if name.eq_ignore_ascii_case("q") {
+                quality = parse_quality(value)?;
+                // Any following parameters are extensions, not part of the range
+                break;
+            }
+            params.push((name, value));
+        }
+
+        Some(MediaRange {
+            kind,
+            subtype,
+            params,
+            quality,
+        })
+    }
+
+    /// Whether this range matches the media type `media`, i.e. its type and
+    /// subtype match, and all its parameters are parameters of `media`.
+    fn matches(&self, media: &MediaRange) -> bool {
+        let kind =
+            self.kind == "*" || self.kind.eq_ignore_ascii_case(media.kind);
+        let subtype = self.subtype == "*"
+            || self.subtype.eq_ignore_ascii_case(media.subtype);
+
+        kind && subtype
+            && self.params.iter().all(|(name, value)| {
+                media.params.iter().any(|(other, other_value)| {
+                    name.eq_ignore_ascii_case(other) && value == other_value
+                })
+            })
+    }
+
+    /// Rank of the range among the ranges matching a type, more specific
+    /// ranges ranking higher.
+    fn specificity(&self) -> usize {
+        match (self.kind, self.subtype) {
+            ("*", _) => 0,
+            (_, "*") => 1,
+            _ => 2 + self.params.len(),
+        }
+    }
+}
+
+/// Parse a quality value from `0` to `1` with at most three decimals into
+/// thousandths.
+fn parse_quality(value: &str) -> Option<u16> {
+    let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+    if frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let thousandths = format!("{:0<3}", frac).parse::<u16>().ok()?;
+    match int {
+        "0" => Some(thousandths),
+        "1" if thousandths == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TYPES: [&str; 3] = ["text/html", "application/json", "text/plain"];
+
+    #[test]
+    fn quality() {
+        let accept =
+            "text/plain;q=0.5, application/json;q=0.8, text/html;q=0.2";
+        assert_eq!(negotiate(accept, &TYPES), Some("application/json"));
+
+        let accept = "text/plain, text/html;q=0.999";
+        assert_eq!(negotiate(accept, &TYPES), Some("text/plain"));
+    }
+
+    #[test]
+    fn order() {
+        // Equal qualities go to the server's preference
+        assert_eq!(
+            negotiate("text/plain, text/html", &TYPES),
+            Some("text/html")
+        );
+        assert_eq!(negotiate("*/*", &TYPES), Some("text/html"));
+        assert_eq!(
+            negotiate("*/*", &["text/plain", "text/html"]),
+            Some("text/plain")
+        );
+        assert_eq!(negotiate("", &TYPES), None);
+    }
+
+    #[test]
+    fn wildcards() {
+        assert_eq!(
+            negotiate("text/*", &["application/json", "text/plain"]),
+            Some("text/plain")
+        );
+        assert_eq!(negotiate("image/*", &TYPES), None);
+
+        // The most specific range decides, whatever the order
+        let accept = "*/*;q=0.9, text/*;q=0.1, text/plain";
+        assert_eq!(negotiate(accept, &TYPES), Some("text/plain"));
+        let accept = "text/plain;q=0.2, */*;q=0.5";
+        assert_eq!(
+            negotiate(accept, &["text/plain", "text/html"]),
+            Some("text/html")
+        );
+        let accept = "TEXT/*;q=0.3, text/html;level=1";
+        assert_eq!(
+            negotiate(accept, &["text/plain", "text/html"]),
+            Some("text/plain")
+        );
+        assert_eq!(
+            negotiate(accept, &["text/plain", "text/html;level=1"]),
+            Some("text/html;level=1")
+        );
+    }
+
+    #[test]
+    fn zero_quality() {
+        assert_eq!(negotiate("text/html;q=0", &TYPES), None);
+        assert_eq!(
+            negotiate("text/html;q=0.000, */*", &TYPES),
+            Some("application/json")
+        );
+        assert_eq!(
+            negotiate("*/*, text/*;q=0", &TYPES),
+            Some("application/json")
+        );
+        assert_eq!(negotiate("*/*;q=0", &TYPES), None);
+    }
+
+    #[test]
+    fn malformed() {
+        for accept in [
+            "text/html;q=2",
+            "text/html;q=0.1234",
+            "text/html;q=-1",
+            "text/html;q=abc",
+            "text/html;q",
+            "text",
+            "/html",
+            "*/html",
+            ";q=1",
+        ] {
+            assert_eq!(negotiate(accept, &TYPES), None, "{}", accept);
+        }
+
+        // Malformed ranges don't spoil the others
+        let accept = "text/html;q=1.5, , garbage, text/plain;q=0.5";
+        assert_eq!(negotiate(accept, &TYPES), Some("text/plain"));
+    }
+
+    #[test]
+    fn qualities() {
+        assert_eq!(parse_quality("1"), Some(1000));
+        assert_eq!(parse_quality("1.000"), Some(1000));
+        assert_eq!(parse_quality("0.5"), Some(500));
+        assert_eq!(parse_quality("0.05"), Some(50));
+        assert_eq!(parse_quality("0."), Some(0));
+        assert_eq!(parse_quality("1.001"), None);
+        assert_eq!(parse_quality(".5"), None);
+    }
+}
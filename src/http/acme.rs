@@ -0,0 +1,119 @@
+use crate::{
+    cli::Config,
+    files,
+    http::{HTTPRequest, HTTPResponse, HTTPStatus, ServedBy},
+};
+use std::{borrow::Cow, io, path::Path};
+
+/// URL path prefix of ACME HTTP challenges, served from `--acme-dir`
+pub const ACME_PATH: &str = "/.well-known/acme-challenge/";
+
+/// Answer a `GET` or `HEAD` request for an ACME HTTP challenge with the file
+/// of the same name in the `acme_dir` of `config`, or return [`None`] if the
+/// request is for another path or no `acme_dir` is set.
+///
+/// Challenges are served whatever the ignore rules, so that certificates can
+/// be issued for a server hiding dotfiles. Requests leading out of the
+/// `acme_dir` are forbidden, and directories aren't listed.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::{cli::Config, http::{acme_challenge, HTTPRequest}};
+/// use std::path::PathBuf;
+/// # fn main() -> Result<(), servum::Error> {
+///
+/// let config = Config {
+///     acme_dir: Some(PathBuf::from("example/pages")),
+///     ..Config::default()
+/// };
+/// let buffer = b"GET /.well-known/acme-challenge/about.html HTTP/1.1";
+/// let req = HTTPRequest::new(buffer)?;
+/// let res = acme_challenge(&req, &config).unwrap();
+///
+/// assert_eq!(res.status.code, 200);
+/// assert_eq!(res.mime.unwrap(), "text/plain");
+///
+/// let req = HTTPRequest::new(b"GET /.well-known/security.txt HTTP/1.1")?;
+/// assert!(acme_challenge(&req, &config).is_none());
+/// # Ok(())
+/// # }
+/// ```
+pub fn acme_challenge<'a>(
+    req: &HTTPRequest,
+    config: &Config,
+) -> Option<HTTPResponse<'a>> {
+    let dir = config.acme_dir.as_ref()?;
+    let token = req.filepath.to_str()?.strip_prefix(ACME_PATH)?;
+    if req.method != "GET" && req.method != "HEAD" {
+        return None;
+    }
+
+    let path = files::path::process_path(Path::new(token)).below(dir);
+    let mut res = match path.strip_prefix(dir) {
+        Ok(rel) if rel.as_os_str().is_empty() || path.is_dir() => {
+            HTTPResponse::from(HTTPStatus::from(404))
+        }
+        Ok(_) => {
+            let contents = files::file::read_regular(&path);
+            let status = HTTPStatus::from(&contents);
+            HTTPResponse::new(
+                status,
+                Some(Cow::Borrowed("text/plain")),
+                contents,
+            )
+        }
+        Err(_) => HTTPResponse::from(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Directory traversal is not allowed!",
+        )),
+    };
+
+    res.served_by = ServedBy::Internal("acme");
+    Some(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    fn challenge(
+        target: &str,
+        config: &Config,
+    ) -> Option<HTTPResponse<'static>> {
+        let buffer = format!("GET {} HTTP/1.1\r\n\r\n", target);
+        let req = HTTPRequest::new(buffer.as_bytes()).unwrap();
+        acme_challenge(&req, config)
+    }
+
+    #[test]
+    fn challenges() {
+        let dir = TempDir::new();
+        dir.file("acme/tok3n-_A", "tok3n-_A.thumbprint");
+        dir.file("acme/sub/token", "nested");
+        dir.file("secret.txt", "secret");
+        let config = Config {
+            acme_dir: Some(dir.path().join("acme")),
+            ..Config::default()
+        };
+        let status =
+            |target: &str| challenge(target, &config).unwrap().status.code;
+
+        let res = challenge(&[ACME_PATH, "tok3n-_A"].concat(), &config);
+        let res = res.unwrap();
+        assert_eq!(res.status.code, 200);
+        assert_eq!(res.body, b"tok3n-_A.thumbprint");
+        assert_eq!(res.served_by, ServedBy::Internal("acme"));
+
+        assert_eq!(status(&[ACME_PATH, "missing"].concat()), 404);
+        assert_eq!(status(ACME_PATH), 404);
+        assert_eq!(status(&[ACME_PATH, "sub"].concat()), 404);
+        assert_eq!(status(&[ACME_PATH, "../secret.txt"].concat()), 403);
+        assert_eq!(status(&[ACME_PATH, "%2E%2E/secret.txt"].concat()), 403);
+
+        assert!(challenge("/.well-known/acme-challenge", &config).is_none());
+        assert!(challenge("/secret.txt", &config).is_none());
+        assert!(challenge(ACME_PATH, &Config::default()).is_none());
+    }
+}
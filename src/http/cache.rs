@@ -0,0 +1,106 @@
+use crate::{files::glob::Glob, http::validate_header};
+
+/// A single `Cache-Control` rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CacheRule {
+    glob: Glob,
+    value: String,
+}
+
+/// Ordered list of `Cache-Control` values by URL path.
+///
+/// Rules are given as `GLOB=VALUE` with the `--cache-rule` flag. Globs are
+/// matched against the URL path without the leading `/` and the value of the
+/// first matching rule is used. See [`Glob`] for the supported pattern syntax.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::CacheRules;
+/// let mut rules = CacheRules::default();
+/// rules.add("assets/**=max-age=31536000, immutable").unwrap();
+/// rules.add("**/*.html=no-cache").unwrap();
+///
+/// assert_eq!(
+///     rules.lookup("/assets/app.3f2a.js"),
+///     Some("max-age=31536000, immutable")
+/// );
+/// assert_eq!(rules.lookup("/index.html"), Some("no-cache"));
+/// assert_eq!(rules.lookup("/robots.txt"), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheRules {
+    rules: Vec<CacheRule>,
+}
+
+impl CacheRules {
+    /// Add a rule of the form `GLOB=VALUE`.
+    ///
+    /// Returns [`None`] if the rule has no `=`, the glob is invalid or the
+    /// value is empty or can't be sent, see [`validate_header`].
+    pub fn add(&mut self, rule: &str) -> Option<()> {
+        let (pattern, value) = rule.split_once('=')?;
+        let glob = Glob::new(pattern.trim())?;
+        let value = validate_header("Cache-Control", value).ok()?.trim();
+
+        if value.is_empty() {
+            return None;
+        }
+
+        self.rules.push(CacheRule {
+            glob,
+            value: value.to_string(),
+        });
+        Some(())
+    }
+
+    /// Whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Iterate over all rules as `GLOB=VALUE`, in order.
+    pub fn rules(&self) -> impl Iterator<Item = String> + '_ {
+        self.rules
+            .iter()
+            .map(|rule| format!("{}={}", rule.glob, rule.value))
+    }
+
+    /// Return the value of the first rule matching a URL path, if any.
+    pub fn lookup(&self, path: &str) -> Option<&str> {
+        let path = path.trim_start_matches('/');
+
+        self.rules
+            .iter()
+            .find(|rule| rule.glob.matches(path))
+            .map(|rule| rule.value.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let mut rules = CacheRules::default();
+
+        assert!(rules.add("*.html = no-cache").is_some());
+        assert!(rules.add("*.html").is_none());
+        assert!(rules.add("*.html=").is_none());
+        assert!(rules.add("/=no-cache").is_none());
+        assert!(rules.add("*.js=no-cache\r\nSet-Cookie: a=1").is_none());
+        assert_eq!(rules.rules().collect::<Vec<_>>(), vec!["*.html=no-cache"]);
+    }
+
+    #[test]
+    fn first_match() {
+        let mut rules = CacheRules::default();
+        rules.add("assets/vendor/**=max-age=60").unwrap();
+        rules.add("assets/**=immutable").unwrap();
+
+        assert_eq!(rules.lookup("/assets/vendor/lib.js"), Some("max-age=60"));
+        assert_eq!(rules.lookup("/assets/app.js"), Some("immutable"));
+        assert_eq!(rules.lookup("/app.js"), None);
+    }
+}
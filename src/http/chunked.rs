@@ -0,0 +1,302 @@
+use crate::http::{HTTPRequest, HTTPStatus};
+use std::io::{self, BufRead, BufReader, Read};
+
+/// Maximum length of a chunk size line or trailer field, so that a client
+/// can't make the server buffer an endless line
+const MAX_LINE_LEN: u64 = 4 * 1024;
+
+/// Maximum number of trailer fields after the last chunk
+const MAX_TRAILERS: usize = 64;
+
+/// Maximum size of a chunked body read and discarded by
+/// [`discard_chunked`], as requests other than uploads aren't expected to
+/// have one
+pub const MAX_DISCARDED_BODY: u64 = 1024 * 1024;
+
+/// Reader decoding a body sent with `Transfer-Encoding: chunked`.
+///
+/// Chunk extensions and trailer fields are skipped. Reading fails with
+/// [`io::ErrorKind::InvalidData`] if the body is malformed, and with
+/// [`io::ErrorKind::UnexpectedEof`] if it ends before its last chunk. The
+/// reader stops right after the body, so nothing following it is read.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::ChunkedBody;
+/// use std::io::Read;
+///
+/// let body = &b"5\r\nHello\r\n8;ext=1\r\n, world!\r\n0\r\nX-A: 1\r\n\r\nnext"[..];
+/// let mut decoded = String::new();
+/// let mut chunked = ChunkedBody::new(body);
+/// chunked.read_to_string(&mut decoded).unwrap();
+///
+/// assert_eq!(decoded, "Hello, world!");
+/// ```
+#[derive(Debug)]
+pub struct ChunkedBody<R> {
+    inner: BufReader<R>,
+    /// Bytes left in the current chunk
+    remaining: u64,
+    done: bool,
+}
+
+impl<R: Read> ChunkedBody<R> {
+    /// Create a new decoder of the chunked body read from `inner`.
+    pub fn new(inner: R) -> Self {
+        ChunkedBody {
+            // Reads byte by byte, so that nothing after the body is consumed
+            inner: BufReader::with_capacity(1, inner),
+            remaining: 0,
+            done: false,
+        }
+    }
+
+    /// Read a line ending with CRLF, without it.
+    fn line(&mut self) -> io::Result<Vec<u8>> {
+        let mut line = Vec::new();
+        (&mut self.inner)
+            .take(MAX_LINE_LEN)
+            .read_until(b'\n', &mut line)?;
+
+        match line.strip_suffix(b"\r\n") {
+            Some(content) => Ok(content.to_vec()),
+            None if line.len() as u64 == MAX_LINE_LEN => {
+                Err(invalid("Chunk line is too long"))
+            }
+            None if line.ends_with(b"\n") => {
+                Err(invalid("Chunk line doesn't end with CRLF"))
+            }
+            None => Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+
+    /// Read the size line starting a chunk.
+    fn chunk_size(&mut self) -> io::Result<u64> {
+        let line = self.line()?;
+        let size = match line.iter().position(|&b| b == b';') {
+            Some(end) => &line[..end],
+            None => &line[..],
+        };
+        let size = std::str::from_utf8(size)
+            .map_err(|_| invalid("Invalid chunk size"))?
+            .trim_end_matches([' ', '\t']);
+
+        if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid("Invalid chunk size"));
+        }
+        u64::from_str_radix(size, 16)
+            .map_err(|_| invalid("Chunk size is too large"))
+    }
+
+    /// Skip the trailer fields after the last chunk, up to the blank line
+    /// ending the body.
+    fn trailers(&mut self) -> io::Result<()> {
+        for _ in 0..=MAX_TRAILERS {
+            if self.line()?.is_empty() {
+                return Ok(());
+            }
+        }
+        Err(invalid("Too many trailer fields"))
+    }
+}
+
+impl<R: Read> Read for ChunkedBody<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            self.remaining = self.chunk_size()?;
+            if self.remaining == 0 {
+                self.trailers()?;
+                self.done = true;
+                return Ok(0);
+            }
+        }
+
+        let max = buf.len().min(self.remaining as usize);
+        let n = self.inner.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        self.remaining -= n as u64;
+        if self.remaining == 0 && !self.line()?.is_empty() {
+            return Err(invalid("Chunk is longer than its size"));
+        }
+        Ok(n)
+    }
+}
+
+/// Return an error of kind [`io::ErrorKind::InvalidData`] with `msg`.
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Return whether the body of a request is sent with the chunked transfer
+/// coding, or the status to answer with if it uses a coding servum doesn't
+/// support, i.e. `501 Not Implemented`, or sets both a `Transfer-Encoding`
+/// and a `Content-Length`, i.e. `400 Bad Request`, as its length is then
+/// ambiguous.
+pub(crate) fn is_chunked<'a>(
+    req: &HTTPRequest,
+) -> Result<bool, HTTPStatus<'a>> {
+    let coding = match req.header("Transfer-Encoding") {
+        Some(coding) => coding,
+        None => return Ok(false),
+    };
+
+    if req.header("Content-Length").is_some() {
+        return Err(HTTPStatus::new(
+            400,
+            "Bad Request",
+            Some(String::from(
+                "Requests can't have both a Transfer-Encoding and a \
+                 Content-Length",
+            )),
+        ));
+    }
+    if !coding.eq_ignore_ascii_case("chunked") {
+        return Err(HTTPStatus::new(
+            501,
+            "Not Implemented",
+            Some(String::from("Only chunked request bodies are supported")),
+        ));
+    }
+
+    Ok(true)
+}
+
+/// Read and discard a chunked request body, of which `body_start` was read
+/// with the head of the request, so that the connection isn't closed with
+/// unread data.
+///
+/// Returns the status to answer with instead if the body is malformed, i.e.
+/// `400 Bad Request`, or larger than [`MAX_DISCARDED_BODY`], i.e. `413
+/// Payload Too Large`.
+pub(crate) fn discard_chunked<'a, R: Read>(
+    body_start: &[u8],
+    stream: R,
+) -> Result<(), HTTPStatus<'a>> {
+    let mut body =
+        ChunkedBody::new(body_start.chain(stream)).take(MAX_DISCARDED_BODY + 1);
+
+    match io::copy(&mut body, &mut io::sink()) {
+        Ok(len) if len > MAX_DISCARDED_BODY => Err(HTTPStatus::new(
+            413,
+            "Payload Too Large",
+            Some(String::from("Request body is too large")),
+        )),
+        Ok(_) => Ok(()),
+        Err(e) => Err(HTTPStatus::new(
+            400,
+            "Bad Request",
+            Some(format!("Invalid chunked body: {}", e)),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode(body: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        ChunkedBody::new(body).read_to_end(&mut decoded)?;
+        Ok(decoded)
+    }
+
+    #[test]
+    fn well_formed() {
+        assert_eq!(decode(b"0\r\n\r\n").unwrap(), b"");
+        assert_eq!(
+            decode(b"3\r\nabc\r\nA\r\n0123456789\r\n0\r\n\r\n").unwrap(),
+            b"abc0123456789"
+        );
+        assert_eq!(
+            decode(b"3;name=\"v\" \r\nabc\r\n0\r\nX-A: 1\r\nX-B: 2\r\n\r\n")
+                .unwrap(),
+            b"abc"
+        );
+        assert_eq!(decode(b"00003 \r\na\r\n\r\n0\r\n\r\n").unwrap(), b"a\r\n");
+
+        // Nothing after the body is read
+        let mut stream = &b"1\r\na\r\n0\r\n\r\nGET / HTTP/1.1"[..];
+        decode_from(&mut stream);
+        assert_eq!(stream, b"GET / HTTP/1.1");
+    }
+
+    fn decode_from(stream: &mut &[u8]) {
+        let mut decoded = Vec::new();
+        ChunkedBody::new(stream).read_to_end(&mut decoded).unwrap();
+    }
+
+    #[test]
+    fn malformed() {
+        for body in [
+            &b"x\r\nabc\r\n0\r\n\r\n"[..],
+            b"\r\nabc\r\n0\r\n\r\n",
+            b"-3\r\nabc\r\n0\r\n\r\n",
+            b"0x3\r\nabc\r\n0\r\n\r\n",
+            b"3\nabc\r\n0\r\n\r\n",
+            b"2\r\nabc\r\n0\r\n\r\n",
+            b"11111111111111111\r\n",
+        ] {
+            let err = decode(body).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", body);
+        }
+
+        let long = [&b"1"[..], &[b' '; 8192], b"\r\na\r\n0\r\n\r\n"].concat();
+        assert_eq!(
+            decode(&long).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        let trailers = [&b"0\r\n"[..], &b"X-A: 1\r\n".repeat(100)].concat();
+        assert_eq!(
+            decode(&trailers).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        for body in [&b"3\r\nab"[..], b"3\r\nabc\r\n", b"3\r\nabc\r\n0\r\n"] {
+            let err = decode(body).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "{:?}", body);
+        }
+    }
+
+    fn request(head: &str) -> Result<bool, usize> {
+        let head = format!("POST / HTTP/1.1\r\n{}\r\n\r\n", head);
+        let req = HTTPRequest::new(head.as_bytes()).unwrap();
+        is_chunked(&req).map_err(|status| status.code)
+    }
+
+    #[test]
+    fn codings() {
+        assert_eq!(request("Host: a"), Ok(false));
+        assert_eq!(request("Transfer-Encoding: chunked"), Ok(true));
+        assert_eq!(request("transfer-encoding:  Chunked "), Ok(true));
+        assert_eq!(request("Transfer-Encoding: gzip, chunked"), Err(501));
+        assert_eq!(request("Transfer-Encoding: identity"), Err(501));
+        assert_eq!(
+            request("Transfer-Encoding: chunked\r\nContent-Length: 3"),
+            Err(400)
+        );
+    }
+
+    #[test]
+    fn discard() {
+        let status = |body_start: &[u8], rest: &[u8]| {
+            discard_chunked(body_start, rest).map_err(|status| status.code)
+        };
+
+        assert_eq!(status(b"3\r\nab", b"c\r\n0\r\n\r\n"), Ok(()));
+        assert_eq!(status(b"3\r\nabc\r\n", b""), Err(400));
+        assert_eq!(status(b"z\r\n", b""), Err(400));
+
+        // Reading stops at the limit, before the end of the chunk
+        let size = format!("{:x}\r\n", 2 * MAX_DISCARDED_BODY);
+        let body = vec![b'a'; 2 * MAX_DISCARDED_BODY as usize];
+        assert_eq!(status(size.as_bytes(), &body), Err(413));
+    }
+}
@@ -0,0 +1,181 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Abbreviated month names, as used in HTTP and log dates
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct",
+    "Nov", "Dec",
+];
+
+/// Abbreviated weekday names, starting on Sunday
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// A UTC calendar date and time with millisecond precision.
+///
+/// Converted from a [`SystemTime`] without a time zone database, which is
+/// enough for HTTP headers and logs.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::DateTime;
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// let date = DateTime::from(UNIX_EPOCH + Duration::from_millis(784_111_777_123));
+///
+/// assert_eq!(date.to_http(), "Sun, 06 Nov 1994 08:49:37 GMT");
+/// assert_eq!(date.to_iso8601(), "1994-11-06T08:49:37.123Z");
+/// assert_eq!(date.to_common_log(), "06/Nov/1994:08:49:37 +0000");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    /// Month from 1 to 12
+    pub month: u32,
+    /// Day of the month from 1 to 31
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub millis: u32,
+    /// Day of the week from 0 (Sunday) to 6 (Saturday)
+    pub weekday: u32,
+}
+
+impl DateTime {
+    /// Return the current date and time.
+    pub fn now() -> DateTime {
+        DateTime::from(SystemTime::now())
+    }
+
+    /// Format the date as an HTTP date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`,
+    /// as used in the `Date` and `Last-Modified` headers.
+    pub fn to_http(&self) -> String {
+        format!(
+            "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+            WEEKDAYS[self.weekday as usize],
+            self.day,
+            MONTHS[self.month as usize - 1],
+            self.year,
+            self.hour,
+            self.minute,
+            self.second
+        )
+    }
+
+    /// Format the date as an ISO 8601 date with milliseconds, e.g.
+    /// `1994-11-06T08:49:37.123Z`.
+    pub fn to_iso8601(&self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            self.year,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+            self.millis
+        )
+    }
+
+    /// Format the date as in the Common Log Format, e.g.
+    /// `06/Nov/1994:08:49:37 +0000`.
+    pub fn to_common_log(&self) -> String {
+        format!(
+            "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+            self.day,
+            MONTHS[self.month as usize - 1],
+            self.year,
+            self.hour,
+            self.minute,
+            self.second
+        )
+    }
+}
+
+impl From<SystemTime> for DateTime {
+    fn from(time: SystemTime) -> Self {
+        let millis = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_millis() as i64,
+            Err(e) => -(e.duration().as_millis() as i64),
+        };
+        let secs = millis.div_euclid(1000);
+        let days = secs.div_euclid(86400);
+        let secs_of_day = secs.rem_euclid(86400) as u32;
+
+        // Civil date from days since the epoch, see
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        DateTime {
+            year,
+            month,
+            day,
+            hour: secs_of_day / 3600,
+            minute: secs_of_day / 60 % 60,
+            second: secs_of_day % 60,
+            millis: millis.rem_euclid(1000) as u32,
+            // 1970-01-01 was a Thursday
+            weekday: (days + 4).rem_euclid(7) as u32,
+        }
+    }
+}
+
+impl From<Duration> for DateTime {
+    /// Create a date from a duration since the Unix epoch.
+    fn from(since_epoch: Duration) -> Self {
+        DateTime::from(UNIX_EPOCH + since_epoch)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Helper
+    fn date(secs: u64) -> DateTime {
+        DateTime::from(Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn epoch() {
+        assert_eq!(date(0).to_http(), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(date(0).to_iso8601(), "1970-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn dates() {
+        let cases = [
+            (951_782_400, "Tue, 29 Feb 2000 00:00:00 GMT"),
+            (971_186_136, "Tue, 10 Oct 2000 13:55:36 GMT"),
+            (1_234_567_890, "Fri, 13 Feb 2009 23:31:30 GMT"),
+            (4_102_444_799, "Thu, 31 Dec 2099 23:59:59 GMT"),
+        ];
+
+        for (secs, expected) in cases.iter() {
+            assert_eq!(date(*secs).to_http(), *expected);
+        }
+    }
+
+    #[test]
+    fn common_log() {
+        assert_eq!(
+            date(971_186_136).to_common_log(),
+            "10/Oct/2000:13:55:36 +0000"
+        );
+    }
+
+    #[test]
+    fn before_epoch() {
+        let date = DateTime::from(UNIX_EPOCH - Duration::from_millis(1));
+
+        assert_eq!(date.to_iso8601(), "1969-12-31T23:59:59.999Z");
+        assert_eq!(date.weekday, 3);
+    }
+}
@@ -0,0 +1,74 @@
+use std::net::IpAddr;
+
+/// Header in which proxies pass on the address of the client, trusted with
+/// `--trust-proxy`
+pub const FORWARDED_FOR_HEADER: &str = "X-Forwarded-For";
+
+/// Return the client address of an `X-Forwarded-For` header value, i.e. its
+/// first address, or [`None`] if that isn't an IP literal.
+///
+/// Each proxy appends the address it got the request from, so the first one
+/// is the client's. Bracketed IPv6 addresses are accepted, but ports and
+/// names aren't, so that garbage isn't logged as a client address.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::forwarded_for;
+/// use std::net::IpAddr;
+///
+/// let client = forwarded_for("203.0.113.7, 10.0.0.2").unwrap();
+/// assert_eq!(client, "203.0.113.7".parse::<IpAddr>().unwrap());
+///
+/// assert_eq!(forwarded_for("unknown, 10.0.0.2"), None);
+/// ```
+pub fn forwarded_for(value: &str) -> Option<IpAddr> {
+    let first = value.split(',').next()?.trim();
+    let first = match first.strip_prefix('[') {
+        Some(rest) => rest.strip_suffix(']')?,
+        None => first,
+    };
+
+    first.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ip(addr: &str) -> Option<IpAddr> {
+        Some(addr.parse().unwrap())
+    }
+
+    #[test]
+    fn hops() {
+        assert_eq!(forwarded_for("203.0.113.7"), ip("203.0.113.7"));
+        assert_eq!(
+            forwarded_for("203.0.113.7,10.0.0.2 ,  127.0.0.1"),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            forwarded_for("  2001:db8::1 , 10.0.0.2"),
+            ip("2001:db8::1")
+        );
+        assert_eq!(forwarded_for("[2001:db8::1], 10.0.0.2"), ip("2001:db8::1"));
+    }
+
+    #[test]
+    fn garbage() {
+        for value in [
+            "",
+            " , 10.0.0.2",
+            "unknown",
+            "example.com, 10.0.0.2",
+            "203.0.113.7:8080",
+            "[2001:db8::1]:443",
+            "[2001:db8::1",
+            "203.0.113.300",
+            "203.0.113.7; rm -rf /",
+            "<script>",
+        ] {
+            assert_eq!(forwarded_for(value), None, "{}", value);
+        }
+    }
+}
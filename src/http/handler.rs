@@ -1,27 +1,127 @@
-use crate::http::{self, html_doc, HTTPRequest, HTTPResponse, HTTPStatus};
-use crate::{cli::Config, files};
-use std::{fs, io, path::Path, sync::Arc};
+use crate::http::{
+    self, health_check, html_doc, inject_reload_script, request_id,
+    HTTPRequest, HTTPResponse, HTTPStatus, ListingFormat, ResolveStep,
+    ResolveTrace, ServedBy, HEALTH_PATH, MANIFEST_PATH, REQUEST_ID_HEADER,
+};
+use crate::{
+    cli::Config,
+    files::{self, base_dir::INDEX_FILE, file::Entry, path::ProcessedPath},
+};
+use std::{
+    borrow::Cow,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Maximum width of the name column of plain text listings, beyond which
+/// longer names push their line's columns to the right
+const MAX_NAME_WIDTH: usize = 48;
+
+/// Form uploading files to the listed directory, shown above directory
+/// listings with `--upload`
+const UPLOAD_FORM: &str = "<form method=\"post\" \
+    enctype=\"multipart/form-data\"><input type=\"file\" name=\"file\" \
+    multiple required> <button>Upload</button></form>";
 
 /// List a directory for a given [`Path`].
 ///
 /// Turn a directory of subdirectories and files into an HTML list. This list is
 /// turned into an HTML document using [`html_doc`] for representing directories
-/// to the front-end user. Possible errors while read the directory are returned
-/// as [`std::io::Error`].
+/// to the front-end user. Entries matching the user's ignore rules are left out.
+/// Entries whose metadata cannot be read are kept and marked as unreadable.
+/// With overlays, the entries of the same directory in every overlay are
+/// listed as well, those found first winning on name conflicts.
+/// With `--listing-cache-ttl`, rendered listings are reused from the
+/// [`ListingCache`](http::ListingCache) of the config unless uploads are
+/// enabled.
+/// With `--upload`, the list is preceded by a form uploading files to the
+/// directory. With [`ListingFormat::Text`], the entries are listed one per
+/// line instead, see [`Entry::to_text_row`].
+/// Possible errors while read the directory are returned as
+/// [`std::io::Error`].
 ///
 /// [`Path`]: std::path::Path
 /// [`html_doc`]: crate::http::html_doc
-fn list_dir(path: &Path) -> io::Result<Vec<u8>> {
-    let mut result = fs::read_dir(path)?
-        .filter_map(|f| match f {
-            Ok(entry) => Some(files::file::File(entry).to_string()),
-            Err(_) => None,
-        })
-        .collect::<Vec<String>>();
+fn list_dir(
+    path: &Path,
+    config: &Config,
+    format: ListingFormat,
+) -> io::Result<Vec<u8>> {
+    let roots = config.roots();
+    // The listed directory, followed by the same directory in the other
+    // roots if it is below one of them
+    let mut dirs = vec![(roots[0].as_ref(), path.to_path_buf())];
+    let found = roots
+        .iter()
+        .find_map(|root| Some((root, path.strip_prefix(root).ok()?)));
+    if let Some((found, rel)) = found {
+        dirs = roots
+            .iter()
+            .skip_while(|root| *root != found)
+            .map(|root| (root.as_ref(), root.join(rel)))
+            .collect();
+    }
+
+    let ttl = match config.listing_cache_ttl {
+        Some(ttl) if !config.upload => ttl,
+        _ => return render_listing(path, &dirs, config, format),
+    };
+    let mtimes = dirs
+        .iter()
+        .map(|(_, dir)| fs::metadata(dir).and_then(|m| m.modified()).ok())
+        .collect::<Vec<_>>();
+    let now = config.clock.instant();
+
+    let cache = &config.listing_cache;
+    if let Some(listing) = cache.get(path, format, &mtimes, now, ttl) {
+        return Ok(listing);
+    }
+    let listing = render_listing(path, &dirs, config, format)?;
+    cache.insert(path, format, mtimes, now, listing.clone());
+    Ok(listing)
+}
+
+/// Render the listing of `path` from the entries of `dirs`, each given along
+/// with the root directory it is in. Entries of earlier directories win on
+/// name conflicts.
+fn render_listing(
+    path: &Path,
+    dirs: &[(&Path, PathBuf)],
+    config: &Config,
+    format: ListingFormat,
+) -> io::Result<Vec<u8>> {
+    let mut entries = Vec::new();
+    for (i, (root, dir)) in dirs.iter().enumerate() {
+        let read = match fs::read_dir(dir) {
+            Ok(read) => read,
+            Err(e) if i == 0 => return Err(e),
+            Err(_) => continue,
+        };
+        // Entries which can't be read are kept, marked as unreadable
+        entries.extend(read.filter_map(|f| match f {
+            Ok(entry) if is_ignored_entry(&entry, root, config) => None,
+            Ok(entry) => Some(Entry::from_dir_entry_lossy(entry)),
+            Err(e) => Some(Entry::from_error(&e)),
+        }));
+    }
 
-    result.sort_unstable();
+    // Stable, so that the first root wins on name conflicts. Nameless
+    // placeholders are all kept.
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries.dedup_by(|a, b| !a.name.is_empty() && a.name == b.name);
+
+    if format == ListingFormat::Text {
+        return Ok(text_listing(path, &entries).into_bytes());
+    }
+
+    let result = entries
+        .iter()
+        .map(Entry::to_html_row)
+        .collect::<Vec<String>>();
 
     let result = [
+        if config.upload { UPLOAD_FORM } else { "" },
         "<a href=\"./../\">&uarr; Parent Directory</a><ul><li>",
         &result.join("</li><li>"),
         "</li></ul>",
@@ -36,6 +136,39 @@ fn list_dir(path: &Path) -> io::Result<Vec<u8>> {
     .into_bytes())
 }
 
+/// Render a plain text listing of `entries`, with aligned columns.
+fn text_listing(path: &Path, entries: &[Entry]) -> String {
+    let name_width = entries
+        .iter()
+        .map(|entry| entry.name.chars().count() + entry.is_dir as usize)
+        .max()
+        .unwrap_or(0)
+        .min(MAX_NAME_WIDTH);
+
+    let mut listing = format!("Listing for {}\n\n", path.display());
+    for entry in entries {
+        listing.push_str(&entry.to_text_row(name_width));
+        listing.push('\n');
+    }
+    listing
+}
+
+/// Check whether a directory entry below `base_dir` matches the user's ignore
+/// rules.
+fn is_ignored_entry(
+    entry: &fs::DirEntry,
+    base_dir: &Path,
+    config: &Config,
+) -> bool {
+    let path = entry.path();
+    let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+
+    match path.strip_prefix(base_dir) {
+        Ok(rel) => config.ignore.is_ignored(rel, is_dir),
+        Err(_) => false,
+    }
+}
+
 /// Handle incoming HTTP requests.
 ///
 /// This function validates and executes incoming HTTP requests by normalizing
@@ -44,6 +177,36 @@ fn list_dir(path: &Path) -> io::Result<Vec<u8>> {
 ///
 /// This function solely handles HTTP `GET` and `HEAD` requests, as these are
 /// the only requests types that must be supported by simple static server.
+/// With `--upload`, `DELETE` requests remove files as well, while uploads are
+/// handled by [`handle_upload`](http::handle_upload) as they come with a body.
+///
+/// Successful responses get a `Cache-Control` header from the first of the
+/// user's cache rules matching the URL path, or else from the global
+/// `cache_control` option, if any.
+///
+/// Every response gets an `X-Request-Id` header, see [`request_id`].
+///
+/// Requests with one of the user's `blocked_methods` are answered with `405
+/// Method Not Allowed` first, without looking at their path.
+///
+/// With `--health`, `GET` and `HEAD` requests to [`HEALTH_PATH`] are answered
+/// by [`health_check`] before the path is resolved, and with `--manifest`,
+/// requests to [`MANIFEST_PATH`] by [`manifest`](http::manifest). With
+/// `--acme-dir`, ACME
+/// challenges are answered by [`acme_challenge`](http::acme_challenge),
+/// regardless of the ignore rules. Requests matching one of
+/// the user's redirect rules are redirected before the path is resolved as
+/// well, see [`RedirectRules`](http::RedirectRules). Requests for a directory
+/// without a trailing `/` are redirected to the path with one, if directories
+/// are listed. With `--live-reload`, HTML responses get the live reload
+/// script, see [`inject_reload_script`].
+///
+/// With `--cache`, `GET` and `HEAD` requests for paths found missing shortly
+/// before are answered with `404 Not Found` without looking them up again,
+/// unless uploads are enabled, see [`NotFoundCache`](http::NotFoundCache).
+///
+/// With `--verbose`, the steps resolving the request path to a file or
+/// listing are recorded in the `trace` of the response, see [`ResolveTrace`].
 ///
 /// # Example
 ///
@@ -53,13 +216,14 @@ fn list_dir(path: &Path) -> io::Result<Vec<u8>> {
 /// # use servum::http::{handle_connection, HTTPRequest};
 /// use servum::cli::Config;
 /// use std::{str, sync::Arc};
+/// # fn main() -> Result<(), servum::Error> {
 ///
 /// // Standard config with sane defaults
 /// let config = Arc::new(Config::default());
 ///
 /// // Body left out for brevity...
 /// let buf = b"POST /contact HTTP/1.1";
-/// let req = HTTPRequest::new(buf).unwrap();
+/// let req = HTTPRequest::new(buf)?;
 ///
 /// let res = handle_connection(&req, config.clone());
 /// let res_body = str::from_utf8(&res.body).unwrap();
@@ -77,52 +241,313 @@ fn list_dir(path: &Path) -> io::Result<Vec<u8>> {
 /// assert!(res_body
 ///     .find("<p>Server only supports GET and HEAD requests</p>")
 ///     .is_some());
+/// # Ok(())
+/// # }
 /// ```
 pub fn handle_connection<'a>(
     req: &HTTPRequest,
     config: Arc<Config>,
 ) -> HTTPResponse<'a> {
-    if !(req.method == "GET" || req.method == "HEAD") {
-        return HTTPResponse::from(HTTPStatus::new(
+    let id = (String::from(REQUEST_ID_HEADER), request_id(req));
+
+    if config
+        .blocked_methods
+        .iter()
+        .any(|method| method == req.method)
+    {
+        let mut res = method_not_allowed(req, &config);
+        res.headers.push(id);
+        return res;
+    }
+
+    if config.health
+        && (req.method == "GET" || req.method == "HEAD")
+        && req.filepath == Path::new(HEALTH_PATH)
+    {
+        let mut res = health_check(&config, http::uptime());
+        res.headers.push(id);
+        return res;
+    }
+
+    if config.manifest
+        && (req.method == "GET" || req.method == "HEAD")
+        && req.filepath == Path::new(MANIFEST_PATH)
+    {
+        let mut res = http::manifest(&config);
+        res.headers.push(id);
+        return res;
+    }
+
+    if let Some(mut res) = http::acme_challenge(req, &config) {
+        res.headers.push(id);
+        return res;
+    }
+
+    if let Some(mut res) = http::redirect::redirect(req, &config.redirects) {
+        res.headers.push(id);
+        return res;
+    }
+
+    let mut trace = match config.verbosity.shows_debug() {
+        true => ResolveTrace::new(),
+        false => ResolveTrace::disabled(),
+    };
+    let mut res = respond(req, &config, &mut trace);
+    res.headers.push(id);
+    if config.verbosity.shows_debug() {
+        res.trace = Some(trace);
+    }
+
+    if config.live_reload && res.mime.as_deref() == Some("text/html") {
+        inject_reload_script(&mut res.body);
+    }
+
+    if res.status.code < 300 {
+        let path = req.filepath.to_string_lossy();
+        let cache_control = config
+            .cache_rules
+            .lookup(&path)
+            .or(config.cache_control.as_deref());
+
+        if let Some(value) = cache_control {
+            res.headers
+                .push((String::from("Cache-Control"), value.to_string()));
+        }
+    }
+
+    res
+}
+
+/// Resolve the decoded path of a request, without its leading `/`, to a path
+/// below the base directory, see [`files::path::process_path`].
+///
+/// Returns the status to answer with instead if the path has more components
+/// than `max_path_depth` or one longer than `max_segment_len`, leads out of
+/// the base directory or matches the user's ignore rules, so that reads and
+/// uploads are checked alike.
+pub(crate) fn resolve<'a>(
+    path: &ProcessedPath,
+    config: &Config,
+) -> Result<PathBuf, HTTPStatus<'a>> {
+    resolve_traced(path, config, &mut ResolveTrace::disabled())
+}
+
+/// Resolve the path of a request like [`resolve`], recording the steps taken
+/// in `trace`.
+fn resolve_traced<'a>(
+    path: &ProcessedPath,
+    config: &Config,
+    trace: &mut ResolveTrace,
+) -> Result<PathBuf, HTTPStatus<'a>> {
+    resolve_in(path, &config.current_base_dir(), config, trace)
+}
+
+/// Resolve the path of a `GET` or `HEAD` request like [`resolve_traced`],
+/// falling back to the first of the user's overlays having that path if the
+/// base directory doesn't.
+///
+/// Paths are checked against each directory they are resolved in. If no
+/// directory has the path, it is resolved in the base directory.
+fn resolve_overlaid<'a>(
+    path: &ProcessedPath,
+    config: &Config,
+    trace: &mut ResolveTrace,
+) -> Result<PathBuf, HTTPStatus<'a>> {
+    let filename = resolve_traced(path, config, trace)?;
+    if filename.exists() {
+        return Ok(filename);
+    }
+
+    for overlay in &config.overlays {
+        trace.push(ResolveStep::Overlay(overlay.clone()));
+        match resolve_in(path, overlay, config, trace) {
+            Ok(found) if found.exists() => return Ok(found),
+            _ => (),
+        }
+    }
+
+    Ok(filename)
+}
+
+/// Resolve the path of a request below `base_dir`, one of the directories
+/// files are served from, recording the steps taken in `trace`.
+fn resolve_in<'a>(
+    path: &ProcessedPath,
+    base_dir: &Path,
+    config: &Config,
+    trace: &mut ResolveTrace,
+) -> Result<PathBuf, HTTPStatus<'a>> {
+    let decoded = path.as_path().to_path_buf();
+    if path.has_encoded_separator() && !config.allow_encoded_slash {
+        trace.push(ResolveStep::Decoded(decoded));
+        trace.push(ResolveStep::EncodedSeparator);
+        return Err(HTTPStatus::new(
+            400,
+            "Bad Request",
+            Some(String::from("Path has an encoded slash or backslash")),
+        ));
+    }
+    let within_limits = files::path::within_limits(
+        &decoded,
+        config.max_path_depth,
+        config.max_segment_len,
+    );
+    if !within_limits {
+        trace.push(ResolveStep::Decoded(decoded));
+        trace.push(ResolveStep::Limits);
+        return Err(HTTPStatus::new(
+            400,
+            "Bad Request",
+            Some(String::from("Path is too deep or too long")),
+        ));
+    }
+    let filename = path.below(base_dir);
+    trace.push(ResolveStep::Decoded(decoded));
+    trace.push(ResolveStep::Normalized(filename.clone()));
+
+    let is_sub = &filename.ancestors().any(|a| a == base_dir);
+
+    if !is_sub {
+        trace.push(ResolveStep::Traversal);
+        return Err(HTTPStatus::from(&io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Directory traversal is not allowed!",
+        )));
+    }
+
+    let is_ignored = filename
+        .strip_prefix(base_dir)
+        .map(|rel| config.ignore.is_ignored(rel, filename.is_dir()))
+        .unwrap_or(false);
+
+    if is_ignored {
+        trace.push(ResolveStep::Ignored);
+        return Err(HTTPStatus::from(404));
+    }
+
+    Ok(filename)
+}
+
+/// Redirect a request for a directory without a trailing `/` to the same
+/// path with one, so that the relative links of its listing resolve below it.
+fn add_slash<'a>(req: &HTTPRequest) -> HTTPResponse<'a> {
+    let location = match req.query {
+        Some(query) => format!("{}/?{}", req.filepath.display(), query),
+        None => format!("{}/", req.filepath.display()),
+    };
+
+    let mut res =
+        HTTPResponse::new(HTTPStatus::from(301), None, Ok(Vec::new()));
+    res.headers.push((String::from("Location"), location));
+    res.served_by = ServedBy::Internal("redirect");
+    res
+}
+
+/// Answer a request with a blocked method with `405 Method Not Allowed`,
+/// listing the methods the server supports in the `Allow` header.
+fn method_not_allowed<'a>(
+    req: &HTTPRequest,
+    config: &Config,
+) -> HTTPResponse<'a> {
+    let mut res = HTTPResponse::from(HTTPStatus::new(
+        405,
+        "Method Not Allowed",
+        Some(format!("{} requests are not allowed", req.method)),
+    ));
+    let allow = match config.upload {
+        true => "GET, HEAD, PUT, POST, DELETE",
+        false => "GET, HEAD",
+    };
+    res.headers.push((String::from("Allow"), allow.to_string()));
+    res
+}
+
+/// Respond to a request, without any additional headers.
+fn respond<'a>(
+    req: &HTTPRequest,
+    config: &Config,
+    trace: &mut ResolveTrace,
+) -> HTTPResponse<'a> {
+    match req.method {
+        "GET" | "HEAD" => read(req, config, trace),
+        "DELETE" if config.upload => http::upload::delete(req, config),
+        _ => HTTPResponse::from(HTTPStatus::new(
             501,
             "Not Implemented",
             Some(String::from("Server only supports GET and HEAD requests")),
-        ));
+        )),
     }
+}
 
+/// Respond to a `GET` or `HEAD` request with the requested file or directory
+/// listing, recording the steps resolving its path in `trace`.
+fn read<'a>(
+    req: &HTTPRequest,
+    config: &Config,
+    trace: &mut ResolveTrace,
+) -> HTTPResponse<'a> {
     let path = req.filepath;
     let req_filename = match path.to_str().unwrap() {
-        "/" => Path::new("index.html"),
+        "/" => {
+            trace.push(ResolveStep::Index);
+            Path::new(INDEX_FILE)
+        }
         _ => match path.starts_with("/") {
             true => path.strip_prefix("/").unwrap(),
             false => path,
         },
     };
 
-    let filename = files::path::process_path(req_filename, &config.base_dir);
+    // Missing paths are remembered below the current base directory, so that
+    // swapping it doesn't keep them missing
+    let not_found = match config.cache && !config.upload {
+        true => Some((
+            config.current_base_dir().join(req_filename),
+            config.clock.instant(),
+        )),
+        false => None,
+    };
+    if let Some((key, now)) = &not_found {
+        if config.not_found_cache.contains(key, *now) {
+            trace.push(ResolveStep::CachedNotFound);
+            return HTTPResponse::from(HTTPStatus::from(404));
+        }
+    }
 
-    let is_sub = &filename.ancestors().any(|a| a == config.base_dir);
+    let processed = files::path::process_path(req_filename);
+    let filename = match resolve_overlaid(&processed, config, trace) {
+        Ok(filename) => filename,
+        Err(status) => return HTTPResponse::from(status),
+    };
 
-    if !is_sub {
-        return HTTPResponse::from(io::Error::new(
-            io::ErrorKind::PermissionDenied,
-            "Directory traversal is not allowed!",
-        ));
-    }
+    let mut filetype =
+        files::mime::guess_mime_type_with(&filename, &config.mime_overrides);
 
-    let mut filetype = files::mime::guess_mime_type(&filename);
+    let has_slash = path.to_str().unwrap().ends_with('/');
+    if config.list_dir && filename.is_dir() && !has_slash {
+        return add_slash(req);
+    }
 
+    let format = ListingFormat::from_request(req);
     let mut contents = match filename.is_dir() {
-        false => fs::read(&filename),
+        false => {
+            trace.push(ResolveStep::File(filename.clone()));
+            files::file::read_regular(&filename)
+        }
         true => {
-            filetype = Some("text/html"); // Directory listings or errs are HTML
+            // Directory listings are HTML or plain text, errs are HTML
+            filetype = Some(Cow::Borrowed(format.mime()));
             match config.list_dir {
-                true => http::handler::list_dir(&filename),
+                true => {
+                    trace.push(ResolveStep::Listing(filename.clone()));
+                    http::handler::list_dir(&filename, config, format)
+                }
                 false => {
+                    trace.push(ResolveStep::ListingDisabled);
                     return HTTPResponse::from(io::Error::new(
                         io::ErrorKind::PermissionDenied,
                         "Directory traversal is not allowed!",
-                    ))
+                    ));
                 }
             }
         }
@@ -131,22 +556,52 @@ pub fn handle_connection<'a>(
     // List directory if /index.html is not found
     if config.list_dir
         && contents.is_err()
-        && req_filename.to_str().unwrap() == "index.html"
+        && req_filename == Path::new(INDEX_FILE)
     {
-        contents = http::handler::list_dir(&config.base_dir);
+        let base_dir = config.current_base_dir();
+        trace.push(ResolveStep::IndexFallback(base_dir.to_path_buf()));
+        contents = http::handler::list_dir(&base_dir, config, format);
+        filetype = Some(Cow::Borrowed(format.mime()));
+    }
+
+    // Sniff the content of files with unknown extensions, falling back to the
+    // user-configured default MIME type
+    if filetype.is_none() {
+        if let Ok(body) = &contents {
+            filetype = files::mime::sniff_mime(body).map(Cow::Borrowed);
+        }
     }
+    let filetype =
+        filetype.or_else(|| config.default_mime.clone().map(Cow::Owned));
 
     let status = HTTPStatus::from(&contents);
+    if let (404, Some((key, now))) = (status.code, not_found) {
+        config.not_found_cache.insert(&key, now);
+    }
     HTTPResponse::new(status, filetype, contents)
 }
 
 #[cfg(test)]
+// Some tests predate these lints
+#[allow(
+    mismatched_lifetime_syntaxes,
+    clippy::field_reassign_with_default,
+    clippy::len_zero
+)]
 mod test {
     use super::*;
+    use crate::cli::tui::Verbosity;
+    use crate::test_utils::TempDir;
+    use crate::testing::{TempSite, TestClient};
 
     #[test]
     fn listdir_success() {
-        let dir_listing = list_dir(Path::new("example/")).unwrap();
+        let dir_listing = list_dir(
+            Path::new("example/"),
+            &Config::default(),
+            ListingFormat::Html,
+        )
+        .unwrap();
         let dir_str = std::str::from_utf8(&dir_listing).unwrap();
 
         assert!(dir_str.starts_with("<!DOCTYPE html>"));
@@ -158,9 +613,117 @@ mod test {
         assert!(dir_str.ends_with("</html>\n"));
     }
 
+    #[test]
+    fn listdir_upload_form() {
+        let listing = |upload| {
+            let conf = Config {
+                upload,
+                ..Config::default()
+            };
+            String::from_utf8(
+                list_dir(Path::new("example/"), &conf, ListingFormat::Html)
+                    .unwrap(),
+            )
+            .unwrap()
+        };
+
+        assert!(!listing(false).contains("<form"));
+        assert!(listing(true).contains(UPLOAD_FORM));
+    }
+
+    #[test]
+    fn listdir_text() {
+        let (dir, conf) = ignore_site();
+        let conf = Some(conf);
+
+        for req in [
+            &b"GET /pages/?format=txt HTTP/1.1\r\n\r\n"[..],
+            b"GET /pages/ HTTP/1.1\r\nUser-Agent: curl/8.4.0\r\n\r\n",
+        ] {
+            let res = simulate_request(req, conf.clone());
+            let body = std::str::from_utf8(&res.body).unwrap();
+
+            assert_eq!(res.status.code, 200);
+            assert_eq!(res.mime.as_deref(), Some("text/plain; charset=utf-8"));
+            assert!(body.starts_with(&format!(
+                "Listing for {}\n\n",
+                dir.path().join("pages").display()
+            )));
+            assert!(body.contains("\nabout.html "));
+            assert!(body.contains("\nbuild "));
+        }
+
+        // The listing replacing a missing index is negotiated as well
+        fs::remove_file(dir.path().join("index.html")).unwrap();
+        let res = simulate_request(b"GET /?format=txt HTTP/1.1", conf);
+        let body = std::str::from_utf8(&res.body).unwrap();
+        assert_eq!(res.mime.as_deref(), Some("text/plain; charset=utf-8"));
+        assert!(body.contains("\npages/ "));
+        assert!(!body.contains(".env"));
+    }
+
+    #[test]
+    fn redirects() {
+        let mut config = Config {
+            base_dir: Path::new("example/").canonicalize().unwrap(),
+            ..Config::default()
+        };
+        config
+            .redirects
+            .add("/index.html=/pages/about.html")
+            .unwrap();
+        config
+            .redirects
+            .add("/pages/*=https://example.com/*:301")
+            .unwrap();
+        let config = Some(config);
+
+        // Redirects take precedence over existing files
+        let res = simulate_request(b"GET /index.html HTTP/1.1", config.clone());
+        assert_eq!(res.status.to_string(), "HTTP/1.1 302 Found");
+        assert_eq!(
+            res.headers[0],
+            (String::from("Location"), String::from("/pages/about.html"))
+        );
+        assert_eq!(res.served_by, ServedBy::Internal("redirect"));
+        assert!(res.body.is_empty());
+
+        let res = simulate_request(
+            b"HEAD /pages/about.html?x=1 HTTP/1.1",
+            config.clone(),
+        );
+        assert_eq!(res.status.code, 301);
+        assert_eq!(res.headers[0].1, "https://example.com/about.html");
+
+        let res = simulate_request(b"GET / HTTP/1.1", config);
+        assert_eq!(res.status.code, 200);
+    }
+
+    #[test]
+    fn directory_slash() {
+        let site = TempSite::build(&[("pages/about.html", "about")]);
+        let client = TestClient::start(site.config());
+
+        let res = client.get("/pages");
+        assert_eq!(res.status, 301);
+        assert_eq!(res.header("Location"), Some("/pages/"));
+        let res = client.request(b"HEAD /pag%65s?format=txt HTTP/1.1\r\n\r\n");
+        assert_eq!(res.header("Location"), Some("/pag%65s/?format=txt"));
+
+        let res = client.get("/pages/");
+        assert_eq!(res.status, 200);
+        assert!(res.text().contains("about.html"));
+        assert_eq!(client.get("/pages/about.html").text(), "about");
+        assert_eq!(client.get("/page").status, 404);
+    }
+
     #[test]
     fn listdir_err() {
-        let dir_listing = list_dir(Path::new("example/i_dont_exist/"));
+        let dir_listing = list_dir(
+            Path::new("example/i_dont_exist/"),
+            &Config::default(),
+            ListingFormat::Html,
+        );
 
         assert!(dir_listing.is_err());
         assert!(matches!(
@@ -192,6 +755,235 @@ mod test {
         assert_eq!(res.status.to_string(), "HTTP/1.1 200 OK");
     }
 
+    #[test]
+    fn served_by() {
+        let conf = || Config {
+            base_dir: Path::new("example/").canonicalize().unwrap(),
+            health: true,
+            ..Config::default()
+        };
+        let served_by =
+            |buffer: &[u8]| simulate_request(buffer, Some(conf())).served_by;
+
+        assert_eq!(served_by(b"GET /index.html HTTP/1.1"), ServedBy::BaseDir);
+        assert_eq!(served_by(b"GET /missing HTTP/1.1"), ServedBy::BaseDir);
+        assert_eq!(served_by(b"GET /../ HTTP/1.1"), ServedBy::BaseDir);
+        assert_eq!(
+            served_by(b"GET /_servum/health HTTP/1.1"),
+            ServedBy::Internal("health")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn special_files() {
+        use std::{process::Command, sync::mpsc, time::Duration};
+
+        let site = TempSite::build(&[("a.txt", "a")]);
+        let fifo = site.path().join("fifo");
+        assert!(Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap()
+            .success());
+        std::os::unix::fs::symlink(&fifo, site.path().join("link")).unwrap();
+
+        let client = TestClient::start(site.config());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for target in ["/fifo", "/link", "/a.txt"] {
+                tx.send(client.get(target)).unwrap();
+            }
+        });
+
+        // Answered right away, without waiting for a writer to the FIFO
+        for _ in 0..2 {
+            let res = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(res.status, 403);
+            assert!(res.text().contains("Special files are not served"));
+        }
+        let res = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!((res.status, res.text().as_str()), (200, "a"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follow_base_symlink() {
+        use crate::clock::TestClock;
+        use crate::files::base_dir::{BaseLink, BASE_LINK_TTL};
+        use std::os::unix::fs::symlink;
+        use std::time::SystemTime;
+
+        let dir = TempDir::new();
+        dir.file("release-1/app.js", "one");
+        dir.file("release-2/app.js", "two");
+        dir.file("release-2/new.js", "new");
+        let current = dir.path().join("current");
+        symlink(dir.path().join("release-1"), &current).unwrap();
+
+        let clock = Arc::new(TestClock::new(SystemTime::UNIX_EPOCH));
+        let conf = Config {
+            base_dir: current.canonicalize().unwrap(),
+            base_link: BaseLink::new(&current),
+            follow_base_symlink: true,
+            clock: clock.clone(),
+            ..Config::default()
+        };
+        let fixed = Config {
+            follow_base_symlink: false,
+            ..conf.clone()
+        };
+        let body = |buffer: &[u8], conf: &Config| {
+            simulate_request(buffer, Some(conf.clone())).body
+        };
+
+        assert_eq!(body(b"GET /app.js HTTP/1.1", &conf), b"one");
+
+        fs::remove_file(&current).unwrap();
+        symlink(dir.path().join("release-2"), &current).unwrap();
+        // The resolved directory is reused for a while
+        assert_eq!(body(b"GET /app.js HTTP/1.1", &conf), b"one");
+
+        clock.advance(BASE_LINK_TTL);
+        assert_eq!(body(b"GET /app.js HTTP/1.1", &conf), b"two");
+        assert_eq!(body(b"GET /new.js HTTP/1.1", &conf), b"new");
+        assert_eq!(body(b"GET /app.js HTTP/1.1", &fixed), b"one");
+
+        // Containment is checked against the new directory
+        let res = simulate_request(
+            b"GET /../release-1/app.js HTTP/1.1",
+            Some(conf.clone()),
+        );
+        assert_eq!(res.status.code, 403);
+    }
+
+    #[test]
+    fn not_found_cache() {
+        use crate::clock::TestClock;
+        use std::time::SystemTime;
+
+        let dir = TempDir::new();
+        let clock = Arc::new(TestClock::new(SystemTime::UNIX_EPOCH));
+        let conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            cache: true,
+            clock: clock.clone(),
+            verbosity: Verbosity::Debug,
+            ..Config::default()
+        };
+        let request = |conf: &Config| {
+            simulate_request(b"GET /status.json HTTP/1.1", Some(conf.clone()))
+        };
+
+        let res = request(&conf);
+        assert_eq!(res.status.code, 404);
+        assert_eq!(conf.not_found_cache.len(), 1);
+
+        // The file is only looked up again once the entry expired
+        dir.file("status.json", "{}");
+        let res = request(&conf);
+        assert_eq!(res.status.code, 404);
+        assert_eq!(res.trace.unwrap().steps(), [ResolveStep::CachedNotFound]);
+
+        clock.advance(http::NOT_FOUND_CACHE_TTL);
+        assert_eq!(request(&conf).status.code, 200);
+
+        // Missing paths aren't kept without --cache or with --upload
+        fs::remove_file(dir.path().join("status.json")).unwrap();
+        for conf in [
+            Config {
+                cache: false,
+                not_found_cache: Default::default(),
+                ..conf.clone()
+            },
+            Config {
+                upload: true,
+                not_found_cache: Default::default(),
+                ..conf.clone()
+            },
+        ] {
+            assert_eq!(request(&conf).status.code, 404);
+            assert!(conf.not_found_cache.is_empty());
+        }
+    }
+
+    #[test]
+    fn resolve_trace() {
+        use ResolveStep::*;
+        let (dir, conf) = ignore_site();
+        let base = dir.path().to_path_buf();
+        let conf = Config {
+            verbosity: Verbosity::Debug,
+            ..conf
+        };
+        let trace = |buffer: &[u8], conf: &Config| {
+            simulate_request(buffer, Some(conf.clone())).trace.unwrap()
+        };
+
+        let about = base.join("pages/about.html");
+        let res = trace(b"GET /pages/./x/../about%2Ehtml HTTP/1.1", &conf);
+        assert_eq!(
+            res.steps(),
+            [
+                Decoded(PathBuf::from("pages/./x/../about.html")),
+                Normalized(about.clone()),
+                File(about.clone()),
+            ]
+        );
+        assert_eq!(res.path(), Some(about.as_path()));
+
+        let res = trace(b"GET /pages/ HTTP/1.1", &conf);
+        assert_eq!(res.last(), Some(&Listing(base.join("pages"))));
+        let res = trace(b"GET / HTTP/1.1", &conf);
+        assert_eq!(res.steps()[0], Index);
+        assert_eq!(res.last(), Some(&File(base.join("index.html"))));
+
+        let res = trace(b"GET /../../etc/passwd HTTP/1.1", &conf);
+        assert_eq!(res.last(), Some(&Traversal));
+        assert_eq!(res.path(), Some(Path::new("/etc/passwd")));
+        assert_eq!(
+            trace(b"GET /debug.log HTTP/1.1", &conf).last(),
+            Some(&Ignored)
+        );
+
+        fs::remove_file(base.join("index.html")).unwrap();
+        let res = trace(b"GET / HTTP/1.1", &conf);
+        assert_eq!(res.last(), Some(&IndexFallback(base.clone())));
+        assert_eq!(res.path(), Some(base.as_path()));
+
+        let no_list = Config {
+            list_dir: false,
+            ..conf
+        };
+        let res = trace(b"GET /pages/ HTTP/1.1", &no_list);
+        assert_eq!(res.last(), Some(&ListingDisabled));
+
+        // Traces are only recorded at the debug level
+        let res = simulate_request(b"GET / HTTP/1.1", Some(Config::default()));
+        assert_eq!(res.trace, None);
+    }
+
+    #[test]
+    fn request_id_header() {
+        let id = |res: HTTPResponse| {
+            res.headers
+                .into_iter()
+                .find(|(name, _)| name == REQUEST_ID_HEADER)
+                .map(|(_, id)| id)
+                .unwrap()
+        };
+
+        let res = simulate_request(
+            b"GET /missing HTTP/1.1\r\nX-Request-Id: from-client\r\n\r\n",
+            None,
+        );
+        assert_eq!(id(res), "from-client");
+
+        let a = id(simulate_request(b"GET / HTTP/1.1\r\n\r\n", None));
+        let b = id(simulate_request(b"GET / HTTP/1.1\r\n\r\n", None));
+        assert_ne!(a, b);
+    }
+
     #[test]
     fn request_invalid_method() {
         let res = simulate_request(b"DELETE /user/post HTTP/1.1", None);
@@ -214,6 +1006,78 @@ mod test {
         assert!(body.ends_with("</html>\n"));
     }
 
+    #[test]
+    fn mime_builtin() {
+        let res = simulate_request(b"GET /index.html HTTP/1.1", None);
+
+        assert_eq!(res.mime.unwrap(), "text/html");
+    }
+
+    #[test]
+    fn mime_override() {
+        let mut conf = Config {
+            base_dir: Path::new("example/").canonicalize().unwrap(),
+            ..Config::default()
+        };
+        conf.mime_overrides
+            .insert(String::from("html"), String::from("text/plain"));
+
+        let res = simulate_request(b"GET /index.html HTTP/1.1", Some(conf));
+        let header = res.header();
+
+        assert_eq!(res.mime.unwrap(), "text/plain");
+        assert!(std::str::from_utf8(&header)
+            .unwrap()
+            .contains("Content-Type: text/plain\r\n"));
+    }
+
+    #[test]
+    fn sniff_mime() {
+        let res = simulate_request(b"GET /README HTTP/1.1", None);
+
+        assert_eq!(res.status.to_string(), "HTTP/1.1 200 OK");
+        assert_eq!(res.mime.unwrap(), "text/plain");
+    }
+
+    #[test]
+    fn no_default_mime() {
+        let res = simulate_request(b"GET /pages/blob HTTP/1.1", None);
+        let header = res.header();
+
+        assert_eq!(res.status.to_string(), "HTTP/1.1 200 OK");
+        assert!(res.mime.is_none());
+        assert!(!std::str::from_utf8(&header)
+            .unwrap()
+            .contains("Content-Type"));
+    }
+
+    #[test]
+    fn default_mime() {
+        let conf = Config {
+            base_dir: Path::new("example/").canonicalize().unwrap(),
+            default_mime: Some(String::from("application/octet-stream")),
+            ..Config::default()
+        };
+
+        let res = simulate_request(b"GET /pages/blob HTTP/1.1", Some(conf));
+
+        assert_eq!(res.status.to_string(), "HTTP/1.1 200 OK");
+        assert_eq!(res.mime.unwrap(), "application/octet-stream");
+    }
+
+    #[test]
+    fn default_mime_ignored_for_known_types() {
+        let conf = Config {
+            base_dir: Path::new("example/").canonicalize().unwrap(),
+            default_mime: Some(String::from("application/octet-stream")),
+            ..Config::default()
+        };
+
+        let res = simulate_request(b"GET /index.html HTTP/1.1", Some(conf));
+
+        assert_eq!(res.mime.unwrap(), "text/html");
+    }
+
     #[test]
     fn file_not_found() {
         let res = simulate_request(b"GET /i-dont-exist HTTP/1.1", None);
@@ -260,4 +1124,575 @@ mod test {
         assert!(body.find("Listing for").is_some());
         assert!(body.find("<h1>Listing for").is_some());
     }
+
+    // Helper
+    fn cache_control(path: &str, conf: &Config) -> Option<String> {
+        let req = format!("GET {} HTTP/1.1", path);
+        let res = simulate_request(req.as_bytes(), Some(conf.clone()));
+
+        res.headers
+            .into_iter()
+            .find(|(name, _)| name == "Cache-Control")
+            .map(|(_, value)| value)
+    }
+
+    #[test]
+    fn cache_rules_precedence() {
+        let mut conf = Config {
+            base_dir: Path::new("example/").canonicalize().unwrap(),
+            cache_control: Some(String::from("max-age=60")),
+            ..Config::default()
+        };
+        conf.cache_rules.add("pages/*.html=no-store").unwrap();
+        conf.cache_rules.add("**/*.html=no-cache").unwrap();
+
+        // First matching rule wins, then the global value
+        assert_eq!(
+            cache_control("/pages/about.html", &conf).as_deref(),
+            Some("no-store")
+        );
+        assert_eq!(
+            cache_control("/index.html", &conf).as_deref(),
+            Some("no-cache")
+        );
+        assert_eq!(
+            cache_control("/README", &conf).as_deref(),
+            Some("max-age=60")
+        );
+        // Rules match the URL path, not the served file
+        assert_eq!(cache_control("/", &conf).as_deref(), Some("max-age=60"));
+        // Errors are never cached
+        assert_eq!(cache_control("/i-dont-exist.html", &conf), None);
+    }
+
+    #[test]
+    fn no_cache_control() {
+        let conf = Config {
+            base_dir: Path::new("example/").canonicalize().unwrap(),
+            ..Config::default()
+        };
+
+        assert_eq!(cache_control("/index.html", &conf), None);
+    }
+
+    // Helper
+    fn ignore_site() -> (TempDir, Config) {
+        let dir = TempDir::new();
+        dir.file("index.html", "<h1>Home</h1>");
+        dir.file("debug.log", "log");
+        dir.file(".env", "SECRET=1");
+        dir.file("build/app.js", "app");
+        dir.file("pages/build", "not a directory");
+        dir.file("pages/about.html", "about");
+        dir.file(files::ignore::IGNORE_FILE, "# Secrets\n.env\nbuild/\n");
+
+        let mut conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        conf.ignore.add("*.log");
+        conf.load_ignore_file().unwrap();
+
+        (dir, conf)
+    }
+
+    #[test]
+    fn listing_cache() {
+        use crate::clock::TestClock;
+        use std::{thread, time::Duration};
+
+        let dir = TempDir::new();
+        dir.file("docs/a.txt", "a");
+        let clock = Arc::new(TestClock::new(std::time::SystemTime::now()));
+        let ttl = Duration::from_secs(5);
+        let conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            listing_cache_ttl: Some(ttl),
+            clock: clock.clone(),
+            ..Config::default()
+        };
+        let listing = |conf: &Config| {
+            let req = b"GET /docs/?format=txt HTTP/1.1";
+            let res = simulate_request(req, Some(conf.clone()));
+            assert_eq!(res.status.code, 200);
+            String::from_utf8(res.body).unwrap()
+        };
+        let size_of_a = |listing: &str| {
+            listing
+                .lines()
+                .nth(2)
+                .unwrap()
+                .split_whitespace()
+                .nth(1)
+                .unwrap()
+                .to_string()
+        };
+
+        let first = listing(&conf);
+        assert_eq!(size_of_a(&first), "1");
+        assert_eq!(conf.listing_cache.len(), 1);
+
+        // Changing a file leaves the directory as is, so the listing is
+        // reused until it expires
+        dir.file("docs/a.txt", "aaa");
+        assert_eq!(listing(&conf), first);
+        clock.advance(ttl);
+        let expired = listing(&conf);
+        assert_eq!(size_of_a(&expired), "3");
+
+        // Adding a file changes the directory, which invalidates the listing
+        thread::sleep(Duration::from_millis(20));
+        dir.file("docs/b.txt", "b");
+        assert!(listing(&conf).contains("b.txt"));
+
+        // Listings aren't cached with uploads
+        let conf = Config {
+            upload: true,
+            listing_cache: Default::default(),
+            ..conf
+        };
+        listing(&conf);
+        assert!(conf.listing_cache.is_empty());
+    }
+
+    #[test]
+    fn overlays() {
+        let dir = TempDir::new();
+        dir.file("dist/index.html", "dist index");
+        dir.file("dist/shared.txt", "dist");
+        dir.file("dist/sub/built.txt", "dist");
+        dir.file("public/shared.txt", "public");
+        dir.file("public/robots.txt", "public");
+        dir.file("public/sub/static.txt", "public");
+        dir.file("public/sub/shared.txt", "public");
+        dir.file("public/.env", "secret");
+        let mut conf = Config {
+            base_dir: dir.path().join("dist"),
+            overlays: vec![dir.path().join("public")],
+            ..Config::default()
+        };
+        conf.ignore.add(".*").unwrap();
+        let get = |target: &str| {
+            let req = format!("GET {} HTTP/1.1", target);
+            let res = simulate_request(req.as_bytes(), Some(conf.clone()));
+            (res.status.code, String::from_utf8(res.body).unwrap())
+        };
+
+        assert_eq!(get("/"), (200, String::from("dist index")));
+        assert_eq!(get("/shared.txt"), (200, String::from("dist")));
+        assert_eq!(get("/robots.txt"), (200, String::from("public")));
+        assert_eq!(get("/sub/static.txt"), (200, String::from("public")));
+        assert_eq!(get("/missing.txt").0, 404);
+        // Ignore rules and containment apply in every root
+        assert_eq!(get("/.env").0, 404);
+        assert_eq!(get("/../public/robots.txt").0, 403);
+
+        // Listings show the union of the entries, without duplicates
+        let (code, listing) = get("/sub/");
+        assert_eq!(code, 200);
+        for name in ["built.txt", "static.txt", "shared.txt"] {
+            let link = format!("<a href=\"./{0}\">{0}</a>", name);
+            assert_eq!(listing.matches(&link).count(), 1, "{}", name);
+        }
+
+        // The index fallback lists every root
+        fs::remove_file(dir.path().join("dist/index.html")).unwrap();
+        let (code, listing) = get("/");
+        assert_eq!(code, 200);
+        assert!(listing.contains("robots.txt"));
+        assert_eq!(listing.matches("href=\"./shared.txt\"").count(), 1);
+        assert!(listing.contains("sub/"));
+        assert!(!listing.contains(".env"));
+    }
+
+    #[test]
+    fn acme_dir() {
+        let (dir, mut conf) = ignore_site();
+        dir.file(".well-known/acme-challenge/t0ken", "t0ken.key");
+        dir.file(".well-known/security.txt", "Contact: me");
+        conf.ignore.add(".*").unwrap();
+        let status = |target: &str, conf: &Config| {
+            let req = format!("GET {} HTTP/1.1", target);
+            simulate_request(req.as_bytes(), Some(conf.clone()))
+                .status
+                .code
+        };
+        let challenge = "/.well-known/acme-challenge/t0ken";
+
+        // Hidden by the ignore rules without --acme-dir
+        assert_eq!(status(challenge, &conf), 404);
+
+        let conf = Config {
+            acme_dir: Some(dir.path().join(".well-known/acme-challenge")),
+            ..conf
+        };
+        let req = format!("GET {} HTTP/1.1", challenge);
+        let res = simulate_request(req.as_bytes(), Some(conf.clone()));
+        assert_eq!(res.status.code, 200);
+        assert_eq!(res.body, b"t0ken.key");
+        assert_eq!(res.served_by, ServedBy::Internal("acme"));
+
+        // Other paths are still governed by the ignore rules
+        assert_eq!(status("/.well-known/security.txt", &conf), 404);
+        assert_eq!(status("/.env", &conf), 404);
+        // Challenges can't lead out of the ACME directory
+        assert_eq!(
+            status("/.well-known/acme-challenge/../../.env", &conf),
+            403
+        );
+    }
+
+    #[test]
+    fn ignored_requests() {
+        let (_dir, conf) = ignore_site();
+        let conf = Some(conf);
+
+        for path in &["/.env", "/debug.log", "/build/", "/build/app.js"] {
+            let req = format!("GET {} HTTP/1.1", path);
+            let res = simulate_request(req.as_bytes(), conf.clone());
+
+            assert_eq!(res.status.to_string(), "HTTP/1.1 404 Not Found");
+        }
+    }
+
+    #[test]
+    fn not_ignored_requests() {
+        let (_dir, conf) = ignore_site();
+        let conf = Some(conf);
+
+        // Directory-only patterns don't match files and patterns are anchored
+        for path in &["/index.html", "/pages/build", "/pages/about.html"] {
+            let req = format!("GET {} HTTP/1.1", path);
+            let res = simulate_request(req.as_bytes(), conf.clone());
+
+            assert_eq!(res.status.to_string(), "HTTP/1.1 200 OK");
+        }
+    }
+
+    #[test]
+    fn ignored_listing() {
+        let (dir, conf) = ignore_site();
+        let listing = list_dir(dir.path(), &conf, ListingFormat::Html).unwrap();
+        let listing = std::str::from_utf8(&listing).unwrap();
+
+        assert!(listing.contains("index.html"));
+        assert!(listing.contains("pages/"));
+        assert!(!listing.contains(".env"));
+        assert!(!listing.contains("debug.log"));
+        assert!(!listing.contains("build/"));
+    }
+
+    #[test]
+    fn no_ignore_file() {
+        let (dir, _) = ignore_site();
+        let conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            ignore_file: false,
+            ..Config::default()
+        };
+
+        let res = simulate_request(b"GET /.env HTTP/1.1", Some(conf));
+
+        assert_eq!(res.status.to_string(), "HTTP/1.1 200 OK");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn listdir_unreadable_entries() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = TempDir::new();
+        let locked = dir.file("locked.txt", "secret");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000))
+            .unwrap();
+        symlink(dir.path().join("gone"), dir.path().join("dangling")).unwrap();
+
+        let conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        let listing = list_dir(dir.path(), &conf, ListingFormat::Html).unwrap();
+        let listing = std::str::from_utf8(&listing).unwrap();
+
+        assert!(listing
+            .contains("<a href=\"./dangling\">dangling</a> (unreadable)"));
+        // Privileged users, e.g. root, can read the file regardless
+        if fs::File::open(&locked).is_ok() {
+            return;
+        }
+        assert!(listing
+            .contains("<a href=\"./locked.txt\">locked.txt</a> (unreadable)"));
+    }
+
+    #[test]
+    fn health_endpoint() {
+        let dir = TempDir::new();
+        let conf = |health| Config {
+            base_dir: dir.path().to_path_buf(),
+            cache_control: Some(String::from("max-age=60")),
+            health,
+            ..Config::default()
+        };
+
+        let res = simulate_request(
+            b"GET /_servum/health HTTP/1.1",
+            Some(conf(false)),
+        );
+        assert_eq!(res.status.code, 404);
+
+        let res =
+            simulate_request(b"GET /_servum/health HTTP/1.1", Some(conf(true)));
+        assert_eq!(res.status.code, 200);
+        assert_eq!(res.mime.as_deref(), Some("application/json"));
+        assert_eq!(
+            res.headers[0],
+            (String::from("Cache-Control"), String::from("no-store"))
+        );
+        assert_eq!(res.headers[1].0, REQUEST_ID_HEADER);
+        assert_eq!(res.headers.len(), 2);
+
+        let res = simulate_request(
+            b"POST /_servum/health HTTP/1.1",
+            Some(conf(true)),
+        );
+        assert_eq!(res.status.code, 501);
+    }
+
+    #[test]
+    fn manifest_endpoint() {
+        let site = TempSite::build(&[
+            ("index.html", "<h1>Gallery</h1>"),
+            ("photos/a.jpg", "jpeg"),
+            ("photos/raw/a.cr2", "raw"),
+        ]);
+        let config = Config {
+            ignore: files::ignore::IgnoreRules::parse("photos/raw/").unwrap(),
+            ..site.config()
+        };
+
+        let client = TestClient::start(config.clone());
+        assert_eq!(client.get(MANIFEST_PATH).status, 404);
+        client.shutdown();
+
+        let client = TestClient::start(Config {
+            manifest: true,
+            ..config
+        });
+        let res = client.get(MANIFEST_PATH);
+        assert_eq!(res.status, 200);
+        assert_eq!(res.header("Content-Type"), Some("application/json"));
+        let body = res.text();
+        assert!(body.contains("{\"path\":\"index.html\",\"size\":16,"));
+        assert!(body.contains("{\"path\":\"photos/a.jpg\",\"size\":4,"));
+        assert!(!body.contains("a.cr2"));
+        assert_eq!(body.matches("\"path\"").count(), 2);
+
+        let res =
+            client.request(b"POST /_servum/manifest.json HTTP/1.1\r\n\r\n");
+        assert_eq!(res.status, 501);
+    }
+
+    #[test]
+    fn decode_once() {
+        let site = TempSite::build(&[
+            ("secret.txt", "secret"),
+            ("site/%2e%2e%2fsecret.txt", "literal"),
+            ("site/docs/index.html", "docs"),
+        ]);
+        let client = TestClient::start(Config {
+            base_dir: site.path().join("site"),
+            overlays: vec![site.path().join("site/docs")],
+            ..site.config()
+        });
+
+        // Decoded once, the path names a file of its own
+        let res = client.get("/%252e%252e%252fsecret.txt");
+        assert_eq!((res.status, res.text().as_str()), (200, "literal"));
+        assert_eq!(client.get("/%2e%2e%2fsecret.txt").status, 400);
+
+        // The same holds for overlays and the root of an overlay
+        for target in ["/%252e%252e%252findex.html", "/%25252e%25252e/"] {
+            assert_eq!(client.get(target).status, 404, "{}", target);
+        }
+
+        assert_eq!(client.get("/%FF%FE").status, 404);
+    }
+
+    #[test]
+    fn encoded_separators() {
+        let site = TempSite::build(&[
+            ("secret.txt", "secret"),
+            ("site/a%2Fb.txt", "literal"),
+            ("site/dir/a.txt", "a"),
+        ]);
+        let config = Config {
+            base_dir: site.path().join("site"),
+            ..site.config()
+        };
+        let traversals = [
+            "/dir/..%2F..%2Fsecret.txt",
+            "/dir%2F..%2F..%2Fsecret.txt",
+            "/%2e%2e%2F%2e%2e%2Fsecret.txt",
+            "/..%5C..%5Csecret.txt",
+            "/dir%5Ca.txt",
+        ];
+
+        let client = TestClient::start(config.clone());
+        for target in traversals.iter().chain(&["/a%2Fb.txt", "/dir%2Fa.txt"]) {
+            assert_eq!(client.get(target).status, 400, "{}", target);
+        }
+        // Encoded once more, the name is plain text
+        let res = client.get("/a%252Fb.txt");
+        assert_eq!((res.status, res.text().as_str()), (200, "literal"));
+        client.shutdown();
+
+        let client = TestClient::start(Config {
+            allow_encoded_slash: true,
+            ..config
+        });
+        for target in traversals.iter().chain(&["/dir%2Fa.txt"]) {
+            assert_eq!(client.get(target).status, 404, "{}", target);
+        }
+        let res = client.get("/a%2Fb.txt");
+        assert_eq!((res.status, res.text().as_str()), (200, "literal"));
+        assert_eq!(client.get("/dir/a.txt").status, 200);
+    }
+
+    #[test]
+    fn path_limits() {
+        let status = |target: &str, config: Option<Config>| {
+            let buffer = format!("GET {} HTTP/1.1", target);
+            simulate_request(buffer.as_bytes(), config).status.code
+        };
+
+        let deep = "/a".repeat(65);
+        assert_eq!(status(&deep, None), 400);
+        assert_eq!(status(&"/..".repeat(65), None), 400);
+        assert_eq!(status(&deep[2..], None), 404);
+
+        let long = format!("/{}", "a".repeat(256));
+        assert_eq!(status(&long, None), 400);
+        assert_eq!(status(&long.replace("aa", "%61a"), None), 400);
+        assert_eq!(status(&long[..256], None), 404);
+
+        let config = || Config {
+            base_dir: Path::new("example/").canonicalize().unwrap(),
+            max_path_depth: 2,
+            max_segment_len: 1024,
+            ..Config::default()
+        };
+        assert_eq!(status("/pages/about.html", Some(config())), 200);
+        assert_eq!(status("/pages/a/b", Some(config())), 400);
+
+        // Names too long for the file system aren't server errors
+        assert_eq!(status(&long, Some(config())), 404);
+        assert_eq!(
+            status(&format!("/{}", "a".repeat(1024)), Some(config())),
+            404
+        );
+    }
+
+    #[test]
+    fn blocked_methods() {
+        let allow = |res: &HTTPResponse| {
+            res.headers
+                .iter()
+                .find(|(name, _)| name == "Allow")
+                .map(|(_, value)| value.clone())
+        };
+
+        for method in ["TRACE", "TRACK", "CONNECT"] {
+            let buffer = format!("{} /index.html HTTP/1.1", method);
+            let res = simulate_request(buffer.as_bytes(), None);
+            assert_eq!(res.status.code, 405, "{}", method);
+            assert_eq!(allow(&res).as_deref(), Some("GET, HEAD"));
+            assert_eq!(res.headers.last().unwrap().0, REQUEST_ID_HEADER);
+        }
+        // Methods are case-sensitive
+        let res = simulate_request(b"trace / HTTP/1.1", None);
+        assert_eq!(res.status.code, 501);
+
+        // Blocked before the path is even looked at
+        let conf = Config {
+            blocked_methods: vec![String::from("GET")],
+            base_dir: PathBuf::from("/does/not/exist"),
+            health: true,
+            upload: true,
+            ..Config::default()
+        };
+        let res = simulate_request(b"GET /_servum/health HTTP/1.1", Some(conf));
+        assert_eq!(res.status.code, 405);
+        assert_eq!(
+            allow(&res).as_deref(),
+            Some("GET, HEAD, PUT, POST, DELETE")
+        );
+
+        let res = simulate_request(
+            b"TRACE / HTTP/1.1",
+            Some(Config {
+                blocked_methods: Vec::new(),
+                ..Config::default()
+            }),
+        );
+        assert_eq!(res.status.code, 501);
+    }
+
+    #[test]
+    fn health_endpoint_base_dir_gone() {
+        let dir = TempDir::new();
+        let conf = Config {
+            base_dir: dir.path().join("gone"),
+            health: true,
+            ..Config::default()
+        };
+
+        let res =
+            simulate_request(b"HEAD /_servum/health HTTP/1.1", Some(conf));
+        assert_eq!(res.status.code, 503);
+    }
+
+    #[test]
+    fn live_reload_script() {
+        let conf = |live_reload| Config {
+            base_dir: Path::new("example/").canonicalize().unwrap(),
+            live_reload,
+            ..Config::default()
+        };
+        let script = http::RELOAD_SCRIPT.as_bytes();
+
+        let res =
+            simulate_request(b"GET /index.html HTTP/1.1", Some(conf(true)));
+        assert!(res.body.windows(script.len()).any(|w| w == script));
+
+        let res = simulate_request(b"GET /README HTTP/1.1", Some(conf(true)));
+        assert!(!res.body.windows(script.len()).any(|w| w == script));
+
+        let res =
+            simulate_request(b"GET /index.html HTTP/1.1", Some(conf(false)));
+        assert!(!res.body.windows(script.len()).any(|w| w == script));
+    }
+
+    #[test]
+    fn file_changed_while_sending() {
+        let dir = TempDir::new();
+        dir.file("bundle.js", "let a = 1;");
+        let conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        let res = simulate_request(b"GET /bundle.js HTTP/1.1", Some(conf));
+
+        // Bodies are read whole before the header is written, so rewriting
+        // the file in between can't make Content-Length disagree with them
+        for contents in ["let", "let a = 1; let b = 2;"] {
+            dir.file("bundle.js", contents);
+            let mut out = Vec::new();
+            let written = http::write_response(&res, &mut out).unwrap();
+            let (header, body) = out.split_at(written.header);
+
+            assert_eq!(body, b"let a = 1;");
+            assert!(String::from_utf8_lossy(header)
+                .contains("Content-Length: 10\r\n"));
+        }
+    }
 }
@@ -0,0 +1,30 @@
+/// HeaderError representing header fields which can't be sent.
+///
+/// Used by [`HTTPResponse::add_header`] to refuse fields which would corrupt
+/// the response header.
+///
+/// [`HTTPResponse::add_header`]: crate::http::HTTPResponse::add_header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderError {
+    InvalidName,
+    InvalidValue,
+    Reserved,
+}
+
+impl std::error::Error for HeaderError {}
+
+impl std::fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            HeaderError::InvalidName => {
+                write!(f, "Header name is empty or not a valid token")
+            }
+            HeaderError::InvalidValue => {
+                write!(f, "Header value contains control characters")
+            }
+            HeaderError::Reserved => {
+                write!(f, "Header is set by the server itself")
+            }
+        }
+    }
+}
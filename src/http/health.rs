@@ -0,0 +1,126 @@
+use crate::cli::{Config, VERSION};
+use crate::http::{json_string, HTTPResponse, HTTPStatus, ServedBy};
+use std::{
+    borrow::Cow,
+    fs,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+/// URL path of the health endpoint, enabled with `--health`
+pub const HEALTH_PATH: &str = "/_servum/health";
+
+/// Time the server started, see [`mark_started`]
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Record the time the server started, from which the uptime reported by
+/// the health endpoint is measured. Only the first call has an effect.
+pub fn mark_started() {
+    STARTED.get_or_init(Instant::now);
+}
+
+/// Return the time since [`mark_started`] was first called, or zero if it
+/// never was.
+pub fn uptime() -> Duration {
+    STARTED.get().map(Instant::elapsed).unwrap_or_default()
+}
+
+/// Respond to a request to the health endpoint.
+///
+/// The JSON body reports the version of servum, the uptime in seconds and
+/// whether the base directory can be read. If it can't, e.g. because it was
+/// deleted, the status is `503 Service Unavailable` instead of `200 OK`. The
+/// base directory is the only thing looked up on the filesystem.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::{cli::Config, http::health_check};
+/// use std::time::Duration;
+///
+/// let conf = Config {
+///     base_dir: std::env::temp_dir(),
+///     ..Config::default()
+/// };
+/// let res = health_check(&conf, Duration::from_secs(90));
+/// let body = std::str::from_utf8(&res.body).unwrap();
+///
+/// assert_eq!(res.status.code, 200);
+/// assert_eq!(res.mime.unwrap(), "application/json");
+/// assert!(body.contains(r#""uptime":90"#));
+/// assert!(body.contains(r#""base_dir":true"#));
+/// ```
+pub fn health_check<'a>(config: &Config, uptime: Duration) -> HTTPResponse<'a> {
+    let readable = fs::read_dir(config.current_base_dir()).is_ok();
+    let (status, code) = match readable {
+        true => ("ok", 200),
+        false => ("unavailable", 503),
+    };
+    let body = format!(
+        "{{\"status\":{},\"version\":{},\"uptime\":{},\"base_dir\":{}}}\n",
+        json_string(status),
+        json_string(VERSION),
+        uptime.as_secs(),
+        readable
+    );
+
+    let mut res = HTTPResponse::new(
+        HTTPStatus::from(code),
+        Some(Cow::Borrowed("application/json")),
+        Ok(body.into_bytes()),
+    );
+    res.headers
+        .push((String::from("Cache-Control"), String::from("no-store")));
+    res.served_by = ServedBy::Internal("health");
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn healthy() {
+        let dir = TempDir::new();
+        let conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        let res = health_check(&conf, Duration::from_millis(2500));
+
+        assert_eq!(res.status.code, 200);
+        assert_eq!(
+            String::from_utf8(res.body).unwrap(),
+            format!(
+                "{{\"status\":\"ok\",\"version\":\"{}\",\"uptime\":2,\
+                 \"base_dir\":true}}\n",
+                VERSION
+            )
+        );
+    }
+
+    #[test]
+    fn base_dir_missing() {
+        let dir = TempDir::new();
+        let conf = Config {
+            base_dir: dir.path().join("deleted"),
+            ..Config::default()
+        };
+        let res = health_check(&conf, Duration::ZERO);
+        let body = String::from_utf8(res.body).unwrap();
+
+        assert_eq!(res.status.code, 503);
+        assert!(body.contains("\"status\":\"unavailable\""));
+        assert!(body.contains("\"base_dir\":false"));
+    }
+
+    #[test]
+    fn uptime_starts_once() {
+        mark_started();
+        let first = uptime();
+        mark_started();
+
+        assert!(uptime() >= first);
+    }
+}
@@ -0,0 +1,58 @@
+/// Encode a string as a quoted JSON string literal.
+///
+/// Quotes, backslashes and control characters are escaped as required by
+/// [`RFC 8259`]. All other characters, including non-ASCII ones, are kept as
+/// is.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::json_string;
+/// assert_eq!(json_string("say \"hi\"\n"), r#""say \"hi\"\n""#);
+/// ```
+///
+/// [`RFC 8259`]: https://tools.ietf.org/html/rfc8259#section-7
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::json_string;
+
+    #[test]
+    fn plain() {
+        assert_eq!(json_string("index.html"), "\"index.html\"");
+        assert_eq!(json_string(""), "\"\"");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(json_string("a\"b\\c"), r#""a\"b\\c""#);
+        assert_eq!(json_string("\t\r\n"), r#""\t\r\n""#);
+        assert_eq!(json_string("\u{1}\u{1f}"), r#""\u0001\u001f""#);
+    }
+
+    #[test]
+    fn unicode() {
+        assert_eq!(json_string("🦀 ✋"), "\"🦀 ✋\"");
+    }
+}
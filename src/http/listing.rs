@@ -0,0 +1,282 @@
+use crate::http::{negotiate, HTTPRequest};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// User agents of command line clients, which get plain text listings
+const TERMINAL_AGENTS: [&str; 3] = ["curl/", "wget/", "httpie/"];
+
+/// Maximum number of listings kept by a [`ListingCache`]
+pub const LISTING_CACHE_CAPACITY: usize = 256;
+
+/// Format of a directory listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListingFormat {
+    /// HTML document with links, for browsers
+    Html,
+    /// One entry per line, for terminals
+    Text,
+}
+
+impl ListingFormat {
+    /// Choose the listing format of a request.
+    ///
+    /// A `format` query parameter of `txt` or `html` is followed first. Else,
+    /// the format is negotiated from the `Accept` header, see [`negotiate`].
+    /// Command line clients such as curl and wget, recognized by their
+    /// `User-Agent`, prefer plain text when both formats are equally
+    /// acceptable, and get it if neither is.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::http::{HTTPRequest, ListingFormat};
+    /// # fn main() -> Result<(), servum::Error> {
+    /// let buffer = b"GET /pages/ HTTP/1.1\r\nUser-Agent: curl/8.4.0\r\n\r\n";
+    /// let req = HTTPRequest::new(buffer)?;
+    /// assert_eq!(ListingFormat::from_request(&req), ListingFormat::Text);
+    ///
+    /// let buffer = b"GET /pages/?format=html HTTP/1.1\r\nUser-Agent: curl/8.4.0\r\n\r\n";
+    /// let req = HTTPRequest::new(buffer)?;
+    /// assert_eq!(ListingFormat::from_request(&req), ListingFormat::Html);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_request(req: &HTTPRequest) -> Self {
+        match req.query_param("format") {
+            Some("txt") | Some("text") => return ListingFormat::Text,
+            Some("html") => return ListingFormat::Html,
+            _ => (),
+        }
+
+        let is_terminal = req.header("User-Agent").is_some_and(|agent| {
+            let agent = agent.to_ascii_lowercase();
+            TERMINAL_AGENTS
+                .iter()
+                .any(|prefix| agent.starts_with(prefix))
+        });
+
+        let formats = match is_terminal {
+            true => [ListingFormat::Text, ListingFormat::Html],
+            false => [ListingFormat::Html, ListingFormat::Text],
+        };
+
+        let accept = req.header("Accept").unwrap_or("*/*");
+        let media =
+            negotiate(accept, &[formats[0].media(), formats[1].media()]);
+        match media {
+            Some(media) if media == formats[1].media() => formats[1],
+            _ => formats[0],
+        }
+    }
+
+    /// Return the media type of this format, without parameters.
+    fn media(&self) -> &'static str {
+        match self {
+            ListingFormat::Html => "text/html",
+            ListingFormat::Text => "text/plain",
+        }
+    }
+
+    /// Return the MIME type of listings in this format.
+    pub fn mime(&self) -> &'static str {
+        match self {
+            ListingFormat::Html => "text/html",
+            ListingFormat::Text => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+/// Listing kept by a [`ListingCache`]
+#[derive(Debug)]
+struct CachedListing {
+    /// When the listing was rendered
+    rendered: Instant,
+    /// Modification times of the listed directories when it was rendered
+    mtimes: Vec<Option<SystemTime>>,
+    body: Vec<u8>,
+}
+
+/// Cache of rendered directory listings, shared by the clones of a
+/// [`Config`](crate::cli::Config), so that large directories aren't read
+/// again on every request.
+///
+/// Listings are keyed by directory and format. They are valid for a given
+/// time to live, and as long as the modification times of the listed
+/// directories are unchanged, i.e. no entry was added, removed or renamed.
+/// Changes to the files themselves, e.g. of their size, only show once the
+/// listing expired. At most [`LISTING_CACHE_CAPACITY`] listings are kept,
+/// the oldest being dropped first.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::{ListingCache, ListingFormat};
+/// use std::{path::Path, time::{Duration, Instant, UNIX_EPOCH}};
+///
+/// let cache = ListingCache::default();
+/// let (dir, ttl, now) = (Path::new("/srv"), Duration::from_secs(5), Instant::now());
+/// let mtimes = vec![Some(UNIX_EPOCH)];
+///
+/// cache.insert(dir, ListingFormat::Text, mtimes.clone(), now, b"a.txt".to_vec());
+/// let hit = cache.get(dir, ListingFormat::Text, &mtimes, now, ttl);
+/// assert_eq!(hit.as_deref(), Some(&b"a.txt"[..]));
+///
+/// assert!(cache.get(dir, ListingFormat::Html, &mtimes, now, ttl).is_none());
+/// assert!(cache.get(dir, ListingFormat::Text, &[None], now, ttl).is_none());
+/// assert!(cache.get(dir, ListingFormat::Text, &mtimes, now + ttl, ttl).is_none());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ListingCache {
+    listings: Arc<Mutex<HashMap<(PathBuf, ListingFormat), CachedListing>>>,
+}
+
+impl ListingCache {
+    /// Return the listing of `dir` in `format`, if it was rendered less than
+    /// `ttl` before `now` and the listed directories still have the
+    /// modification times `mtimes`.
+    pub fn get(
+        &self,
+        dir: &Path,
+        format: ListingFormat,
+        mtimes: &[Option<SystemTime>],
+        now: Instant,
+        ttl: Duration,
+    ) -> Option<Vec<u8>> {
+        let listings = self.listings.lock().unwrap_or_else(|e| e.into_inner());
+        let listing = listings.get(&(dir.to_path_buf(), format))?;
+
+        let fresh = now.saturating_duration_since(listing.rendered) < ttl;
+        match fresh && listing.mtimes == mtimes {
+            true => Some(listing.body.clone()),
+            false => None,
+        }
+    }
+
+    /// Keep the listing of `dir` in `format`, rendered at `now` while the
+    /// listed directories had the modification times `mtimes`.
+    pub fn insert(
+        &self,
+        dir: &Path,
+        format: ListingFormat,
+        mtimes: Vec<Option<SystemTime>>,
+        now: Instant,
+        body: Vec<u8>,
+    ) {
+        let mut listings =
+            self.listings.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (dir.to_path_buf(), format);
+
+        if listings.len() >= LISTING_CACHE_CAPACITY
+            && !listings.contains_key(&key)
+        {
+            let oldest = listings
+                .iter()
+                .min_by_key(|(_, listing)| listing.rendered)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                listings.remove(&oldest);
+            }
+        }
+
+        listings.insert(
+            key,
+            CachedListing {
+                rendered: now,
+                mtimes,
+                body,
+            },
+        );
+    }
+
+    /// Return the number of listings kept.
+    pub fn len(&self) -> usize {
+        self.listings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    /// Whether no listing is kept.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn format(target: &str, headers: &str) -> ListingFormat {
+        let buffer = format!("GET {} HTTP/1.1\r\n{}\r\n", target, headers);
+        let req = HTTPRequest::new(buffer.as_bytes()).unwrap();
+
+        ListingFormat::from_request(&req)
+    }
+
+    #[test]
+    fn precedence() {
+        use ListingFormat::{Html, Text};
+        let curl = "User-Agent: curl/8.4.0\r\n";
+        let accept_text = "Accept: text/plain\r\n";
+        let accept_html = "Accept: text/html,application/xhtml+xml\r\n";
+
+        // Query parameter beats Accept beats User-Agent
+        assert_eq!(format("/?format=txt", accept_html), Text);
+        assert_eq!(
+            format("/?format=html", &[accept_text, curl].concat()),
+            Html
+        );
+        assert_eq!(format("/", &[accept_html, curl].concat()), Html);
+        assert_eq!(format("/", accept_text), Text);
+        assert_eq!(format("/", curl), Text);
+
+        assert_eq!(format("/?format=xml", curl), Text);
+        assert_eq!(format("/", &["Accept: */*\r\n", curl].concat()), Text);
+        assert_eq!(format("/", "User-Agent: Wget/1.21\r\n"), Text);
+        assert_eq!(format("/", "User-Agent: Mozilla/5.0\r\n"), Html);
+        assert_eq!(format("/", ""), Html);
+    }
+
+    #[test]
+    fn accept_quality() {
+        use ListingFormat::{Html, Text};
+        let curl = "User-Agent: curl/8.4.0\r\n";
+
+        let accept = "Accept: text/html;q=0.5, text/plain\r\n";
+        assert_eq!(format("/", accept), Text);
+        let accept = "Accept: text/html, text/plain;q=0.5\r\n";
+        assert_eq!(format("/", &[accept, curl].concat()), Html);
+
+        // Ties and unacceptable formats depend on the User-Agent
+        let accept = "Accept: text/plain, text/html\r\n";
+        assert_eq!(format("/", accept), Html);
+        assert_eq!(format("/", &[accept, curl].concat()), Text);
+        assert_eq!(format("/", "Accept: application/json\r\n"), Html);
+        let accept = "Accept: application/json\r\n";
+        assert_eq!(format("/", &[accept, curl].concat()), Text);
+    }
+
+    #[test]
+    fn cache_capacity() {
+        let cache = ListingCache::default();
+        let start = Instant::now();
+        let ttl = Duration::from_secs(60);
+        let dir = |i: usize| PathBuf::from(format!("/srv/{}", i));
+
+        for i in 0..=LISTING_CACHE_CAPACITY {
+            let now = start + Duration::from_millis(i as u64);
+            cache.insert(&dir(i), ListingFormat::Html, vec![], now, vec![]);
+        }
+
+        // The oldest listing made room for the last one
+        assert_eq!(cache.len(), LISTING_CACHE_CAPACITY);
+        let get = |i| cache.get(&dir(i), ListingFormat::Html, &[], start, ttl);
+        assert!(get(0).is_none());
+        assert!(get(1).is_some());
+        assert!(get(LISTING_CACHE_CAPACITY).is_some());
+    }
+}
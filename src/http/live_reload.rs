@@ -0,0 +1,289 @@
+use std::{
+    io::{self, Write},
+    sync::atomic::{AtomicUsize, Ordering},
+    sync::{Condvar, Mutex, MutexGuard},
+    time::Duration,
+};
+
+/// URL path of the live reload event stream, enabled with `--live-reload`
+pub const EVENTS_PATH: &str = "/_servum/events";
+
+/// Script injected into HTML responses with `--live-reload`, reloading the
+/// page on each `reload` event
+pub const RELOAD_SCRIPT: &str = "<script>new EventSource(\"/_servum/events\")\
+    .addEventListener(\"reload\", () => location.reload());</script>";
+
+/// Header of the event stream response. The body is streamed until the
+/// connection closes, so it has no `Content-Length`.
+pub const EVENT_STREAM_HEADER: &str = "HTTP/1.1 200 OK\r\n\
+    Content-Type: text/event-stream\r\n\
+    Cache-Control: no-store\r\n\
+    Connection: close\r\n\r\n";
+
+/// Interval of the comments sent to idle event streams, to detect closed
+/// connections
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// State of a [`LiveReload`], guarded by its lock
+#[derive(Debug, Default)]
+struct State {
+    /// Number of changes so far
+    generation: u64,
+    /// Paths of the last change
+    paths: Vec<String>,
+    closed: bool,
+}
+
+/// Broadcast of file changes to the live reload event streams.
+///
+/// The file watcher calls [`LiveReload::notify`] on each change, and every
+/// open event stream, served by [`LiveReload::stream`], sends a `reload`
+/// event. Once closed, all streams end.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::LiveReload;
+/// use std::{sync::Arc, thread};
+///
+/// let reload = Arc::new(LiveReload::new());
+/// let streaming = {
+///     let reload = reload.clone();
+///     thread::spawn(move || {
+///         let mut out = Vec::new();
+///         reload.stream(&mut out).unwrap();
+///         out
+///     })
+/// };
+///
+/// while reload.streams() == 0 {
+///     thread::yield_now();
+/// }
+/// reload.notify(&["index.html"]);
+/// reload.close();
+///
+/// let out = String::from_utf8(streaming.join().unwrap()).unwrap();
+/// assert!(out.ends_with("event: reload\ndata: index.html\n\n"));
+/// ```
+#[derive(Debug, Default)]
+pub struct LiveReload {
+    state: Mutex<State>,
+    changed: Condvar,
+    streams: AtomicUsize,
+}
+
+impl LiveReload {
+    /// Create a live reload broadcast without any stream.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Send a `reload` event listing `paths` to all open streams.
+    pub fn notify<S: AsRef<str>>(&self, paths: &[S]) {
+        let mut state = self.lock();
+        state.generation += 1;
+        state.paths = paths.iter().map(|p| p.as_ref().to_string()).collect();
+        drop(state);
+
+        self.changed.notify_all();
+    }
+
+    /// End all streams, now and in the future.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.changed.notify_all();
+    }
+
+    /// Whether [`LiveReload::close`] was called.
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    /// Number of streams currently served.
+    pub fn streams(&self) -> usize {
+        self.streams.load(Ordering::SeqCst)
+    }
+
+    /// Write the body of an event stream to `out`, until the broadcast is
+    /// closed or writing fails, e.g. because the client went away.
+    ///
+    /// The response header, [`EVENT_STREAM_HEADER`], must already be written.
+    /// A comment is sent after a while without events, so that closed
+    /// connections are noticed.
+    pub fn stream<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let seen = self.lock().generation;
+
+        self.streams.fetch_add(1, Ordering::SeqCst);
+        let res = self.write_events(&mut out, seen);
+        self.streams.fetch_sub(1, Ordering::SeqCst);
+
+        res
+    }
+
+    fn write_events<W: Write>(
+        &self,
+        out: &mut W,
+        mut seen: u64,
+    ) -> io::Result<()> {
+        // Reconnect quickly once the server is back after a restart
+        out.write_all(b"retry: 1000\n\n")?;
+        out.flush()?;
+
+        loop {
+            let mut state = self.lock();
+            if state.generation == seen && !state.closed {
+                state = self
+                    .changed
+                    .wait_timeout(state, KEEPALIVE_INTERVAL)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+            }
+
+            // Changes made before closing are still sent
+            let msg = if state.generation != seen {
+                sse_event("reload", &state.paths.join("\n"))
+            } else if state.closed {
+                return Ok(());
+            } else {
+                String::from(": keepalive\n\n")
+            };
+            seen = state.generation;
+            drop(state);
+
+            out.write_all(msg.as_bytes())?;
+            out.flush()?;
+        }
+    }
+}
+
+/// Format a server-sent event with the type `name`.
+///
+/// Each line of `data` is sent as its own `data` field, as required by the
+/// [event stream format]. Empty data is sent as a single empty field, as
+/// browsers don't dispatch events without one.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::sse_event;
+/// assert_eq!(
+///     sse_event("reload", "a.css\nb.js"),
+///     "event: reload\ndata: a.css\ndata: b.js\n\n"
+/// );
+/// ```
+///
+/// [event stream format]: https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation
+pub fn sse_event(name: &str, data: &str) -> String {
+    let mut msg = format!("event: {}\n", name);
+
+    for line in data.split('\n') {
+        msg.push_str("data: ");
+        msg.push_str(line);
+        msg.push('\n');
+    }
+
+    msg.push('\n');
+    msg
+}
+
+/// Insert [`RELOAD_SCRIPT`] into an HTML document, before the last closing
+/// `</body>` tag in any case, or at the end if there is none.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::{inject_reload_script, RELOAD_SCRIPT};
+/// let mut html = b"<body><h1>Hi</h1></BODY></html>".to_vec();
+/// inject_reload_script(&mut html);
+///
+/// assert_eq!(
+///     String::from_utf8(html).unwrap(),
+///     format!("<body><h1>Hi</h1>{}</BODY></html>", RELOAD_SCRIPT)
+/// );
+/// ```
+pub fn inject_reload_script(html: &mut Vec<u8>) {
+    const TAG: &[u8] = b"</body>";
+
+    let pos = html
+        .windows(TAG.len())
+        .rposition(|window| window.eq_ignore_ascii_case(TAG))
+        .unwrap_or(html.len());
+
+    html.splice(pos..pos, RELOAD_SCRIPT.bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{sync::Arc, thread};
+
+    #[test]
+    fn events() {
+        assert_eq!(sse_event("reload", ""), "event: reload\ndata: \n\n");
+        assert_eq!(
+            sse_event("reload", "index.html"),
+            "event: reload\ndata: index.html\n\n"
+        );
+    }
+
+    #[test]
+    fn inject() {
+        let mut html = b"<p>no body</p>".to_vec();
+        inject_reload_script(&mut html);
+        assert_eq!(html, format!("<p>no body</p>{}", RELOAD_SCRIPT).as_bytes());
+
+        let mut html = b"<body></body><!-- </body> --></body>".to_vec();
+        inject_reload_script(&mut html);
+        assert!(html.ends_with(format!("{}</body>", RELOAD_SCRIPT).as_bytes()));
+    }
+
+    #[test]
+    fn stream() {
+        let reload = Arc::new(LiveReload::new());
+        let streaming = {
+            let reload = reload.clone();
+            thread::spawn(move || {
+                let mut out = Vec::new();
+                reload.stream(&mut out).unwrap();
+                out
+            })
+        };
+
+        while reload.streams() == 0 {
+            thread::yield_now();
+        }
+        reload.close();
+
+        assert_eq!(streaming.join().unwrap(), b"retry: 1000\n\n");
+        assert_eq!(reload.streams(), 0);
+
+        // Streams opened after closing end right away
+        assert!(reload.is_closed());
+        reload.stream(io::sink()).unwrap();
+    }
+
+    #[test]
+    fn stream_write_error() {
+        struct Closed;
+
+        impl Write for Closed {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let reload = LiveReload::new();
+        let err = reload.stream(Closed).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(reload.streams(), 0);
+    }
+}
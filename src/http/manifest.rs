@@ -0,0 +1,271 @@
+use crate::cli::Config;
+use crate::files::{self, manifest::Manifest};
+use crate::http::{json_string, HTTPResponse, HTTPStatus, ServedBy};
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// URL path of the manifest endpoint, enabled with `--manifest`
+pub const MANIFEST_PATH: &str = "/_servum/manifest.json";
+
+/// Time for which a [`ManifestCache`] reuses a manifest at most
+pub const MANIFEST_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Manifest rendered last
+#[derive(Debug)]
+struct CachedManifest {
+    base_dir: PathBuf,
+    /// When the manifest was rendered
+    rendered: Instant,
+    /// Walked directories with their modification time, see
+    /// [`Manifest::dirs`]
+    dirs: Vec<(PathBuf, Option<SystemTime>)>,
+    body: Vec<u8>,
+}
+
+/// Cache of the manifest last rendered by [`manifest`], shared by the clones
+/// of a [`Config`], so that the base directory isn't walked on every
+/// request.
+///
+/// The manifest is reused for up to [`MANIFEST_CACHE_TTL`], as long as none
+/// of the walked directories changed, i.e. no file was added, removed or
+/// renamed, which only takes a `stat` of each directory. Changes to the files
+/// themselves, e.g. of their size, only show once the manifest expired, or
+/// right away if the cache is cleared, e.g. by the live reload watcher.
+#[derive(Debug, Clone, Default)]
+pub struct ManifestCache {
+    cached: Arc<Mutex<Option<CachedManifest>>>,
+}
+
+impl ManifestCache {
+    fn lock(&self) -> MutexGuard<'_, Option<CachedManifest>> {
+        self.cached.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Return the manifest of `base_dir` if it was rendered less than
+    /// [`MANIFEST_CACHE_TTL`] before `now` and its directories are unchanged.
+    fn get(&self, base_dir: &Path, now: Instant) -> Option<Vec<u8>> {
+        let cached = self.lock();
+        let cached = cached.as_ref().filter(|c| c.base_dir == base_dir)?;
+
+        let fresh =
+            now.saturating_duration_since(cached.rendered) < MANIFEST_CACHE_TTL;
+        let unchanged = cached.dirs.iter().all(|(dir, modified)| {
+            fs::metadata(dir).and_then(|m| m.modified()).ok() == *modified
+        });
+        match fresh && unchanged {
+            true => Some(cached.body.clone()),
+            false => None,
+        }
+    }
+
+    /// Keep the manifest of `base_dir`, rendered at `now` from `manifest`.
+    fn insert(
+        &self,
+        base_dir: &Path,
+        manifest: Manifest,
+        now: Instant,
+        body: Vec<u8>,
+    ) {
+        *self.lock() = Some(CachedManifest {
+            base_dir: base_dir.to_path_buf(),
+            rendered: now,
+            dirs: manifest.dirs,
+            body,
+        });
+    }
+
+    /// Forget the manifest, e.g. because files changed.
+    pub fn clear(&self) {
+        *self.lock() = None;
+    }
+
+    /// Whether no manifest is kept.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_none()
+    }
+}
+
+/// Respond to a request to the manifest endpoint.
+///
+/// The JSON body lists every file served from the base directory, walked
+/// with [`files::manifest::walk`] up to the `manifest_max_depth` and
+/// `manifest_max_entries` of `config`, with its path, size, modification
+/// time in seconds since the Unix epoch and MIME type, the latter two being
+/// `null` if unknown. `truncated` tells whether files were left out for
+/// reaching a limit. Overlays aren't walked.
+///
+/// The manifest is reused from the [`ManifestCache`] of `config` for a
+/// while, as long as no file was added, removed or renamed, without walking
+/// the base directory again.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::{cli::Config, http::manifest};
+/// use std::path::PathBuf;
+///
+/// let conf = Config {
+///     base_dir: PathBuf::from("example").canonicalize().unwrap(),
+///     ..Config::default()
+/// };
+/// let res = manifest(&conf);
+/// let body = std::str::from_utf8(&res.body).unwrap();
+///
+/// assert_eq!(res.status.code, 200);
+/// assert_eq!(res.mime.unwrap(), "application/json");
+/// assert!(body.contains(r#"{"path":"index.html","size":"#));
+/// assert!(body.contains(r#""mime":"text/html"}"#));
+/// assert!(body.ends_with("\"truncated\":false}\n"));
+/// ```
+pub fn manifest<'a>(config: &Config) -> HTTPResponse<'a> {
+    let base_dir = config.current_base_dir();
+    let now = config.clock.instant();
+    let cache = &config.manifest_cache;
+
+    let body = match cache.get(&base_dir, now) {
+        Some(body) => Ok(body),
+        None => files::manifest::walk(
+            &base_dir,
+            &config.ignore,
+            config.manifest_max_depth,
+            config.manifest_max_entries,
+        )
+        .map(|manifest| {
+            let body = render(&manifest, config).into_bytes();
+            cache.insert(&base_dir, manifest, now, body.clone());
+            body
+        }),
+    };
+
+    let mut res = match body {
+        Ok(body) => HTTPResponse::new(
+            HTTPStatus::from(200),
+            Some(Cow::Borrowed("application/json")),
+            Ok(body),
+        ),
+        Err(e) => HTTPResponse::from(e),
+    };
+    res.headers
+        .push((String::from("Cache-Control"), String::from("no-cache")));
+    res.served_by = ServedBy::Internal("manifest");
+    res
+}
+
+/// Render a manifest as JSON, one object per file.
+fn render(manifest: &Manifest, config: &Config) -> String {
+    let files = manifest
+        .files
+        .iter()
+        .map(|file| {
+            let modified = file
+                .modified
+                .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs().to_string())
+                .unwrap_or_else(|| String::from("null"));
+            let mime = files::mime::guess_mime_type_with(
+                Path::new(&file.path),
+                &config.mime_overrides,
+            )
+            .map(|mime| json_string(&mime))
+            .unwrap_or_else(|| String::from("null"));
+
+            format!(
+                "{{\"path\":{},\"size\":{},\"modified\":{},\"mime\":{}}}",
+                json_string(&file.path),
+                file.len,
+                modified,
+                mime
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!(
+        "{{\"files\":[{}],\"truncated\":{}}}\n",
+        files.join(","),
+        manifest.truncated
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    fn config(dir: &TempDir) -> Config {
+        Config {
+            base_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn render_files() {
+        let dir = TempDir::new();
+        dir.file("photos/a \"1\".jpg", "jpeg");
+        dir.file("data.unknown", "");
+        let mut conf = config(&dir);
+        conf.mime_overrides
+            .insert(String::from("jpg"), String::from("image/x-test"));
+
+        let res = manifest(&conf);
+        let body = String::from_utf8(res.body).unwrap();
+        assert_eq!(res.status.code, 200);
+        assert!(body.starts_with(
+            "{\"files\":[{\"path\":\"data.unknown\",\"size\":0,\"modified\":"
+        ));
+        assert!(body.contains(
+            ",\"mime\":null},{\"path\":\"photos/a \\\"1\\\".jpg\",\"size\":4,"
+        ));
+        assert!(body
+            .ends_with(",\"mime\":\"image/x-test\"}],\"truncated\":false}\n"));
+    }
+
+    #[test]
+    fn cached() {
+        use crate::clock::TestClock;
+
+        let dir = TempDir::new();
+        dir.file("a/a.txt", "a");
+        let clock = Arc::new(TestClock::new(UNIX_EPOCH));
+        let conf = Config {
+            clock: clock.clone(),
+            ..config(&dir)
+        };
+        let body = || String::from_utf8(manifest(&conf).body).unwrap();
+        assert!(conf.manifest_cache.is_empty());
+
+        let first = body();
+        assert!(first.contains("\"path\":\"a/a.txt\",\"size\":1,"));
+        assert!(!conf.manifest_cache.is_empty());
+
+        // A hit doesn't walk the tree, so the new size of a file rewritten in
+        // place doesn't show until the manifest expires
+        dir.file("a/a.txt", "changed");
+        assert_eq!(body(), first);
+        clock.advance(MANIFEST_CACHE_TTL);
+        assert!(body().contains("\"path\":\"a/a.txt\",\"size\":7,"));
+
+        // Added files show right away, as their directory changed
+        dir.file("a/b.txt", "b");
+        assert!(body().contains("\"path\":\"a/b.txt\""));
+
+        conf.manifest_cache.clear();
+        assert!(conf.manifest_cache.is_empty());
+    }
+
+    #[test]
+    fn base_dir_missing() {
+        let dir = TempDir::new();
+        let conf = Config {
+            base_dir: dir.path().join("deleted"),
+            ..Config::default()
+        };
+
+        assert_eq!(manifest(&conf).status.code, 404);
+    }
+}
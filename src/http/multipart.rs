@@ -0,0 +1,354 @@
+use std::io::{self, Read, Write};
+
+/// Size of the chunks read from the body of a multipart request
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Maximum size of the header of a part, so that a client can't make the
+/// server buffer an endless header
+const MAX_PART_HEADER_LEN: usize = 8 * 1024;
+
+/// Return the boundary of a `multipart/form-data` body from the value of its
+/// `Content-Type` header, or [`None`] if it is another type or the boundary
+/// is missing.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::multipart_boundary;
+/// assert_eq!(
+///     multipart_boundary("multipart/form-data; boundary=\"x-42\""),
+///     Some("x-42")
+/// );
+/// assert_eq!(multipart_boundary("text/plain"), None);
+/// ```
+pub fn multipart_boundary(content_type: &str) -> Option<&str> {
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+
+    params
+        .split(';')
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim().trim_matches('"'))
+        .filter(|boundary| !boundary.is_empty() && boundary.len() <= 70)
+}
+
+/// Header of a part of a multipart body
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormPart {
+    /// Name of the form field
+    pub name: Option<String>,
+    /// Name of the uploaded file, for file fields
+    pub filename: Option<String>,
+}
+
+/// Streaming reader of a `multipart/form-data` body, as sent by HTML forms
+/// uploading files.
+///
+/// Parts are read one after the other with [`Multipart::next_part`], and
+/// their contents are copied with [`Multipart::copy_part`], so that files of
+/// any size are uploaded without being held in memory.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::Multipart;
+/// let body = b"--x\r\n\
+///     Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+///     \r\n\
+///     Hello\r\n\
+///     --x--\r\n";
+/// let mut multipart = Multipart::new(&body[..], "x");
+///
+/// let part = multipart.next_part().unwrap().unwrap();
+/// assert_eq!(part.filename.as_deref(), Some("a.txt"));
+///
+/// let mut contents = Vec::new();
+/// multipart.copy_part(&mut contents).unwrap();
+/// assert_eq!(contents, b"Hello");
+///
+/// assert_eq!(multipart.next_part().unwrap(), None);
+/// ```
+#[derive(Debug)]
+pub struct Multipart<R> {
+    reader: R,
+    /// Delimiter preceding each part, i.e. `\r\n--` and the boundary
+    delimiter: Vec<u8>,
+    /// Bytes read but not consumed yet
+    buf: Vec<u8>,
+    /// Whether the contents of the current part are still to be consumed
+    in_part: bool,
+    /// Whether the closing delimiter was read
+    done: bool,
+}
+
+impl<R: Read> Multipart<R> {
+    /// Create a reader of the multipart body `reader`, see
+    /// [`multipart_boundary`].
+    pub fn new(reader: R, boundary: &str) -> Self {
+        Multipart {
+            reader,
+            delimiter: [b"\r\n--", boundary.as_bytes()].concat(),
+            // The first delimiter isn't preceded by a line break
+            buf: b"\r\n".to_vec(),
+            in_part: true,
+            done: false,
+        }
+    }
+
+    /// Advance to the next part and return its header, or [`None`] after the
+    /// last one. The contents of the current part are skipped, unless they
+    /// were copied already.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if the body
+    /// is malformed, or [`io::ErrorKind::UnexpectedEof`] if it ends early.
+    pub fn next_part(&mut self) -> io::Result<Option<FormPart>> {
+        if self.in_part {
+            self.copy_part(&mut io::sink())?;
+        }
+        if self.done {
+            return Ok(None);
+        }
+
+        // The delimiter is followed by `--` after the last part
+        self.fill_to(2)?;
+        if self.buf.starts_with(b"--") {
+            self.done = true;
+            return Ok(None);
+        }
+
+        let end = loop {
+            if let Some(i) = find(&self.buf, b"\r\n\r\n") {
+                break i;
+            }
+            if self.buf.len() > MAX_PART_HEADER_LEN {
+                return Err(invalid("Part header too long"));
+            }
+            if self.fill()? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        };
+
+        let header = String::from_utf8_lossy(&self.buf[..end]).into_owned();
+        self.buf.drain(..end + 4);
+        self.in_part = true;
+
+        Ok(Some(parse_part_header(&header)))
+    }
+
+    /// Copy the contents of the current part to `w`, returning the number of
+    /// bytes copied.
+    pub fn copy_part<W: Write>(&mut self, w: &mut W) -> io::Result<u64> {
+        if !self.in_part {
+            return Ok(0);
+        }
+
+        let mut copied = 0;
+        loop {
+            if let Some(i) = find(&self.buf, &self.delimiter) {
+                w.write_all(&self.buf[..i])?;
+                copied += i as u64;
+                self.buf.drain(..i + self.delimiter.len());
+                self.in_part = false;
+                return Ok(copied);
+            }
+
+            // Keep enough bytes to find a delimiter split across two reads
+            let keep = self.delimiter.len() - 1;
+            if self.buf.len() > keep {
+                let n = self.buf.len() - keep;
+                w.write_all(&self.buf[..n])?;
+                copied += n as u64;
+                self.buf.drain(..n);
+            }
+
+            if self.fill()? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+    }
+
+    /// Read the next chunk of the body into the buffer, returning its size.
+    fn fill(&mut self) -> io::Result<usize> {
+        let len = self.buf.len();
+        self.buf.resize(len + CHUNK_SIZE, 0);
+
+        let res = self.reader.read(&mut self.buf[len..]);
+        self.buf.truncate(len + *res.as_ref().unwrap_or(&0));
+        res
+    }
+
+    /// Read until the buffer holds at least `n` bytes.
+    fn fill_to(&mut self, n: usize) -> io::Result<()> {
+        while self.buf.len() < n {
+            if self.fill()? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse the header of a part, starting with the line break ending its
+/// delimiter, for the `name` and `filename` of its `Content-Disposition`.
+fn parse_part_header(header: &str) -> FormPart {
+    let disposition = header
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| {
+            name.trim().eq_ignore_ascii_case("content-disposition")
+        })
+        .map_or("", |(_, value)| value);
+    let mut part = FormPart::default();
+
+    for (name, value) in params(disposition) {
+        match name.to_ascii_lowercase().as_str() {
+            "name" => part.name = Some(value),
+            "filename" => part.filename = Some(value),
+            _ => (),
+        }
+    }
+
+    part
+}
+
+/// Split the `name=value` parameters of a header value, separated by `;`,
+/// unquoting quoted values, in which `;` may appear.
+fn params(value: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut rest = value;
+
+    while let Some((_, after)) = rest.split_once(';') {
+        let (name, after) = match after.split_once('=') {
+            Some((name, after)) => (name.trim(), after.trim_start()),
+            None => break,
+        };
+
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                (&quoted[..end], quoted.get(end + 1..).unwrap_or(""))
+            }
+            None => {
+                let end = after.find(';').unwrap_or(after.len());
+                (after[..end].trim_end(), &after[end..])
+            }
+        };
+
+        params.push((name.to_string(), value.to_string()));
+        rest = after;
+    }
+
+    params
+}
+
+/// Return the index of the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Create an [`io::ErrorKind::InvalidData`] error.
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Reader returning at most `n` bytes per read, to split the body at
+    /// every possible point
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.1.min(buf.len()).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    const BODY: &[u8] = b"preamble\r\n\
+        --b0undary\r\n\
+        Content-Disposition: form-data; name=\"note\"\r\n\
+        \r\n\
+        hi\r\n\
+        --b0undary\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"a;b.bin\"\r\n\
+        Content-Type: application/octet-stream\r\n\
+        \r\n\
+        \0\r\n--b0und\r\n\r\n\
+        --b0undary--\r\n";
+
+    #[test]
+    fn parts() {
+        for chunk in [1, 2, 7, 64, BODY.len()] {
+            let mut multipart =
+                Multipart::new(Trickle(BODY, chunk), "b0undary");
+
+            let part = multipart.next_part().unwrap().unwrap();
+            assert_eq!(part.name.as_deref(), Some("note"));
+            assert_eq!(part.filename, None);
+
+            let part = multipart.next_part().unwrap().unwrap();
+            assert_eq!(part.filename.as_deref(), Some("a;b.bin"));
+            let mut contents = Vec::new();
+            multipart.copy_part(&mut contents).unwrap();
+            assert_eq!(contents, b"\0\r\n--b0und\r\n");
+
+            assert_eq!(multipart.next_part().unwrap(), None);
+            assert_eq!(multipart.next_part().unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let body = &BODY[..BODY.len() - 20];
+        let mut multipart = Multipart::new(body, "b0undary");
+
+        multipart.next_part().unwrap();
+        multipart.next_part().unwrap();
+        let err = multipart.copy_part(&mut io::sink()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut multipart = Multipart::new(BODY, "other");
+        assert!(multipart.next_part().is_err());
+    }
+
+    #[test]
+    fn boundaries() {
+        let boundary = multipart_boundary;
+
+        assert_eq!(boundary("multipart/form-data; boundary=abc"), Some("abc"));
+        assert_eq!(
+            boundary("Multipart/Form-Data;charset=utf-8; BOUNDARY=\"a b\""),
+            Some("a b")
+        );
+        assert_eq!(boundary("multipart/form-data"), None);
+        assert_eq!(boundary("multipart/form-data; boundary="), None);
+        assert_eq!(boundary("multipart/mixed; boundary=abc"), None);
+    }
+
+    #[test]
+    fn part_header() {
+        let header = "\r\ncontent-disposition: form-data; filename=\"x.txt\"; \
+                      name=file";
+
+        assert_eq!(
+            parse_part_header(header),
+            FormPart {
+                name: Some(String::from("file")),
+                filename: Some(String::from("x.txt")),
+            }
+        );
+        assert_eq!(
+            parse_part_header("\r\nContent-Type: text/plain"),
+            FormPart::default()
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// Maximum number of paths kept by a [`NotFoundCache`]
+pub const NOT_FOUND_CACHE_CAPACITY: usize = 256;
+
+/// Time for which a [`NotFoundCache`] remembers a missing path
+pub const NOT_FOUND_CACHE_TTL: Duration = Duration::from_secs(1);
+
+/// Cache of request paths recently answered with `404 Not Found`, shared by
+/// the clones of a [`Config`](crate::cli::Config), so that clients polling a
+/// missing path don't make servum look it up on every request.
+///
+/// Paths are remembered for [`NOT_FOUND_CACHE_TTL`], so files created in the
+/// meantime show once the entry expired, or right away if the cache is
+/// cleared, e.g. by the live reload watcher. At most
+/// [`NOT_FOUND_CACHE_CAPACITY`] paths are kept, expired ones being dropped
+/// first, then those expiring soonest.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::{NotFoundCache, NOT_FOUND_CACHE_TTL};
+/// use std::{path::Path, time::Instant};
+///
+/// let cache = NotFoundCache::default();
+/// let (path, now) = (Path::new("/srv/api/status"), Instant::now());
+///
+/// cache.insert(path, now);
+/// assert!(cache.contains(path, now));
+/// assert!(!cache.contains(path, now + NOT_FOUND_CACHE_TTL));
+///
+/// cache.clear();
+/// assert!(!cache.contains(path, now));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NotFoundCache {
+    /// Expiry of each missing path
+    paths: Arc<Mutex<HashMap<PathBuf, Instant>>>,
+}
+
+impl NotFoundCache {
+    fn lock(&self) -> MutexGuard<'_, HashMap<PathBuf, Instant>> {
+        self.paths.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether `path` was found missing less than [`NOT_FOUND_CACHE_TTL`]
+    /// before `now`.
+    pub fn contains(&self, path: &Path, now: Instant) -> bool {
+        self.lock().get(path).is_some_and(|&expiry| now < expiry)
+    }
+
+    /// Remember that `path` was found missing at `now`.
+    pub fn insert(&self, path: &Path, now: Instant) {
+        let mut paths = self.lock();
+
+        if paths.len() >= NOT_FOUND_CACHE_CAPACITY && !paths.contains_key(path)
+        {
+            paths.retain(|_, expiry| now < *expiry);
+        }
+        if paths.len() >= NOT_FOUND_CACHE_CAPACITY {
+            let soonest = paths
+                .iter()
+                .min_by_key(|(_, expiry)| **expiry)
+                .map(|(path, _)| path.clone());
+            if let Some(soonest) = soonest {
+                paths.remove(&soonest);
+            }
+        }
+
+        paths.insert(path.to_path_buf(), now + NOT_FOUND_CACHE_TTL);
+    }
+
+    /// Forget all paths, e.g. because files changed.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Return the number of paths kept, including expired ones.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no path is kept.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn capacity() {
+        let cache = NotFoundCache::default();
+        let start = Instant::now();
+        let path = |i: usize| PathBuf::from(format!("/srv/{}", i));
+
+        for i in 0..=NOT_FOUND_CACHE_CAPACITY {
+            cache.insert(&path(i), start + Duration::from_millis(i as u64));
+        }
+
+        // The path expiring soonest made room for the last one
+        assert_eq!(cache.len(), NOT_FOUND_CACHE_CAPACITY);
+        assert!(!cache.contains(&path(0), start));
+        assert!(cache.contains(&path(1), start));
+        assert!(cache.contains(&path(NOT_FOUND_CACHE_CAPACITY), start));
+
+        // Expired paths are dropped all at once
+        let later = start + 2 * NOT_FOUND_CACHE_TTL;
+        cache.insert(Path::new("/srv/new"), later);
+        assert_eq!(cache.len(), 1);
+    }
+}
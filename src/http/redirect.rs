@@ -0,0 +1,348 @@
+use crate::http::{
+    strip_path_prefix, validate_header, HTTPRequest, HTTPResponse, HTTPStatus,
+    ServedBy,
+};
+use std::net::IpAddr;
+
+/// Status codes allowed for redirect rules
+const REDIRECT_CODES: [usize; 5] = [301, 302, 303, 307, 308];
+
+/// Default status code of redirect rules
+const DEFAULT_REDIRECT_CODE: usize = 302;
+
+/// A single redirect rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RedirectRule {
+    /// URL path to match, without the trailing `*` of prefix rules
+    from: String,
+    is_prefix: bool,
+    to: String,
+    code: usize,
+}
+
+/// Ordered list of redirects by URL path.
+///
+/// Rules are given as `FROM=TO[:CODE]` with the `--redirect` flag. `FROM` is
+/// either an exact URL path, or a prefix of it when it ends with `*`. Prefixes
+/// match whole segments, see [`strip_path_prefix`], so `/go*` matches `/go`
+/// and `/go/x` but not `/gopher`, and `/docs/*` matches below `/docs/` only.
+/// `TO` is either a path on the same server or an absolute `http` or `https`
+/// URL, and is sent as is. With a prefix rule, a trailing `*` in `TO` is
+/// replaced by the rest of the matched path. `CODE` is one of `301`, `302`, `303`,
+/// `307` or `308`, and defaults to `302`. The first matching rule wins.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::RedirectRules;
+/// let mut rules = RedirectRules::default();
+/// rules.add("/old-blog=/blog").unwrap();
+/// rules.add("/docs/*=https://docs.example.com/*:301").unwrap();
+///
+/// assert_eq!(rules.lookup("/old-blog"), Some((302, String::from("/blog"))));
+/// assert_eq!(
+///     rules.lookup("/docs/setup.html"),
+///     Some((301, String::from("https://docs.example.com/setup.html")))
+/// );
+/// assert_eq!(rules.lookup("/old-blog/post"), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedirectRules {
+    rules: Vec<RedirectRule>,
+}
+
+impl RedirectRules {
+    /// Add a rule of the form `FROM=TO[:CODE]`.
+    ///
+    /// Returns [`None`] if the rule has no `=`, `FROM` isn't a path, `TO`
+    /// isn't a path or an `http` or `https` URL, or `CODE` isn't a redirect
+    /// status code.
+    pub fn add(&mut self, rule: &str) -> Option<()> {
+        let (from, to) = rule.split_once('=')?;
+        let (from, to) = (from.trim(), to.trim());
+
+        let (to, code) = match to.rsplit_once(':') {
+            Some((to, code)) if code.len() == 3 => (to, code.parse().ok()?),
+            _ => (to, DEFAULT_REDIRECT_CODE),
+        };
+        let (from, is_prefix) = match from.strip_suffix('*') {
+            Some(prefix) => (prefix, true),
+            None => (from, false),
+        };
+
+        let is_target = to.starts_with('/')
+            || to.starts_with("http://")
+            || to.starts_with("https://");
+        let is_valid = from.starts_with('/')
+            && is_target
+            && !to.contains(char::is_whitespace)
+            && validate_header("Location", to).is_ok()
+            && REDIRECT_CODES.contains(&code);
+
+        if !is_valid {
+            return None;
+        }
+
+        self.rules.push(RedirectRule {
+            from: from.to_string(),
+            is_prefix,
+            to: to.to_string(),
+            code,
+        });
+        Some(())
+    }
+
+    /// Whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Iterate over all rules as `FROM=TO:CODE`, in order.
+    pub fn rules(&self) -> impl Iterator<Item = String> + '_ {
+        self.rules.iter().map(|rule| {
+            let star = if rule.is_prefix { "*" } else { "" };
+            format!("{}{}={}:{}", rule.from, star, rule.to, rule.code)
+        })
+    }
+
+    /// Return the status code and `Location` of the first rule matching a URL
+    /// path, if any.
+    pub fn lookup(&self, path: &str) -> Option<(usize, String)> {
+        self.rules.iter().find_map(|rule| {
+            let location = match rule.is_prefix {
+                true => {
+                    let rest = strip_path_prefix(path, &rule.from)?;
+                    let rest = match rule.from.ends_with('/') {
+                        true => rest.strip_prefix('/')?,
+                        false => rest,
+                    };
+                    match rule.to.strip_suffix('*') {
+                        Some(to) => format!("{}{}", to, rest),
+                        None => rule.to.clone(),
+                    }
+                }
+                false if path == rule.from => rule.to.clone(),
+                false => return None,
+            };
+
+            Some((rule.code, location))
+        })
+    }
+}
+
+/// Answer a request matching one of the user's redirect rules, see
+/// [`RedirectRules`], or return [`None`] if none matches.
+pub(crate) fn redirect<'a>(
+    req: &HTTPRequest,
+    rules: &RedirectRules,
+) -> Option<HTTPResponse<'a>> {
+    let (code, location) = rules.lookup(req.filepath.to_str()?)?;
+
+    let mut res =
+        HTTPResponse::new(HTTPStatus::from(code), None, Ok(Vec::new()));
+    res.headers.push((String::from("Location"), location));
+    res.served_by = ServedBy::Internal("redirect");
+    Some(res)
+}
+
+/// Answer a plain HTTP request with `301 Moved Permanently` to the same path
+/// over HTTPS, on `port`, as done by the `--redirect-http` listener.
+///
+/// The host of the `Location` is taken from the `Host` header, without its
+/// port. If the request has none, or an invalid one, `address` is used
+/// instead, or `localhost` if it is a wildcard address. The port is left out
+/// if it is `443`, the default port of HTTPS.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::{https_redirect, HTTPRequest};
+/// # fn main() -> Result<(), servum::Error> {
+/// let buffer = b"GET /docs/?page=2 HTTP/1.1\r\nHost: example.com:8080\r\n\r\n";
+/// let req = HTTPRequest::new(buffer)?;
+/// let res = https_redirect(&req, "0.0.0.0", 8443);
+///
+/// assert_eq!(res.status.code, 301);
+/// assert_eq!(
+///     res.headers[0],
+///     (
+///         String::from("Location"),
+///         String::from("https://example.com:8443/docs/?page=2")
+///     )
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn https_redirect<'a>(
+    req: &HTTPRequest,
+    address: &str,
+    port: u16,
+) -> HTTPResponse<'a> {
+    let host = match req.header("Host").and_then(host_name) {
+        Some(host) => host.to_string(),
+        None => fallback_host(address),
+    };
+    // Requests in absolute form or for `*` are sent to the root instead
+    let target = req.target();
+    let path = match target.starts_with('/') {
+        true => &target,
+        false => "/",
+    };
+    let location = match port {
+        443 => format!("https://{}{}", host, path),
+        port => format!("https://{}:{}{}", host, port, path),
+    };
+
+    let mut res =
+        HTTPResponse::new(HTTPStatus::from(301), None, Ok(Vec::new()));
+    res.headers.push((String::from("Location"), location));
+    res.served_by = ServedBy::Internal("redirect");
+    res
+}
+
+/// Return the host of a `Host` header, leaving out its port, or [`None`] if
+/// it isn't a plain host name or address.
+fn host_name(host: &str) -> Option<&str> {
+    let name = match host.strip_prefix('[') {
+        Some(rest) => &host[..rest.find(']')? + 2],
+        None => host.split(':').next()?,
+    };
+    let valid = name.bytes().all(|b| {
+        b.is_ascii_alphanumeric()
+            || matches!(b, b'-' | b'.' | b':' | b'[' | b']')
+    });
+
+    match valid && !name.is_empty() {
+        true => Some(name),
+        false => None,
+    }
+}
+
+/// Return the host to redirect to when the client didn't send one: the
+/// address the server listens on, enclosed in brackets if it is an IPv6
+/// address, or `localhost` for a wildcard address.
+fn fallback_host(address: &str) -> String {
+    match address.parse::<IpAddr>() {
+        Ok(ip) if ip.is_unspecified() => String::from("localhost"),
+        Ok(IpAddr::V6(ip)) => format!("[{}]", ip),
+        _ => address.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn location(req: &[u8], address: &str, port: u16) -> String {
+        let req = HTTPRequest::new(req).unwrap();
+        let res = https_redirect(&req, address, port);
+
+        assert_eq!(res.status.code, 301);
+        assert!(res.body.is_empty());
+        res.headers[0].1.clone()
+    }
+
+    #[test]
+    fn redirect_location() {
+        assert_eq!(
+            location(
+                b"GET /a.html HTTP/1.1\r\nHost: site.test\r\n\r\n",
+                "::",
+                443
+            ),
+            "https://site.test/a.html"
+        );
+        assert_eq!(
+            location(
+                b"HEAD / HTTP/1.1\r\nhost: [::1]:8080\r\n\r\n",
+                "::",
+                8443
+            ),
+            "https://[::1]:8443/"
+        );
+        assert_eq!(
+            location(b"GET http://a.test/ HTTP/1.1\r\n\r\n", "0.0.0.0", 8443),
+            "https://localhost:8443/"
+        );
+        assert_eq!(
+            location(b"GET /x HTTP/1.1\r\n\r\n", "::1", 8443),
+            "https://[::1]:8443/x"
+        );
+        assert_eq!(
+            location(b"GET /x HTTP/1.1\r\nHost: a/b\r\n\r\n", "10.0.0.2", 8443),
+            "https://10.0.0.2:8443/x"
+        );
+    }
+
+    #[test]
+    fn redirect_rules() {
+        let mut rules = RedirectRules::default();
+        rules.add("/a.html=/b.html").unwrap();
+        rules.add("/app/*=/v2/*:308").unwrap();
+        rules.add("/app/legacy=/never").unwrap();
+        rules.add("/go*=https://example.com:8443/go").unwrap();
+
+        let lookup = |path| rules.lookup(path);
+        assert_eq!(lookup("/a.html"), Some((302, String::from("/b.html"))));
+        assert_eq!(lookup("/a.html/x"), None);
+        assert_eq!(lookup("/app/"), Some((308, String::from("/v2/"))));
+        assert_eq!(lookup("/app/x/y"), Some((308, String::from("/v2/x/y"))));
+        assert_eq!(lookup("/app/legacy"), Some((308, "/v2/legacy".into())));
+        assert_eq!(lookup("/app"), None);
+        assert_eq!(lookup("/app/../secret"), None);
+        assert_eq!(lookup("/%61pp/x"), Some((308, String::from("/v2/x"))));
+        assert_eq!(
+            lookup("/go/x"),
+            Some((302, String::from("https://example.com:8443/go")))
+        );
+        assert_eq!(lookup("/go"), lookup("/go/x"));
+        assert_eq!(lookup("/gopher"), None);
+
+        assert_eq!(
+            rules.rules().collect::<Vec<_>>()[..2],
+            ["/a.html=/b.html:302", "/app/*=/v2/*:308"]
+        );
+    }
+
+    #[test]
+    fn invalid_redirect_rules() {
+        let mut rules = RedirectRules::default();
+
+        for rule in [
+            "/a",
+            "a=/b",
+            "/a=b",
+            "/a=ftp://example.com",
+            "/a=/b:200",
+            "/a=/b:404",
+            "/a=/b:abc",
+            "/a=/b c",
+            "/a=/b\x07",
+            "/a=",
+        ] {
+            assert!(rules.add(rule).is_none(), "{}", rule);
+        }
+        assert!(rules.is_empty());
+
+        // Ports aren't taken for status codes
+        rules.add("/a=http://localhost:8080").unwrap();
+        assert_eq!(
+            rules.lookup("/a"),
+            Some((302, String::from("http://localhost:8080")))
+        );
+    }
+
+    #[test]
+    fn host_names() {
+        assert_eq!(host_name("example.com"), Some("example.com"));
+        assert_eq!(host_name("example.com:80"), Some("example.com"));
+        assert_eq!(host_name("127.0.0.1:8080"), Some("127.0.0.1"));
+        assert_eq!(host_name("[::1]:8080"), Some("[::1]"));
+        assert_eq!(host_name("[::1]"), Some("[::1]"));
+        assert_eq!(host_name("[::1"), None);
+        assert_eq!(host_name(""), None);
+        assert_eq!(host_name(":80"), None);
+        assert_eq!(host_name("evil.test/path"), None);
+        assert_eq!(host_name("a b"), None);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::http::HTTPRequestError;
+use crate::http::{response::Preview, HTTPRequestError};
 use std::path::Path;
 use std::{fmt, str};
 
@@ -7,24 +7,31 @@ use std::{fmt, str};
 /// This struct is used to pass HTTP requests to the according request handling
 /// functions (mainly [`handle_connection`]). It extracts information from a
 /// [`std::net::TcpStream`] buffer. Basic validation is performed, with possible
-/// errors being returned as [`HTTPRequestError`] variant.
+/// errors being returned as [`HTTPRequestError`] variant. The query string of
+/// the request target, if any, is split off the `filepath` into `query`.
 ///
 /// # Example
 ///
 /// ```rust
 /// # use servum::http::{HTTPRequest};
+/// # fn main() -> Result<(), servum::Error> {
 /// let buffer = b"GET / HTTP/1.1";
-/// let req = HTTPRequest::new(buffer).unwrap();
+/// let req = HTTPRequest::new(buffer)?;
 ///
 /// assert_eq!(req.method, "GET");
 /// assert_eq!(req.filepath.to_str().unwrap(), "/");
+/// # Ok(())
+/// # }
 /// ```
 ///
 /// [`handle_connection`]: crate::http::handle_connection
-#[derive(Debug)]
 pub struct HTTPRequest<'a> {
     pub method: &'a str,
     pub filepath: &'a Path,
+    /// Query string following the `?` of the request target, if any
+    pub query: Option<&'a str>,
+    /// Header lines following the request line
+    head: &'a str,
 }
 
 impl<'a> HTTPRequest<'a> {
@@ -37,11 +44,14 @@ impl<'a> HTTPRequest<'a> {
     ///
     /// ```rust
     /// # use servum::http::{HTTPRequest};
+    /// # fn main() -> Result<(), servum::Error> {
     /// let buffer = b"HEAD /static/logo.svg HTTP/1.1";
-    /// let req = HTTPRequest::new(buffer).unwrap();
+    /// let req = HTTPRequest::new(buffer)?;
     ///
     /// assert_eq!(req.method, "HEAD");
     /// assert_eq!(req.filepath.to_str().unwrap(), "/static/logo.svg");
+    /// # Ok(())
+    /// # }
     /// ```
     ///
     /// Another example, in which the request is empty and thus cannot be
@@ -56,23 +66,108 @@ impl<'a> HTTPRequest<'a> {
     /// assert!(matches!(req.unwrap_err(), HTTPRequestError::NoMethod));
     /// ```
     pub fn new(buffer: &'a [u8]) -> Result<Self, HTTPRequestError> {
-        let mut first_line = str::from_utf8(buffer)?.split_ascii_whitespace();
+        let text = str::from_utf8(buffer)?;
+        let mut first_line = text.split_ascii_whitespace();
 
         let method = first_line.next().ok_or(HTTPRequestError::NoMethod)?;
-        let filepath =
-            Path::new(first_line.next().ok_or(HTTPRequestError::NoPath)?);
+        let target = first_line.next().ok_or(HTTPRequestError::NoPath)?;
+        let (filepath, query) = match target.split_once('?') {
+            Some((path, query)) => (Path::new(path), Some(query)),
+            None => (Path::new(target), None),
+        };
 
         if first_line.next().is_none() {
             return Err(HTTPRequestError::NoPath);
         }
 
-        Ok(Self { method, filepath })
+        let head = text.split_once('\n').map_or("", |(_, head)| head);
+
+        Ok(Self {
+            method,
+            filepath,
+            query,
+            head,
+        })
+    }
+
+    /// Return the value of the first query parameter called `name`, or an
+    /// empty string if it has no value. Values are not percent-decoded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::http::{HTTPRequest};
+    /// # fn main() -> Result<(), servum::Error> {
+    /// let req = HTTPRequest::new(b"GET /pages/?sort=name&raw HTTP/1.1")?;
+    ///
+    /// assert_eq!(req.filepath.to_str().unwrap(), "/pages/");
+    /// assert_eq!(req.query_param("sort"), Some("name"));
+    /// assert_eq!(req.query_param("raw"), Some(""));
+    /// assert_eq!(req.query_param("page"), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_param(&self, name: &str) -> Option<&'a str> {
+        self.query?
+            .split('&')
+            .map(|param| param.split_once('=').unwrap_or((param, "")))
+            .find(|(param, _)| *param == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Return the request target as sent, i.e. the `filepath` followed by
+    /// the query string, if any.
+    pub fn target(&self) -> String {
+        match self.query {
+            Some(query) => format!("{}?{}", self.filepath.display(), query),
+            None => self.filepath.display().to_string(),
+        }
+    }
+
+    /// Return the value of the first header field called `name`, ignoring
+    /// case, with surrounding whitespace removed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::http::{HTTPRequest};
+    /// # fn main() -> Result<(), servum::Error> {
+    /// let buffer = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+    /// let req = HTTPRequest::new(buffer)?;
+    ///
+    /// assert_eq!(req.header("host"), Some("example.com"));
+    /// assert_eq!(req.header("Accept"), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn header(&self, name: &str) -> Option<&'a str> {
+        self.head
+            .lines()
+            .take_while(|line| !line.trim().is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .find(|(field, _)| field.trim().eq_ignore_ascii_case(name))
+            // The unused end of the read buffer is zeroed
+            .map(|(_, value)| {
+                value.trim_matches(|c: char| c.is_whitespace() || c == '\0')
+            })
     }
 }
 
 impl fmt::Display for HTTPRequest<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {} HTTP/1.1", self.method, self.filepath.display())
+        write!(f, "{} {} HTTP/1.1", self.method, self.target())
+    }
+}
+
+// Header lines are shown like bodies of responses, see `HTTPResponse`
+impl fmt::Debug for HTTPRequest<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HTTPRequest")
+            .field("method", &self.method)
+            .field("filepath", &self.filepath)
+            .field("query", &self.query)
+            .field("head", &Preview(self.head.as_bytes()))
+            .finish()
     }
 }
 
@@ -88,6 +183,59 @@ mod test {
         assert_eq!(req.filepath.to_str().unwrap(), "/index.html");
     }
 
+    #[test]
+    fn query() {
+        let req = HTTPRequest::new(b"GET /a.html?x=1&y&x=2 HTTP/1.1").unwrap();
+
+        assert_eq!(req.filepath.to_str().unwrap(), "/a.html");
+        assert_eq!(req.query, Some("x=1&y&x=2"));
+        assert_eq!(req.query_param("x"), Some("1"));
+        assert_eq!(req.query_param("y"), Some(""));
+        assert_eq!(req.query_param("z"), None);
+        assert_eq!(req.target(), "/a.html?x=1&y&x=2");
+        assert_eq!(req.to_string(), "GET /a.html?x=1&y&x=2 HTTP/1.1");
+
+        let req = HTTPRequest::new(b"GET /a.html? HTTP/1.1").unwrap();
+        assert_eq!(req.query, Some(""));
+        assert_eq!(req.query_param("x"), None);
+
+        let req = HTTPRequest::new(b"GET /a.html HTTP/1.1").unwrap();
+        assert_eq!(req.query, None);
+        assert_eq!(req.target(), "/a.html");
+    }
+
+    #[test]
+    fn headers() {
+        let mut buffer = [0; 64];
+        let req = b"GET / HTTP/1.1\r\nX-Id: a\r\nx-id: b\r\nAccept: */*";
+        buffer[..req.len()].copy_from_slice(req);
+        let req = HTTPRequest::new(&buffer).unwrap();
+
+        assert_eq!(req.header("X-ID"), Some("a"));
+        assert_eq!(req.header("Accept"), Some("*/*"));
+
+        let req = HTTPRequest::new(b"GET / HTTP/1.1\r\n\r\nX-Id: a").unwrap();
+        assert_eq!(req.header("X-Id"), None);
+        assert_eq!(
+            HTTPRequest::new(b"GET / HTTP/1.1").unwrap().header(""),
+            None
+        );
+    }
+
+    #[test]
+    fn debug() {
+        let req = HTTPRequest::new(b"GET /a?b HTTP/1.1\r\nX-Id: 1").unwrap();
+        assert_eq!(
+            format!("{:?}", req),
+            "HTTPRequest { method: \"GET\", filepath: \"/a\", \
+             query: Some(\"b\"), head: \"X-Id: 1\" }"
+        );
+
+        let buffer = format!("GET / HTTP/1.1\r\nX-Pad: {}", "a".repeat(2000));
+        let req = HTTPRequest::new(buffer.as_bytes()).unwrap();
+        assert!(format!("{:?}", req).ends_with("head: <2007 bytes> }"));
+    }
+
     #[test]
     fn no_method() {
         let req = HTTPRequest::new(b"");
@@ -0,0 +1,140 @@
+use crate::http::HTTPRequest;
+use std::{
+    ptr,
+    sync::atomic::{AtomicU32, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Header carrying the id of a request, sent with every response
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Maximum length of a request id sent by a client to be echoed
+const MAX_CLIENT_ID_LEN: usize = 64;
+
+/// Number of ids generated so far
+static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Generate a short id for a request, made of 12 hexadecimal digits.
+///
+/// The last 8 digits are a counter, so ids are unique within a run of the
+/// server until it wraps around after 2³² requests. The first 4 digits mix
+/// the address of a stack variable and the current time, so that ids of
+/// different runs are unlikely to repeat, without a random number generator.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::generate_request_id;
+/// let (a, b) = (generate_request_id(), generate_request_id());
+///
+/// assert_eq!(a.len(), 12);
+/// assert_ne!(a, b);
+/// ```
+pub fn generate_request_id() -> String {
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let marker = 0u8;
+    let addr = ptr::addr_of!(marker) as usize as u64;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.subsec_nanos())
+        .unwrap_or(0);
+
+    format!("{:04x}{:08x}", mix(addr ^ u64::from(nanos)) as u16, count)
+}
+
+/// Return the id of a request: the `X-Request-Id` sent by the client, or a
+/// new one from [`generate_request_id`].
+///
+/// Ids sent by the client are only echoed if they are made of at most 64
+/// visible ASCII characters.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::{request_id, HTTPRequest};
+/// # fn main() -> Result<(), servum::Error> {
+/// let buffer = b"GET / HTTP/1.1\r\nX-Request-Id: abc-123\r\n\r\n";
+/// let req = HTTPRequest::new(buffer)?;
+///
+/// assert_eq!(request_id(&req), "abc-123");
+/// # Ok(())
+/// # }
+/// ```
+pub fn request_id(req: &HTTPRequest) -> String {
+    match req.header(REQUEST_ID_HEADER) {
+        Some(id) if is_valid_client_id(id) => id.to_string(),
+        _ => generate_request_id(),
+    }
+}
+
+/// Whether an id sent by a client can be echoed in the response header and
+/// written to the log as is.
+fn is_valid_client_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_CLIENT_ID_LEN
+        && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// Scramble the bits of `x`, using the finalizer of MurmurHash3.
+fn mix(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    x ^ (x >> 33)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{collections::HashSet, thread};
+
+    #[test]
+    fn unique_ids() {
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                thread::spawn(|| {
+                    (0..1000).map(|_| generate_request_id()).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let ids: HashSet<_> = threads
+            .into_iter()
+            .flat_map(|t| t.join().unwrap())
+            .collect();
+
+        assert_eq!(ids.len(), 4000);
+        assert!(ids
+            .iter()
+            .all(|id| id.len() == 12
+                && id.bytes().all(|b| b.is_ascii_hexdigit())));
+    }
+
+    #[test]
+    fn echo_client_id() {
+        let req = |buffer: &'static [u8]| HTTPRequest::new(buffer).unwrap();
+
+        assert_eq!(
+            request_id(&req(b"GET / HTTP/1.1\r\nx-request-id:  f00\r\n\r\n")),
+            "f00"
+        );
+        assert_eq!(request_id(&req(b"GET / HTTP/1.1\r\n\r\n")).len(), 12);
+
+        let long = format!(
+            "GET / HTTP/1.1\r\nX-Request-Id: {}\r\n\r\n",
+            "a".repeat(65)
+        );
+        let req = HTTPRequest::new(long.as_bytes()).unwrap();
+        assert_eq!(request_id(&req).len(), 12);
+    }
+
+    #[test]
+    fn client_ids() {
+        assert!(is_valid_client_id("req-42_abc.DEF"));
+        assert!(!is_valid_client_id(""));
+        assert!(!is_valid_client_id("two words"));
+        assert!(!is_valid_client_id("é"));
+        assert!(!is_valid_client_id(&"a".repeat(65)));
+    }
+}
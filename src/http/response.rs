@@ -1,5 +1,29 @@
-use crate::http::HTTPStatus;
-use std::{fmt, io, str};
+use crate::http::{HTTPStatus, HeaderError, ResolveTrace, ServedBy};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt,
+    io::{self, Write},
+    str,
+};
+
+/// Maximum length of the bodies shown when formatting responses, see
+/// [`HTTPResponse`]
+pub const PREVIEW_LIMIT: usize = 1024;
+
+/// Header fields written by [`HTTPResponse::header`] itself, which can't be
+/// added, so that they always agree with the response
+const RESERVED_HEADERS: [&str; 3] =
+    ["Content-Length", "Transfer-Encoding", "Connection"];
+
+/// Largest body copied after the header into the scratch buffer, to be
+/// written in a single call. Larger bodies are written on their own.
+const INLINE_BODY_LEN: usize = 16 * 1024;
+
+thread_local! {
+    /// Scratch buffer of each worker thread, reused for every response
+    static SCRATCH: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
 
 /// A struct representing an HTTP response.
 ///
@@ -9,6 +33,20 @@ use std::{fmt, io, str};
 ///
 /// HTTPResponse supports conversion from [`io::Error`] and [`HTTPStatus`].
 ///
+/// The MIME type is stored as a [`Cow`], so that both the static built-in MIME
+/// types and owned, user-configured overrides can be used without copying.
+/// Additional header fields, such as `Cache-Control`, can be added to
+/// `headers` as name-value pairs, preferably with
+/// [`HTTPResponse::add_header`], which checks them. Fields pushed directly
+/// are checked when writing the header, see [`HTTPResponse::header`].
+/// `served_by` tells the request log which part of the server answered, the
+/// base directory by default, and `trace` how the request path was resolved,
+/// if it was recorded.
+///
+/// Responses to `HEAD` requests are marked with [`HTTPResponse::respond_to`],
+/// so that whatever their status, they are written with the header, and the
+/// `Content-Length`, of the body they would have, but without the body.
+///
 /// # Example
 ///
 /// ```rust
@@ -17,7 +55,7 @@ use std::{fmt, io, str};
 ///
 /// let resp = HTTPResponse::new(
 ///     HTTPStatus::from(200),
-///     Some("text/plain"),
+///     Some("text/plain".into()),
 ///     io::Result::Ok(b"Hello World".to_vec()),
 /// );
 ///
@@ -45,18 +83,30 @@ use std::{fmt, io, str};
 /// assert_eq!(resp.mime.unwrap(), "text/html");
 /// ```
 ///
+/// When formatted for debugging, with [`fmt::Display`] or [`fmt::Debug`],
+/// bodies which aren't UTF-8 or longer than [`PREVIEW_LIMIT`] bytes are
+/// replaced by their size, e.g. `<52311 bytes>`, so that images and large
+/// files neither panic nor flood the output.
+///
 /// [`handle_connection`]: crate::http::handle_connection
-#[derive(Debug)]
 pub struct HTTPResponse<'a> {
     pub status: HTTPStatus<'a>,
-    pub mime: Option<&'a str>,
+    pub mime: Option<Cow<'a, str>>,
+    pub headers: Vec<(String, String)>,
     pub body: Vec<u8>,
+    pub served_by: ServedBy,
+    pub trace: Option<ResolveTrace>,
+    /// Whether the body is left out when writing the response
+    omit_body: bool,
 }
 
 impl<'a> HTTPResponse<'a> {
     /// Create a new HTTPResponse from an [`HTTPStatus`], an optional MIME type
     /// and an [`io::Result`]
     ///
+    /// If the body is an error, the MIME type is ignored and the HTML
+    /// representation of the status is used as body instead.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -71,29 +121,108 @@ impl<'a> HTTPResponse<'a> {
     /// ```
     pub fn new(
         status: HTTPStatus<'a>,
-        mime: Option<&'a str>,
+        mime: Option<Cow<'a, str>>,
         body: io::Result<Vec<u8>>,
     ) -> Self {
         HTTPResponse {
             mime: match body {
                 Ok(_) => mime,
-                Err(_) => Some("text/html"),
+                Err(_) => Some(Cow::Borrowed("text/html")),
             },
             body: body.unwrap_or_else(|_| status.to_html().into_bytes()),
+            headers: Vec::new(),
             status,
+            served_by: ServedBy::BaseDir,
+            trace: None,
+            omit_body: false,
         }
     }
 
+    /// Adapt the response to a request with `method`, leaving out the body
+    /// when writing it in answer to `HEAD` requests. The header is unchanged,
+    /// including the `Content-Length` of the body.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::http::{HTTPResponse, HTTPStatus};
+    /// let get = HTTPResponse::from(HTTPStatus::from(404)).respond_to("GET");
+    /// let head = HTTPResponse::from(HTTPStatus::from(404)).respond_to("HEAD");
+    ///
+    /// assert!(head.omits_body());
+    /// assert_eq!(head.header(), get.header());
+    /// assert_eq!(head.sent_body(), b"");
+    /// assert_eq!(head.into_bytes(), get.header());
+    /// ```
+    pub fn respond_to(mut self, method: &str) -> Self {
+        self.omit_body = method == "HEAD";
+        self
+    }
+
+    /// Whether the body is left out when writing the response, see
+    /// [`HTTPResponse::respond_to`].
+    pub fn omits_body(&self) -> bool {
+        self.omit_body
+    }
+
+    /// Return the body as written, i.e. empty if it is left out.
+    pub fn sent_body(&self) -> &[u8] {
+        match self.omit_body {
+            true => &[],
+            false => &self.body,
+        }
+    }
+
+    /// Add a header field, after checking it with [`validate_header`].
+    ///
+    /// The value is stored without surrounding whitespace. A `Content-Type`
+    /// replaces the MIME type, so that it is only sent once.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::http::{HeaderError, HTTPResponse, HTTPStatus};
+    /// let mut resp = HTTPResponse::from(HTTPStatus::from(200));
+    ///
+    /// resp.add_header("X-Frame-Options", " DENY ").unwrap();
+    /// resp.add_header("content-type", "text/plain").unwrap();
+    /// assert_eq!(
+    ///     resp.add_header("Set-Cookie", "a=1\r\nContent-Length: 0"),
+    ///     Err(HeaderError::InvalidValue)
+    /// );
+    ///
+    /// assert_eq!(resp.headers, [("X-Frame-Options".into(), "DENY".into())]);
+    /// assert_eq!(resp.mime.unwrap(), "text/plain");
+    /// ```
+    pub fn add_header(
+        &mut self,
+        name: &str,
+        value: &str,
+    ) -> Result<(), HeaderError> {
+        let value = validate_header(name, value)?;
+
+        match name.eq_ignore_ascii_case("Content-Type") {
+            true => self.mime = Some(Cow::Owned(value.to_string())),
+            false => self.headers.push((name.to_string(), value.to_string())),
+        }
+        Ok(())
+    }
+
     /// Generate a HTTP header by from the response and return it as bytes.
     ///
     /// This function uses the MIME type, the [`HTTPStatus`] and the length of
     /// the body to generate a HTTP header with the following fields:
     ///
     /// - HTTP status
-    /// - Content-Length
+    /// - Content-Length (except for `204 No Content`)
     /// - Content-Type (optional)
+    /// - Additional `headers`, in order
     /// - Connection: close
     ///
+    /// Additional fields failing [`validate_header`] are left out, as well as
+    /// any `Content-Type` but the first, so that no field pushed to `headers`
+    /// directly can end the header early or contradict the body.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -105,23 +234,54 @@ impl<'a> HTTPResponse<'a> {
     /// assert!(header_str.ends_with("Connection: close\r\n\r\n"));
     /// ```
     pub fn header(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.push_header(&mut buf);
+        buf
+    }
+
+    /// Append the header of [`HTTPResponse::header`] to `buf`, without any
+    /// allocation if it has enough capacity.
+    fn push_header(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(b"HTTP/1.1 ");
+        push_number(buf, self.status.code);
+        buf.push(b' ');
+        buf.extend_from_slice(self.status.msg.as_bytes());
+        buf.extend_from_slice(b"\r\n");
+        // Responses without content must not have a length either
+        if self.status.code != 204 {
+            buf.extend_from_slice(b"Content-Length: ");
+            push_number(buf, self.body.len());
+            buf.extend_from_slice(b"\r\n");
+        }
+
+        let mut has_type = false;
+        let mime = self.mime.as_deref().map(|mime| ("Content-Type", mime));
+        let headers =
+            self.headers.iter().map(|(n, v)| (n.as_str(), v.as_str()));
+
+        for (name, value) in mime.into_iter().chain(headers) {
+            let value = match validate_header(name, value) {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+            if name.eq_ignore_ascii_case("Content-Type") {
+                if has_type {
+                    continue;
+                }
+                has_type = true;
+            }
+            push_field(buf, name, value);
+        }
+
         // Sure about "Connection: close"?
-        format!(
-            "{status}\r\nContent-Length: {len}\r\n{mime}Connection: close\r\n\r\n",
-            status = self.status,
-            len = self.body.len(),
-            mime = match self.mime {
-                Some(t) => String::from("Content-Type: ") + t + "\r\n",
-                None => String::from(""),
-            },
-        )
-        .into_bytes()
+        buf.extend_from_slice(b"Connection: close\r\n\r\n");
     }
 
     /// Turn the HTTPResponse into a vector of bytes by consuming the response.
     ///
     /// This function internally calls the [`HTTPResponse::header`] method and
-    /// chains it to the response body before returning it.
+    /// chains it to the response body before returning it, unless the body
+    /// is left out, see [`HTTPResponse::respond_to`].
     ///
     /// # Example
     ///
@@ -134,10 +294,193 @@ impl<'a> HTTPResponse<'a> {
     /// assert!(resp_str.ends_with("<h1>404</h1><p>Not Found</p></body></html>\n"));
     /// ```
     pub fn into_bytes(self) -> Vec<u8> {
-        self.header()
-            .into_iter()
-            .chain(self.body.into_iter())
-            .collect()
+        let mut bytes = self.header();
+        bytes.extend_from_slice(self.sent_body());
+        bytes
+    }
+}
+
+/// Number of bytes of a response written to a client, as returned by
+/// [`write_response`] and [`write_header`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Written {
+    /// Bytes of the status line and header fields, including the blank line
+    /// ending them
+    pub header: usize,
+    /// Bytes of the body
+    pub body: usize,
+}
+
+/// Write the header and body of `resp` to `w`, with the same bytes as
+/// [`HTTPResponse::into_bytes`], and return the number of bytes written. The
+/// body is left out of responses to `HEAD` requests, see
+/// [`HTTPResponse::respond_to`].
+///
+/// The header is formatted into a scratch buffer reused by the thread, so
+/// that no allocation is needed. Small bodies are copied after it, so that
+/// the whole response is written in a single call.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::{write_response, HTTPResponse, HTTPStatus};
+/// let resp = HTTPResponse::from(HTTPStatus::from(404));
+/// let mut out = Vec::new();
+/// let written = write_response(&resp, &mut out).unwrap();
+///
+/// assert_eq!(written.body, resp.body.len());
+/// assert_eq!(written.header + written.body, out.len());
+/// assert_eq!(out, resp.into_bytes());
+/// ```
+pub fn write_response<W: Write>(
+    resp: &HTTPResponse,
+    w: &mut W,
+) -> io::Result<Written> {
+    SCRATCH.with(|scratch| {
+        let mut buf = scratch.borrow_mut();
+        buf.clear();
+        resp.push_header(&mut buf);
+        let header = buf.len();
+        let body = resp.sent_body();
+
+        if body.len() <= INLINE_BODY_LEN {
+            buf.extend_from_slice(body);
+            w.write_all(&buf)?;
+        } else {
+            w.write_all(&buf)?;
+            w.write_all(body)?;
+        }
+
+        Ok(Written {
+            header,
+            body: body.len(),
+        })
+    })
+}
+
+/// Write the header of `resp` to `w`, without the body, e.g. to write the
+/// body separately, and return the number of bytes written. See
+/// [`write_response`].
+pub fn write_header<W: Write>(
+    resp: &HTTPResponse,
+    w: &mut W,
+) -> io::Result<Written> {
+    SCRATCH.with(|scratch| {
+        let mut buf = scratch.borrow_mut();
+        buf.clear();
+        resp.push_header(&mut buf);
+
+        w.write_all(&buf)?;
+        Ok(Written {
+            header: buf.len(),
+            body: 0,
+        })
+    })
+}
+
+/// Check that a header field can be sent, returning its value without
+/// surrounding spaces and tabs.
+///
+/// The name must be a token, i.e. made of letters, digits and
+/// ``!#$%&'*+-.^_`|~``, and the value must not contain control characters
+/// such as line breaks, which would end the field early. Fields written by
+/// the server itself, `Content-Length`, `Transfer-Encoding` and `Connection`,
+/// are reserved, so that they can't disagree with the response.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::{validate_header, HeaderError};
+/// assert_eq!(validate_header("Cache-Control", " no-cache\t"), Ok("no-cache"));
+/// assert_eq!(validate_header("X Id", "1"), Err(HeaderError::InvalidName));
+/// assert_eq!(validate_header("X-Id", "1\n2"), Err(HeaderError::InvalidValue));
+/// assert_eq!(validate_header("content-length", "0"), Err(HeaderError::Reserved));
+/// ```
+pub fn validate_header<'v>(
+    name: &str,
+    value: &'v str,
+) -> Result<&'v str, HeaderError> {
+    if !is_token(name) {
+        return Err(HeaderError::InvalidName);
+    }
+    if RESERVED_HEADERS
+        .iter()
+        .any(|reserved| name.eq_ignore_ascii_case(reserved))
+    {
+        return Err(HeaderError::Reserved);
+    }
+
+    let value = value.trim_matches(|c| c == ' ' || c == '\t');
+    match value.chars().any(|c| c.is_control() && c != '\t') {
+        true => Err(HeaderError::InvalidValue),
+        false => Ok(value),
+    }
+}
+
+/// Check that `s` is a token, such as a header name or request method, i.e.
+/// made of letters, digits and ``!#$%&'*+-.^_`|~``.
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes().all(|b| {
+            b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+        })
+}
+
+/// Append a header field to `buf`.
+fn push_field(buf: &mut Vec<u8>, name: &str, value: &str) {
+    buf.extend_from_slice(name.as_bytes());
+    buf.extend_from_slice(b": ");
+    buf.extend_from_slice(value.as_bytes());
+    buf.extend_from_slice(b"\r\n");
+}
+
+/// Append the decimal digits of `n` to `buf`, without formatting it into a
+/// [`String`] first.
+fn push_number(buf: &mut Vec<u8>, mut n: usize) {
+    let mut digits = [0; 20];
+    let mut start = digits.len();
+
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+
+    buf.extend_from_slice(&digits[start..]);
+}
+
+/// Bytes formatted as text, or as their size, e.g. `<52311 bytes>`, if they
+/// aren't UTF-8 or longer than [`PREVIEW_LIMIT`], see [`HTTPResponse`].
+pub(crate) struct Preview<'b>(pub(crate) &'b [u8]);
+
+impl Preview<'_> {
+    /// Return the bytes as text, if short enough and UTF-8.
+    fn text(&self) -> Option<&str> {
+        match self.0.len() <= PREVIEW_LIMIT {
+            true => str::from_utf8(self.0).ok(),
+            false => None,
+        }
+    }
+}
+
+impl fmt::Display for Preview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.text() {
+            Some(text) => write!(f, "{}", text),
+            None => write!(f, "<{} bytes>", self.0.len()),
+        }
+    }
+}
+
+impl fmt::Debug for Preview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.text() {
+            Some(text) => write!(f, "{:?}", text),
+            None => write!(f, "<{} bytes>", self.0.len()),
+        }
     }
 }
 
@@ -147,12 +490,26 @@ impl fmt::Display for HTTPResponse<'_> {
         write!(
             f,
             "{}{}",
-            std::str::from_utf8(&self.header()).unwrap(),
-            str::from_utf8(&self.body).unwrap()
+            String::from_utf8_lossy(&self.header()),
+            Preview(&self.body)
         )
     }
 }
 
+impl fmt::Debug for HTTPResponse<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HTTPResponse")
+            .field("status", &self.status)
+            .field("mime", &self.mime)
+            .field("headers", &self.headers)
+            .field("body", &Preview(&self.body))
+            .field("served_by", &self.served_by)
+            .field("trace", &self.trace)
+            .field("omit_body", &self.omit_body)
+            .finish()
+    }
+}
+
 impl From<io::Error> for HTTPResponse<'_> {
     fn from(error: io::Error) -> Self {
         Self::from(HTTPStatus::from(&error))
@@ -164,14 +521,176 @@ impl<'a> From<HTTPStatus<'a>> for HTTPResponse<'a> {
         Self {
             body: status.to_html().into_bytes(),
             status,
-            mime: Some("text/html"),
+            mime: Some(Cow::Borrowed("text/html")),
+            headers: Vec::new(),
+            served_by: ServedBy::BaseDir,
+            trace: None,
+            omit_body: false,
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::io_other_error)] // Tests predate the lint
 mod test {
-    use super::{io, HTTPResponse, HTTPStatus};
+    use super::*;
+
+    #[test]
+    fn rejected_headers() {
+        let cases = [
+            ("", "1", HeaderError::InvalidName),
+            ("X Id", "1", HeaderError::InvalidName),
+            ("X-Id:", "1", HeaderError::InvalidName),
+            ("X-Id\r\nSet-Cookie", "1", HeaderError::InvalidName),
+            ("X-Ïd", "1", HeaderError::InvalidName),
+            ("(X-Id)", "1", HeaderError::InvalidName),
+            ("X-Id", "1\r\nContent-Length: 0", HeaderError::InvalidValue),
+            ("X-Id", "1\n", HeaderError::InvalidValue),
+            ("X-Id", "a\0b", HeaderError::InvalidValue),
+            ("X-Id", "a\x7fb", HeaderError::InvalidValue),
+            ("Content-Length", "0", HeaderError::Reserved),
+            ("content-length", "12", HeaderError::Reserved),
+            ("Transfer-Encoding", "chunked", HeaderError::Reserved),
+            ("CONNECTION", "keep-alive", HeaderError::Reserved),
+        ];
+
+        for (name, value, err) in cases {
+            let mut resp = HTTPResponse::from(HTTPStatus::from(200));
+            assert_eq!(resp.add_header(name, value), Err(err), "{:?}", name);
+            assert!(resp.headers.is_empty());
+        }
+    }
+
+    #[test]
+    fn accepted_headers() {
+        assert_eq!(validate_header("X-Id", "\t a b \t"), Ok("a b"));
+        assert_eq!(validate_header("X-Id", "a\tb"), Ok("a\tb"));
+        assert_eq!(validate_header("X-Id", ""), Ok(""));
+        assert_eq!(validate_header("X-Name", "Ünïcode"), Ok("Ünïcode"));
+        assert_eq!(validate_header("!#$%&'*+-.^_`|~09", "1"), Ok("1"));
+    }
+
+    #[test]
+    fn header_output() {
+        let mut resp = HTTPResponse::new(
+            HTTPStatus::from(200),
+            Some("text/plain".into()),
+            Ok(b"Hello".to_vec()),
+        );
+        resp.add_header("Content-Type", "text/html").unwrap();
+        // Fields pushed directly are checked when writing
+        for (name, value) in [
+            ("Content-Length", "100"),
+            ("content-type", "image/png"),
+            ("X-Bad", "a\r\n\r\nHTTP/1.1 200 OK"),
+            ("X-Good", "  yes  "),
+        ] {
+            resp.headers.push((name.to_string(), value.to_string()));
+        }
+
+        let header = String::from_utf8(resp.header()).unwrap();
+        assert_eq!(
+            header,
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\
+             Content-Type: text/html\r\nX-Good: yes\r\n\
+             Connection: close\r\n\r\n"
+        );
+
+        let mut resp = HTTPResponse::new(
+            HTTPStatus::from(200),
+            Some("text/plain\r\nX-Injected: 1".into()),
+            Ok(Vec::new()),
+        );
+        resp.headers
+            .push(("Content-Type".into(), "text/css".into()));
+        let header = String::from_utf8(resp.header()).unwrap();
+        assert!(!header.contains("X-Injected"));
+        assert!(header.contains("\r\nContent-Type: text/css\r\n"));
+    }
+
+    #[test]
+    fn numbers() {
+        for n in [0, 7, 10, 404, 1_234_567, usize::MAX] {
+            let mut buf = Vec::new();
+            push_number(&mut buf, n);
+            assert_eq!(buf, n.to_string().as_bytes());
+        }
+    }
+
+    #[test]
+    fn streamed_response() {
+        let mut with_headers = HTTPResponse::new(
+            HTTPStatus::new(200, "OK", None),
+            Some("application/wasm".into()),
+            Ok(b"\0asm".to_vec()),
+        );
+        with_headers.headers = vec![
+            (String::from("Cache-Control"), String::from("no-store")),
+            (String::from("X-Request-Id"), String::from("abc")),
+        ];
+        let responses = vec![
+            with_headers,
+            HTTPResponse::from(HTTPStatus::from(503)),
+            HTTPResponse::new(HTTPStatus::from(200), None, Ok(Vec::new())),
+            HTTPResponse::new(
+                HTTPStatus::from(200),
+                None,
+                Ok(vec![b'x'; INLINE_BODY_LEN + 1]),
+            ),
+        ];
+
+        for res in responses {
+            let mut out = Vec::new();
+            let written = write_response(&res, &mut out).unwrap();
+
+            let mut header = Vec::new();
+            let header_written = write_header(&res, &mut header).unwrap();
+
+            assert_eq!(header, res.header());
+            assert_eq!(written.header, header.len());
+            assert_eq!(written.header + written.body, out.len());
+            assert_eq!(header_written.body, 0);
+            assert_eq!(out, res.into_bytes());
+        }
+    }
+
+    #[test]
+    fn head_responses() {
+        let response = |code| {
+            let mut res = match code {
+                200 => HTTPResponse::new(
+                    HTTPStatus::from(200),
+                    Some("text/plain".into()),
+                    Ok(b"Hello".to_vec()),
+                ),
+                304 => {
+                    HTTPResponse::new(HTTPStatus::from(304), None, Ok(vec![]))
+                }
+                code => HTTPResponse::from(HTTPStatus::from(code)),
+            };
+            res.headers
+                .push((String::from("X-Request-Id"), String::from("abc")));
+            res
+        };
+
+        for code in [200, 301, 304, 404, 503] {
+            let get = response(code).respond_to("GET");
+            let head = response(code).respond_to("HEAD");
+            assert!(!get.omits_body());
+            assert!(head.omits_body());
+
+            let (mut get_out, mut head_out) = (Vec::new(), Vec::new());
+            let get_written = write_response(&get, &mut get_out).unwrap();
+            let head_written = write_response(&head, &mut head_out).unwrap();
+
+            // Same header, Content-Length included, but no body
+            assert_eq!(head_out, get.header(), "{}", code);
+            assert_eq!(get_out, [get.header(), get.body.clone()].concat());
+            assert_eq!(head_written.header, get_written.header);
+            assert_eq!(head_written.body, 0);
+            assert_eq!(head.into_bytes(), head_out);
+        }
+    }
 
     #[test]
     fn httpresponse() {
@@ -192,6 +711,48 @@ mod test {
         assert!(body_str.find("<p>Not Found</p>").is_some());
     }
 
+    #[test]
+    fn owned_mime() {
+        let res = HTTPResponse::new(
+            HTTPStatus::from(200),
+            Some(String::from("application/wasm").into()),
+            Ok(b"\0asm".to_vec()),
+        );
+        let header = res.header();
+        let header_str = std::str::from_utf8(&header).unwrap();
+
+        assert_eq!(res.mime.unwrap(), "application/wasm");
+        assert!(header_str.contains("Content-Type: application/wasm\r\n"));
+    }
+
+    #[test]
+    fn no_mime() {
+        let res = HTTPResponse::new(
+            HTTPStatus::from(200),
+            None,
+            Ok(b"Hello".to_vec()),
+        );
+        let header = res.header();
+        let header_str = std::str::from_utf8(&header).unwrap();
+
+        assert!(res.mime.is_none());
+        assert!(!header_str.contains("Content-Type"));
+    }
+
+    #[test]
+    fn extra_headers() {
+        let mut res = HTTPResponse::from(HTTPStatus::from(200));
+        res.headers
+            .push((String::from("Cache-Control"), String::from("no-cache")));
+        let header = res.header();
+        let header_str = std::str::from_utf8(&header).unwrap();
+
+        assert!(header_str.ends_with(
+            "Content-Type: text/html\r\nCache-Control: no-cache\r\n\
+             Connection: close\r\n\r\n"
+        ));
+    }
+
     #[test]
     fn from_io_err() {
         let err =
@@ -227,4 +788,41 @@ mod test {
         assert!(body_str.find("<h1>501</h1>").is_some());
         assert!(body_str.find("<p>Not Implemented</p>").is_some());
     }
+
+    #[test]
+    fn binary_display() {
+        let jpeg = vec![0xff, 0xd8, 0xff, 0xe0, 0x00, 0x10, b'J', b'F'];
+        let res = HTTPResponse::new(
+            HTTPStatus::from(200),
+            Some(Cow::Borrowed("image/jpeg")),
+            Ok(jpeg),
+        );
+
+        let display = res.to_string();
+        assert!(display.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(display.contains("Content-Type: image/jpeg\r\n"));
+        assert!(display.ends_with("\r\n\r\n<8 bytes>"));
+        assert!(format!("{:?}", res).contains("body: <8 bytes>"));
+
+        let large = vec![b'a'; PREVIEW_LIMIT + 1];
+        let res = HTTPResponse::new(HTTPStatus::from(200), None, Ok(large));
+        assert!(res.to_string().ends_with("<1025 bytes>"));
+        assert!(format!("{:?}", res).contains("body: <1025 bytes>"));
+        assert!(format!("{:#?}", res).len() < 1024);
+    }
+
+    #[test]
+    fn text_display() {
+        let body = vec![b'a'; PREVIEW_LIMIT];
+        let res = HTTPResponse::new(HTTPStatus::from(200), None, Ok(body));
+        assert!(res.to_string().ends_with(&"a".repeat(PREVIEW_LIMIT)));
+
+        let res = HTTPResponse::new(
+            HTTPStatus::from(200),
+            None,
+            Ok(b"caf\xc3\xa9".to_vec()),
+        );
+        assert!(res.to_string().ends_with("\r\n\r\ncafé"));
+        assert!(format!("{:?}", res).contains("body: \"café\""));
+    }
 }
@@ -0,0 +1,30 @@
+use std::fmt;
+
+/// Part of the server that answered a request, shown in the request log so
+/// that it's clear where a response, e.g. a `404`, came from.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::ServedBy;
+/// assert_eq!(ServedBy::BaseDir.to_string(), "base-dir");
+/// assert_eq!(ServedBy::Internal("health").to_string(), "internal:health");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ServedBy {
+    /// The files below the base directory, including directory listings and
+    /// errors resolving the request path
+    #[default]
+    BaseDir,
+    /// A built-in endpoint, by name, such as the health check
+    Internal(&'static str),
+}
+
+impl fmt::Display for ServedBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServedBy::BaseDir => write!(f, "base-dir"),
+            ServedBy::Internal(endpoint) => write!(f, "internal:{}", endpoint),
+        }
+    }
+}
@@ -108,9 +108,23 @@ impl From<usize> for HTTPStatus<'_> {
     fn from(code: usize) -> Self {
         let msg = match code {
             200 => "OK",
+            201 => "Created",
+            204 => "No Content",
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            304 => "Not Modified",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            400 => "Bad Request",
             403 => "Forbidden",
             404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            411 => "Length Required",
+            413 => "Payload Too Large",
             501 => "Not Implemented",
+            503 => "Service Unavailable",
             _ => "Internal Server Error",
         };
 
@@ -131,8 +145,16 @@ impl From<&io::Error> for HTTPStatus<'_> {
     fn from(error: &io::Error) -> Self {
         let comment = error.get_ref().map(|e| e.to_string());
 
+        // Running out of file descriptors is temporary, so clients may retry
+        if is_fd_exhausted(error) {
+            return Self::new(503, "Service Unavailable", comment);
+        }
+
         match error.kind() {
-            io::ErrorKind::NotFound => Self::new(404, "Not Found", comment),
+            // A name too long for the file system can't name a file either
+            io::ErrorKind::NotFound | io::ErrorKind::InvalidFilename => {
+                Self::new(404, "Not Found", comment)
+            }
             io::ErrorKind::PermissionDenied => {
                 Self::new(403, "Forbidden", comment)
             }
@@ -144,9 +166,30 @@ impl From<&io::Error> for HTTPStatus<'_> {
     }
 }
 
+/// Whether `err` means the process or system ran out of file descriptors,
+/// i.e. `EMFILE` or `ENFILE`.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::is_fd_exhausted;
+/// use std::io;
+///
+/// assert!(is_fd_exhausted(&io::Error::from_raw_os_error(24)));
+/// assert!(!is_fd_exhausted(&io::ErrorKind::NotFound.into()));
+/// ```
+pub fn is_fd_exhausted(err: &io::Error) -> bool {
+    #[cfg(windows)]
+    let codes = [4, 10024];
+    #[cfg(not(windows))]
+    let codes = [23, 24];
+
+    err.raw_os_error().is_some_and(|code| codes.contains(&code))
+}
+
 #[cfg(test)]
 mod test {
-    use super::{io, HTTPStatus};
+    use super::{io, is_fd_exhausted, HTTPStatus};
 
     #[test]
     fn httpstatus() {
@@ -164,6 +207,12 @@ mod test {
         );
     }
 
+    #[test]
+    fn from_io_name_too_long() {
+        let err = io::Error::from(io::ErrorKind::InvalidFilename);
+        assert_eq!(HTTPStatus::from(&err).code, 404);
+    }
+
     #[test]
     fn from_io_ok() {
         let res = io::Result::Ok("");
@@ -184,6 +233,17 @@ mod test {
         assert_eq!(status.comment.unwrap(), "File not found");
     }
 
+    #[test]
+    fn from_fd_exhausted() {
+        let res = io::Error::from_raw_os_error(24);
+        assert!(is_fd_exhausted(&res));
+        assert_eq!(HTTPStatus::from(&res).code, 503);
+
+        let res = io::Error::from_raw_os_error(23);
+        assert!(is_fd_exhausted(&res));
+        assert!(!is_fd_exhausted(&io::ErrorKind::Other.into()));
+    }
+
     #[test]
     fn from_usize() {
         let status = HTTPStatus::from(200);
@@ -191,6 +251,8 @@ mod test {
         assert_eq!(status.code, 200);
         assert_eq!(status.msg, "OK");
         assert_eq!(status.comment, None);
+        assert_eq!(HTTPStatus::from(301).msg, "Moved Permanently");
+        assert_eq!(HTTPStatus::from(308).msg, "Permanent Redirect");
     }
 
     #[test]
@@ -0,0 +1,94 @@
+use std::{
+    io::{self, Write},
+    thread,
+    time::Duration,
+};
+
+/// Number of chunks written per second by a [`Throttle`]
+const CHUNKS_PER_SEC: u64 = 10;
+
+/// A writer limiting the rate at which bytes are written to the inner writer,
+/// to simulate slow connections.
+///
+/// Writes are split into chunks of a tenth of the rate, each followed by a
+/// pause as long as sending the chunk takes at the given rate.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::Throttle;
+/// use std::{io::Write, time::{Duration, Instant}};
+///
+/// let start = Instant::now();
+/// let mut out = Vec::new();
+/// Throttle::new(&mut out, 10).write_all(&[0; 1000]).unwrap();
+///
+/// assert_eq!(out.len(), 1000);
+/// assert!(start.elapsed() >= Duration::from_millis(100));
+/// ```
+#[derive(Debug)]
+pub struct Throttle<W> {
+    inner: W,
+    rate: u64,
+}
+
+impl<W: Write> Throttle<W> {
+    /// Limit writes to `inner` to `kbps` kilobytes (1000 bytes) per second.
+    ///
+    /// A rate of `0` is treated as `1`.
+    pub fn new(inner: W, kbps: u64) -> Self {
+        Throttle {
+            inner,
+            rate: kbps.max(1) * 1000,
+        }
+    }
+
+    /// Size of the chunks written at once
+    fn chunk_size(&self) -> usize {
+        (self.rate / CHUNKS_PER_SEC) as usize
+    }
+}
+
+impl<W: Write> Write for Throttle<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.chunk_size());
+        let written = self.inner.write(&buf[..len])?;
+        self.inner.flush()?;
+
+        thread::sleep(Duration::from_secs_f64(
+            written as f64 / self.rate as f64,
+        ));
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn rate() {
+        let start = Instant::now();
+        let mut out = Vec::new();
+        Throttle::new(&mut out, 20).write_all(&[1; 10_000]).unwrap();
+
+        // 10 kB at 20 kB/s
+        assert_eq!(out, vec![1; 10_000]);
+        assert!(start.elapsed() >= Duration::from_millis(500));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn chunks() {
+        let mut out = Vec::new();
+        let mut throttle = Throttle::new(&mut out, 1);
+
+        assert_eq!(throttle.write(&[0; 500]).unwrap(), 100);
+        assert_eq!(throttle.write(&[0; 50]).unwrap(), 50);
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Step of the resolution of a request path to a file or listing, see
+/// [`ResolveTrace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveStep {
+    /// The root path was mapped to the index file
+    Index,
+    /// The URL path after percent-decoding, relative to the base directory
+    Decoded(PathBuf),
+    /// The decoded path joined onto the base directory and normalized
+    Normalized(PathBuf),
+    /// The path was rejected for having too many or too long components
+    Limits,
+    /// The path was rejected for having a percent-encoded slash or backslash
+    EncodedSeparator,
+    /// The path was rejected for leading out of the base directory
+    Traversal,
+    /// The path was hidden by the user's ignore rules
+    Ignored,
+    /// The path was found missing shortly before, see
+    /// [`NotFoundCache`](crate::http::NotFoundCache)
+    CachedNotFound,
+    /// The path was served as a file, or failed to be
+    File(PathBuf),
+    /// The path was served as a directory listing
+    Listing(PathBuf),
+    /// The path wasn't found, and is looked for in an overlay directory
+    Overlay(PathBuf),
+    /// The path is a directory, but directory listings are disabled
+    ListingDisabled,
+    /// The missing index file was replaced by a listing of the base directory
+    IndexFallback(PathBuf),
+}
+
+impl ResolveStep {
+    /// Return the filesystem path of the step, if any.
+    fn path(&self) -> Option<&Path> {
+        match self {
+            ResolveStep::Normalized(path)
+            | ResolveStep::File(path)
+            | ResolveStep::Listing(path)
+            | ResolveStep::IndexFallback(path) => Some(path),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ResolveStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolveStep::Index => write!(f, "index"),
+            ResolveStep::Decoded(path) => {
+                write!(f, "decoded {}", path.display())
+            }
+            ResolveStep::Normalized(path) => {
+                write!(f, "normalized {}", path.display())
+            }
+            ResolveStep::Limits => write!(f, "over limits"),
+            ResolveStep::EncodedSeparator => write!(f, "encoded separator"),
+            ResolveStep::Traversal => write!(f, "traversal"),
+            ResolveStep::Ignored => write!(f, "ignored"),
+            ResolveStep::CachedNotFound => write!(f, "cached not found"),
+            ResolveStep::File(path) => write!(f, "file {}", path.display()),
+            ResolveStep::Listing(path) => {
+                write!(f, "listing {}", path.display())
+            }
+            ResolveStep::Overlay(dir) => {
+                write!(f, "overlay {}", dir.display())
+            }
+            ResolveStep::ListingDisabled => write!(f, "listing disabled"),
+            ResolveStep::IndexFallback(path) => {
+                write!(f, "index fallback {}", path.display())
+            }
+        }
+    }
+}
+
+/// Record of the steps resolving a request path to a filesystem path, logged
+/// with `--verbose` to tell a wrong URL from a wrong base directory.
+///
+/// Steps are only recorded by traces created with [`ResolveTrace::new`], so
+/// that requests aren't slowed down when the trace isn't logged.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::{ResolveStep, ResolveTrace};
+/// use std::path::{Path, PathBuf};
+///
+/// let mut trace = ResolveTrace::new();
+/// trace.push(ResolveStep::Decoded(PathBuf::from("my page.html")));
+/// trace.push(ResolveStep::Normalized(PathBuf::from("/srv/my page.html")));
+/// trace.push(ResolveStep::File(PathBuf::from("/srv/my page.html")));
+///
+/// assert_eq!(trace.path(), Some(Path::new("/srv/my page.html")));
+/// assert_eq!(
+///     trace.to_string(),
+///     "decoded my page.html -> normalized /srv/my page.html -> file /srv/my page.html"
+/// );
+///
+/// let mut trace = ResolveTrace::disabled();
+/// trace.push(ResolveStep::Index);
+/// assert!(trace.steps().is_empty());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolveTrace {
+    steps: Vec<ResolveStep>,
+    enabled: bool,
+}
+
+impl ResolveTrace {
+    /// Create an empty trace recording steps.
+    pub fn new() -> Self {
+        ResolveTrace {
+            steps: Vec::new(),
+            enabled: true,
+        }
+    }
+
+    /// Create a trace ignoring all steps.
+    pub fn disabled() -> Self {
+        ResolveTrace {
+            steps: Vec::new(),
+            enabled: false,
+        }
+    }
+
+    /// Record a step, if the trace is enabled.
+    pub fn push(&mut self, step: ResolveStep) {
+        if self.enabled {
+            self.steps.push(step);
+        }
+    }
+
+    /// Return the recorded steps, in order.
+    pub fn steps(&self) -> &[ResolveStep] {
+        &self.steps
+    }
+
+    /// Return the step which produced the answer, i.e. the last one.
+    pub fn last(&self) -> Option<&ResolveStep> {
+        self.steps.last()
+    }
+
+    /// Return the final filesystem path the request resolved to, if it got
+    /// that far.
+    pub fn path(&self) -> Option<&Path> {
+        self.steps.iter().rev().find_map(ResolveStep::path)
+    }
+}
+
+impl Default for ResolveTrace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for ResolveTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                write!(f, " -> ")?;
+            }
+            write!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,620 @@
+use crate::{
+    cli::Config,
+    files,
+    http::{
+        generate_request_id, handler, multipart_boundary, request_id,
+        HTTPRequest, HTTPResponse, HTTPStatus, Multipart, REQUEST_ID_HEADER,
+    },
+};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// Prefix of the temporary files uploads are written to before being renamed
+/// to their target
+const TEMP_PREFIX: &str = ".servum-upload-";
+
+/// Answer a `PUT` or `POST` request with `--upload`, writing the uploaded
+/// files below the base directory.
+///
+/// - `PUT /path/file` writes the request body to that file, answering `201
+///   Created` for a new file or `204 No Content` if it was replaced.
+/// - `POST /path/` with a `multipart/form-data` body, as sent by the form
+///   above directory listings, writes every uploaded file to that directory,
+///   answering `303 See Other` back to the listing.
+///
+/// The body is read from `body_start`, the part of it read along with the
+/// request head, followed by `stream`, up to its `Content-Length`. Requests
+/// without one are refused with `411 Length Required`, and requests larger
+/// than `max_upload_size` with `413 Payload Too Large`.
+///
+/// Targets are checked like reads: paths out of the base directory are
+/// forbidden and ignored paths are not found. Uploads never follow symbolic
+/// links, and neither create directories nor replace them. Files are
+/// streamed to a temporary file next to their target, which is then renamed,
+/// so that the target is never seen half written.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::{cli::Config, http::{handle_upload, HTTPRequest}};
+/// # fn main() -> Result<(), servum::Error> {
+/// let dir = std::env::temp_dir().canonicalize().unwrap();
+/// let config = Config {
+///     base_dir: dir.clone(),
+///     upload: true,
+///     ..Config::default()
+/// };
+///
+/// let name = format!("servum-doc-upload-{}.txt", std::process::id());
+/// let head = format!("PUT /{} HTTP/1.1\r\nContent-Length: 5\r\n\r\n", name);
+/// let req = HTTPRequest::new(head.as_bytes())?;
+/// let mut stream = std::io::Cursor::new(b"lo".to_vec());
+/// let res = handle_upload(&req, b"Hel", &mut stream, &config);
+///
+/// assert_eq!(res.status.code, 201);
+/// assert_eq!(std::fs::read(dir.join(&name)).unwrap(), b"Hello");
+/// # std::fs::remove_file(dir.join(&name)).unwrap();
+/// # Ok(())
+/// # }
+/// ```
+pub fn handle_upload<'a, S: Read + Write>(
+    req: &HTTPRequest,
+    body_start: &[u8],
+    stream: &mut S,
+    config: &Config,
+) -> HTTPResponse<'a> {
+    let mut res = upload(req, body_start, stream, config)
+        .unwrap_or_else(HTTPResponse::from);
+    res.headers
+        .push((String::from(REQUEST_ID_HEADER), request_id(req)));
+    res
+}
+
+/// Write the upload of a request, returning the response to send, or the
+/// status to answer with if it fails.
+fn upload<'a, S: Read + Write>(
+    req: &HTTPRequest,
+    body_start: &[u8],
+    stream: &mut S,
+    config: &Config,
+) -> Result<HTTPResponse<'a>, HTTPStatus<'a>> {
+    let len = req
+        .header("Content-Length")
+        .and_then(|len| len.parse::<u64>().ok())
+        .ok_or_else(|| error(411, "Uploads need a Content-Length"))?;
+
+    let max = config.max_upload_size.saturating_mul(1_000_000);
+    if len > max {
+        return Err(error(
+            413,
+            &format!("Uploads are limited to {} MB", config.max_upload_size),
+        ));
+    }
+
+    let path = req.filepath.strip_prefix("/").unwrap_or(req.filepath);
+    let path = files::path::process_path(path);
+    let target = handler::resolve(&path, config)?;
+    let meta = inspect(&target, config)?;
+
+    let is_dir = meta.as_ref().is_some_and(fs::Metadata::is_dir);
+    let is_put = req.method == "PUT";
+    if is_put && is_dir {
+        return Err(error(409, "Can't replace a directory"));
+    }
+    if !is_put && !is_dir {
+        return Err(error(409, "Files are posted to a directory"));
+    }
+
+    if req
+        .header("Expect")
+        .is_some_and(|expect| expect.eq_ignore_ascii_case("100-continue"))
+    {
+        stream
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .map_err(|e| HTTPStatus::from(&e))?;
+    }
+    let mut body = body_start.chain(&mut *stream).take(len);
+
+    if is_put {
+        write_atomic(&target, |file| match io::copy(&mut body, file)? == len {
+            true => Ok(()),
+            false => Err(io::ErrorKind::UnexpectedEof.into()),
+        })
+        .map_err(body_error)?;
+
+        let status = if meta.is_some() { 204 } else { 201 };
+        return Ok(HTTPResponse::new(
+            HTTPStatus::from(status),
+            None,
+            Ok(Vec::new()),
+        ));
+    }
+
+    let boundary = req
+        .header("Content-Type")
+        .and_then(multipart_boundary)
+        .ok_or_else(|| error(400, "Expected a multipart/form-data body"))?;
+    let mut multipart = Multipart::new(body, boundary);
+
+    while let Some(part) = multipart.next_part().map_err(body_error)? {
+        let name = match part.filename.as_deref().and_then(file_name) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        let file = handler::resolve(&path.join(name), config)?;
+        if inspect(&file, config)?.is_some_and(|meta| meta.is_dir()) {
+            return Err(error(409, "Can't replace a directory"));
+        }
+        write_atomic(&file, |out| multipart.copy_part(out).map(|_| ()))
+            .map_err(body_error)?;
+    }
+
+    let mut res =
+        HTTPResponse::new(HTTPStatus::from(303), None, Ok(Vec::new()));
+    res.headers.push((
+        String::from("Location"),
+        req.filepath.to_string_lossy().into_owned(),
+    ));
+    Ok(res)
+}
+
+/// Answer a `DELETE` request with `--upload`, removing a file or an empty
+/// directory below the base directory with `204 No Content`.
+///
+/// Targets are checked like uploads: paths out of the base directory are
+/// forbidden, ignored or missing paths are not found, and symbolic links are
+/// neither followed nor removed. Non-empty directories are refused with `409
+/// Conflict`, and the base directory itself can't be deleted.
+pub(crate) fn delete<'a>(
+    req: &HTTPRequest,
+    config: &Config,
+) -> HTTPResponse<'a> {
+    remove(req, config).unwrap_or_else(HTTPResponse::from)
+}
+
+/// Remove the target of a `DELETE` request, returning the response to send,
+/// or the status to answer with if it fails.
+fn remove<'a>(
+    req: &HTTPRequest,
+    config: &Config,
+) -> Result<HTTPResponse<'a>, HTTPStatus<'a>> {
+    let path = req.filepath.strip_prefix("/").unwrap_or(req.filepath);
+    let target = handler::resolve(&files::path::process_path(path), config)?;
+
+    if target == config.current_base_dir() {
+        return Err(error(403, "Can't delete the base directory"));
+    }
+    if let Err(e) = fs::symlink_metadata(&target) {
+        return Err(HTTPStatus::from(&e));
+    }
+
+    let res = match inspect(&target, config)? {
+        Some(meta) if meta.is_dir() => {
+            let mut entries =
+                fs::read_dir(&target).map_err(|e| HTTPStatus::from(&e))?;
+            if entries.next().is_some() {
+                return Err(error(409, "Can't delete a non-empty directory"));
+            }
+            fs::remove_dir(&target)
+        }
+        _ => fs::remove_file(&target),
+    };
+    res.map_err(|e| HTTPStatus::from(&e))?;
+
+    Ok(HTTPResponse::new(
+        HTTPStatus::from(204),
+        None,
+        Ok(Vec::new()),
+    ))
+}
+
+/// Check every component of a resolved `path` below the base directory,
+/// returning the metadata of `path`, or [`None`] if it doesn't exist yet.
+///
+/// Symbolic links are forbidden, and all parents must be existing
+/// directories.
+fn inspect<'a>(
+    path: &Path,
+    config: &Config,
+) -> Result<Option<fs::Metadata>, HTTPStatus<'a>> {
+    let base_dir = config.current_base_dir();
+    let rel = path.strip_prefix(&base_dir).unwrap_or(Path::new(""));
+    let mut current = base_dir.to_path_buf();
+    let mut meta =
+        Some(fs::metadata(&current).map_err(|e| HTTPStatus::from(&e))?);
+
+    for component in rel.components() {
+        if !meta.as_ref().is_some_and(fs::Metadata::is_dir) {
+            return Err(error(409, "The parent directory doesn't exist"));
+        }
+
+        current.push(component);
+        meta = match fs::symlink_metadata(&current) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(error(403, "Symbolic links can't be written to"))
+            }
+            Ok(meta) => Some(meta),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(HTTPStatus::from(&e)),
+        };
+    }
+
+    Ok(meta)
+}
+
+/// Write a file by passing a new temporary file next to it to `write`, and
+/// renaming it to `path` once written. The temporary file is removed if
+/// anything fails.
+fn write_atomic<F>(path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut fs::File) -> io::Result<()>,
+{
+    let temp = temp_path(path);
+    let res = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp, path));
+
+    if res.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    res
+}
+
+/// Return a unique path for the temporary file of an upload to `path`.
+fn temp_path(path: &Path) -> PathBuf {
+    path.with_file_name(format!("{}{}", TEMP_PREFIX, generate_request_id()))
+}
+
+/// Return the name to save an uploaded file as: the last segment of the
+/// `filename` sent by the client, which some browsers send with a path, or
+/// [`None`] if it is empty or refers to a directory.
+fn file_name(filename: &str) -> Option<&str> {
+    let name = filename.rsplit(['/', '\\']).next()?;
+
+    match name {
+        "" | "." | ".." => None,
+        name => Some(name),
+    }
+}
+
+/// Turn an error while reading the body of an upload into a status:
+/// truncated or malformed bodies are a `400 Bad Request`.
+fn body_error<'a>(err: io::Error) -> HTTPStatus<'a> {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidData => {
+            error(400, "Incomplete or malformed upload")
+        }
+        _ => HTTPStatus::from(&err),
+    }
+}
+
+/// Create an error status with `code` and a `comment`.
+fn error<'a>(code: usize, comment: &str) -> HTTPStatus<'a> {
+    let mut status = HTTPStatus::from(code);
+    status.comment = Some(comment.to_string());
+    status
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    /// Stream reading the request from `input`, and writing to `output`
+    #[derive(Default)]
+    struct Duplex {
+        input: io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Duplex {
+        fn new(input: &[u8]) -> Self {
+            Duplex {
+                input: io::Cursor::new(input.to_vec()),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Duplex {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Duplex {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Helper
+    fn site() -> (TempDir, Config) {
+        let dir = TempDir::new();
+        dir.file("old.txt", "old");
+        dir.file("docs/readme.md", "readme");
+        dir.file("secret.log", "log");
+
+        let mut conf = Config {
+            base_dir: dir.path().to_path_buf(),
+            upload: true,
+            ..Config::default()
+        };
+        conf.ignore.add("*.log");
+
+        (dir, conf)
+    }
+
+    // Helper
+    fn put<'a>(path: &str, body: &[u8], conf: &Config) -> HTTPResponse<'a> {
+        let head = format!(
+            "PUT {} HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            path,
+            body.len()
+        );
+        let req = HTTPRequest::new(head.as_bytes()).unwrap();
+
+        handle_upload(&req, b"", &mut Duplex::new(body), conf)
+    }
+
+    // Helper
+    fn temp_files(dir: &Path) -> usize {
+        fs::read_dir(dir)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with(TEMP_PREFIX)
+            })
+            .count()
+    }
+
+    #[test]
+    fn put_files() {
+        let (dir, conf) = site();
+
+        let res = put("/new.bin", b"\0\x01\xff", &conf);
+        assert_eq!(res.status.code, 201);
+        assert_eq!(
+            fs::read(dir.path().join("new.bin")).unwrap(),
+            b"\0\x01\xff"
+        );
+        assert!(res
+            .headers
+            .iter()
+            .any(|(name, _)| name == REQUEST_ID_HEADER));
+
+        let res = put("/old.txt", b"new", &conf);
+        assert_eq!(res.status.code, 204);
+        assert_eq!(fs::read(dir.path().join("old.txt")).unwrap(), b"new");
+
+        let res = put("/docs/sub%20dir.txt", b"x", &conf);
+        assert_eq!(res.status.code, 201);
+        assert!(dir.path().join("docs/sub dir.txt").is_file());
+
+        assert_eq!(put("/docs", b"x", &conf).status.code, 409);
+        assert_eq!(put("/missing/a.txt", b"x", &conf).status.code, 409);
+        assert_eq!(put("/old.txt/a.txt", b"x", &conf).status.code, 409);
+        assert_eq!(temp_files(dir.path()), 0);
+    }
+
+    #[test]
+    fn put_body_in_head_buffer() {
+        let (dir, conf) = site();
+        let head = b"PUT /a.txt HTTP/1.1\r\nContent-Length: 6\r\n\
+                     Expect: 100-continue\r\n\r\n";
+        let req = HTTPRequest::new(head).unwrap();
+        let mut stream = Duplex::new(b"def, and more");
+
+        let res = handle_upload(&req, b"abc", &mut stream, &conf);
+
+        assert_eq!(res.status.code, 201);
+        assert_eq!(fs::read(dir.path().join("a.txt")).unwrap(), b"abcdef");
+        assert_eq!(stream.output, b"HTTP/1.1 100 Continue\r\n\r\n");
+    }
+
+    #[test]
+    fn traversal() {
+        let (dir, conf) = site();
+        let outside = dir.path().parent().unwrap().join("servum-escaped.txt");
+
+        for path in ["/../servum-escaped.txt", "/docs/../../servum-escaped.txt"]
+        {
+            assert_eq!(put(path, b"x", &conf).status.code, 403);
+        }
+        assert!(!outside.exists());
+
+        assert_eq!(put("/secret.log", b"x", &conf).status.code, 404);
+        assert_eq!(fs::read(dir.path().join("secret.log")).unwrap(), b"log");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks() {
+        let (dir, conf) = site();
+        let outside = TempDir::new();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("out"))
+            .unwrap();
+        std::os::unix::fs::symlink(
+            dir.path().join("old.txt"),
+            dir.path().join("link.txt"),
+        )
+        .unwrap();
+
+        assert_eq!(put("/out/a.txt", b"x", &conf).status.code, 403);
+        assert!(!outside.path().join("a.txt").exists());
+
+        assert_eq!(put("/link.txt", b"x", &conf).status.code, 403);
+        assert_eq!(fs::read(dir.path().join("old.txt")).unwrap(), b"old");
+    }
+
+    #[test]
+    fn size_limits() {
+        let (dir, mut conf) = site();
+        conf.max_upload_size = 1;
+
+        assert_eq!(put("/big.bin", &[0; 1_000_000], &conf).status.code, 201);
+
+        let head = b"PUT /big.bin HTTP/1.1\r\nContent-Length: 1000001\r\n\r\n";
+        let req = HTTPRequest::new(head).unwrap();
+        let res = handle_upload(&req, b"", &mut Duplex::default(), &conf);
+        assert_eq!(res.status.code, 413);
+
+        let req = HTTPRequest::new(b"PUT /a.txt HTTP/1.1\r\n\r\n").unwrap();
+        let res = handle_upload(&req, b"", &mut Duplex::default(), &conf);
+        assert_eq!(res.status.code, 411);
+
+        // The body ends before its announced length
+        let head = b"PUT /short.txt HTTP/1.1\r\nContent-Length: 10\r\n\r\n";
+        let req = HTTPRequest::new(head).unwrap();
+        let res = handle_upload(&req, b"abc", &mut Duplex::default(), &conf);
+        assert_eq!(res.status.code, 400);
+        assert!(!dir.path().join("short.txt").exists());
+        assert_eq!(temp_files(dir.path()), 0);
+    }
+
+    #[test]
+    fn post_form() {
+        let (dir, conf) = site();
+        let body = b"--xyz\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+            \r\n\
+            first\r\n\
+            --xyz\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"C:\\\\tmp\\\\b.txt\"\r\n\
+            \r\n\
+            second\r\n\
+            --xyz\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"\"\r\n\
+            \r\n\
+            \r\n\
+            --xyz--\r\n";
+        let head = format!(
+            "POST /docs/ HTTP/1.1\r\nContent-Length: {}\r\n\
+             Content-Type: multipart/form-data; boundary=xyz\r\n\r\n",
+            body.len()
+        );
+        let req = HTTPRequest::new(head.as_bytes()).unwrap();
+        let res = handle_upload(&req, b"", &mut Duplex::new(body), &conf);
+
+        assert_eq!(res.status.code, 303);
+        assert_eq!(res.headers[0], (String::from("Location"), "/docs/".into()));
+        assert_eq!(fs::read(dir.path().join("docs/a.txt")).unwrap(), b"first");
+        assert_eq!(fs::read(dir.path().join("docs/b.txt")).unwrap(), b"second");
+
+        let head = "POST /old.txt HTTP/1.1\r\nContent-Length: 0\r\n\r\n";
+        let req = HTTPRequest::new(head.as_bytes()).unwrap();
+        let res = handle_upload(&req, b"", &mut Duplex::default(), &conf);
+        assert_eq!(res.status.code, 409);
+
+        let head = "POST / HTTP/1.1\r\nContent-Length: 0\r\n\r\n";
+        let req = HTTPRequest::new(head.as_bytes()).unwrap();
+        let res = handle_upload(&req, b"", &mut Duplex::default(), &conf);
+        assert_eq!(res.status.code, 400);
+    }
+
+    #[test]
+    fn post_form_encoded_name() {
+        let (dir, conf) = site();
+        let body = b"--xyz\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"%2e%2e%2fold.txt\"\r\n\
+            \r\n\
+            new\r\n\
+            --xyz--\r\n";
+        let head = format!(
+            "POST /%252e%252e/ HTTP/1.1\r\nContent-Length: {}\r\n\
+             Content-Type: multipart/form-data; boundary=xyz\r\n\r\n",
+            body.len()
+        );
+        fs::create_dir(dir.path().join("%2e%2e")).unwrap();
+        let req = HTTPRequest::new(head.as_bytes()).unwrap();
+        let res = handle_upload(&req, b"", &mut Duplex::new(body), &conf);
+
+        // Neither the path nor the file name are decoded a second time
+        assert_eq!(res.status.code, 303);
+        assert_eq!(fs::read(dir.path().join("old.txt")).unwrap(), b"old");
+        let uploaded = dir.path().join("%2e%2e/%2e%2e%2fold.txt");
+        assert_eq!(fs::read(uploaded).unwrap(), b"new");
+    }
+
+    // Helper
+    fn delete(path: &str, conf: &Config) -> HTTPResponse<'static> {
+        let head = format!("DELETE {} HTTP/1.1\r\n\r\n", path);
+        let req = HTTPRequest::new(head.as_bytes()).unwrap();
+
+        crate::http::handle_connection(&req, std::sync::Arc::new(conf.clone()))
+    }
+
+    #[test]
+    fn delete_files() {
+        let (dir, conf) = site();
+        fs::create_dir(dir.path().join("empty")).unwrap();
+
+        let res = delete("/old.txt", &conf);
+        assert_eq!(res.status.code, 204);
+        assert!(res.body.is_empty());
+        assert!(!dir.path().join("old.txt").exists());
+        assert_eq!(delete("/old.txt", &conf).status.code, 404);
+        assert_eq!(delete("/missing/a.txt", &conf).status.code, 404);
+
+        assert_eq!(delete("/docs", &conf).status.code, 409);
+        assert!(dir.path().join("docs/readme.md").exists());
+        assert_eq!(delete("/empty/", &conf).status.code, 204);
+        assert!(!dir.path().join("empty").exists());
+        assert_eq!(delete("/", &conf).status.code, 403);
+
+        let conf = Config {
+            upload: false,
+            ..conf
+        };
+        assert_eq!(delete("/docs/readme.md", &conf).status.code, 501);
+        assert!(dir.path().join("docs/readme.md").exists());
+    }
+
+    #[test]
+    fn delete_traversal() {
+        let (dir, conf) = site();
+        let outside = TempDir::new();
+        outside.file("keep.txt", "keep");
+        let name = outside.path().file_name().unwrap().to_str().unwrap();
+
+        let path = format!("/../{}/keep.txt", name);
+        assert_eq!(delete(&path, &conf).status.code, 403);
+        assert!(outside.path().join("keep.txt").exists());
+
+        assert_eq!(delete("/secret.log", &conf).status.code, 404);
+        assert!(dir.path().join("secret.log").exists());
+
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("link.txt");
+            std::os::unix::fs::symlink(outside.path().join("keep.txt"), &link)
+                .unwrap();
+            assert_eq!(delete("/link.txt", &conf).status.code, 403);
+            assert!(fs::symlink_metadata(&link).is_ok());
+        }
+    }
+
+    #[test]
+    fn file_names() {
+        assert_eq!(file_name("a.txt"), Some("a.txt"));
+        assert_eq!(file_name("dir/a.txt"), Some("a.txt"));
+        assert_eq!(file_name("C:\\dir\\a.txt"), Some("a.txt"));
+        assert_eq!(file_name("../.."), None);
+        assert_eq!(file_name("dir/"), None);
+        assert_eq!(file_name(""), None);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::files::path::decode_percents;
+use std::{fmt::Write, path::Path};
+
+/// Percent-encode a single segment of a URL path, the counterpart of
+/// [`decode_percents`](crate::files::path::decode_percents).
+///
+/// Unreserved characters and the sub-delimiters allowed in path segments by
+/// [`RFC 3986`] are kept as is, except for `&`, so that the result can be
+/// written into an HTML attribute without escaping. Everything else, including
+/// `/`, `%` and non-ASCII characters, is encoded byte by byte.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::encode_path_segment;
+/// assert_eq!(encode_path_segment("My Photos 2023"), "My%20Photos%202023");
+/// assert_eq!(encode_path_segment("🦀#1.html"), "%F0%9F%A6%80%231.html");
+/// ```
+///
+/// [`RFC 3986`]: https://tools.ietf.org/html/rfc3986#section-3.3
+pub fn encode_path_segment(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b'!'
+            | b'$'
+            | b'\''
+            | b'('
+            | b')'
+            | b'*'
+            | b'+'
+            | b','
+            | b';'
+            | b'='
+            | b':'
+            | b'@' => out.push(char::from(byte)),
+            byte => {
+                let _ = write!(out, "%{:02X}", byte);
+            }
+        }
+    }
+
+    out
+}
+
+/// Return the rest of the URL `path` after `prefix`, if `path` is `prefix`
+/// itself or lies below it, matching whole segments only.
+///
+/// A trailing `/` of `prefix` is ignored, so `/app` and `/app/` both match
+/// `/app`, `/app/` and `/app/x`, returning `""`, `"/"` and `"/x"`, but not
+/// `/apple`. Segments are compared percent-decoded, so `/%61pp` matches as
+/// well, while an encoded `/` doesn't separate segments. Paths leading out of
+/// the prefix again with a `..` segment don't match.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::http::strip_path_prefix;
+/// assert_eq!(strip_path_prefix("/app/x.html", "/app"), Some("/x.html"));
+/// assert_eq!(strip_path_prefix("/app", "/app/"), Some(""));
+/// assert_eq!(strip_path_prefix("/apple", "/app"), None);
+/// assert_eq!(strip_path_prefix("/app/../x", "/app"), None);
+/// ```
+pub fn strip_path_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let mut rest = path;
+
+    for segment in prefix.split('/').filter(|segment| !segment.is_empty()) {
+        let after = rest.strip_prefix('/')?;
+        let (head, tail) = match after.find('/') {
+            Some(end) => after.split_at(end),
+            None => (after, ""),
+        };
+
+        if decode_percents(head) != decode_percents(segment) {
+            return None;
+        }
+        rest = tail;
+    }
+
+    let leaves = rest
+        .split('/')
+        .any(|segment| decode_percents(segment) == Path::new(".."));
+    match leaves || !(rest.is_empty() || rest.starts_with('/')) {
+        true => None,
+        false => Some(rest),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode_path_segment, strip_path_prefix};
+    use crate::files::path::decode_percents;
+    use std::path::PathBuf;
+
+    #[test]
+    fn path_prefix() {
+        for (path, prefix, rest) in [
+            ("/app", "/app", Some("")),
+            ("/app/", "/app", Some("/")),
+            ("/app/x/y.html", "/app", Some("/x/y.html")),
+            ("/app", "/app/", Some("")),
+            ("/app/x", "/app/", Some("/x")),
+            ("/app//x", "/app", Some("//x")),
+            ("/apple", "/app", None),
+            ("/ap", "/app", None),
+            ("/", "/app", None),
+            ("/app/../x", "/app", None),
+            ("/app/x/../../y", "/app", None),
+            ("/app/%2e%2e/x", "/app", None),
+            ("/app/..x", "/app", Some("/..x")),
+            ("/%61pp/x", "/app", Some("/x")),
+            ("/app%2Fx", "/app", None),
+            ("/my%20docs/a", "/my docs", Some("/a")),
+            ("/my docs/a", "/my%20docs/", Some("/a")),
+            ("/a/b/c", "/a/b", Some("/c")),
+            ("/a/bc", "/a/b", None),
+            ("/x", "/", Some("/x")),
+            ("/", "/", Some("/")),
+        ] {
+            assert_eq!(
+                strip_path_prefix(path, prefix),
+                rest,
+                "{} {}",
+                path,
+                prefix
+            );
+        }
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(encode_path_segment("index.html"), "index.html");
+        assert_eq!(encode_path_segment("a~b_c-d(1)"), "a~b_c-d(1)");
+        assert_eq!(encode_path_segment(""), "");
+    }
+
+    #[test]
+    fn reserved() {
+        assert_eq!(encode_path_segment("a b"), "a%20b");
+        assert_eq!(encode_path_segment("100%"), "100%25");
+        assert_eq!(encode_path_segment("#1?x=y&z"), "%231%3Fx=y%26z");
+        assert_eq!(encode_path_segment("a/b\"<>"), "a%2Fb%22%3C%3E");
+    }
+
+    #[test]
+    fn round_trip() {
+        for segment in [
+            "My Photos 2023",
+            "100% done",
+            "#hash?query",
+            "🦀 crab.html",
+            "Ünïcödé",
+            "%20already%2Fencoded",
+            "%",
+            "a&b=c",
+        ] {
+            assert_eq!(
+                decode_percents(&encode_path_segment(segment)),
+                PathBuf::from(segment),
+                "{}",
+                segment
+            );
+        }
+    }
+}
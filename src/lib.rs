@@ -58,6 +58,17 @@
 //! [`https://github.com/umcconnell/servum`]: https://github.com/umcconnell/servum
 //! [`https://www.rust-lang.org/tools/install`]: https://www.rust-lang.org/tools/install
 pub mod cli;
+pub mod clock;
+mod error;
 pub mod files;
 pub mod http;
 pub mod multiprocessing;
+pub mod server;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+pub use error::Error;
+pub use server::{serve, ServeHandle};
+
+#[cfg(test)]
+mod test_utils;
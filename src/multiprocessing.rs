@@ -4,10 +4,22 @@
 //!
 //! [`https://doc.rust-lang.org/stable/book/ch20-02-multithreaded.html`]:
 //! https://doc.rust-lang.org/stable/book/ch20-02-multithreaded.html
+mod connection;
+mod error;
+mod job;
 mod message;
+mod queue;
+mod stats;
 mod threadpool;
 mod worker;
 
+pub use connection::{Connection, PeerAddr};
+pub use error::{JobPanicked, PoolError, PoolFull, ShutdownError};
+pub use job::JobHandle;
 pub use message::Message;
-pub use threadpool::ThreadPool;
-pub use worker::Worker;
+pub use queue::{Queue, TryPushError};
+pub use stats::{
+    ConnectionGuard, Counters, PoolStats, WorkerSnapshot, WorkerStats,
+};
+pub use threadpool::{ConnectionHandler, Log, ThreadPool, DEFAULT_QUEUE_LIMIT};
+pub use worker::{panic_message, Worker};
@@ -0,0 +1,115 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+/// Stream whose remote end may have an address, e.g. for the request log.
+pub trait PeerAddr {
+    /// Return the address of the remote end, or [`None`] if it is unknown or
+    /// not an IP address, e.g. for Unix sockets.
+    fn peer(&self) -> Option<SocketAddr>;
+}
+
+impl PeerAddr for TcpStream {
+    fn peer(&self) -> Option<SocketAddr> {
+        self.peer_addr().ok()
+    }
+}
+
+#[cfg(unix)]
+impl PeerAddr for UnixStream {
+    fn peer(&self) -> Option<SocketAddr> {
+        None
+    }
+}
+
+/// Accepted connection passed to a [`ConnectionHandler`], over TCP or, on
+/// Unix, a Unix domain socket.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::multiprocessing::{Connection, PeerAddr};
+/// use std::net::{TcpListener, TcpStream};
+///
+/// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+/// let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+/// let conn = Connection::from(listener.accept().unwrap().0);
+///
+/// assert_eq!(conn.peer(), client.local_addr().ok());
+/// ```
+///
+/// [`ConnectionHandler`]: crate::multiprocessing::ConnectionHandler
+#[derive(Debug)]
+pub enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl From<TcpStream> for Connection {
+    fn from(stream: TcpStream) -> Self {
+        Connection::Tcp(stream)
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixStream> for Connection {
+    fn from(stream: UnixStream) -> Self {
+        Connection::Unix(stream)
+    }
+}
+
+impl Connection {
+    /// Set the write timeout of the underlying socket, after which blocked
+    /// writes fail, or remove it with [`None`].
+    pub fn set_write_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.set_write_timeout(timeout),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.set_write_timeout(timeout),
+        }
+    }
+}
+
+impl PeerAddr for Connection {
+    fn peer(&self) -> Option<SocketAddr> {
+        match self {
+            Connection::Tcp(stream) => stream.peer(),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.peer(),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.flush(),
+        }
+    }
+}
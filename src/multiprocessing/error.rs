@@ -0,0 +1,103 @@
+use std::{error::Error, fmt, io};
+
+/// Error returned by [`ThreadPool::try_execute`] when the job queue is full.
+///
+/// [`ThreadPool::try_execute`]: crate::multiprocessing::ThreadPool::try_execute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolFull;
+
+impl fmt::Display for PoolFull {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The job queue of the thread pool is full")
+    }
+}
+
+impl Error for PoolFull {}
+
+/// Error returned by [`JobHandle::wait`] when the job didn't return a value.
+///
+/// [`JobHandle::wait`]: crate::multiprocessing::JobHandle::wait
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobPanicked {
+    /// Message of the panic, if the job panicked
+    pub message: String,
+}
+
+impl JobPanicked {
+    /// Error for a job dropped before it returned.
+    pub(crate) fn dropped() -> JobPanicked {
+        JobPanicked {
+            message: String::from("The job was dropped before it finished"),
+        }
+    }
+}
+
+impl fmt::Display for JobPanicked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The job panicked: {}", self.message)
+    }
+}
+
+impl Error for JobPanicked {}
+
+/// Error returned when creating a [`ThreadPool`].
+///
+/// [`ThreadPool`]: crate::multiprocessing::ThreadPool
+#[derive(Debug)]
+pub enum PoolError {
+    /// The pool was asked for zero workers.
+    ZeroSize,
+    /// The thread of a worker could not be spawned.
+    SpawnFailed(io::Error),
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PoolError::ZeroSize => {
+                write!(f, "A thread pool needs at least one worker")
+            }
+            PoolError::SpawnFailed(e) => {
+                write!(f, "Could not spawn worker thread: {}", e)
+            }
+        }
+    }
+}
+
+impl Error for PoolError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PoolError::ZeroSize => None,
+            PoolError::SpawnFailed(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for PoolError {
+    fn from(err: io::Error) -> PoolError {
+        PoolError::SpawnFailed(err)
+    }
+}
+
+/// Error returned by [`ThreadPool::shutdown`] when some workers didn't stop.
+///
+/// A worker fails to stop if its thread is still running at the deadline or
+/// if the thread itself panicked. Workers still running are detached.
+///
+/// [`ThreadPool::shutdown`]: crate::multiprocessing::ThreadPool::shutdown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownError {
+    /// Ids of the workers that failed to stop
+    pub workers: Vec<usize>,
+}
+
+impl fmt::Display for ShutdownError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ids: Vec<String> =
+            self.workers.iter().map(|id| id.to_string()).collect();
+
+        write!(f, "Workers failed to stop: {}", ids.join(", "))
+    }
+}
+
+impl Error for ShutdownError {}
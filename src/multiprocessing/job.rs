@@ -0,0 +1,48 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::multiprocessing::error::JobPanicked;
+
+/// Handle to the result of a job submitted with [`ThreadPool::submit`].
+///
+/// Dropping the handle doesn't cancel the job, its result is just discarded.
+///
+/// [`ThreadPool::submit`]: crate::multiprocessing::ThreadPool::submit
+#[derive(Debug)]
+pub struct JobHandle<T> {
+    receiver: mpsc::Receiver<Result<T, JobPanicked>>,
+}
+
+impl<T> JobHandle<T> {
+    /// Create a handle receiving the result of a job from `receiver`.
+    pub(crate) fn new(
+        receiver: mpsc::Receiver<Result<T, JobPanicked>>,
+    ) -> Self {
+        JobHandle { receiver }
+    }
+
+    /// Block until the job finished and return its result.
+    ///
+    /// Returns [`JobPanicked`] if the job panicked or was dropped without
+    /// running, e.g. because all workers stopped.
+    pub fn wait(self) -> Result<T, JobPanicked> {
+        self.receiver
+            .recv()
+            .unwrap_or_else(|_| Err(JobPanicked::dropped()))
+    }
+
+    /// Like [`JobHandle::wait`], but give up after `timeout`, returning the
+    /// handle to wait again later.
+    pub fn wait_timeout(
+        self,
+        timeout: Duration,
+    ) -> Result<Result<T, JobPanicked>, Self> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(result) => Ok(result),
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(self),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Ok(Err(JobPanicked::dropped()))
+            }
+        }
+    }
+}
@@ -1,9 +1,26 @@
+use crate::multiprocessing::{stats::ConnectionGuard, Connection};
+
 type Job = Box<dyn FnOnce() + Send + 'static>;
 
 /// Message to a [`threadpool::Worker`]
 ///
+/// Accepted connections are sent as is and passed to the connection handler
+/// of the pool, without boxing a job for each of them. The guard counts the
+/// connection as open until the message is dropped.
+///
 /// [`threadpool::Worker`]: crate::threadpool::Worker
 pub enum Message {
     NewJob(Job),
+    Connection(Connection, ConnectionGuard),
     Terminate,
 }
+
+impl std::fmt::Debug for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Message::NewJob(_) => write!(f, "NewJob"),
+            Message::Connection(..) => write!(f, "Connection"),
+            Message::Terminate => write!(f, "Terminate"),
+        }
+    }
+}
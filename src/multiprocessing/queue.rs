@@ -0,0 +1,243 @@
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+use crate::multiprocessing::message::Message;
+
+/// Reason a message couldn't be pushed with [`Queue::try_push`]
+#[derive(Debug)]
+pub enum TryPushError {
+    /// The queue holds as many jobs as it can
+    Full(Message),
+    /// The queue was closed
+    Closed(Message),
+}
+
+/// State of a [`Queue`], guarded by its lock
+#[derive(Default)]
+struct State {
+    messages: VecDeque<Message>,
+    /// Number of terminate messages to hand out once no message is left
+    terminating: usize,
+    /// Number of workers blocked in [`Queue::pop`]
+    waiting: usize,
+    closed: bool,
+}
+
+/// Bounded multi-consumer queue of [`Message`]s shared by the workers of a
+/// [`ThreadPool`].
+///
+/// The lock is only held to push or pop a message, never while a job runs.
+/// A poisoned lock is recovered, as the queue is valid after every
+/// operation.
+///
+/// The queue accepts up to `limit` messages beyond those about to be taken
+/// by waiting workers, so with a limit of `0`, messages are only accepted by
+/// idle workers.
+///
+/// Workers are terminated with [`Queue::terminate`] rather than by pushing
+/// [`Message::Terminate`], so that no worker stops while jobs are still
+/// queued.
+///
+/// [`ThreadPool`]: crate::multiprocessing::ThreadPool
+pub struct Queue {
+    state: Mutex<State>,
+    /// Signaled when a message is pushed or the queue is closed
+    available: Condvar,
+    /// Signaled when a message is popped or a worker starts waiting
+    space: Condvar,
+    limit: usize,
+}
+
+impl Queue {
+    /// Create an empty queue holding up to `limit` waiting messages.
+    pub fn new(limit: usize) -> Queue {
+        Queue {
+            state: Mutex::new(State::default()),
+            available: Condvar::new(),
+            space: Condvar::new(),
+            limit,
+        }
+    }
+
+    /// Lock the state, recovering it from a poisoned lock.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether a message can be pushed without exceeding the limit.
+    fn has_room(&self, state: &State) -> bool {
+        state.messages.len() < self.limit + state.waiting
+    }
+
+    /// Push a message, blocking while the queue is full.
+    ///
+    /// Returns the message if the queue is closed.
+    pub fn push(&self, msg: Message) -> Result<(), Message> {
+        let mut state = self.lock();
+
+        while !state.closed && !self.has_room(&state) {
+            state = self.space.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+
+        self.enqueue(state, msg)
+    }
+
+    /// Push a message unless the queue is full or closed.
+    pub fn try_push(&self, msg: Message) -> Result<(), TryPushError> {
+        let state = self.lock();
+
+        if !state.closed && !self.has_room(&state) {
+            return Err(TryPushError::Full(msg));
+        }
+
+        self.enqueue(state, msg).map_err(TryPushError::Closed)
+    }
+
+    /// Hand out `count` [`Message::Terminate`]s once all queued messages
+    /// have been taken.
+    pub fn terminate(&self, count: usize) {
+        self.lock().terminating += count;
+        self.available.notify_all();
+    }
+
+    /// Remove all queued messages and return how many there were.
+    pub fn clear(&self) -> usize {
+        let mut state = self.lock();
+        let count = state.messages.len();
+        state.messages.clear();
+        drop(state);
+        self.space.notify_all();
+
+        count
+    }
+
+    fn enqueue(
+        &self,
+        mut state: MutexGuard<'_, State>,
+        msg: Message,
+    ) -> Result<(), Message> {
+        if state.closed {
+            return Err(msg);
+        }
+
+        state.messages.push_back(msg);
+        drop(state);
+        self.available.notify_one();
+
+        Ok(())
+    }
+
+    /// Take the next message, blocking while the queue is empty.
+    ///
+    /// Pending terminations are handed out once the queue is empty. Returns
+    /// [`None`] once the queue is closed and empty.
+    pub fn pop(&self) -> Option<Message> {
+        let mut state = self.lock();
+
+        loop {
+            if let Some(msg) = state.messages.pop_front() {
+                drop(state);
+                self.space.notify_one();
+                return Some(msg);
+            }
+
+            if state.terminating > 0 {
+                state.terminating -= 1;
+                return Some(Message::Terminate);
+            }
+
+            if state.closed {
+                return None;
+            }
+
+            state.waiting += 1;
+            self.space.notify_one();
+            state = self
+                .available
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+            state.waiting -= 1;
+        }
+    }
+
+    /// Panic while holding the lock, to poison it in tests.
+    #[cfg(test)]
+    pub fn poison(&self) {
+        let _state = self.state.lock().unwrap();
+        panic!("Poison the queue");
+    }
+
+    /// Close the queue. Pushing fails from now on, and workers stop once the
+    /// remaining messages are taken.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.available.notify_all();
+        self.space.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn is_terminate(msg: Option<Message>) -> bool {
+        matches!(msg, Some(Message::Terminate))
+    }
+
+    #[test]
+    fn limit() {
+        let queue = Queue::new(1);
+
+        assert!(queue.try_push(Message::Terminate).is_ok());
+        assert!(matches!(
+            queue.try_push(Message::Terminate),
+            Err(TryPushError::Full(_))
+        ));
+        assert_eq!(queue.clear(), 1);
+        assert!(queue.try_push(Message::Terminate).is_ok());
+    }
+
+    #[test]
+    fn terminate_last() {
+        let queue = Queue::new(4);
+        queue.terminate(1);
+        queue.push(Message::NewJob(Box::new(|| ()))).unwrap();
+        queue.push(Message::NewJob(Box::new(|| ()))).unwrap();
+
+        assert!(matches!(queue.pop(), Some(Message::NewJob(_))));
+        assert!(matches!(queue.pop(), Some(Message::NewJob(_))));
+        assert!(is_terminate(queue.pop()));
+
+        queue.close();
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn close() {
+        let queue = Queue::new(4);
+        queue.push(Message::Terminate).unwrap();
+        queue.close();
+
+        assert!(queue.push(Message::Terminate).is_err());
+        assert!(matches!(
+            queue.try_push(Message::Terminate),
+            Err(TryPushError::Closed(_))
+        ));
+        assert!(is_terminate(queue.pop()));
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn poisoned() {
+        let queue = Arc::new(Queue::new(4));
+        let poison = Arc::clone(&queue);
+
+        thread::spawn(move || poison.poison()).join().unwrap_err();
+
+        assert!(queue.state.is_poisoned());
+        queue.push(Message::Terminate).unwrap();
+        assert!(is_terminate(queue.pop()));
+    }
+}
@@ -0,0 +1,334 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Counters of a single [`Worker`], updated by the worker thread.
+///
+/// [`Worker`]: crate::multiprocessing::Worker
+#[derive(Debug, Default)]
+pub struct WorkerStats {
+    jobs: AtomicU64,
+    busy_nanos: AtomicU64,
+    last_job_nanos: AtomicU64,
+    active: AtomicBool,
+}
+
+impl WorkerStats {
+    /// Mark the worker as running a job.
+    pub fn start(&self) {
+        self.active.store(true, Ordering::Relaxed);
+    }
+
+    /// Record a finished job that ran for `elapsed`.
+    pub fn finish(&self, elapsed: Duration) {
+        let nanos = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+
+        self.jobs.fetch_add(1, Ordering::Relaxed);
+        self.busy_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.last_job_nanos.store(nanos, Ordering::Relaxed);
+        self.active.store(false, Ordering::Relaxed);
+    }
+
+    /// Whether the worker is running a job.
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Return a snapshot of the counters of worker `id`.
+    pub fn snapshot(&self, id: usize) -> WorkerSnapshot {
+        let jobs = self.jobs.load(Ordering::Relaxed);
+
+        WorkerSnapshot {
+            id,
+            jobs,
+            busy_time: Duration::from_nanos(
+                self.busy_nanos.load(Ordering::Relaxed),
+            ),
+            last_job: match jobs {
+                0 => None,
+                _ => Some(Duration::from_nanos(
+                    self.last_job_nanos.load(Ordering::Relaxed),
+                )),
+            },
+            active: self.active.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counters shared by a [`ThreadPool`] and all its workers.
+///
+/// A job is counted as queued from the moment it is submitted until a worker
+/// takes it, and as in flight until it finishes. A job taken by a worker is
+/// counted as in flight before it stops being queued, so the pool is never
+/// seen idle while a job is handed over.
+///
+/// Connections passed to the pool are also counted as open until they are
+/// closed, see [`Counters::open_connection`].
+///
+/// [`ThreadPool`]: crate::multiprocessing::ThreadPool
+#[derive(Debug, Default)]
+pub struct Counters {
+    queued: AtomicUsize,
+    in_flight: AtomicUsize,
+    connections: AtomicUsize,
+    /// Guards notifications, so none is missed by [`Counters::wait_until`]
+    idle_lock: Mutex<()>,
+    /// Signaled when the pool becomes idle or a connection is closed
+    idle: Condvar,
+}
+
+impl Counters {
+    /// Number of jobs waiting in the queue
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// Number of jobs being executed by workers
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Number of open connections passed to the pool
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+
+    /// Whether no job is queued or being executed.
+    pub fn is_idle(&self) -> bool {
+        self.in_flight() == 0 && self.queued() == 0
+    }
+
+    /// Count a job about to be pushed to the queue.
+    pub fn queue_job(&self) {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Undo [`Counters::queue_job`] for a job that couldn't be queued.
+    pub fn unqueue_job(&self) {
+        self.unqueue_jobs(1);
+    }
+
+    /// Undo [`Counters::queue_job`] for `count` jobs that were dropped from
+    /// the queue.
+    pub fn unqueue_jobs(&self, count: usize) {
+        self.queued.fetch_sub(count, Ordering::SeqCst);
+        self.notify_if_idle();
+    }
+
+    /// Count a job taken from the queue by a worker.
+    pub fn start_job(&self) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Count a job finished by a worker.
+    pub fn finish_job(&self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.notify_if_idle();
+    }
+
+    /// Count a connection as open until the returned guard is dropped.
+    pub fn open_connection(self: &Arc<Self>) -> ConnectionGuard {
+        self.connections.fetch_add(1, Ordering::SeqCst);
+        ConnectionGuard(Arc::clone(self))
+    }
+
+    fn notify_if_idle(&self) {
+        if self.is_idle() {
+            self.notify();
+        }
+    }
+
+    fn notify(&self) {
+        let _lock = self.idle_lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.idle.notify_all();
+    }
+
+    /// Block until no job is queued or being executed, for at most
+    /// `timeout`. Returns whether the pool became idle.
+    pub fn wait_idle(&self, timeout: Option<Duration>) -> bool {
+        self.wait_until(|| self.is_idle(), timeout)
+    }
+
+    /// Block until fewer than `max` connections are open, for at most
+    /// `timeout`. Returns whether a connection slot is free.
+    pub fn wait_connections(
+        &self,
+        max: usize,
+        timeout: Option<Duration>,
+    ) -> bool {
+        self.wait_until(|| self.connections() < max, timeout)
+    }
+
+    /// Block until `done` returns `true`, for at most `timeout`. `done` is
+    /// checked whenever the pool becomes idle or a connection is closed.
+    fn wait_until<F>(&self, done: F, timeout: Option<Duration>) -> bool
+    where
+        F: Fn() -> bool,
+    {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut lock = self.idle_lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        while !done() {
+            lock = match deadline {
+                None => self.idle.wait(lock).unwrap_or_else(|e| e.into_inner()),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+
+                    self.idle
+                        .wait_timeout(lock, deadline - now)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+            };
+        }
+
+        true
+    }
+}
+
+/// Open connection counted by [`Counters::open_connection`], until dropped.
+///
+/// The guard travels with the connection, so that the connection is counted
+/// as closed however its handling ends, even if the handler panics or the
+/// connection is dropped from the queue.
+#[derive(Debug)]
+pub struct ConnectionGuard(Arc<Counters>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.connections.fetch_sub(1, Ordering::SeqCst);
+        self.0.notify();
+    }
+}
+
+/// Statistics of a single worker at some point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkerSnapshot {
+    /// Id of the worker
+    pub id: usize,
+    /// Number of jobs executed, including jobs that panicked
+    pub jobs: u64,
+    /// Total time spent executing jobs
+    pub busy_time: Duration,
+    /// Duration of the last executed job, if any
+    pub last_job: Option<Duration>,
+    /// Whether the worker is executing a job
+    pub active: bool,
+}
+
+/// Statistics of a [`ThreadPool`] at some point in time, returned by
+/// [`ThreadPool::stats`].
+///
+/// [`ThreadPool`]: crate::multiprocessing::ThreadPool
+/// [`ThreadPool::stats`]: crate::multiprocessing::ThreadPool::stats
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Statistics of every worker, by worker id
+    pub workers: Vec<WorkerSnapshot>,
+    /// Number of jobs waiting for a free worker
+    pub queued: usize,
+    /// Number of open connections, either queued or being handled
+    pub connections: usize,
+}
+
+impl PoolStats {
+    /// Total number of jobs executed by all workers.
+    pub fn jobs(&self) -> u64 {
+        self.workers.iter().map(|worker| worker.jobs).sum()
+    }
+
+    /// Total time all workers spent executing jobs.
+    pub fn busy_time(&self) -> Duration {
+        self.workers.iter().map(|worker| worker.busy_time).sum()
+    }
+
+    /// Number of workers currently executing a job.
+    pub fn active(&self) -> usize {
+        self.workers.iter().filter(|worker| worker.active).count()
+    }
+}
+
+impl fmt::Display for PoolStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Threads busy: {}/{}, connections: {} ({} queued), jobs: {}, \
+             busy time: {:.2}s",
+            self.active(),
+            self.workers.len(),
+            self.connections,
+            self.queued,
+            self.jobs(),
+            self.busy_time().as_secs_f64()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record_jobs() {
+        let workers = [WorkerStats::default(), WorkerStats::default()];
+        let snapshot = || PoolStats {
+            workers: vec![workers[0].snapshot(0), workers[1].snapshot(1)],
+            queued: 0,
+            connections: 0,
+        };
+        workers[1].start();
+
+        let stats = snapshot();
+        assert_eq!(stats.active(), 1);
+        assert_eq!(stats.workers[1].last_job, None);
+
+        workers[1].finish(Duration::from_millis(30));
+        workers[1].start();
+        workers[1].finish(Duration::from_millis(10));
+        workers[0].start();
+        workers[0].finish(Duration::from_millis(5));
+
+        let stats = snapshot();
+        assert_eq!(stats.jobs(), 3);
+        assert_eq!(stats.active(), 0);
+        assert_eq!(stats.busy_time(), Duration::from_millis(45));
+        assert_eq!(
+            stats.workers[1],
+            WorkerSnapshot {
+                id: 1,
+                jobs: 2,
+                busy_time: Duration::from_millis(40),
+                last_job: Some(Duration::from_millis(10)),
+                active: false,
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "Threads busy: 0/2, connections: 0 (0 queued), jobs: 3, \
+             busy time: 0.04s"
+        );
+    }
+
+    #[test]
+    fn connections() {
+        let counters = Arc::new(Counters::default());
+        let first = counters.open_connection();
+        let second = counters.open_connection();
+
+        assert_eq!(counters.connections(), 2);
+        assert!(!counters.wait_connections(2, Some(Duration::ZERO)));
+
+        drop(first);
+        assert!(counters.wait_connections(2, Some(Duration::ZERO)));
+
+        let closing = std::thread::spawn(move || drop(second));
+        assert!(counters.wait_connections(1, None));
+        assert_eq!(counters.connections(), 0);
+        closing.join().unwrap();
+    }
+}
@@ -1,53 +1,243 @@
-use std::sync::{mpsc, Arc, Mutex};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use crate::multiprocessing::connection::Connection;
+use crate::multiprocessing::error::{
+    JobPanicked, PoolError, PoolFull, ShutdownError,
+};
+use crate::multiprocessing::job::JobHandle;
 use crate::multiprocessing::message::Message;
-use crate::multiprocessing::worker::Worker;
+use crate::multiprocessing::queue::{Queue, TryPushError};
+use crate::multiprocessing::stats::{Counters, PoolStats};
+use crate::multiprocessing::worker::{panic_message, Worker};
+
+/// Default number of jobs waiting for a free worker before the queue is full
+pub const DEFAULT_QUEUE_LIMIT: usize = 1024;
+
+/// Interval at which a shutdown with a deadline checks on the workers
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Callback receiving the status messages of a [`ThreadPool`] and its workers
+pub type Log = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Callback handling the connections passed to a [`ThreadPool`] with
+/// [`ThreadPool::try_handle_connection`]
+pub type ConnectionHandler = Arc<dyn Fn(Connection) + Send + Sync>;
 
 /// ThreadPool for multi-thread computations.
 ///
 /// Code from the Rust Book Chapter 20:
 /// [`https://doc.rust-lang.org/stable/book/ch20-02-multithreaded.html`]
 ///
+/// Unlike the book's version, the job queue is bounded: once it holds the
+/// queue limit of jobs waiting for a free worker, [`ThreadPool::execute`]
+/// blocks and [`ThreadPool::try_execute`] fails.
+///
+/// Besides jobs, the pool handles accepted connections with a single
+/// connection handler shared by all workers, sparing a job allocation per
+/// connection.
+///
+/// Workers can be added or removed while the pool runs with
+/// [`ThreadPool::resize`]. The pool is shut down with
+/// [`ThreadPool::shutdown`], or on drop without a deadline, ignoring workers
+/// that fail to stop.
+///
 /// [`https://doc.rust-lang.org/stable/book/ch20-02-multithreaded.html`]: https://doc.rust-lang.org/stable/book/ch20-02-multithreaded.html
 pub struct ThreadPool {
     workers: Vec<Worker>,
-    sender: mpsc::Sender<Message>,
+    queue: Arc<Queue>,
+    log: Log,
+    counters: Arc<Counters>,
+    handler: ConnectionHandler,
+    next_id: usize,
 }
 
 impl ThreadPool {
     /// Create a new ThreadPool.
     ///
-    /// The size is the number of threads in the pool.
+    /// The size is the number of threads in the pool. The job queue holds up
+    /// to [`DEFAULT_QUEUE_LIMIT`] jobs.
+    ///
+    /// # Errors
     ///
-    /// # Panics
+    /// Returns [`PoolError::ZeroSize`] if the size is zero and
+    /// [`PoolError::SpawnFailed`] if a worker thread cannot be spawned.
+    pub fn new(size: usize) -> Result<ThreadPool, PoolError> {
+        ThreadPool::with_capacity(size, DEFAULT_QUEUE_LIMIT)
+    }
+
+    /// Create a new ThreadPool with a job queue holding up to `queue_limit`
+    /// jobs waiting for a free worker.
+    ///
+    /// With a `queue_limit` of `0`, jobs are only accepted by idle workers.
+    ///
+    /// # Errors
     ///
-    /// The `new` function will panic if the size is zero.
-    pub fn new(size: usize) -> ThreadPool {
-        assert!(size > 0);
+    /// Fails like [`ThreadPool::new`].
+    pub fn with_capacity(
+        size: usize,
+        queue_limit: usize,
+    ) -> Result<ThreadPool, PoolError> {
+        ThreadPool::with_log(size, queue_limit, Arc::new(|_| ()))
+    }
 
-        let (sender, receiver) = mpsc::channel();
-        let receiver = Arc::new(Mutex::new(receiver));
+    /// Create a new ThreadPool passing status messages of the pool and its
+    /// workers to `log`, e.g. when shutting down.
+    ///
+    /// Pools created with [`ThreadPool::new`] and
+    /// [`ThreadPool::with_capacity`] discard these messages.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`ThreadPool::new`].
+    pub fn with_log(
+        size: usize,
+        queue_limit: usize,
+        log: Log,
+    ) -> Result<ThreadPool, PoolError> {
+        ThreadPool::with_handler(size, queue_limit, log, Arc::new(drop))
+    }
+
+    /// Create a new ThreadPool passing connections to `handler` and status
+    /// messages to `log`.
+    ///
+    /// Pools created with the other constructors close connections right
+    /// away.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`ThreadPool::new`]. Workers spawned before a failure stop
+    /// once the pool is gone.
+    pub fn with_handler(
+        size: usize,
+        queue_limit: usize,
+        log: Log,
+        handler: ConnectionHandler,
+    ) -> Result<ThreadPool, PoolError> {
+        if size == 0 {
+            return Err(PoolError::ZeroSize);
+        }
+
+        let mut pool = ThreadPool {
+            workers: Vec::with_capacity(size),
+            queue: Arc::new(Queue::new(queue_limit)),
+            log,
+            counters: Arc::new(Counters::default()),
+            handler,
+            next_id: 0,
+        };
+        pool.spawn(size)?;
+
+        Ok(pool)
+    }
+
+    /// Spawn `count` new workers.
+    fn spawn(&mut self, count: usize) -> Result<(), PoolError> {
+        for _ in 0..count {
+            self.workers.push(Worker::new(
+                self.next_id,
+                Arc::clone(&self.queue),
+                Arc::clone(&self.log),
+                Arc::clone(&self.counters),
+                Arc::clone(&self.handler),
+            )?);
+            self.next_id += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Return the number of workers in the pool.
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
 
-        let mut workers = Vec::with_capacity(size);
+    /// Change the number of workers in the pool to `size`.
+    ///
+    /// New workers get fresh ids. When shrinking, terminate messages are
+    /// handed out once the job queue is empty, so no job is interrupted or
+    /// dropped, and the workers receiving them are removed. This blocks until
+    /// that many workers have stopped, i.e. until all queued jobs have been
+    /// picked up.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PoolError::ZeroSize`] if the size is zero and
+    /// [`PoolError::SpawnFailed`] if a new worker thread cannot be spawned,
+    /// keeping the workers spawned so far.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::multiprocessing::ThreadPool;
+    /// # fn main() -> Result<(), servum::Error> {
+    /// let mut pool = ThreadPool::new(2)?;
+    ///
+    /// pool.resize(4)?;
+    /// assert_eq!(pool.size(), 4);
+    ///
+    /// pool.resize(1)?;
+    /// assert_eq!(pool.size(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resize(&mut self, size: usize) -> Result<(), PoolError> {
+        if size == 0 {
+            return Err(PoolError::ZeroSize);
+        }
+
+        // Workers that already stopped won't take a terminate message, so
+        // they must not count toward the workers to stop
+        self.reap();
+
+        if size > self.workers.len() {
+            return self.spawn(size - self.workers.len());
+        }
+
+        let mut stopping = self.workers.len() - size;
+        self.queue.terminate(stopping);
 
-        for id in 0..size {
-            workers.push(Worker::new(id, Arc::clone(&receiver)));
+        // Any worker may receive a terminate message, so wait for whichever
+        // workers stop first
+        while stopping > 0 {
+            let stopped = self.workers.len();
+            self.reap();
+            stopping = stopping.saturating_sub(stopped - self.workers.len());
+
+            if stopping > 0 {
+                thread::sleep(POLL_INTERVAL);
+            }
         }
 
-        ThreadPool { workers, sender }
+        Ok(())
+    }
+
+    /// Remove the workers whose thread has stopped.
+    fn reap(&mut self) {
+        self.workers.retain_mut(|worker| {
+            if !worker.is_finished() {
+                return true;
+            }
+            if let Some(thread) = worker.thread.take() {
+                let _ = thread.join();
+            }
+            false
+        });
     }
 
     /// Execute a job closure
     ///
     /// Notify the threadpool a new job is pending using the [`Message::NewJob`]
     /// enum. The job will be picked up and processed by the next waiting
-    /// worker.
+    /// worker. Blocks while the job queue is full.
     ///
     /// # Example
     ///
     /// ```rust,ignore
     /// # use servum::threadpool::ThreadPool;
-    /// let pool = ThreadPool::new(2);
+    /// let pool = ThreadPool::new(2).unwrap();
     ///
     /// for i in (0..=3).rev() {
     ///     pool.execute(move || {
@@ -62,23 +252,738 @@ impl ThreadPool {
         F: FnOnce() + Send + 'static,
     {
         let job = Box::new(f);
-        self.sender.send(Message::NewJob(job)).unwrap();
+        self.counters.queue_job();
+        self.queue.push(Message::NewJob(job)).unwrap();
+    }
+
+    /// Execute a job closure and return a handle to wait for its result.
+    ///
+    /// A panic in the job is caught and returned as [`JobPanicked`] by
+    /// [`JobHandle::wait`]. Blocks while the job queue is full, like
+    /// [`ThreadPool::execute`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::multiprocessing::ThreadPool;
+    /// # fn main() -> Result<(), servum::Error> {
+    /// let pool = ThreadPool::new(2)?;
+    /// let sum = pool.submit(|| (1..=10).sum::<u32>());
+    /// let fail = pool.submit(|| -> u32 { panic!("No result") });
+    ///
+    /// assert_eq!(sum.wait(), Ok(55));
+    /// assert_eq!(fail.wait().unwrap_err().message, "No result");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn submit<F, T>(&self, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        self.execute(move || {
+            let result =
+                panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+                    JobPanicked {
+                        message: panic_message(payload.as_ref()).to_string(),
+                    }
+                });
+            let _ = sender.send(result);
+        });
+
+        JobHandle::new(receiver)
+    }
+
+    /// Execute a job closure, unless the job queue is full.
+    ///
+    /// Like [`ThreadPool::execute`], but returns [`PoolFull`] immediately
+    /// instead of blocking when the queue is full. The job is dropped in that
+    /// case.
+    pub fn try_execute<F>(&self, f: F) -> Result<(), PoolFull>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let job = Box::new(f);
+        self.counters.queue_job();
+
+        match self.queue.try_push(Message::NewJob(job)) {
+            Ok(()) => Ok(()),
+            Err(TryPushError::Full(_)) => {
+                self.counters.unqueue_job();
+                Err(PoolFull)
+            }
+            Err(TryPushError::Closed(_)) => {
+                panic!("All workers of the thread pool have stopped")
+            }
+        }
+    }
+
+    /// Pass a connection to the connection handler, unless the job queue is
+    /// full.
+    ///
+    /// Connections share the job queue and are counted as jobs, and as open
+    /// connections until they are closed. The stream is returned if the queue
+    /// is full, e.g. to answer the client that the server is busy.
+    pub fn try_handle_connection(
+        &self,
+        stream: Connection,
+    ) -> Result<(), Connection> {
+        self.counters.queue_job();
+        let guard = self.counters.open_connection();
+
+        match self.queue.try_push(Message::Connection(stream, guard)) {
+            Ok(()) => Ok(()),
+            Err(TryPushError::Full(Message::Connection(stream, _))) => {
+                self.counters.unqueue_job();
+                Err(stream)
+            }
+            Err(_) => panic!("All workers of the thread pool have stopped"),
+        }
+    }
+
+    /// Return the statistics of the pool and its workers.
+    ///
+    /// Counters are updated independently, so a snapshot taken while jobs
+    /// run may be slightly inconsistent, e.g. a job may be counted as
+    /// neither queued nor executed yet.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::multiprocessing::ThreadPool;
+    /// # fn main() -> Result<(), servum::Error> {
+    /// let pool = ThreadPool::new(4)?;
+    /// let stats = pool.stats();
+    ///
+    /// assert_eq!(stats.workers.len(), 4);
+    /// assert_eq!(stats.jobs(), 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            workers: self
+                .workers
+                .iter()
+                .map(|worker| worker.stats.snapshot(worker.id))
+                .collect(),
+            queued: self.counters.queued(),
+            connections: self.counters.connections(),
+        }
+    }
+
+    /// Number of open connections passed to the pool, either queued or
+    /// being handled.
+    pub fn connections(&self) -> usize {
+        self.counters.connections()
+    }
+
+    /// Block until fewer than `max` connections passed to the pool are
+    /// open, for at most `timeout`. Returns whether a connection slot is
+    /// free before the timeout.
+    pub fn wait_connections(
+        &self,
+        max: usize,
+        timeout: Option<Duration>,
+    ) -> bool {
+        self.counters.wait_connections(max, timeout)
+    }
+
+    /// Block until no job is queued or being executed, for at most
+    /// `timeout`. Returns whether the pool became idle before the timeout.
+    ///
+    /// Jobs submitted while waiting are waited for as well.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::multiprocessing::ThreadPool;
+    /// use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+    /// # fn main() -> Result<(), servum::Error> {
+    ///
+    /// let pool = ThreadPool::new(2)?;
+    /// let done = Arc::new(AtomicUsize::new(0));
+    ///
+    /// for _ in 0..8 {
+    ///     let done = Arc::clone(&done);
+    ///     pool.execute(move || {
+    ///         done.fetch_add(1, Ordering::SeqCst);
+    ///     });
+    /// }
+    ///
+    /// assert!(pool.wait_idle(None));
+    /// assert_eq!(done.load(Ordering::SeqCst), 8);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn wait_idle(&self, timeout: Option<Duration>) -> bool {
+        self.counters.wait_idle(timeout)
+    }
+
+    /// Shut down the pool, waiting for queued jobs to finish.
+    ///
+    /// The pool is drained with [`ThreadPool::wait_idle`], then every worker
+    /// is sent a terminate message and joined. Workers only receive the
+    /// message once the job queue is empty, so no worker stops while jobs
+    /// are left. With a `timeout`, workers still executing a job at the
+    /// deadline are detached and reported in the [`ShutdownError`], along
+    /// with workers whose thread panicked. Jobs still queued at that point
+    /// run on the detached workers.
+    ///
+    /// See [`ThreadPool::shutdown_now`] to drop the queued jobs instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::multiprocessing::ThreadPool;
+    /// use std::time::Duration;
+    /// # fn main() -> Result<(), servum::Error> {
+    ///
+    /// let pool = ThreadPool::new(2)?;
+    /// pool.execute(|| ());
+    ///
+    /// assert!(pool.shutdown(Some(Duration::from_secs(5))).is_ok());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn shutdown(
+        mut self,
+        timeout: Option<Duration>,
+    ) -> Result<(), ShutdownError> {
+        self.stop(timeout)
+    }
+
+    /// Shut down the pool, dropping the jobs still queued.
+    ///
+    /// Jobs already being executed are waited for like with
+    /// [`ThreadPool::shutdown`]. [`JobHandle`]s of dropped jobs return
+    /// [`JobPanicked`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::multiprocessing::ThreadPool;
+    /// use std::{thread, time::Duration};
+    /// # fn main() -> Result<(), servum::Error> {
+    ///
+    /// let pool = ThreadPool::new(1)?;
+    /// let slow = pool.submit(|| thread::sleep(Duration::from_millis(50)));
+    /// let queued = pool.submit(|| ());
+    /// thread::sleep(Duration::from_millis(10));
+    ///
+    /// assert!(pool.shutdown_now(None).is_ok());
+    /// assert!(slow.wait().is_ok());
+    /// assert!(queued.wait().is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn shutdown_now(
+        mut self,
+        timeout: Option<Duration>,
+    ) -> Result<(), ShutdownError> {
+        let dropped = self.queue.clear();
+        self.counters.unqueue_jobs(dropped);
+        self.stop(timeout)
+    }
+
+    /// Terminate and join all workers. Does nothing once the workers are
+    /// stopped.
+    fn stop(&mut self, timeout: Option<Duration>) -> Result<(), ShutdownError> {
+        if self.workers.is_empty() {
+            return Ok(());
+        }
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        (self.log)("Waiting for queued jobs to finish");
+        self.wait_idle(timeout);
+
+        (self.log)("Sending terminate message to all workers");
+        self.queue.terminate(self.workers.len());
+
+        (self.log)("Shutting down all workers");
+        let mut failed = Vec::new();
+
+        for mut worker in self.workers.drain(..) {
+            let thread = match worker.thread.take() {
+                Some(thread) => thread,
+                None => continue,
+            };
+
+            if let Some(deadline) = deadline {
+                // Idle workers are about to take a job or terminate, so they
+                // are waited for past the deadline
+                while !thread.is_finished()
+                    && (Instant::now() < deadline || !worker.stats.is_active())
+                {
+                    thread::sleep(POLL_INTERVAL);
+                }
+
+                if !thread.is_finished() {
+                    failed.push(worker.id);
+                    continue;
+                }
+            }
+
+            if thread.join().is_err() {
+                failed.push(worker.id);
+            }
+        }
+
+        match failed.is_empty() {
+            true => Ok(()),
+            false => Err(ShutdownError { workers: failed }),
+        }
     }
 }
 
 impl Drop for ThreadPool {
     fn drop(&mut self) {
-        println!("Sending terminate message to all workers");
-        for _ in &self.workers {
-            self.sender.send(Message::Terminate).unwrap();
+        if let Err(e) = self.stop(None) {
+            (self.log)(&e.to_string());
+        }
+
+        // Let detached workers stop once the queue is empty
+        self.queue.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{mpsc, Mutex};
+
+    #[test]
+    fn queue_full() {
+        let pool = ThreadPool::with_capacity(1, 2).unwrap();
+        let (started_tx, started) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+
+        // Block the only worker
+        pool.execute(move || {
+            started_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        });
+        started.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_eq!(pool.try_execute(|| ()), Ok(()));
+        assert_eq!(pool.try_execute(|| ()), Ok(()));
+        assert_eq!(pool.try_execute(|| ()), Err(PoolFull));
+
+        release.send(()).unwrap();
+    }
+
+    #[test]
+    fn queue_drains() {
+        let pool = ThreadPool::with_capacity(2, 0).unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        for i in 0..10 {
+            let tx = tx.clone();
+            pool.execute(move || tx.send(i).unwrap());
         }
 
-        println!("Shutting down all workers");
+        let mut done: Vec<i32> = rx.iter().take(10).collect();
+        done.sort_unstable();
+        assert_eq!(done, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn shutdown_clean() {
+        let (log_tx, log_rx) = mpsc::channel::<String>();
+        let log_tx = Mutex::new(log_tx);
+        let log: Log = Arc::new(move |msg| {
+            log_tx.lock().unwrap().send(msg.to_string()).unwrap()
+        });
+        let pool = ThreadPool::with_log(2, 8, log).unwrap();
+        let (tx, rx) = mpsc::channel();
 
-        for worker in &mut self.workers {
-            if let Some(thread) = worker.thread.take() {
-                thread.join().unwrap();
+        for i in 0..4 {
+            let tx = tx.clone();
+            pool.execute(move || tx.send(i).unwrap());
+        }
+
+        assert_eq!(pool.shutdown(Some(Duration::from_secs(5))), Ok(()));
+
+        // Queued jobs finish before the workers stop
+        assert_eq!(rx.try_iter().count(), 4);
+
+        let log: Vec<String> = log_rx.try_iter().collect();
+        assert!(log.contains(&String::from("Worker 0 was terminated")));
+        assert!(log.contains(&String::from("Worker 1 was terminated")));
+    }
+
+    #[test]
+    fn shutdown_timeout() {
+        let pool = ThreadPool::new(2).unwrap();
+        let (started_tx, started) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+
+        // Block worker 0 or 1
+        pool.execute(move || {
+            started_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        });
+        started.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        let start = Instant::now();
+        let err = pool.shutdown(Some(Duration::from_millis(50))).unwrap_err();
+
+        assert_eq!(err.workers.len(), 1);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        release.send(()).unwrap();
+    }
+
+    #[test]
+    fn shutdown_twice() {
+        let mut pool = ThreadPool::new(2).unwrap();
+
+        assert_eq!(pool.stop(None), Ok(()));
+        assert_eq!(pool.stop(Some(Duration::ZERO)), Ok(()));
+        assert!(pool.workers.is_empty());
+    }
+
+    #[test]
+    fn zero_size() {
+        assert!(matches!(ThreadPool::new(0), Err(PoolError::ZeroSize)));
+        assert!(matches!(
+            ThreadPool::with_capacity(0, 8),
+            Err(PoolError::ZeroSize)
+        ));
+    }
+
+    #[test]
+    fn stats() {
+        let pool = ThreadPool::with_capacity(2, 8).unwrap();
+        let (started_tx, started) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+        let release_rx = Arc::new(Mutex::new(release_rx));
+
+        // Block both workers
+        for _ in 0..2 {
+            let started_tx = started_tx.clone();
+            let release_rx = Arc::clone(&release_rx);
+            pool.execute(move || {
+                started_tx.send(()).unwrap();
+                release_rx.lock().unwrap().recv().unwrap();
+            });
+        }
+        for _ in 0..2 {
+            started.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+
+        for _ in 0..3 {
+            pool.execute(|| ());
+        }
+        assert_eq!(pool.try_execute(|| ()), Ok(()));
+
+        let stats = pool.stats();
+        assert_eq!(stats.queued, 4);
+        assert_eq!(stats.active(), 2);
+        assert_eq!(stats.jobs(), 0);
+
+        release.send(()).unwrap();
+        release.send(()).unwrap();
+
+        let start = Instant::now();
+        while pool.stats().jobs() < 6 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        let stats = pool.stats();
+        assert_eq!(stats.queued, 0);
+        assert_eq!(stats.active(), 0);
+        assert_eq!(stats.workers.iter().map(|w| w.jobs).sum::<u64>(), 6);
+        assert!(stats
+            .workers
+            .iter()
+            .all(|w| w.busy_time >= w.last_job.unwrap_or_default()));
+    }
+
+    #[test]
+    fn resize_while_busy() {
+        let mut pool = ThreadPool::with_capacity(2, 64).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let submit = |pool: &ThreadPool, range: std::ops::Range<usize>| {
+            for i in range {
+                let tx = tx.clone();
+                pool.execute(move || {
+                    thread::sleep(Duration::from_millis(1));
+                    tx.send(i).unwrap();
+                });
             }
+        };
+
+        submit(&pool, 0..20);
+        pool.resize(6).unwrap();
+        assert_eq!(pool.size(), 6);
+
+        submit(&pool, 20..40);
+        pool.resize(1).unwrap();
+        assert_eq!(pool.size(), 1);
+        assert_eq!(pool.stats().workers.len(), 1);
+
+        submit(&pool, 40..60);
+
+        // No job is lost while workers come and go
+        let mut done: Vec<usize> = rx.iter().take(60).collect();
+        done.sort_unstable();
+        assert_eq!(done, (0..60).collect::<Vec<_>>());
+
+        let ids: Vec<usize> = pool.workers.iter().map(|w| w.id).collect();
+        assert!(ids.iter().all(|&id| id < 6));
+        assert_eq!(
+            pool.resize(0).unwrap_err().to_string(),
+            PoolError::ZeroSize.to_string()
+        );
+        assert_eq!(pool.shutdown(None), Ok(()));
+    }
+
+    #[test]
+    fn resize_with_stopped_worker() {
+        let mut pool = ThreadPool::with_capacity(2, 8).unwrap();
+
+        // A worker whose thread is gone without a terminate message
+        pool.workers.push(Worker {
+            id: 2,
+            thread: Some(thread::spawn(|| ())),
+            stats: Default::default(),
+        });
+        while !pool.workers[2].is_finished() {
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        pool.resize(1).unwrap();
+        assert_eq!(pool.size(), 1);
+
+        // No terminate message is left over for the remaining worker
+        let (tx, rx) = mpsc::channel();
+        pool.execute(move || tx.send(()).unwrap());
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(!pool.workers[0].is_finished());
+    }
+
+    #[test]
+    fn many_jobs() {
+        let pool = ThreadPool::with_capacity(8, 64).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let start = Instant::now();
+
+        for _ in 0..10_000 {
+            let tx = tx.clone();
+            pool.execute(move || tx.send(()).unwrap());
+        }
+
+        assert_eq!(rx.iter().take(10_000).count(), 10_000);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn poisoned_queue() {
+        let pool = ThreadPool::new(2).unwrap();
+        let queue = Arc::clone(&pool.queue);
+
+        thread::spawn(move || queue.poison()).join().unwrap_err();
+
+        let (tx, rx) = mpsc::channel();
+        for i in 0..4 {
+            let tx = tx.clone();
+            pool.execute(move || tx.send(i).unwrap());
+        }
+
+        let mut done: Vec<i32> = rx.iter().take(4).collect();
+        done.sort_unstable();
+        assert_eq!(done, vec![0, 1, 2, 3]);
+        assert_eq!(pool.shutdown(Some(Duration::from_secs(5))), Ok(()));
+    }
+
+    #[test]
+    fn submit() {
+        let pool = ThreadPool::new(4).unwrap();
+        let handles: Vec<_> =
+            (0..20).map(|i| pool.submit(move || i * 2)).collect();
+        let fail = pool.submit(|| -> i32 { panic!("Job {} failed", 1) });
+
+        let results: Vec<i32> =
+            handles.into_iter().map(|h| h.wait().unwrap()).collect();
+        assert_eq!(results, (0..20).map(|i| i * 2).collect::<Vec<_>>());
+        assert_eq!(
+            fail.wait(),
+            Err(JobPanicked {
+                message: String::from("Job 1 failed")
+            })
+        );
+
+        // The worker that ran the failed job is still available
+        assert_eq!(pool.stats().workers.len(), 4);
+        assert_eq!(pool.submit(|| "ok").wait(), Ok("ok"));
+    }
+
+    #[test]
+    fn submit_timeout() {
+        let pool = ThreadPool::new(1).unwrap();
+        let (release, release_rx) = mpsc::channel::<()>();
+        let handle = pool.submit(move || release_rx.recv().is_ok());
+
+        let handle =
+            handle.wait_timeout(Duration::from_millis(10)).unwrap_err();
+        release.send(()).unwrap();
+
+        assert_eq!(
+            handle.wait_timeout(Duration::from_secs(5)).unwrap(),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn wait_idle() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let pool = ThreadPool::new(3).unwrap();
+        let done = Arc::new(AtomicUsize::new(0));
+
+        assert!(pool.wait_idle(Some(Duration::ZERO)));
+
+        for _ in 0..12 {
+            let done = Arc::clone(&done);
+            pool.execute(move || {
+                thread::sleep(Duration::from_millis(5));
+                done.fetch_add(1, Ordering::SeqCst);
+            });
         }
+
+        assert!(pool.wait_idle(Some(Duration::from_secs(5))));
+        assert_eq!(done.load(Ordering::SeqCst), 12);
+        assert_eq!(pool.stats().active(), 0);
+    }
+
+    #[test]
+    fn wait_idle_timeout() {
+        let pool = ThreadPool::new(1).unwrap();
+        let (release, release_rx) = mpsc::channel::<()>();
+
+        pool.execute(move || release_rx.recv().unwrap());
+
+        let start = Instant::now();
+        assert!(!pool.wait_idle(Some(Duration::from_millis(20))));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        release.send(()).unwrap();
+        assert!(pool.wait_idle(None));
+    }
+
+    // Helper: a pool of `size` workers with a blocked job per worker and
+    // `jobs` more jobs queued, counting finished queued jobs
+    fn busy_pool(
+        size: usize,
+        jobs: usize,
+    ) -> (ThreadPool, mpsc::Sender<()>, Arc<Mutex<usize>>) {
+        let pool = ThreadPool::with_capacity(size, jobs + 1).unwrap();
+        let (started_tx, started) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+        let release_rx = Arc::new(Mutex::new(release_rx));
+        let done = Arc::new(Mutex::new(0));
+
+        for _ in 0..size {
+            let started_tx = started_tx.clone();
+            let release_rx = Arc::clone(&release_rx);
+            pool.execute(move || {
+                started_tx.send(()).unwrap();
+                let _ = release_rx.lock().unwrap().recv();
+            });
+        }
+        for _ in 0..size {
+            started.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+        for _ in 0..jobs {
+            let done = Arc::clone(&done);
+            pool.execute(move || *done.lock().unwrap() += 1);
+        }
+
+        (pool, release, done)
+    }
+
+    #[test]
+    fn shutdown_drains_queue() {
+        let (pool, release, done) = busy_pool(3, 100);
+
+        // Release a single worker, which drains the queue on its own while
+        // the others are still busy
+        release.send(()).unwrap();
+        let start = Instant::now();
+        while *done.lock().unwrap() < 100 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(POLL_INTERVAL);
+        }
+        drop(release);
+
+        assert_eq!(pool.shutdown(Some(Duration::from_secs(5))), Ok(()));
+        assert_eq!(*done.lock().unwrap(), 100);
+    }
+
+    #[test]
+    fn shutdown_terminates_after_jobs() {
+        let (pool, release, done) = busy_pool(2, 100);
+        let stopping = thread::spawn(move || pool.shutdown(None));
+
+        thread::sleep(Duration::from_millis(20));
+        drop(release);
+
+        assert_eq!(stopping.join().unwrap(), Ok(()));
+        assert_eq!(*done.lock().unwrap(), 100);
+    }
+
+    #[test]
+    fn shutdown_now_drops_queue() {
+        let (pool, release, done) = busy_pool(2, 100);
+        let pending = pool.submit(|| ());
+        let stopping = thread::spawn(move || pool.shutdown_now(None));
+
+        thread::sleep(Duration::from_millis(20));
+        drop(release);
+
+        assert_eq!(stopping.join().unwrap(), Ok(()));
+        assert_eq!(*done.lock().unwrap(), 0);
+        assert!(pending.wait().is_err());
+    }
+
+    #[test]
+    fn connections() {
+        use std::io::{Read, Write};
+        use std::net::{TcpListener, TcpStream};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler: ConnectionHandler = Arc::new(|mut stream: Connection| {
+            stream.write_all(b"handled").unwrap();
+        });
+        let pool =
+            ThreadPool::with_handler(1, 0, Arc::new(|_| ()), handler).unwrap();
+        assert!(pool.wait_idle(Some(Duration::from_secs(5))));
+
+        // Wait for the worker to be ready to take a connection
+        let start = Instant::now();
+        let mut client = TcpStream::connect(addr).unwrap();
+        let mut stream = Connection::from(listener.accept().unwrap().0);
+        loop {
+            match pool.try_handle_connection(stream) {
+                Ok(()) => break,
+                Err(busy) => stream = busy,
+            }
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        let mut res = String::new();
+        client.read_to_string(&mut res).unwrap();
+        assert_eq!(res, "handled");
+        assert!(pool.wait_idle(Some(Duration::from_secs(5))));
+        assert_eq!(pool.stats().jobs(), 1);
+        assert_eq!(pool.connections(), 0);
     }
 }
@@ -1,42 +1,188 @@
-use std::sync::{mpsc, Arc, Mutex};
+use std::any::Any;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
 use std::thread;
+use std::time::Instant;
 
-use crate::multiprocessing::message::Message;
+use crate::multiprocessing::{
+    message::Message,
+    queue::Queue,
+    stats::{Counters, WorkerStats},
+    threadpool::{ConnectionHandler, Log},
+};
 
 /// ThreadPool Worker
 pub struct Worker {
     pub id: usize,
     pub thread: Option<thread::JoinHandle<()>>,
+    pub stats: Arc<WorkerStats>,
 }
 
 impl Worker {
     /// Create a new Worker
     ///
-    /// The worker receives a (unique) id and a receiver end of
-    /// [`std::sync::mpsc`]. New [`Message`]s are read from receiver end and
-    /// executed in a seperate thread.
+    /// The worker receives a (unique) id and the [`Queue`] of the pool. New
+    /// [`Message`]s are taken from the queue and
+    /// executed in a seperate thread named `servum-worker-<id>`, as shown by
+    /// debuggers and panic messages.
+    ///
+    /// A panicking job doesn't take down the worker: the panic is caught and
+    /// reported to `log` along with the worker id, and the worker moves on to
+    /// the next message.
+    ///
+    /// Connections are passed to `handler`. Status messages, such as the
+    /// worker being terminated, are passed to `log`. Executed jobs and
+    /// connections are counted in the worker's `stats` and in the pool's
+    /// `counters`.
+    ///
+    /// Returns an error if the thread cannot be spawned.
     pub fn new(
         id: usize,
-        receiver: Arc<Mutex<mpsc::Receiver<Message>>>,
-    ) -> Worker {
-        let thread = thread::spawn(move || loop {
-            let message = receiver.lock().unwrap().recv().unwrap();
-
-            match message {
-                Message::NewJob(job) => {
-                    // println!("Worker {} got a job; executing", id);
-                    job();
-                }
-                Message::Terminate => {
-                    println!("Worker {} was terminated", id);
-                    break;
+        queue: Arc<Queue>,
+        log: Log,
+        counters: Arc<Counters>,
+        handler: ConnectionHandler,
+    ) -> io::Result<Worker> {
+        let stats = Arc::new(WorkerStats::default());
+        let worker_stats = Arc::clone(&stats);
+        let thread =
+            thread::Builder::new().name(name(id)).spawn(move || loop {
+                // The pool is gone without terminating this worker, e.g. after
+                // a shutdown deadline
+                let message = match queue.pop() {
+                    Some(message) => message,
+                    None => break,
+                };
+
+                match message {
+                    Message::NewJob(job) => {
+                        run(id, &log, &counters, &worker_stats, job)
+                    }
+                    Message::Connection(stream, guard) => {
+                        // Count the connection as closed before the job is
+                        // finished, so an idle pool has no open connection
+                        run(id, &log, &counters, &worker_stats, || {
+                            let _guard = guard;
+                            handler(stream)
+                        })
+                    }
+                    Message::Terminate => {
+                        log(&format!("Worker {} was terminated", id));
+                        break;
+                    }
                 }
-            }
-        });
+            })?;
 
-        Worker {
+        Ok(Worker {
             id,
             thread: Some(thread),
-        }
+            stats,
+        })
+    }
+
+    /// Return whether the thread of the worker has stopped.
+    pub fn is_finished(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_none_or(|thread| thread.is_finished())
+    }
+}
+
+/// Run a job taken from the queue on worker `id`, counting it and catching
+/// a panic, which is passed to `log`.
+fn run<F: FnOnce()>(
+    id: usize,
+    log: &Log,
+    counters: &Counters,
+    stats: &WorkerStats,
+    job: F,
+) {
+    counters.start_job();
+    stats.start();
+    let start = Instant::now();
+
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(job)) {
+        log(&format!(
+            "ERR: Worker {} recovered from a panic: {}",
+            id,
+            panic_message(payload.as_ref())
+        ));
+    }
+
+    stats.finish(start.elapsed());
+    counters.finish_job();
+}
+
+/// Return the name of the thread of the worker with `id`.
+fn name(id: usize) -> String {
+    format!("servum-worker-{}", id)
+}
+
+/// Return the message of a panic payload, if it is a string.
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(msg) => msg,
+        None => payload
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .unwrap_or("Box<dyn Any>"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::multiprocessing::ThreadPool;
+    use std::sync::{mpsc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn survives_panic() {
+        let (log_tx, log_rx) = mpsc::channel::<String>();
+        let log_tx = Mutex::new(log_tx);
+        let log: Log = Arc::new(move |msg| {
+            log_tx.lock().unwrap().send(msg.to_string()).unwrap()
+        });
+        let pool = ThreadPool::with_log(1, 8, log).unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        pool.execute(|| panic!("Job failed"));
+        pool.execute(move || tx.send(42).unwrap());
+
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(42));
+        assert_eq!(
+            log_rx.try_recv().as_deref(),
+            Ok("ERR: Worker 0 recovered from a panic: Job failed")
+        );
+    }
+
+    #[test]
+    fn thread_name() {
+        let pool = ThreadPool::new(1).unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        pool.execute(move || {
+            let name = thread::current().name().map(String::from);
+            tx.send(name).unwrap();
+        });
+
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)),
+            Ok(Some(String::from("servum-worker-0")))
+        );
+    }
+
+    #[test]
+    fn panic_messages() {
+        let payload = panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "static");
+
+        let payload =
+            panic::catch_unwind(|| panic!("formatted {}", 1)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "formatted 1");
+
+        let payload = panic::catch_unwind(|| panic::panic_any(1)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "Box<dyn Any>");
     }
 }
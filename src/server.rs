@@ -0,0 +1,2617 @@
+//! The servum server: accepting and handling connections
+use std::cell::Cell;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, prelude::*};
+use std::mem;
+use std::net::{
+    IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream,
+};
+use std::ops::Deref;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tls")]
+use crate::cli::tls;
+use crate::cli::{logger::Logger, net, CliError, Config};
+use crate::clock::Clock;
+use crate::files::{pid::PidFile, watch::Watcher};
+use crate::http::{
+    self, HTTPRequest, HTTPResponse, HTTPStatus, LiveReload, ServedBy,
+    Throttle, Written, EVENTS_PATH, EVENT_STREAM_HEADER,
+};
+use crate::multiprocessing::{
+    panic_message, Connection, ConnectionHandler, PeerAddr, PoolFull,
+    ThreadPool,
+};
+use crate::Error;
+
+/// Time given to workers to finish their requests when shutting down
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+/// Pause of the accept loop after running out of file descriptors
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+/// Minimum time between two warnings about running out of file descriptors
+const FD_WARNING_INTERVAL: Duration = Duration::from_secs(10);
+/// Interval at which a waiting accept loop checks for a shutdown
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Initial size of the buffer reading the head of requests
+const READ_BUFFER_SIZE: usize = 1024;
+/// Size of the buffer reading the head of requests beyond which longer heads
+/// are cut short
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+/// Time a client of the `--redirect-http` listener has to send its request
+const REDIRECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Largest write to a stream with a `--request-deadline`, so that the
+/// deadline is checked between chunks of large responses
+const DEADLINE_CHUNK_SIZE: usize = 64 * 1024;
+/// Interval at which the base directory is checked for changes with
+/// `--live-reload`
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What the server does with new connections while `--max-connections`
+/// connections are open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Answer `503 Service Unavailable` right away, the default
+    #[default]
+    Reject,
+    /// Stop accepting connections until one is closed. New connections wait
+    /// in the backlog of the operating system meanwhile.
+    Wait,
+}
+
+impl FromStr for Overflow {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Overflow::Reject),
+            "wait" => Ok(Overflow::Wait),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Overflow::Reject => "reject",
+            Overflow::Wait => "wait",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A servum server, bound to its address and ready to accept connections.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::{cli::{tui::Verbosity, Config}, server::Server};
+/// use std::{io::prelude::*, net::TcpStream, thread};
+///
+/// # fn main() -> Result<(), servum::Error> {
+/// let server = Server::bind(Config {
+///     address: String::from("127.0.0.1"),
+///     port: 0,
+///     verbosity: Verbosity::Quiet,
+///     ..Config::default()
+/// })?;
+/// let addr = server.local_addr();
+/// let shutdown = server.shutdown_handle();
+/// let running = thread::spawn(move || server.run());
+///
+/// let mut stream = TcpStream::connect(addr)?;
+/// stream.write_all(b"HEAD / HTTP/1.1\r\n\r\n")?;
+/// let mut res = String::new();
+/// stream.read_to_string(&mut res)?;
+/// assert!(res.starts_with("HTTP/1.1 200 OK"));
+///
+/// shutdown.shutdown();
+/// running.join().unwrap()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Server {
+    /// Listener of the accept loop, or of each worker with `--reuse-port`
+    listeners: Arc<Vec<Listener>>,
+    config: Arc<Config>,
+    logger: Arc<Logger>,
+    pool: ThreadPool,
+    handler: ConnectionHandler,
+    shutdown: ShutdownHandle,
+    live_reload: Option<Arc<LiveReload>>,
+    fd_reserve: Arc<FdReserve>,
+    /// Plain HTTP listener redirecting to HTTPS, with `--redirect-http`
+    redirect: Option<TcpListener>,
+    /// Removes the pid file of `--pid-file` once the server is dropped
+    _pid_file: Option<PidFile>,
+    /// Removes the socket file of `--unix-socket` once the server is dropped
+    _socket_file: Option<SocketFile>,
+}
+
+impl Server {
+    /// Bind a server to the address and port of `config`, falling back to
+    /// the following ports as allowed by `port_retry`, or to its Unix socket
+    /// if set. With `reuse_port`, a listener is bound for each worker thread.
+    /// With `redirect_http`, a listener redirecting to HTTPS is bound to that
+    /// port as well, see [`bind_redirect`]. With `pid_file`, the process id is
+    /// written to that file once the listeners are bound, see [`PidFile`].
+    ///
+    /// The ports of the returned server's config are the ports actually
+    /// bound, e.g. when chosen by the operating system for port `0`.
+    ///
+    /// Returns an [`Error::Io`] if the address cannot be bound, the pid file
+    /// belongs to a running process or the log file cannot be opened, an
+    /// [`Error::Config`] if the TLS configuration cannot be loaded, or an
+    /// [`Error::Pool`] if the worker threads cannot be started.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use servum::{cli::Config, server::Server, Error};
+    /// # fn main() -> Result<(), Error> {
+    /// let config = Config {
+    ///     port: 0,
+    ///     ..Config::default()
+    /// };
+    /// let server = Server::bind(config)?;
+    /// assert_ne!(server.config().port, 0);
+    ///
+    /// let taken = Server::bind(Config {
+    ///     port: server.config().port,
+    ///     ..Config::default()
+    /// });
+    /// assert!(matches!(taken, Err(Error::Io(_))));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bind(mut config: Config) -> Result<Server, Error> {
+        let listeners = listen(&config)?;
+        let socket_file = config.unix_socket.clone().map(SocketFile);
+        let addr = match &listeners[0] {
+            Listener::Tcp(listener) => listener.local_addr()?,
+            #[cfg(unix)]
+            Listener::Unix(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+        };
+        if config.unix_socket.is_none() {
+            config.port = addr.port();
+        }
+        let redirect = match config.redirect_http {
+            Some(port) => Some(bind_redirect(&config.address, port)?),
+            None => None,
+        };
+        let redirect_addr = match &redirect {
+            Some(listener) => Some(listener.local_addr()?),
+            None => None,
+        };
+        config.redirect_http = redirect_addr.map(|addr| addr.port());
+        let pid_file = match &config.pid_file {
+            Some(path) => Some(pid_file(path, &config)?),
+            None => None,
+        };
+        http::mark_started();
+
+        #[cfg(feature = "tls")]
+        let tls = config.tls_config().map_err(config_error)?;
+        #[cfg(not(feature = "tls"))]
+        config.tls_config().map_err(config_error)?;
+
+        let config = Arc::new(config);
+        let logger = Logger::new(&config).map(Arc::new).map_err(|e| {
+            io::Error::new(e.kind(), format!("Could not open log file: {}", e))
+        })?;
+
+        let live_reload = match config.live_reload {
+            true => Some(watch(&config)?),
+            false => None,
+        };
+
+        let handler: ConnectionHandler = {
+            let conf = config.clone();
+            let logger = logger.clone();
+            let reload = live_reload.clone();
+
+            Arc::new(move |stream: Connection| {
+                let remote = stream.peer();
+                let reload = reload.as_ref();
+
+                // Writes blocked by a client not reading fail once the
+                // request deadline passed, see Deadline
+                let res = stream.set_write_timeout(conf.request_deadline);
+                #[cfg(feature = "tls")]
+                let res = res.and_then(|()| match &tls {
+                    Some(tls) => tls::accept(tls, stream).and_then(|stream| {
+                        serve_owned(stream, &conf, &logger, remote, reload)
+                    }),
+                    None => serve_owned(stream, &conf, &logger, remote, reload),
+                });
+                #[cfg(not(feature = "tls"))]
+                let res = res.and_then(|()| {
+                    serve_owned(stream, &conf, &logger, remote, reload)
+                });
+
+                match res {
+                    Ok(stats) if conf.verbosity.shows_debug() => eprintln!(
+                        "DEBUG: Connection closed after {} request(s), {} \
+                         bytes read and {} bytes written",
+                        stats.requests, stats.bytes_read, stats.bytes_written
+                    ),
+                    Ok(_) => (),
+                    Err(e) if is_disconnect(&e) => {
+                        if conf.verbosity.shows_debug() {
+                            eprintln!(
+                                "DEBUG: Connection closed by client: {}",
+                                e
+                            );
+                        }
+                    }
+                    Err(e) if conf.verbosity.shows_requests() => {
+                        eprintln!("ERR: {}", e)
+                    }
+                    Err(_) => (),
+                }
+            })
+        };
+        let log = {
+            let logger = logger.clone();
+            Arc::new(move |msg: &str| logger.info(msg))
+        };
+        let pool = ThreadPool::with_handler(
+            config.threads,
+            config.queue,
+            log,
+            handler.clone(),
+        )
+        .map_err(Error::Pool)?;
+
+        let shutdown = ShutdownHandle {
+            stopped: Arc::new(AtomicBool::new(false)),
+            addr,
+            unix_socket: config.unix_socket.clone(),
+            redirect_addr,
+        };
+
+        Ok(Server {
+            listeners: Arc::new(listeners),
+            config,
+            logger,
+            pool,
+            handler,
+            shutdown,
+            live_reload,
+            fd_reserve: Arc::new(FdReserve::new()),
+            redirect,
+            _pid_file: pid_file,
+            _socket_file: socket_file,
+        })
+    }
+
+    /// Return the address the server is bound to, or the unspecified address
+    /// `0.0.0.0:0` if it listens on a Unix socket.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.shutdown.addr
+    }
+
+    /// Return the configuration of the server.
+    pub fn config(&self) -> &Arc<Config> {
+        &self.config
+    }
+
+    /// Return a handle to stop the server from another thread.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Accept and handle connections until the server is shut down.
+    ///
+    /// Connections are handled by the thread pool. While its queue is full,
+    /// new connections are answered with `503 Service Unavailable`, as are
+    /// connections beyond `max_connections`, unless the server waits for a
+    /// connection to close instead (see [`Overflow`]). With `reuse_port`,
+    /// each worker accepts and handles connections from its own listener
+    /// instead. Failing to accept a connection is logged and doesn't stop the
+    /// server. Once shut down, requests being handled are given a few seconds
+    /// to finish, and a summary of the requests served is printed unless the
+    /// server is quiet.
+    ///
+    /// With `redirect_http`, the listener redirecting to HTTPS is served on a
+    /// thread of its own, see [`redirect_stream`].
+    pub fn run(mut self) -> io::Result<()> {
+        self.logger.header();
+
+        let redirecting = match self.redirect.take() {
+            Some(listener) => Some(self.redirect(listener)?),
+            None => None,
+        };
+
+        let server = &self;
+        thread::scope(|s| {
+            if let Some(interval) = server.config.stats_interval {
+                s.spawn(move || server.print_stats(interval));
+            }
+
+            match server.config.reuse_port {
+                true => server.accept_on_workers(),
+                false => server.accept(),
+            }
+        });
+
+        if let Some(thread) = redirecting {
+            let _ = thread.join();
+        }
+
+        self.logger.info("Shutting down");
+        if let Some(reload) = &self.live_reload {
+            reload.close();
+        }
+        let res = self
+            .pool
+            .shutdown(Some(SHUTDOWN_TIMEOUT))
+            .map_err(io::Error::other);
+
+        let summary = self.logger.metrics().summary().to_string();
+        self.logger.info(summary.trim_end());
+        res
+    }
+
+    /// Print the statistics of the thread pool every `interval` until the
+    /// server is shut down.
+    fn print_stats(&self, interval: Duration) {
+        let mut last = Instant::now();
+        while !self.shutdown.is_shutdown() {
+            thread::sleep(SHUTDOWN_POLL_INTERVAL.min(interval));
+            if last.elapsed() >= interval {
+                self.logger.info(&self.pool.stats().to_string());
+                last = Instant::now();
+            }
+        }
+    }
+
+    /// Start a thread answering the connections of `listener` with redirects
+    /// to HTTPS, one at a time, until the server is shut down.
+    ///
+    /// Redirects are cheap, so they are answered right away instead of going
+    /// through the thread pool. Clients get [`REDIRECT_TIMEOUT`] to send
+    /// their request, so that a slow one doesn't hold up the others for long.
+    fn redirect(
+        &self,
+        listener: TcpListener,
+    ) -> io::Result<thread::JoinHandle<()>> {
+        let config = self.config.clone();
+        let logger = self.logger.clone();
+        let shutdown = self.shutdown.clone();
+
+        thread::Builder::new()
+            .name(String::from("servum-redirect"))
+            .spawn(move || loop {
+                let stream = accept_connection(&listener, &config);
+                if shutdown.is_shutdown() {
+                    break;
+                }
+
+                let res = stream.and_then(|stream| {
+                    stream.set_read_timeout(Some(REDIRECT_TIMEOUT))?;
+                    stream.set_write_timeout(Some(REDIRECT_TIMEOUT))?;
+                    let remote = stream.peer();
+                    redirect_stream(stream, &config, &logger, remote)
+                });
+
+                match res {
+                    Ok(_) => (),
+                    Err(e) if is_disconnect(&e) => (),
+                    Err(e) if config.verbosity.shows_requests() => {
+                        eprintln!("ERR: Could not redirect to HTTPS: {}", e)
+                    }
+                    Err(_) => (),
+                }
+            })
+    }
+
+    /// Accept connections and hand them over to the thread pool until the
+    /// server is shut down.
+    fn accept(&self) {
+        loop {
+            let stream = self.listeners[0].accept(&self.config);
+            if self.shutdown.is_shutdown() {
+                break;
+            }
+
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    accept_error(
+                        &e,
+                        &self.listeners[0],
+                        &self.fd_reserve,
+                        &self.config,
+                    );
+                    continue;
+                }
+            };
+
+            match self.config.max_connections {
+                Some(max) if self.pool.connections() >= max => {
+                    self.reject(stream, "Too many open connections")
+                }
+                _ => {
+                    if let Err(stream) = self.pool.try_handle_connection(stream)
+                    {
+                        self.reject(stream, PoolFull);
+                    }
+                }
+            }
+
+            if let Some(max) = self.config.max_connections {
+                if self.config.connection_overflow == Overflow::Wait {
+                    self.wait_for_slot(max);
+                }
+            }
+        }
+    }
+
+    /// Run an accept loop for each listener on the workers, each handling
+    /// the connections it accepts itself, until the server is shut down.
+    ///
+    /// Only one loop is woken up by [`ShutdownHandle::shutdown`], which then
+    /// shuts down all listeners so that the other loops stop as well.
+    fn accept_on_workers(&self) {
+        for i in 0..self.listeners.len() {
+            let listeners = self.listeners.clone();
+            let handler = self.handler.clone();
+            let shutdown = self.shutdown.clone();
+            let config = self.config.clone();
+            let fd_reserve = self.fd_reserve.clone();
+
+            self.pool.execute(move || {
+                loop {
+                    let stream = listeners[i].accept(&config);
+                    if shutdown.is_shutdown() {
+                        break;
+                    }
+
+                    match stream {
+                        Ok(stream) => {
+                            let res =
+                                panic::catch_unwind(AssertUnwindSafe(|| {
+                                    handler(stream)
+                                }));
+                            if let Err(payload) = res {
+                                eprintln!(
+                                    "ERR: Recovered from a panic: {}",
+                                    panic_message(payload.as_ref())
+                                );
+                            }
+                        }
+                        Err(e) => accept_error(
+                            &e,
+                            &listeners[i],
+                            &fd_reserve,
+                            &config,
+                        ),
+                    }
+                }
+
+                for listener in listeners.iter() {
+                    listener.shutdown();
+                }
+            });
+        }
+
+        self.pool.wait_idle(None);
+    }
+
+    /// Answer `503 Service Unavailable` to a connection that can't be
+    /// handled and close it.
+    fn reject(&self, stream: Connection, reason: impl fmt::Display) {
+        if self.config.verbosity.shows_requests() {
+            eprintln!("ERR: {}, answering 503", reason);
+        }
+
+        unavailable(stream, &self.config);
+    }
+
+    /// Block until fewer than `max` connections are open or the server is
+    /// shut down.
+    fn wait_for_slot(&self, max: usize) {
+        while !self
+            .pool
+            .wait_connections(max, Some(SHUTDOWN_POLL_INTERVAL))
+            && !self.shutdown.is_shutdown()
+        {}
+    }
+}
+
+/// Start a thread watching the base directory of `config` for changes,
+/// until the returned [`LiveReload`] is closed. Each change clears the
+/// [`NotFoundCache`](http::NotFoundCache) and the
+/// [`ManifestCache`](http::ManifestCache) of `config` as well.
+fn watch(config: &Config) -> io::Result<Arc<LiveReload>> {
+    let reload = Arc::new(LiveReload::new());
+    let mut watcher = Watcher::new(&config.base_dir, config.ignore.clone());
+
+    thread::Builder::new()
+        .name(String::from("servum-watcher"))
+        .spawn({
+            let reload = reload.clone();
+            let not_found = config.not_found_cache.clone();
+            let manifest = config.manifest_cache.clone();
+            move || {
+                while !reload.is_closed() {
+                    thread::sleep(WATCH_INTERVAL);
+
+                    let changed = watcher.poll();
+                    if !changed.is_empty() {
+                        not_found.clear();
+                        manifest.clear();
+                        let paths: Vec<_> = changed
+                            .iter()
+                            .map(|path| path.to_string_lossy())
+                            .collect();
+                        reload.notify(&paths);
+                    }
+                }
+            }
+        })?;
+
+    Ok(reload)
+}
+
+/// Serve a connection with [`serve_connection`], handing it over to a thread
+/// of its own if it requests the live reload event stream, so that no worker
+/// is held for as long as the page stays open.
+fn serve_owned<S>(
+    mut stream: S,
+    conf: &Arc<Config>,
+    logger: &Logger,
+    remote: Option<SocketAddr>,
+    reload: Option<&Arc<LiveReload>>,
+) -> io::Result<ConnStats>
+where
+    S: Read + Write + Send + 'static,
+{
+    let stats = serve_connection(&mut stream, conf, logger, remote)?;
+
+    if let (Handled::EventStream, Some(reload)) = (stats.handled, reload) {
+        let reload = reload.clone();
+        thread::Builder::new()
+            .name(String::from("servum-events"))
+            .spawn(move || {
+                let _ = reload.stream(stream);
+            })?;
+    }
+
+    Ok(stats)
+}
+
+/// Socket a [`Server`] accepts connections on
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    /// Accept a connection, see [`accept_connection`].
+    fn accept(&self, config: &Config) -> io::Result<Connection> {
+        match self {
+            Listener::Tcp(listener) => {
+                accept_connection(listener, config).map(Connection::from)
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => listener
+                .accept()
+                .map(|(stream, _)| Connection::from(stream)),
+        }
+    }
+
+    /// Wake up the threads accepting connections on a listener bound with
+    /// `--reuse-port`, see [`net::shutdown_listener`].
+    fn shutdown(&self) {
+        match self {
+            Listener::Tcp(listener) => {
+                let _ = net::shutdown_listener(listener);
+            }
+            #[cfg(unix)]
+            Listener::Unix(_) => (),
+        }
+    }
+}
+
+/// Create the pid file at `path`, warning about a stale file it replaces.
+fn pid_file(path: &Path, config: &Config) -> io::Result<PidFile> {
+    let pid_file = PidFile::create(path).map_err(|e| {
+        io::Error::new(e.kind(), format!("Could not write pid file: {}", e))
+    })?;
+
+    if let Some(stale) = pid_file.stale() {
+        if config.verbosity.shows_startup() {
+            eprintln!(
+                "Warning: Replaced stale pid file {} of process {:?}, which \
+                 isn't running",
+                path.display(),
+                stale
+            );
+        }
+    }
+
+    Ok(pid_file)
+}
+
+/// Socket file of a Unix socket listener, removed when dropped, so that it
+/// isn't left behind however the server stops.
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Bind the listeners of `config`: one listener for its Unix socket or its
+/// address, or one for each worker thread with `reuse_port`.
+fn listen(config: &Config) -> io::Result<Vec<Listener>> {
+    if let Some(path) = &config.unix_socket {
+        return bind_unix(path).map(|listener| vec![listener]);
+    }
+
+    let listeners = match config.reuse_port {
+        true => net::bind_reuse_port(
+            &config.address,
+            config.port,
+            config.port_retry,
+            config.threads,
+        )?,
+        false => {
+            vec![net::bind(&config.address, config.port, config.port_retry)?]
+        }
+    };
+
+    listeners
+        .into_iter()
+        .map(|listener| {
+            net::set_buffer_sizes(
+                &listener,
+                config.send_buffer,
+                config.recv_buffer,
+            )?;
+            Ok(Listener::Tcp(listener))
+        })
+        .collect()
+}
+
+/// Bind a listener to the Unix socket at `path`, see [`net::bind_unix`].
+#[cfg(unix)]
+fn bind_unix(path: &Path) -> io::Result<Listener> {
+    net::bind_unix(path).map(Listener::Unix)
+}
+
+/// Bind a listener to a Unix socket, which is only supported on Unix.
+#[cfg(not(unix))]
+fn bind_unix(_path: &Path) -> io::Result<Listener> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--unix-socket is only supported on Unix",
+    ))
+}
+
+/// Accept a connection on `listener` and apply the socket options of
+/// `config` to it.
+///
+/// The buffer sizes are set on the listener instead, see
+/// [`net::set_buffer_sizes`].
+pub fn accept_connection(
+    listener: &TcpListener,
+    config: &Config,
+) -> io::Result<TcpStream> {
+    let (stream, _) = listener.accept()?;
+    stream.set_nodelay(config.nodelay)?;
+
+    Ok(stream)
+}
+
+/// Bind the listener redirecting plain HTTP to HTTPS, see
+/// [`Config::redirect_http`], to `address` and `port`.
+///
+/// Errors name the `--redirect-http` option, to tell them apart from errors
+/// binding the main port.
+pub fn bind_redirect(address: &str, port: u16) -> io::Result<TcpListener> {
+    net::bind(address, port, 0).map_err(|e| {
+        io::Error::new(e.kind(), format!("--redirect-http: {}", e))
+    })
+}
+
+/// Answer `503 Service Unavailable` to a connection and close it.
+fn unavailable(mut stream: Connection, config: &Config) {
+    // A plain-text response is meaningless to a TLS client, so the
+    // connection is simply closed
+    if config.scheme() == "http" {
+        let res = HTTPResponse::from(HTTPStatus::from(503));
+        let _ = stream.write_all(&res.into_bytes());
+    }
+}
+
+/// Log an error accepting a connection from `listener`.
+///
+/// After running out of file descriptors, the spare descriptor of `reserve`
+/// is used to answer the pending connection with `503 Service Unavailable`,
+/// and the accept loop pauses so that it doesn't spin until some are closed.
+/// The warning is only printed every [`FD_WARNING_INTERVAL`].
+fn accept_error(
+    err: &io::Error,
+    listener: &Listener,
+    reserve: &FdReserve,
+    config: &Config,
+) {
+    if !http::is_fd_exhausted(err) {
+        if config.verbosity.shows_requests() {
+            eprintln!("ERR: Could not accept connection: {}", err);
+        }
+        return;
+    }
+
+    if reserve.should_warn(&*config.clock) {
+        eprintln!(
+            "ERR: Could not accept connection: {}, answering 503 until \
+             file descriptors are available again",
+            err
+        );
+    }
+    reserve.release(|| {
+        if let Ok(stream) = listener.accept(config) {
+            unavailable(stream, config);
+        }
+    });
+    thread::sleep(ACCEPT_BACKOFF);
+}
+
+/// Spare file descriptor, kept open so that one can be freed after running
+/// out of them, see [`accept_error`].
+#[derive(Debug)]
+struct FdReserve {
+    spare: Mutex<Option<File>>,
+    /// Time of the last warning about running out of file descriptors
+    warned: Mutex<Option<Instant>>,
+}
+
+impl FdReserve {
+    /// Open the spare file descriptor. Failing to do so isn't fatal, the
+    /// server then simply has none to free.
+    fn new() -> Self {
+        FdReserve {
+            spare: Mutex::new(open_spare().ok()),
+            warned: Mutex::new(None),
+        }
+    }
+
+    /// Whether a warning should be printed now, at most once every
+    /// [`FD_WARNING_INTERVAL`] of `clock`.
+    fn should_warn(&self, clock: &dyn Clock) -> bool {
+        let mut warned = self.warned.lock().unwrap_or_else(|e| e.into_inner());
+        let now = clock.instant();
+
+        match *warned {
+            Some(last) if now - last < FD_WARNING_INTERVAL => false,
+            _ => {
+                *warned = Some(now);
+                true
+            }
+        }
+    }
+
+    /// Run `f` with the spare file descriptor closed, reopening it after.
+    fn release<T>(&self, f: impl FnOnce() -> T) -> T {
+        let mut spare = self.spare.lock().unwrap_or_else(|e| e.into_inner());
+
+        drop(spare.take());
+        let res = f();
+        *spare = open_spare().ok();
+
+        res
+    }
+}
+
+/// Open the null device as a spare file descriptor, see [`FdReserve`].
+fn open_spare() -> io::Result<File> {
+    #[cfg(windows)]
+    let path = "NUL";
+    #[cfg(not(windows))]
+    let path = "/dev/null";
+
+    File::open(path)
+}
+
+/// Whether `err` means the client closed the connection, e.g. a browser
+/// canceling a request. These are expected and not worth an error message.
+fn is_disconnect(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::UnexpectedEof
+    )
+}
+
+/// Convert an error loading part of the configuration into an
+/// [`Error::Config`].
+fn config_error(err: CliError) -> Error {
+    Error::Config(err.to_string())
+}
+
+/// Cloneable handle stopping a [`Server`], returned by
+/// [`Server::shutdown_handle`].
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle {
+    stopped: Arc<AtomicBool>,
+    addr: SocketAddr,
+    unix_socket: Option<PathBuf>,
+    redirect_addr: Option<SocketAddr>,
+}
+
+impl ShutdownHandle {
+    /// Stop the server. [`Server::run`] returns once the requests being
+    /// handled finished.
+    pub fn shutdown(&self) {
+        if self.stopped.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        // Wake up the accept loops with a connection of our own
+        match &self.unix_socket {
+            #[cfg(unix)]
+            Some(path) => {
+                let _ = UnixStream::connect(path);
+            }
+            _ => wake(self.addr),
+        }
+        if let Some(addr) = self.redirect_addr {
+            wake(addr);
+        }
+    }
+
+    /// Whether the server was shut down.
+    pub fn is_shutdown(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+}
+
+/// Bind a server with `config` and run it on a background thread, e.g. to
+/// serve files in the integration tests of another crate.
+///
+/// Returns the errors of [`Server::bind`]. Use port `0` to bind any free
+/// port, and [`ServeHandle::addr`] to connect to it.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::cli::{tui::Verbosity, Config};
+/// use std::{env, fs, io::prelude::*, net::TcpStream, process};
+///
+/// # fn main() -> Result<(), servum::Error> {
+/// let dir = env::temp_dir().join(format!("servum-serve-{}", process::id()));
+/// fs::create_dir_all(&dir)?;
+/// fs::write(dir.join("hello.txt"), "Hello, world!")?;
+///
+/// let handle = servum::serve(Config {
+///     base_dir: dir.clone(),
+///     address: String::from("127.0.0.1"),
+///     port: 0,
+///     verbosity: Verbosity::Quiet,
+///     ..Config::default()
+/// })?;
+///
+/// let mut stream = TcpStream::connect(handle.addr())?;
+/// stream.write_all(b"GET /hello.txt HTTP/1.1\r\nConnection: close\r\n\r\n")?;
+/// let mut res = String::new();
+/// stream.read_to_string(&mut res)?;
+/// assert!(res.starts_with("HTTP/1.1 200 OK"));
+/// assert!(res.ends_with("\r\n\r\nHello, world!"));
+///
+/// handle.shutdown()?;
+/// fs::remove_dir_all(dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn serve(config: Config) -> Result<ServeHandle, Error> {
+    let server = Server::bind(config)?;
+    let addr = server.local_addr();
+    let shutdown = server.shutdown_handle();
+    let join = thread::Builder::new()
+        .name(String::from("servum-serve"))
+        .spawn(move || server.run())?;
+
+    Ok(ServeHandle {
+        addr,
+        shutdown,
+        join: Some(join),
+    })
+}
+
+/// Handle of a server running in the background, returned by [`serve`].
+///
+/// The server is stopped when the handle is dropped, without waiting for it
+/// to finish. Use [`ServeHandle::shutdown`] to wait for it instead.
+#[derive(Debug)]
+pub struct ServeHandle {
+    addr: SocketAddr,
+    shutdown: ShutdownHandle,
+    join: Option<thread::JoinHandle<io::Result<()>>>,
+}
+
+impl ServeHandle {
+    /// Return the address the server is bound to.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Return a handle to stop the server from another thread.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Stop the server and wait for the requests being handled to finish.
+    ///
+    /// Returns the error the server stopped with, if any.
+    pub fn shutdown(mut self) -> Result<(), Error> {
+        self.shutdown.shutdown();
+
+        match self.join.take().map(thread::JoinHandle::join) {
+            Some(Ok(res)) => Ok(res?),
+            Some(Err(e)) => Err(Error::Io(io::Error::other(format!(
+                "Server panicked: {}",
+                panic_message(&*e)
+            )))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ServeHandle {
+    fn drop(&mut self) {
+        self.shutdown.shutdown();
+    }
+}
+
+/// Connect to a listener bound to `addr`, waking up the thread accepting
+/// connections on it. Wildcard addresses are reached through the loopback
+/// interface.
+fn wake(mut addr: SocketAddr) {
+    if addr.ip().is_unspecified() {
+        addr.set_ip(match addr.ip() {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        });
+    }
+    let _ = TcpStream::connect_timeout(&addr, Duration::from_secs(1));
+}
+
+/// How a connection was handled by [`handle_stream`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Handled {
+    /// The response was written and the connection can be closed
+    #[default]
+    Done,
+    /// The header of the live reload event stream was written. The events are
+    /// to be written with [`LiveReload::stream`] until the connection closes.
+    EventStream,
+    /// The connection was closed before sending a request, or sent an invalid
+    /// one, and nothing was written
+    NoRequest,
+}
+
+/// Statistics of a connection served by [`serve_connection`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConnStats {
+    /// Number of requests answered
+    pub requests: usize,
+    /// Number of bytes read from the stream
+    pub bytes_read: u64,
+    /// Number of bytes written to the stream, headers included
+    pub bytes_written: u64,
+    /// How the last request was handled
+    pub handled: Handled,
+}
+
+/// Serve a connection over any stream, e.g. TCP, a Unix socket or TLS, with
+/// [`handle_stream`], and count the bytes read and written.
+///
+/// Requests are logged and counted in the [`Logger::metrics`]. The
+/// connection is answered once and then meant to be closed, so pipelined
+/// requests after the first one are ignored.
+///
+/// If handling the request panics, a `500 Internal Server Error` response is
+/// attempted before closing the stream, and the panic is returned as an error.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::{cli::{logger::Logger, tui::Verbosity, Config}, server};
+/// use std::{io::Cursor, sync::Arc};
+///
+/// let conf = Arc::new(Config {
+///     base_dir: std::env::temp_dir(),
+///     verbosity: Verbosity::Quiet,
+///     ..Config::default()
+/// });
+/// let logger = Logger::new(&conf).unwrap();
+///
+/// let req = b"GET /missing.html HTTP/1.1\r\n\r\n";
+/// let mut stream = Cursor::new(req.to_vec());
+/// let stats =
+///     server::serve_connection(&mut stream, &conf, &logger, None).unwrap();
+///
+/// // The response is written after the request
+/// let written = stream.get_ref().len() - req.len();
+/// assert_eq!(stats.requests, 1);
+/// assert_eq!(stats.bytes_read, req.len() as u64);
+/// assert_eq!(stats.bytes_written, written as u64);
+/// ```
+pub fn serve_connection<S: Read + Write>(
+    stream: S,
+    conf: &Arc<Config>,
+    logger: &Logger,
+    remote: Option<SocketAddr>,
+) -> io::Result<ConnStats> {
+    let mut stream = Counted {
+        inner: stream,
+        read: 0,
+        written: 0,
+    };
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        handle_stream(&mut stream, conf, logger, remote)
+    }));
+
+    let handled = match res {
+        Ok(res) => res?,
+        Err(payload) => {
+            let res = HTTPResponse::from(HTTPStatus::from(500));
+            stream.write_all(&res.into_bytes())?;
+            stream.flush()?;
+
+            return Err(io::Error::other(format!(
+                "Panic while handling request: {}",
+                panic_message(payload.as_ref())
+            )));
+        }
+    };
+
+    Ok(ConnStats {
+        requests: match handled {
+            Handled::NoRequest => 0,
+            _ => 1,
+        },
+        bytes_read: stream.read,
+        bytes_written: stream.written,
+        handled,
+    })
+}
+
+/// Stream counting the bytes read from and written to it
+struct Counted<S> {
+    inner: S,
+    read: u64,
+    written: u64,
+}
+
+impl<S: Read> Read for Counted<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        Ok(n)
+    }
+}
+
+impl<S: Write> Write for Counted<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Stream whose writes fail with [`io::ErrorKind::TimedOut`] once the
+/// `--request-deadline` of the request they answer has passed.
+///
+/// Writes are split into chunks of at most [`DEADLINE_CHUNK_SIZE`], as a
+/// single write of a large body could block for as long as the client takes
+/// to read it. A chunk blocked by a client not reading at all is bounded by
+/// the write timeout of the connection, which [`Server`] sets to the deadline
+/// as well.
+struct Deadline<'a, S> {
+    inner: S,
+    clock: &'a dyn Clock,
+    /// Time the request was read at
+    start: Instant,
+    /// Time given to answer the request, if limited
+    limit: Option<Duration>,
+}
+
+impl<'a, S> Deadline<'a, S> {
+    /// Bound writes to `inner` to the deadline of `conf`, counted from
+    /// `start`.
+    fn new(inner: S, conf: &'a Config, start: Instant) -> Self {
+        Deadline {
+            inner,
+            clock: conf.clock.as_ref(),
+            start,
+            limit: conf.request_deadline,
+        }
+    }
+}
+
+impl<S: Write> Write for Deadline<'_, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return self.inner.write(buf),
+        };
+
+        let expired = || {
+            io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "Response not sent within the request deadline of {} \
+                     s, closing the connection",
+                    limit.as_secs()
+                ),
+            )
+        };
+
+        if self.clock.instant().duration_since(self.start) >= limit {
+            return Err(expired());
+        }
+        self.inner
+            .write(&buf[..buf.len().min(DEADLINE_CHUNK_SIZE)])
+            .map_err(|e| match e.kind() {
+                // The write timeout of the socket ran out
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                    expired()
+                }
+                _ => e,
+            })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Read a request from `stream`, respond to it and log it.
+///
+/// `remote` is the address of the client, if known, for the request log. The
+/// request is logged once the response is written, with the bytes actually
+/// sent, so the logged response time includes the simulated `--delay` and
+/// `--throttle`.
+///
+/// Writing the response fails with [`io::ErrorKind::TimedOut`] if it takes
+/// longer than the `--request-deadline` from reading the request, so that the
+/// connection is closed. The request is logged with the bytes sent until
+/// then.
+///
+/// With `--live-reload`, only the header is written in response to `GET`
+/// requests to [`EVENTS_PATH`], and [`Handled::EventStream`] is returned.
+/// With `--upload`, `PUT` and `POST` requests are answered by
+/// [`http::handle_upload`], which reads their body from the stream. Other
+/// requests sent with a chunked body have it read and discarded first, see
+/// [`http::ChunkedBody`], while other transfer codings are answered with
+/// `501 Not Implemented`.
+/// If the stream is closed before sending a request, or sends an invalid one,
+/// nothing is written and [`Handled::NoRequest`] is returned.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::{cli::{logger::Logger, tui::Verbosity, Config}, server};
+/// use std::{io::Cursor, sync::Arc};
+///
+/// let conf = Arc::new(Config {
+///     base_dir: std::env::temp_dir(),
+///     verbosity: Verbosity::Quiet,
+///     ..Config::default()
+/// });
+/// let logger = Logger::new(&conf).unwrap();
+///
+/// let mut stream = Cursor::new(b"GET /missing.html HTTP/1.1\r\n\r\n".to_vec());
+/// server::handle_stream(&mut stream, &conf, &logger, None).unwrap();
+///
+/// let res = String::from_utf8_lossy(stream.get_ref());
+/// assert!(res.contains("HTTP/1.1 404 Not Found"));
+/// ```
+pub fn handle_stream<S: Read + Write>(
+    mut stream: S,
+    conf: &Arc<Config>,
+    logger: &Logger,
+    remote: Option<SocketAddr>,
+) -> io::Result<Handled> {
+    let mut buffer = ReadBuffer::take();
+
+    let len = buffer.read_head(&mut stream)?;
+    if len == 0 {
+        return Ok(Handled::NoRequest);
+    }
+
+    let timer = conf.clock.instant();
+    // Only the head is parsed, the rest of the buffer starts the body
+    let head_len = head_end(&buffer[..len]);
+    let req = HTTPRequest::new(&buffer[..head_len.unwrap_or(len)]);
+
+    if let Ok(req) = req {
+        if conf.live_reload
+            && req.method == "GET"
+            && req.filepath == Path::new(EVENTS_PATH)
+        {
+            let mut res = HTTPResponse::new(
+                HTTPStatus::from(200),
+                Some("text/event-stream".into()),
+                Ok(Vec::new()),
+            );
+            res.served_by = ServedBy::Internal("events");
+            let written = Written {
+                header: EVENT_STREAM_HEADER.len(),
+                body: 0,
+            };
+            logger.log(&req, &res, written, timer, remote);
+
+            stream.write_all(EVENT_STREAM_HEADER.as_bytes())?;
+            stream.flush()?;
+            return Ok(Handled::EventStream);
+        }
+
+        let is_upload =
+            conf.upload && (req.method == "PUT" || req.method == "POST");
+        let res = match head_len {
+            Some(head_len) => {
+                let body_start = &buffer[head_len..len];
+                match http::is_chunked(&req) {
+                    Err(status) => HTTPResponse::from(status),
+                    Ok(_) if is_upload => {
+                        http::handle_upload(&req, body_start, &mut stream, conf)
+                    }
+                    Ok(true) => {
+                        match http::discard_chunked(body_start, &mut stream) {
+                            Ok(()) => {
+                                http::handle_connection(&req, conf.clone())
+                            }
+                            Err(status) => HTTPResponse::from(status),
+                        }
+                    }
+                    Ok(false) => http::handle_connection(&req, conf.clone()),
+                }
+            }
+            None => http::handle_connection(&req, conf.clone()),
+        };
+
+        let res = res.respond_to(req.method);
+        if conf.delay > 0 {
+            thread::sleep(Duration::from_millis(conf.delay));
+        }
+
+        let (written, res_write) =
+            send(Deadline::new(&mut stream, conf, timer), &res, conf.throttle);
+        logger.log(&req, &res, written, timer, remote);
+        if let Some(trace) = &res.trace {
+            eprintln!("DEBUG: {} resolved: {}", req, trace);
+        }
+        res_write?;
+    } else {
+        if conf.verbosity.shows_requests() {
+            eprintln!("ERR: Invalid HTTP request: {}", req.unwrap_err());
+        }
+        return Ok(Handled::NoRequest);
+    }
+
+    stream.flush()?;
+    Ok(Handled::Done)
+}
+
+/// Read a request from `stream` and answer it with `301 Moved Permanently`
+/// to the same URL over HTTPS, on the port of `conf`, see
+/// [`http::https_redirect`]. The request is logged like the others.
+///
+/// With `--acme-dir`, ACME challenges are answered over plain HTTP instead,
+/// as certificate authorities request them on port 80, see
+/// [`http::acme_challenge`].
+///
+/// If the stream is closed before sending a request, or sends an invalid one,
+/// nothing is written and [`Handled::NoRequest`] is returned.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::{cli::{logger::Logger, tui::Verbosity, Config}, server};
+/// use std::{io::Cursor, sync::Arc};
+///
+/// let conf = Arc::new(Config {
+///     port: 8443,
+///     verbosity: Verbosity::Quiet,
+///     ..Config::default()
+/// });
+/// let logger = Logger::new(&conf).unwrap();
+///
+/// let req = b"GET /docs/ HTTP/1.1\r\nHost: example.com\r\n\r\n";
+/// let mut stream = Cursor::new(req.to_vec());
+/// server::redirect_stream(&mut stream, &conf, &logger, None).unwrap();
+///
+/// let res = String::from_utf8_lossy(stream.get_ref());
+/// assert!(res.contains("HTTP/1.1 301 Moved Permanently"));
+/// assert!(res.contains("Location: https://example.com:8443/docs/"));
+/// ```
+pub fn redirect_stream<S: Read + Write>(
+    mut stream: S,
+    conf: &Config,
+    logger: &Logger,
+    remote: Option<SocketAddr>,
+) -> io::Result<Handled> {
+    let mut buffer = ReadBuffer::take();
+
+    let len = buffer.read_head(&mut stream)?;
+    if len == 0 {
+        return Ok(Handled::NoRequest);
+    }
+
+    let timer = conf.clock.instant();
+    let req = match HTTPRequest::new(&buffer[..len]) {
+        Ok(req) => req,
+        Err(e) => {
+            if conf.verbosity.shows_requests() {
+                eprintln!("ERR: Invalid HTTP request: {}", e);
+            }
+            return Ok(Handled::NoRequest);
+        }
+    };
+
+    let res = match http::acme_challenge(&req, conf) {
+        Some(res) => res,
+        None => http::https_redirect(&req, &conf.address, conf.port),
+    }
+    .respond_to(req.method);
+    let (written, res_write) =
+        send(Deadline::new(&mut stream, conf, timer), &res, 0);
+    logger.log(&req, &res, written, timer, remote);
+    res_write?;
+
+    stream.flush()?;
+    Ok(Handled::Done)
+}
+
+thread_local! {
+    /// Read buffer of the connections handled by the current thread
+    static READ_BUFFER: Cell<Vec<u8>> = const { Cell::new(Vec::new()) };
+}
+
+/// Buffer reading the head of a request, taken from the current thread and
+/// given back when dropped, so that the connections handled by a worker reuse
+/// the same allocation.
+///
+/// The buffer starts at [`READ_BUFFER_SIZE`] bytes and grows up to
+/// [`MAX_HEAD_SIZE`] for longer heads, keeping its size for later
+/// connections. Bytes past the length read are left over from previous
+/// requests and must not be used.
+struct ReadBuffer(Vec<u8>);
+
+impl ReadBuffer {
+    /// Take the read buffer of the current thread.
+    fn take() -> Self {
+        ReadBuffer(READ_BUFFER.with(Cell::take))
+    }
+
+    /// Read the start of a request from `stream`, returning the number of
+    /// bytes read, or `0` if the stream was closed.
+    ///
+    /// Reading stops after a read leaving room in the buffer, or once the
+    /// end of the head was read. Heads longer than [`MAX_HEAD_SIZE`] are cut
+    /// short.
+    fn read_head<S: Read>(&mut self, stream: &mut S) -> io::Result<usize> {
+        if self.0.len() < READ_BUFFER_SIZE {
+            self.0.resize(READ_BUFFER_SIZE, 0);
+        }
+
+        let mut len = 0;
+        loop {
+            let read = stream.read(&mut self.0[len..])?;
+            len += read;
+
+            if read == 0
+                || len < self.0.len()
+                || self.0.len() >= MAX_HEAD_SIZE
+                || head_end(&self.0[..len]).is_some()
+            {
+                return Ok(len);
+            }
+            let size = (self.0.len() * 2).min(MAX_HEAD_SIZE);
+            self.0.resize(size, 0);
+        }
+    }
+}
+
+impl Deref for ReadBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Drop for ReadBuffer {
+    fn drop(&mut self) {
+        let buffer = mem::take(&mut self.0);
+        let _ = READ_BUFFER.try_with(|cell| cell.set(buffer));
+    }
+}
+
+/// Return the length of the head of a request starting `buffer`, including
+/// the empty line ending it, or [`None`] if the head doesn't end in `buffer`.
+fn head_end(buffer: &[u8]) -> Option<usize> {
+    buffer
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|i| i + 4)
+}
+
+/// Write `res` to `stream`, leaving out its body if it answers a `HEAD`
+/// request, see [`HTTPResponse::respond_to`], and throttled to `kbps`
+/// kilobytes per second unless `0`.
+///
+/// Returns the bytes written along with the result, so that a response cut
+/// short, e.g. by the client closing the connection, is logged with the bytes
+/// that were actually sent.
+fn send<S: Write>(
+    stream: S,
+    res: &HTTPResponse,
+    kbps: u64,
+) -> (Written, io::Result<()>) {
+    let mut stream = Counted {
+        inner: stream,
+        read: 0,
+        written: 0,
+    };
+
+    let result = match kbps {
+        0 => http::write_response(res, &mut stream),
+        kbps => http::write_header(res, &mut stream).and_then(|header| {
+            let body = res.sent_body();
+            Throttle::new(&mut stream, kbps).write_all(body)?;
+            Ok(Written {
+                body: body.len(),
+                ..header
+            })
+        }),
+    };
+
+    match result {
+        Ok(written) => (written, Ok(())),
+        Err(e) => {
+            // The header is written first, so whatever follows it is body
+            let total = stream.written as usize;
+            let header = res.header().len().min(total);
+            let written = Written {
+                header,
+                body: total - header,
+            };
+            (written, Err(e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(unix)]
+    use crate::cli::signal;
+    use crate::cli::{logger::LogFormat, tui::Verbosity};
+    use crate::clock::TestClock;
+    use crate::test_utils::TempDir;
+    use std::collections::VecDeque;
+    use std::time::SystemTime;
+
+    /// In-memory connection, reading the request sent by the client and
+    /// keeping the response written to it
+    struct Duplex {
+        input: VecDeque<u8>,
+        output: VecDeque<u8>,
+        /// Number of bytes after which writing fails, as if the client had
+        /// closed the connection
+        write_limit: Option<usize>,
+    }
+
+    impl Duplex {
+        fn new(request: &[u8]) -> Self {
+            Duplex {
+                input: request.iter().copied().collect(),
+                output: VecDeque::new(),
+                write_limit: None,
+            }
+        }
+
+        fn failing_after(request: &[u8], limit: usize) -> Self {
+            Duplex {
+                write_limit: Some(limit),
+                ..Duplex::new(request)
+            }
+        }
+
+        fn response(&self) -> String {
+            let (front, back) = self.output.as_slices();
+            String::from_utf8_lossy(&[front, back].concat()).into_owned()
+        }
+    }
+
+    impl Read for Duplex {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Duplex {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let room = match self.write_limit {
+                Some(limit) if self.output.len() >= limit => {
+                    return Err(io::ErrorKind::BrokenPipe.into())
+                }
+                Some(limit) => limit - self.output.len(),
+                None => buf.len(),
+            };
+
+            self.output.write(&buf[..buf.len().min(room)])
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Helper: quiet config serving the example/ directory
+    fn config() -> Config {
+        Config {
+            base_dir: Path::new("example/").canonicalize().unwrap(),
+            verbosity: Verbosity::Quiet,
+            ..Config::default()
+        }
+    }
+
+    // Helper: start a server for `conf` on a free port
+    fn start(
+        conf: Config,
+    ) -> (
+        SocketAddr,
+        ShutdownHandle,
+        thread::JoinHandle<io::Result<()>>,
+    ) {
+        let server = Server::bind(Config {
+            address: String::from("127.0.0.1"),
+            port: 0,
+            ..conf
+        })
+        .unwrap();
+        let addr = server.local_addr();
+        let shutdown = server.shutdown_handle();
+
+        (addr, shutdown, thread::spawn(move || server.run()))
+    }
+
+    // Helper: send a raw request and return the raw response
+    fn request(addr: SocketAddr, req: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(req.as_bytes()).unwrap();
+
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        res
+    }
+
+    #[test]
+    fn server_requests() {
+        let (addr, shutdown, running) = start(config());
+
+        let res = request(addr, "GET /pages/about.html HTTP/1.1\r\n\r\n");
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(res.contains("Content-Type: text/html"));
+
+        let res = request(addr, "GET /missing.html HTTP/1.1\r\n\r\n");
+        assert!(res.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+        assert!(TcpStream::connect(addr).is_err());
+    }
+
+    #[test]
+    fn stats_interval() {
+        let (addr, shutdown, running) = start(Config {
+            stats_interval: Some(Duration::from_secs(1)),
+            ..config()
+        });
+
+        let res = request(addr, "GET /pages/about.html HTTP/1.1\r\n\r\n");
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        thread::sleep(Duration::from_millis(1200));
+
+        // The stats printer doesn't hold up the shutdown
+        let start = Instant::now();
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn server_shutdown() {
+        let (addr, shutdown, running) = start(config());
+        let other = shutdown.clone();
+
+        assert!(!shutdown.is_shutdown());
+        shutdown.shutdown();
+        other.shutdown();
+
+        assert!(other.is_shutdown());
+        running.join().unwrap().unwrap();
+        assert!(TcpStream::connect(addr).is_err());
+    }
+
+    /// Signal handlers are set for the whole process, so tests sending
+    /// signals take turns
+    #[cfg(unix)]
+    static SIGNALS: Mutex<()> = Mutex::new(());
+
+    // Helper: run a server bound with `conf` as the binary does until it is
+    // stopped with SIGTERM, and return the address it was bound to
+    #[cfg(unix)]
+    fn run_until_signal(conf: Config) -> io::Result<SocketAddr> {
+        let _turn = SIGNALS.lock().unwrap_or_else(|e| e.into_inner());
+        let server = Server::bind(Config {
+            address: String::from("127.0.0.1"),
+            port: 0,
+            ..conf
+        })
+        .unwrap();
+        let shutdown = server.shutdown_handle();
+        signal::on_shutdown(move || shutdown.shutdown())?;
+
+        let addr = server.local_addr();
+        let running = thread::spawn(move || server.run());
+        signal::send(signal::SIGTERM)?;
+        running.join().unwrap()?;
+        Ok(addr)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shutdown_signal() {
+        let addr = run_until_signal(config()).unwrap();
+
+        assert!(TcpStream::connect(addr).is_err());
+    }
+
+    #[test]
+    fn redirect_http() {
+        // Without a certificate, the main port serves plain HTTP, but the
+        // redirect listener behaves the same
+        let server = Server::bind(Config {
+            address: String::from("127.0.0.1"),
+            port: 0,
+            redirect_http: Some(0),
+            ..config()
+        })
+        .unwrap();
+        let addr = server.local_addr();
+        let port = server.config().redirect_http.unwrap();
+        let redirect_addr = SocketAddr::from(([127, 0, 0, 1], port));
+        let shutdown = server.shutdown_handle();
+        let running = thread::spawn(move || server.run());
+
+        let res = request(addr, "GET /pages/about.html HTTP/1.1\r\n\r\n");
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let res = request(
+            redirect_addr,
+            "GET /pages/about.html?a=1 HTTP/1.1\r\nHost: site.test\r\n\r\n",
+        );
+        assert!(res.starts_with("HTTP/1.1 301 Moved Permanently\r\n"));
+        assert!(res.contains(&format!(
+            "Location: https://site.test:{}/pages/about.html?a=1\r\n",
+            addr.port()
+        )));
+
+        // A client sending nothing doesn't hold up the others
+        let _idle = TcpStream::connect(redirect_addr).unwrap();
+        let res = request(redirect_addr, "HEAD / HTTP/1.1\r\n\r\n");
+        assert!(res.contains(&format!(
+            "Location: https://127.0.0.1:{}/\r\n",
+            addr.port()
+        )));
+
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+        assert!(TcpStream::connect(addr).is_err());
+        assert!(TcpStream::connect(redirect_addr).is_err());
+    }
+
+    #[test]
+    fn redirect_stream_acme() {
+        let conf = Config {
+            acme_dir: Some(Path::new("example/pages").canonicalize().unwrap()),
+            ..config()
+        };
+        let logger = Logger::new(&conf).unwrap();
+
+        let req =
+            b"GET /.well-known/acme-challenge/about.html HTTP/1.1\r\n\r\n";
+        let mut stream = Duplex::new(req);
+        redirect_stream(&mut stream, &conf, &logger, None).unwrap();
+        assert!(stream.response().starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let mut stream = Duplex::new(b"GET /pages/ HTTP/1.1\r\n\r\n");
+        redirect_stream(&mut stream, &conf, &logger, None).unwrap();
+        assert!(stream.response().starts_with("HTTP/1.1 301"));
+    }
+
+    #[test]
+    fn head_requests() {
+        let conf = Arc::new(Config {
+            health: true,
+            ..config()
+        });
+        let gone = Arc::new(Config {
+            base_dir: conf.base_dir.join("gone"),
+            health: true,
+            ..config()
+        });
+        let logger = Logger::new(&conf).unwrap();
+        let respond = |method: &str, path: &str, conf: &Arc<Config>| {
+            let req = format!(
+                "{} {} HTTP/1.1\r\nX-Request-Id: abc\r\n\r\n",
+                method, path
+            );
+            let mut stream = Duplex::new(req.as_bytes());
+            handle_stream(&mut stream, conf, &logger, None).unwrap();
+            stream.response()
+        };
+
+        for (path, conf, code) in [
+            ("/index.html", &conf, "200"),
+            ("/pages", &conf, "301"),
+            ("/missing.html", &conf, "404"),
+            ("/_servum/health", &gone, "503"),
+        ] {
+            let get = respond("GET", path, conf);
+            let head = respond("HEAD", path, conf);
+            let (header, body) = get.split_once("\r\n\r\n").unwrap();
+
+            assert!(get.starts_with(&format!("HTTP/1.1 {}", code)), "{}", get);
+            assert!(
+                get.contains(&format!("Content-Length: {}\r\n", body.len()))
+            );
+            assert_eq!(head, format!("{}\r\n\r\n", header), "{}", path);
+        }
+
+        // Redirects to HTTPS alike
+        let mut get = Duplex::new(b"GET /docs/ HTTP/1.1\r\n\r\n");
+        let mut head = Duplex::new(b"HEAD /docs/ HTTP/1.1\r\n\r\n");
+        redirect_stream(&mut get, &conf, &logger, None).unwrap();
+        redirect_stream(&mut head, &conf, &logger, None).unwrap();
+        let (get, head) = (get.response(), head.response());
+        assert!(get.starts_with("HTTP/1.1 301"));
+        assert!(get.starts_with(&head));
+        assert!(head.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn redirect_stream_invalid() {
+        let conf = config();
+        let logger = Logger::new(&conf).unwrap();
+
+        let mut stream = Duplex::new(b"\xff\xfe\r\n\r\n");
+        let handled = redirect_stream(&mut stream, &conf, &logger, None);
+        assert_eq!(handled.unwrap(), Handled::NoRequest);
+        assert_eq!(stream.response(), "");
+        assert_eq!(logger.metrics().summary().requests, 0);
+    }
+
+    #[test]
+    fn serve_handle() {
+        let conf = || Config {
+            address: String::from("127.0.0.1"),
+            port: 0,
+            ..config()
+        };
+
+        let handle = serve(conf()).unwrap();
+        let res = request(handle.addr(), "HEAD / HTTP/1.1\r\n\r\n");
+        assert!(res.starts_with("HTTP/1.1 200 OK"), "{}", res);
+        handle.shutdown().unwrap();
+
+        // Dropping the handle stops the server too
+        let handle = serve(conf()).unwrap();
+        let shutdown = handle.shutdown_handle();
+        drop(handle);
+        assert!(shutdown.is_shutdown());
+    }
+
+    #[test]
+    fn server_bind_error() {
+        let (addr, shutdown, running) = start(config());
+        let err = Server::bind(Config {
+            address: String::from("127.0.0.1"),
+            port: addr.port(),
+            ..config()
+        })
+        .err()
+        .unwrap();
+
+        assert!(matches!(
+            &err,
+            Error::Io(e) if e.kind() == io::ErrorKind::AddrInUse
+        ));
+        assert!(err.to_string().contains(&addr.port().to_string()));
+
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+    }
+
+    thread_local! {
+        /// Number of allocations made by the current thread
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// System allocator counting the allocations of each thread
+    struct CountingAlloc;
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+            unsafe { std::alloc::System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            unsafe { std::alloc::System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(
+            &self,
+            ptr: *mut u8,
+            layout: std::alloc::Layout,
+            size: usize,
+        ) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+            unsafe { std::alloc::System.realloc(ptr, layout, size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOC: CountingAlloc = CountingAlloc;
+
+    #[test]
+    fn read_buffer_reuse() {
+        let conf = Arc::new(config());
+        let logger = Logger::new(&conf).unwrap();
+        let padding = "a".repeat(3000);
+        let req = format!("HEAD / HTTP/1.1\r\nX-Padding: {}\r\n\r\n", padding);
+
+        // Allocations of handling the request on a thread of its own
+        let handle = || {
+            let mut conn = Duplex::new(req.as_bytes());
+            let before = ALLOCATIONS.with(Cell::get);
+            handle_stream(&mut conn, &conf, &logger, None).unwrap();
+            let allocations = ALLOCATIONS.with(Cell::get) - before;
+
+            assert!(conn.response().starts_with("HTTP/1.1 200 OK"));
+            allocations
+        };
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                let first = handle();
+                let buffer = READ_BUFFER.with(Cell::take);
+                assert_eq!(buffer.len(), 4 * READ_BUFFER_SIZE);
+                let ptr = buffer.as_ptr();
+                READ_BUFFER.with(|cell| cell.set(buffer));
+
+                // The buffer grew from 1 to 4 KB once, and is then reused
+                let second = handle();
+                assert!(first >= second + 2, "{} vs {}", first, second);
+                let buffer = READ_BUFFER.with(Cell::take);
+                assert_eq!(buffer.as_ptr(), ptr);
+            });
+        });
+    }
+
+    #[test]
+    fn read_head() {
+        let mut buffer = ReadBuffer(Vec::new());
+
+        let mut stream =
+            io::Cursor::new(b"GET / HTTP/1.1\r\n\r\nbody".to_vec());
+        let len = buffer.read_head(&mut stream).unwrap();
+        assert_eq!(&buffer[..len], b"GET / HTTP/1.1\r\n\r\nbody");
+
+        // Heads beyond the limit are cut short
+        let long =
+            format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEAD_SIZE));
+        let mut stream = io::Cursor::new(long.into_bytes());
+        assert_eq!(buffer.read_head(&mut stream).unwrap(), MAX_HEAD_SIZE);
+        assert_eq!(head_end(&buffer[..MAX_HEAD_SIZE]), None);
+
+        // Leftovers of the previous request are past the length read
+        let mut stream = io::Cursor::new(b"HEAD /x HTTP/1.1\r\n\r\n".to_vec());
+        let len = buffer.read_head(&mut stream).unwrap();
+        assert_eq!(head_end(&buffer[..len]), Some(len));
+        assert_eq!(buffer.read_head(&mut stream).unwrap(), 0);
+    }
+
+    #[test]
+    fn get() {
+        let conf = Arc::new(config());
+        let logger = Logger::new(&conf).unwrap();
+        let mut conn = Duplex::new(b"GET /index.html HTTP/1.1\r\n\r\n");
+
+        handle_stream(&mut conn, &conf, &logger, None).unwrap();
+
+        let res = conn.response();
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(res.ends_with("</html>\n"));
+
+        let summary = logger.metrics().summary();
+        assert_eq!(summary.bytes + summary.header_bytes, res.len() as u64);
+        assert!(summary.header_bytes > 0);
+    }
+
+    #[test]
+    fn head() {
+        let conf = Arc::new(config());
+        let logger = Logger::new(&conf).unwrap();
+        let mut conn = Duplex::new(b"HEAD /index.html HTTP/1.1\r\n\r\n");
+
+        handle_stream(&mut conn, &conf, &logger, None).unwrap();
+
+        let res = conn.response();
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(res.ends_with("\r\n\r\n"));
+
+        // Only the header is counted as sent
+        let summary = logger.metrics().summary();
+        assert_eq!(summary.bytes, 0);
+        assert_eq!(summary.header_bytes, res.len() as u64);
+    }
+
+    #[test]
+    fn upload() {
+        let dir = TempDir::new();
+        let body = (0..3000).map(|i| i as u8).collect::<Vec<u8>>();
+        let mut req = format!(
+            "PUT /up.bin HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        req.extend_from_slice(&body);
+
+        let conf = Arc::new(Config {
+            base_dir: dir.path().to_path_buf(),
+            ..config()
+        });
+        let logger = Logger::new(&conf).unwrap();
+        let mut conn = Duplex::new(&req);
+        handle_stream(&mut conn, &conf, &logger, None).unwrap();
+
+        assert!(conn.response().starts_with("HTTP/1.1 501 Not Implemented"));
+        assert!(!dir.path().join("up.bin").exists());
+
+        let conf = Arc::new(Config {
+            upload: true,
+            ..(*conf).clone()
+        });
+        let mut conn = Duplex::new(&req);
+        handle_stream(&mut conn, &conf, &logger, None).unwrap();
+
+        assert!(conn.response().starts_with("HTTP/1.1 201 Created"));
+        assert_eq!(fs::read(dir.path().join("up.bin")).unwrap(), body);
+    }
+
+    #[test]
+    fn chunked_body() {
+        let conf = Arc::new(config());
+        let logger = Logger::new(&conf).unwrap();
+        let status = |req: &[u8]| {
+            let mut conn = Duplex::new(req);
+            handle_stream(&mut conn, &conf, &logger, None).unwrap();
+            let res = conn.response();
+            // The whole body is read before answering
+            assert!(conn.input.is_empty() || !res.starts_with("HTTP/1.1 2"));
+            res.lines().next().unwrap().to_string()
+        };
+
+        assert_eq!(
+            status(
+                b"GET /index.html HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+                  4;a=b\r\nbody\r\n0\r\nX-Trailer: 1\r\n\r\n"
+            ),
+            "HTTP/1.1 200 OK"
+        );
+        for malformed in [
+            &b"4\r\nbody0\r\n\r\n"[..],
+            b"z\r\nbody\r\n0\r\n\r\n",
+            b"4\r\nbo",
+        ] {
+            let req = [
+                &b"GET /index.html HTTP/1.1\r\n\
+                   Transfer-Encoding: chunked\r\n\r\n"[..],
+                malformed,
+            ]
+            .concat();
+            assert_eq!(status(&req), "HTTP/1.1 400 Bad Request");
+        }
+        assert_eq!(
+            status(
+                b"GET /index.html HTTP/1.1\r\nTransfer-Encoding: gzip\r\n\r\n"
+            ),
+            "HTTP/1.1 501 Not Implemented"
+        );
+        assert_eq!(
+            status(
+                b"GET /index.html HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\
+                  Content-Length: 4\r\n\r\nbody"
+            ),
+            "HTTP/1.1 400 Bad Request"
+        );
+    }
+
+    /// Client reading one chunk of the response per second, as measured by
+    /// `clock`
+    struct SlowClient<'a> {
+        conn: Duplex,
+        clock: &'a TestClock,
+    }
+
+    impl Read for SlowClient<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.conn.read(buf)
+        }
+    }
+
+    impl Write for SlowClient<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.clock.advance(Duration::from_secs(1));
+            self.conn.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn request_deadline() {
+        let dir = TempDir::new();
+        let body = vec![b'a'; 20 * DEADLINE_CHUNK_SIZE];
+        dir.file("big.bin", &body);
+
+        let clock = Arc::new(TestClock::new(SystemTime::UNIX_EPOCH));
+        let conf = Arc::new(Config {
+            base_dir: dir.path().to_path_buf(),
+            clock: clock.clone(),
+            request_deadline: Some(Duration::from_secs(5)),
+            ..config()
+        });
+        let logger = Logger::new(&conf).unwrap();
+        let mut client = SlowClient {
+            conn: Duplex::new(b"GET /big.bin HTTP/1.1\r\n\r\n"),
+            clock: &clock,
+        };
+
+        let err = handle_stream(&mut client, &conf, &logger, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        // The header and four chunks of the body are sent in five seconds
+        let res = client.conn.response();
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        let header = res.find("\r\n\r\n").unwrap() + 4;
+        assert_eq!(res.len() - header, 4 * DEADLINE_CHUNK_SIZE);
+
+        // The request is logged with the bytes sent before giving up
+        let summary = logger.metrics().summary();
+        assert_eq!(summary.bytes, 4 * DEADLINE_CHUNK_SIZE as u64);
+
+        // Without a deadline, the whole body is sent however long it takes
+        let conf = Arc::new(Config {
+            request_deadline: None,
+            ..(*conf).clone()
+        });
+        let mut client = SlowClient {
+            conn: Duplex::new(b"GET /big.bin HTTP/1.1\r\n\r\n"),
+            clock: &clock,
+        };
+        handle_stream(&mut client, &conf, &logger, None).unwrap();
+        assert!(client.conn.response().ends_with(&"a".repeat(100)));
+        assert!(client.conn.output.len() > body.len());
+    }
+
+    #[test]
+    fn request_deadline_blocked_client() {
+        let dir = TempDir::new();
+        // Far more than the socket buffers hold
+        let len = 64 * 1024 * 1024;
+        dir.file("big.bin", vec![b'a'; len]);
+        dir.file("small.txt", "small");
+        let (addr, shutdown, running) = start(Config {
+            base_dir: dir.path().to_path_buf(),
+            request_deadline: Some(Duration::from_millis(300)),
+            threads: 1,
+            ..config()
+        });
+
+        // The client doesn't read, blocking the server's writes, until the
+        // only worker gave up on it and answered another request
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"GET /big.bin HTTP/1.1\r\n\r\n").unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        let mut other = TcpStream::connect(addr).unwrap();
+        other
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        other.write_all(b"GET /small.txt HTTP/1.1\r\n\r\n").unwrap();
+        let mut res = String::new();
+        other.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(res.ends_with("small"));
+
+        client
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut received = Vec::new();
+        match client.read_to_end(&mut received) {
+            Ok(_) => (),
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::ConnectionReset),
+        }
+        assert!(received.starts_with(b"HTTP/1.1 200 OK\r\n"));
+        assert!(received.len() < len);
+
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn event_stream_served_by() {
+        let dir = TempDir::new();
+        let conf = Arc::new(Config {
+            live_reload: true,
+            log_file: Some(dir.path().join("access.log")),
+            log_format: LogFormat::Json,
+            ..config()
+        });
+        let logger = Logger::new(&conf).unwrap();
+        let mut conn = Duplex::new(b"GET /_servum/events HTTP/1.1\r\n\r\n");
+
+        let handled = handle_stream(&mut conn, &conf, &logger, None).unwrap();
+
+        let log = fs::read_to_string(dir.path().join("access.log")).unwrap();
+        assert_eq!(handled, Handled::EventStream);
+        assert!(log.contains(r#""served_by":"internal:events""#));
+    }
+
+    #[test]
+    fn invalid_request() {
+        let conf = Arc::new(config());
+        let logger = Logger::new(&conf).unwrap();
+        let mut conn = Duplex::new(b"");
+
+        let handled = handle_stream(&mut conn, &conf, &logger, None).unwrap();
+
+        assert_eq!(handled, Handled::NoRequest);
+        assert!(conn.output.is_empty());
+    }
+
+    #[test]
+    fn truncated_requests() {
+        let conf = Arc::new(config());
+        let logger = Logger::new(&conf).unwrap();
+
+        // Without the HTTP version, the request line is incomplete
+        let mut conn = Duplex::new(b"GET /index.ht");
+        let stats = serve_connection(&mut conn, &conf, &logger, None).unwrap();
+        assert_eq!(
+            stats,
+            ConnStats {
+                requests: 0,
+                bytes_read: 13,
+                bytes_written: 0,
+                handled: Handled::NoRequest,
+            }
+        );
+        assert!(conn.output.is_empty());
+
+        // Truncated header fields don't keep the file from being served
+        let mut conn = Duplex::new(b"GET /index.html HTTP/1.1\r\nHost: exa");
+        let stats = serve_connection(&mut conn, &conf, &logger, None).unwrap();
+        assert_eq!(stats.requests, 1);
+        assert_eq!(stats.bytes_written, conn.output.len() as u64);
+        assert!(conn.response().starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn pipelined_requests() {
+        let conf = Arc::new(config());
+        let logger = Logger::new(&conf).unwrap();
+        let mut conn = Duplex::new(
+            b"GET /index.html HTTP/1.1\r\n\r\n\
+              GET /pages/about.html HTTP/1.1\r\n\r\n",
+        );
+
+        let stats = serve_connection(&mut conn, &conf, &logger, None).unwrap();
+
+        // Only the first request is answered before closing the connection
+        let res = conn.response();
+        assert_eq!(stats.requests, 1);
+        assert_eq!(res.matches("HTTP/1.1 ").count(), 1);
+        assert!(res.contains("Connection: close\r\n"));
+        assert_eq!(logger.metrics().summary().top_paths.len(), 1);
+    }
+
+    #[test]
+    fn write_error() {
+        let conf = Arc::new(config());
+        let logger = Logger::new(&conf).unwrap();
+
+        for limit in [0, 10, 200] {
+            let conn = Duplex::failing_after(b"GET / HTTP/1.1\r\n\r\n", limit);
+            let err = serve_connection(conn, &conf, &logger, None).unwrap_err();
+
+            assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        }
+
+        // The requests are counted even though the responses didn't make it,
+        // with the bytes written before failing
+        let summary = logger.metrics().summary();
+        assert_eq!(summary.requests, 3);
+        assert_eq!(summary.bytes + summary.header_bytes, 210);
+        assert!(summary.bytes > 0);
+    }
+
+    #[test]
+    fn closed_socket() {
+        let dir = TempDir::new();
+        dir.file("big.bin", vec![0; 8 << 20]);
+        let conf = Arc::new(Config {
+            base_dir: dir.path().to_path_buf(),
+            ..config()
+        });
+        let logger = Logger::new(&conf).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let mut client =
+            TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(b"GET /big.bin HTTP/1.1\r\n\r\n").unwrap();
+        drop(client);
+
+        let (stream, _) = listener.accept().unwrap();
+        let err = handle_stream(stream, &conf, &logger, None).unwrap_err();
+
+        assert!(is_disconnect(&err), "{:?}", err);
+    }
+
+    #[test]
+    fn closed_before_request() {
+        let conf = Arc::new(config());
+        let logger = Logger::new(&conf).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        drop(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+
+        let (stream, _) = listener.accept().unwrap();
+        assert!(handle_stream(stream, &conf, &logger, None).is_ok());
+    }
+
+    #[test]
+    fn server_survives_disconnects() {
+        let (addr, shutdown, running) = start(config());
+
+        for _ in 0..8 {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        }
+
+        let res = request(addr, "GET / HTTP/1.1\r\n\r\n");
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn reuse_port() {
+        let (addr, shutdown, running) = start(Config {
+            threads: 4,
+            reuse_port: true,
+            ..config()
+        });
+
+        for _ in 0..32 {
+            let res = request(addr, "GET /index.html HTTP/1.1\r\n\r\n");
+            assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        }
+
+        // Every accept loop must stop, not only the one woken up
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+        assert!(TcpStream::connect(addr).is_err());
+    }
+
+    #[test]
+    fn pid_file() {
+        let dir = TempDir::new();
+        let path = dir.file("servum.pid", "99999999");
+        let (_, shutdown, running) = start(Config {
+            pid_file: Some(path.clone()),
+            ..config()
+        });
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pid_file_signal() {
+        let dir = TempDir::new();
+        let path = dir.path().join("servum.pid");
+
+        run_until_signal(Config {
+            pid_file: Some(path.clone()),
+            ..config()
+        })
+        .unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn unix_socket() {
+        let dir = TempDir::new();
+        let path = dir.path().join("servum.sock");
+        let server = Server::bind(Config {
+            unix_socket: Some(path.clone()),
+            ..config()
+        })
+        .unwrap();
+        let shutdown = server.shutdown_handle();
+        let running = thread::spawn(move || server.run());
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream
+            .write_all(b"GET /index.html HTTP/1.1\r\n\r\n")
+            .unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn unix_socket_removed() {
+        let dir = TempDir::new();
+        let path = dir.path().join("servum.sock");
+
+        // Stopped with a signal
+        run_until_signal(Config {
+            unix_socket: Some(path.clone()),
+            ..config()
+        })
+        .unwrap();
+        assert!(!path.exists());
+
+        // Failing to start after binding the socket
+        let parent = std::os::unix::process::parent_id().to_string();
+        let res = Server::bind(Config {
+            unix_socket: Some(path.clone()),
+            pid_file: Some(dir.file("servum.pid", &parent)),
+            ..config()
+        });
+        assert!(matches!(res, Err(Error::Io(_))));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn accepted_socket_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let _client = TcpStream::connect(addr).unwrap();
+        let stream = accept_connection(&listener, &config()).unwrap();
+        assert!(stream.nodelay().unwrap());
+
+        let conf = Config {
+            nodelay: false,
+            ..config()
+        };
+        let _client = TcpStream::connect(addr).unwrap();
+        let stream = accept_connection(&listener, &conf).unwrap();
+        assert!(!stream.nodelay().unwrap());
+    }
+
+    #[test]
+    fn accept_fd_exhausted() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client =
+            TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let listener = Listener::Tcp(listener);
+        let reserve = FdReserve::new();
+
+        let err = io::Error::from_raw_os_error(24);
+        accept_error(&err, &listener, &reserve, &config());
+
+        let mut res = String::new();
+        client.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 503 Service Unavailable"));
+        assert!(reserve.spare.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn fd_warning_rate_limit() {
+        let reserve = FdReserve::new();
+        let clock = TestClock::new(SystemTime::UNIX_EPOCH);
+
+        assert!(reserve.should_warn(&clock));
+        assert!(!reserve.should_warn(&clock));
+
+        clock.advance(FD_WARNING_INTERVAL - Duration::from_millis(1));
+        assert!(!reserve.should_warn(&clock));
+        clock.advance(Duration::from_millis(1));
+        assert!(reserve.should_warn(&clock));
+        assert!(!reserve.should_warn(&clock));
+    }
+
+    #[test]
+    fn error_kinds() {
+        assert!(is_disconnect(&io::ErrorKind::ConnectionReset.into()));
+        assert!(!is_disconnect(&io::ErrorKind::PermissionDenied.into()));
+    }
+
+    #[test]
+    fn max_connections_reject() {
+        let (addr, shutdown, running) = start(Config {
+            threads: 4,
+            max_connections: Some(2),
+            ..config()
+        });
+        let open: Vec<_> =
+            (0..2).map(|_| TcpStream::connect(addr).unwrap()).collect();
+
+        // The rejected request isn't read, so sending one could reset the
+        // connection before the response is read
+        let mut res = String::new();
+        let mut last = TcpStream::connect(addr).unwrap();
+        last.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+
+        drop(open);
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn max_connections_wait() {
+        let (addr, shutdown, running) = start(Config {
+            threads: 4,
+            max_connections: Some(2),
+            connection_overflow: Overflow::Wait,
+            ..config()
+        });
+        let mut open: Vec<_> =
+            (0..2).map(|_| TcpStream::connect(addr).unwrap()).collect();
+
+        let mut last = TcpStream::connect(addr).unwrap();
+        last.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        last.set_read_timeout(Some(Duration::from_millis(200)))
+            .unwrap();
+        let err = last.read(&mut [0; 16]).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ));
+
+        open.pop();
+        last.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut res = String::new();
+        last.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        drop(open);
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn live_reload() {
+        let dir = TempDir::new();
+        dir.file("index.html", "<body>Hi</body>");
+        let (addr, shutdown, running) = start(Config {
+            base_dir: dir.path().to_path_buf(),
+            live_reload: true,
+            ..config()
+        });
+
+        let res = request(addr, "GET / HTTP/1.1\r\n\r\n");
+        assert!(res.ends_with(&format!("{}</body>", http::RELOAD_SCRIPT)));
+
+        let mut events = TcpStream::connect(addr).unwrap();
+        events
+            .write_all(b"GET /_servum/events HTTP/1.1\r\n\r\n")
+            .unwrap();
+        events
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut events = io::BufReader::new(events);
+        let mut received = String::new();
+        while !received.ends_with("retry: 1000\n\n") {
+            events.read_line(&mut received).unwrap();
+        }
+        assert!(received.starts_with(EVENT_STREAM_HEADER));
+
+        dir.file("style.css", "body {}");
+        let mut event = String::new();
+        while !event.ends_with("\n\n") {
+            events.read_line(&mut event).unwrap();
+        }
+        assert_eq!(event, "event: reload\ndata: style.css\n\n");
+
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+
+        // The stream ends once the server is shut down
+        let mut rest = String::new();
+        events.read_to_string(&mut rest).unwrap();
+        assert!(!rest.contains("event:"));
+    }
+
+    #[test]
+    fn live_reload_not_found_cache() {
+        use crate::clock::TestClock;
+        use std::time::SystemTime;
+
+        let dir = TempDir::new();
+        // The clock stands still, so that cached paths never expire
+        let (addr, shutdown, running) = start(Config {
+            base_dir: dir.path().to_path_buf(),
+            live_reload: true,
+            cache: true,
+            clock: Arc::new(TestClock::new(SystemTime::UNIX_EPOCH)),
+            ..config()
+        });
+        let status = || {
+            let res = request(addr, "GET /new.txt HTTP/1.1\r\n\r\n");
+            res.lines().next().unwrap().to_string()
+        };
+
+        assert_eq!(status(), "HTTP/1.1 404 Not Found");
+        dir.file("new.txt", "new");
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while status() != "HTTP/1.1 200 OK" {
+            assert!(Instant::now() < deadline, "cache was never cleared");
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        shutdown.shutdown();
+        running.join().unwrap().unwrap();
+    }
+}
@@ -0,0 +1,4 @@
+//! Helpers shared by unit tests
+
+/// Temporary directory, removed with all its contents when dropped
+pub use crate::testing::TempSite as TempDir;
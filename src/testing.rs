@@ -0,0 +1,323 @@
+//! Helpers to test configurations against a real servum server
+//!
+//! Available in servum's own tests and, with the `testing` feature, to
+//! downstream crates, e.g. as a dev-dependency:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! servum = { version = "1", features = ["testing"] }
+//! ```
+//!
+//! A [`TempSite`] lays out the files to serve in a temporary directory, a
+//! [`TestClient`] serves it on a free port of the loopback interface and
+//! sends requests to it, and a [`ParsedResponse`] splits the answers into
+//! status, header fields and body.
+//!
+//! # Example
+//!
+//! ```rust
+//! use servum::{cli::Config, testing::{TempSite, TestClient}};
+//!
+//! let site = TempSite::build(&[
+//!     ("index.html", "<h1>Home</h1>"),
+//!     ("pages/about.html", "<h1>About</h1>"),
+//! ]);
+//! let client = TestClient::start(Config {
+//!     list_dir: false,
+//!     ..site.config()
+//! });
+//!
+//! let res = client.get("/pages/about.html");
+//! assert_eq!(res.status, 200);
+//! assert_eq!(res.header("content-type"), Some("text/html"));
+//! assert_eq!(res.text(), "<h1>About</h1>");
+//!
+//! assert_eq!(client.get("/pages/").status, 403);
+//! ```
+use crate::{
+    cli::{tui::Verbosity, Config},
+    server::{serve, ServeHandle},
+};
+use std::{
+    fs,
+    io::prelude::*,
+    net::{SocketAddr, TcpStream},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Number of temporary sites created so far, to name them apart
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Temporary directory holding files to serve, removed with all its contents
+/// when dropped.
+///
+/// # Panics
+///
+/// As a test helper, every method panics if the file system fails.
+#[derive(Debug)]
+pub struct TempSite(PathBuf);
+
+impl TempSite {
+    /// Create a new, empty and uniquely named temporary directory.
+    pub fn new() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let path = std::env::temp_dir().join(format!(
+            "servum-test-{}-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst),
+            nanos
+        ));
+
+        fs::create_dir_all(&path).unwrap();
+        TempSite(path.canonicalize().unwrap())
+    }
+
+    /// Create a temporary directory with `files`, given as paths relative to
+    /// the directory and their contents.
+    pub fn build(files: &[(&str, &str)]) -> Self {
+        let site = Self::new();
+        for (name, contents) in files {
+            site.file(name, contents);
+        }
+        site
+    }
+
+    /// Return the path of the temporary directory.
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Write a file relative to the temporary directory, creating parent
+    /// directories as needed, and return its path.
+    pub fn file<C: AsRef<[u8]>>(&self, name: &str, contents: C) -> PathBuf {
+        let path = self.0.join(name);
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// Return a default [`Config`] serving the directory, without printing
+    /// anything.
+    pub fn config(&self) -> Config {
+        Config {
+            base_dir: self.0.clone(),
+            verbosity: Verbosity::Silent,
+            ..Config::default()
+        }
+    }
+}
+
+impl Default for TempSite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TempSite {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Client of a servum server started for a test, shut down when dropped.
+///
+/// Each request is sent on a connection of its own, closed once the response
+/// is read.
+///
+/// # Panics
+///
+/// As a test helper, every method panics if the server can't be started or
+/// reached, or if its response can't be parsed.
+#[derive(Debug)]
+pub struct TestClient {
+    handle: ServeHandle,
+}
+
+impl TestClient {
+    /// Start a server for `config` on a free port of `127.0.0.1`, whatever
+    /// the address and port of `config`.
+    pub fn start(config: Config) -> Self {
+        let handle = serve(Config {
+            address: String::from("127.0.0.1"),
+            port: 0,
+            ..config
+        })
+        .unwrap();
+
+        TestClient { handle }
+    }
+
+    /// Return the address the server is bound to.
+    pub fn addr(&self) -> SocketAddr {
+        self.handle.addr()
+    }
+
+    /// Send a `GET` request for `target`, i.e. a path and an optional query
+    /// string, and parse the response.
+    pub fn get(&self, target: &str) -> ParsedResponse {
+        let req = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            target,
+            self.addr()
+        );
+        self.request(req.as_bytes())
+    }
+
+    /// Send a raw request, which should ask to close the connection, and
+    /// parse the response.
+    pub fn request(&self, req: &[u8]) -> ParsedResponse {
+        let res = self.raw(req);
+        ParsedResponse::parse(&res).unwrap_or_else(|| {
+            panic!("Invalid response: {:?}", String::from_utf8_lossy(&res))
+        })
+    }
+
+    /// Send a raw request and return the raw response, read until the server
+    /// closes the connection.
+    pub fn raw(&self, req: &[u8]) -> Vec<u8> {
+        let mut stream = TcpStream::connect(self.addr()).unwrap();
+        stream.write_all(req).unwrap();
+
+        let mut res = Vec::new();
+        stream.read_to_end(&mut res).unwrap();
+        res
+    }
+
+    /// Stop the server, panicking if it stopped with an error.
+    pub fn shutdown(self) {
+        self.handle.shutdown().unwrap();
+    }
+}
+
+/// Response of a server split into its status line, header fields and body.
+///
+/// # Example
+///
+/// ```rust
+/// # use servum::testing::ParsedResponse;
+/// let res = ParsedResponse::parse(
+///     b"HTTP/1.1 404 Not Found\r\nContent-Length: 4\r\n\r\ngone",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(res.status, 404);
+/// assert_eq!(res.reason, "Not Found");
+/// assert_eq!(res.header("Content-Length"), Some("4"));
+/// assert_eq!(res.body, b"gone");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedResponse {
+    pub status: usize,
+    pub reason: String,
+    /// Header fields in the order they were sent, with surrounding
+    /// whitespace removed from their values
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl ParsedResponse {
+    /// Parse a raw response, or return [`None`] if it has no valid status
+    /// line or its header isn't terminated by a blank line.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let end = bytes.windows(4).position(|w| w == b"\r\n\r\n")?;
+        let head = std::str::from_utf8(&bytes[..end]).ok()?;
+        let mut lines = head.split("\r\n");
+
+        let mut status_line = lines.next()?.splitn(3, ' ');
+        if !status_line.next()?.starts_with("HTTP/") {
+            return None;
+        }
+        let status = status_line.next()?.parse().ok()?;
+        let reason = status_line.next().unwrap_or_default().to_string();
+
+        let headers = lines
+            .map(|line| {
+                let (name, value) = line.split_once(':')?;
+                Some((name.to_string(), value.trim().to_string()))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(ParsedResponse {
+            status,
+            reason,
+            headers,
+            body: bytes[end + 4..].to_vec(),
+        })
+    }
+
+    /// Return the value of the first header field called `name`, ignoring
+    /// case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Return the body as text, replacing invalid UTF-8.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let res = ParsedResponse::parse(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nX-A:  1 \r\n\
+              \r\nline\r\n\r\nmore",
+        )
+        .unwrap();
+        assert_eq!(res.status, 200);
+        assert_eq!(res.reason, "OK");
+        assert_eq!(res.header("content-type"), Some("text/plain"));
+        assert_eq!(res.header("x-a"), Some("1"));
+        assert_eq!(res.header("X-B"), None);
+        assert_eq!(res.text(), "line\r\n\r\nmore");
+
+        let res = ParsedResponse::parse(b"HTTP/1.1 204\r\n\r\n").unwrap();
+        assert_eq!((res.status, res.reason.as_str()), (204, ""));
+        assert!(res.headers.is_empty() && res.body.is_empty());
+
+        for invalid in [
+            &b""[..],
+            b"HTTP/1.1 200 OK\r\n",
+            b"HTTP/1.1 OK\r\n\r\n",
+            b"SSH-2.0 200 OK\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nno colon\r\n\r\n",
+        ] {
+            assert_eq!(ParsedResponse::parse(invalid), None);
+        }
+    }
+
+    #[test]
+    fn client() {
+        let site = TempSite::build(&[("a.txt", "a"), ("dir/b.txt", "b")]);
+        let client = TestClient::start(site.config());
+        assert!(client.addr().ip().is_loopback());
+
+        let res = client.get("/dir/b.txt?x=1");
+        assert_eq!((res.status, res.text().as_str()), (200, "b"));
+        assert_eq!(res.header("Content-Length"), Some("1"));
+
+        let res = client.request(b"HEAD /a.txt HTTP/1.1\r\n\r\n");
+        assert_eq!(res.status, 200);
+        assert!(res.body.is_empty());
+        assert!(client
+            .raw(b"GET /missing HTTP/1.1\r\n\r\n")
+            .starts_with(b"HTTP/1.1 404 Not Found\r\n"));
+
+        let addr = client.addr();
+        client.shutdown();
+        assert!(TcpStream::connect(addr).is_err());
+    }
+}